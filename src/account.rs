@@ -1,10 +1,10 @@
-use std::{collections::HashMap, io::ErrorKind, path::Path};
+use std::{collections::HashMap, fmt::Display, io::ErrorKind, path::Path};
 
 use anyhow::{Context, anyhow, bail};
 use serde::{Deserialize, Serialize};
 use tracing::debug;
 
-use crate::{Action, Dollar, Percent};
+use crate::{Action, Dollar, Percent, RelativePercent, date::Date};
 
 /// A representation of the balance of a brokerage account
 #[derive(Serialize, Deserialize, Debug, Default, Clone)]
@@ -26,6 +26,20 @@ impl Balance {
             .map(|holding| holding.current_value)
             .sum()
     }
+
+    /// Change the price of `symbol` by `change` (e.g. -20% for a crash), as if
+    /// it had moved before the balance was taken. Returns whether the account
+    /// holds the symbol.
+    pub fn apply_price_change(&mut self, symbol: &str, change: Percent) -> bool {
+        let factor = 1.0 + change.0 / 100.0;
+        let mut found = false;
+        for holding in self.holdings.iter_mut().filter(|h| h.symbol == symbol) {
+            holding.current_value = holding.current_value * factor;
+            holding.price = holding.price.map(|price| price * factor);
+            found = true;
+        }
+        found
+    }
 }
 
 /// A single investment within a brokerage account
@@ -40,6 +54,64 @@ pub struct Holding {
     /// position', Vanguard calls it your 'Settlement fund'. It is generally cash or a
     /// money market fund.
     pub is_cash: bool,
+    /// The currency that the investment is denominated in, if it is not the
+    /// account's base currency. `current_value` is always expressed in the
+    /// base currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+    /// The rate used to convert one unit of `currency` into the account's base
+    /// currency
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exchange_rate: Option<f32>,
+    /// The date on which a fixed-maturity instrument (e.g. a CD or a treasury
+    /// bill) matures. Such holdings can't be sold before they mature.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub maturity: Option<Date>,
+    /// The number of shares (or units) held, if the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub quantity: Option<f32>,
+    /// The most recent price of a single share, if the provider reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub price: Option<Dollar>,
+    /// The total amount paid for the shares that are held, if the provider
+    /// reports it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cost_basis: Option<Dollar>,
+    /// The date on which the shares that are held were bought, if it is known
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub acquired: Option<Date>,
+    /// Whether this is activity that hasn't settled yet (e.g. a deposit or an
+    /// unsettled trade) rather than an investment. How it is treated is up to
+    /// [AllocationConfig::pending_activity].
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pending: bool,
+}
+
+impl Holding {
+    /// The gain (or loss, if negative) that would be realized by selling the
+    /// entire holding, if its cost basis is known
+    pub fn unrealized_gain(&self) -> Option<Dollar> {
+        self.cost_basis.map(|basis| self.current_value - basis)
+    }
+
+    /// The date from which gains on the holding are long-term (one year after
+    /// it was acquired), if the acquisition date is known
+    pub fn long_term_date(&self) -> Option<Date> {
+        let acquired = self.acquired?;
+        // a holding bought on February 29th is long-term on March 1st
+        Date::new(acquired.year() + 1, acquired.month(), acquired.day())
+            .or_else(|| Date::new(acquired.year() + 1, 3, 1))
+    }
+
+    /// The value of a single share in the account's base currency, if it is
+    /// known from the quantity or the price that the provider reported
+    pub fn share_price(&self) -> Option<Dollar> {
+        match (self.quantity, self.price) {
+            (Some(quantity), _) if quantity != 0.0 => Some(self.current_value / quantity),
+            (_, Some(price)) if price.0 != 0.0 => Some(price * self.exchange_rate.unwrap_or(1.0)),
+            _ => None,
+        }
+    }
 }
 
 /// A description of a current holding and what needs to be done to align it
@@ -50,440 +122,6027 @@ pub struct PositionAdjustment {
     pub target: Percent,
     pub ignored: bool,
     pub action: Action,
+    /// The value that the plan aims for this holding to have, before the
+    /// trades are rounded to whole cents
+    pub desired_value: Dollar,
+    /// Why the trade that would bring this holding to its desired value was
+    /// left out of the plan, if it was
+    pub held: Option<HoldReason>,
+    /// The date from which gains on the holding are long-term, if they are
+    /// still short-term on the date of the plan
+    pub short_term_until: Option<Date>,
 }
 
-/// A definition of the desired state of the cash sweep within a given brokerage account
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-#[serde(rename_all = "PascalCase", deny_unknown_fields)]
-pub struct CashConfig {
-    /// The fund that represents the cash sweep (perhaps a money market fund)
-    pub symbol: String,
-    /// Minimum amount to retain in the core position in dollars
-    pub minimum: Dollar,
+/// Why a trade was left out of a plan
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum HoldReason {
+    /// The holding is within its tolerance band
+    WithinBand,
+    /// The trade would be smaller than the minimum trade
+    BelowMinimumTrade,
+    /// Selling would realize short-term gains
+    ShortTermGain,
+    /// The holding is within its target range
+    WithinRange,
 }
 
-/// A definition of the desired allocations for a given brokerage account
-#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
-#[serde(rename_all = "PascalCase", deny_unknown_fields)]
-pub struct AllocationConfig {
-    /// The account that is being configured
+impl PositionAdjustment {
+    /// The value of the holding after the action has been carried out
+    pub fn result(&self) -> Dollar {
+        self.holding.current_value + &self.action
+    }
+
+    /// The gain (or loss, if negative) that selling would realize, estimated
+    /// from the average cost basis of the holding. `None` if the action isn't
+    /// a sell or the cost basis isn't known.
+    pub fn realized_gain(&self) -> Option<Dollar> {
+        let Action::Sell(amount) = self.action else {
+            return None;
+        };
+        if self.holding.is_cash || self.holding.current_value <= Dollar(0.0) {
+            return None;
+        }
+        let gain = self.holding.unrealized_gain()?;
+        Some(gain * (amount.0 / self.holding.current_value.0))
+    }
+
+    /// The number of shares to buy or sell to carry out the action, if the
+    /// price of a share is known. Cash is traded in dollars, so it has none.
+    pub fn shares(&self) -> Option<f32> {
+        if self.holding.is_cash || matches!(self.action, Action::DoNothing) {
+            return None;
+        }
+        let price = self.holding.share_price()?;
+        Some(self.action.change().0.abs() / price.0)
+    }
+}
+
+/// The full set of adjustments recommended for a single account
+#[derive(Debug, Default)]
+pub struct RebalancePlan {
+    /// The account that this plan applies to
     pub account_id: String,
-    /// A nickname for the account
-    pub nickname: Option<String>,
-    /// the desired state of the cash sweep for this account
-    #[serde(default)]
-    pub cash_sweep: Option<CashConfig>,
-    /// The desired target allocation for specific holdings within this account.
-    /// The percentages for all targets should add up to exactly 100%
-    pub targets: HashMap<String, Percent>,
-    /// Any symbols listed here will be ignored from all analysis
-    #[serde(default)]
-    pub ignored_holdings: Vec<String>,
+    /// The adjustments for each holding (and target) in the account
+    pub adjustments: Vec<PositionAdjustment>,
+    /// Symbols of holdings with no value that were left out of the plan
+    pub empty_holdings: Vec<String>,
+    /// Fixed-maturity holdings that can't be sold yet, ordered by maturity
+    /// date. Their value will become investable cash once they mature.
+    pub upcoming_maturities: Vec<Holding>,
+    /// The total amount that was rounded away or suppressed from the trades
+    /// in this plan. This amount remains in the cash position (if there is
+    /// one) in addition to its target value.
+    pub residual: Dollar,
+    /// Recoverable problems found while calculating the plan, such as
+    /// symbols that the configuration doesn't know about
+    pub warnings: Vec<String>,
+    /// How the adjustments were calculated
+    pub explanation: PlanExplanation,
+    /// The new money that is invested by this plan, if it only invests new
+    /// money rather than rebalancing (see
+    /// [AllocationConfig::plan_contribution]). Nothing is sold in such plans.
+    pub contribution: Option<Dollar>,
+    /// The amount that is raised in the cash sweep to be withdrawn from the
+    /// account, if the plan only sells to raise cash (see
+    /// [AllocationConfig::plan_withdrawal]). Nothing is bought in such plans.
+    pub withdrawal: Option<Dollar>,
+    /// The holdings whose sells were reduced to keep the realized gains of
+    /// the plan under [AllocationConfig::max_realized_gains]
+    pub gains_limited: Vec<String>,
+    /// The other cash-like holdings whose value is included in the cash
+    /// position (see [CashConfig::other_symbols])
+    pub other_cash: Vec<Holding>,
+    /// Whether less is sold (and so bought) in this plan than the targets call
+    /// for (see [Self::reduce_buys])
+    pub buys_reduced: bool,
+    /// The most that this plan was allowed to trade, if its trades had to be
+    /// reduced to stay within it (see [AllocationConfig::max_turnover])
+    pub turnover_limit: Option<Dollar>,
 }
 
-impl AllocationConfig {
-    /// Ensure that the target allocations are reasonable
-    fn validate(&self) -> anyhow::Result<()> {
-        let total_percent: Percent = self.targets.values().sum();
-        anyhow::ensure!(
-            total_percent == Percent(100.0),
-            "Target allocations for account {} do not add up to 100%",
-            self.account_id
-        );
-        Ok(())
+/// The intermediate values that were used to calculate a plan, so that the
+/// plan can be checked by hand
+#[derive(Debug, Default, Clone, PartialEq)]
+pub struct PlanExplanation {
+    /// The value of all holdings that aren't ignored, which is distributed
+    /// according to the targets
+    pub distributable: Dollar,
+    /// The minimum value of the cash sweep
+    pub cash_minimum: Dollar,
+    /// The target allocation of the cash sweep, if the account has one
+    pub cash_target: Option<Percent>,
+    /// Whether the cash minimum is more than the cash target, in which case the
+    /// rest of the holdings share what is left after the minimum
+    pub cash_minimum_enforced: bool,
+    /// The value that the cash sweep should have
+    pub cash_desired: Dollar,
+}
+
+/// The share of an account that is exposed to an asset class, looking through
+/// funds to what they hold (see [AllocationConfig::fund_compositions])
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Exposure {
+    pub class: String,
+    /// The exposure before rebalancing
+    pub current: Percent,
+    /// The exposure that the targets of the holdings add up to, after
+    /// setting aside the cash minimum
+    pub target: Percent,
+    /// The exposure after rebalancing
+    pub result: Percent,
+}
+
+impl Exposure {
+    /// How far the current exposure is from the target
+    pub fn drift(&self) -> RelativePercent {
+        RelativePercent(self.current - self.target)
     }
+}
 
-    /// Load a series of [Config] objects from the given yaml file path
-    pub fn load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<Self>> {
-        let targets_file = match std::fs::File::open(path.as_ref()) {
-            Ok(f) => Ok(f),
-            Err(e) if e.kind() == ErrorKind::NotFound => Err(anyhow!(
-                "Please configure target allocations first. See help for more information."
-            )),
-            e => e.with_context(|| format!("Failed to open file {:?}", path.as_ref())),
-        }?;
-        let targets: Vec<Self> = serde_yaml::from_reader(targets_file)?;
-        targets
-            .into_iter()
-            .map(|t| {
-                t.validate()?;
-                Ok(t)
+/// One step of carrying out a plan, in the order given by
+/// [RebalancePlan::steps]
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "step", rename_all = "snake_case")]
+pub enum Step {
+    Sell {
+        symbol: String,
+        amount: Dollar,
+    },
+    /// Wait for the sells to settle, because the buys that follow are paid
+    /// for with `needed` of their proceeds
+    WaitForSettlement {
+        needed: Dollar,
+    },
+    Buy {
+        symbol: String,
+        amount: Dollar,
+    },
+}
+
+impl Display for Step {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Sell { symbol, amount } => write!(f, "Sell {symbol} {amount}"),
+            Self::WaitForSettlement { needed } => write!(
+                f,
+                "Wait for the sells to settle, since the buys use {needed} of their proceeds"
+            ),
+            Self::Buy { symbol, amount } => write!(f, "Buy {symbol} {amount}"),
+        }
+    }
+}
+
+/// A single order that sells one mutual fund and uses the proceeds to buy
+/// another, the way that brokerages typically let you trade mutual funds
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Exchange {
+    pub from: String,
+    pub to: String,
+    pub amount: Dollar,
+}
+
+impl Display for Exchange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "Exchange {} → {} {}", self.from, self.to, self.amount)
+    }
+}
+
+/// A dollar value as a whole number of cents
+fn dollar_to_cents(value: Dollar) -> i64 {
+    (f64::from(value.0) * 100.0).round() as i64
+}
+
+/// A whole number of cents as a dollar value
+fn cents_to_dollar(cents: i64) -> Dollar {
+    Dollar((cents as f64 / 100.0) as f32)
+}
+
+/// Divide `available` between holdings, given as their current value and their
+/// target, so that the holdings that are furthest below their targets are
+/// bought first, until they are all the same share of the way to their
+/// targets. Returns the amount to buy of each holding, in the same order.
+fn fill_underweight(holdings: &[(Dollar, Percent)], available: Dollar) -> Vec<Dollar> {
+    // the holdings that are bought all end up at `level` times their target,
+    // where `level` is the value that the total would have if every holding
+    // were at its target
+    let mut order: Vec<_> = (0..holdings.len())
+        .filter(|&i| holdings[i].1 > Percent(0.0))
+        .collect();
+    let ratio = |i: usize| holdings[i].0.0 / holdings[i].1.0;
+    order.sort_by(|&a, &b| ratio(a).total_cmp(&ratio(b)));
+    let mut level = 0.0;
+    let (mut value, mut target) = (available.0, 0.0);
+    for (n, &i) in order.iter().enumerate() {
+        value += holdings[i].0.0;
+        target += holdings[i].1.0;
+        level = value / target;
+        if order.get(n + 1).is_none_or(|&next| level <= ratio(next)) {
+            break;
+        }
+    }
+    holdings
+        .iter()
+        .map(|(current, target)| Dollar((level * target.0 - current.0).max(0.0)))
+        .collect()
+}
+
+/// Divide `needed` between holdings, given as their current value and their
+/// target, so that holdings without a target are sold first, then the holdings
+/// that are furthest above their targets, until they are all the same share of
+/// the way to their targets. Returns the amount to sell of each holding, in the
+/// same order.
+fn drain_overweight(holdings: &[(Dollar, Percent)], needed: Dollar) -> Vec<Dollar> {
+    let mut sells = vec![Dollar(0.0); holdings.len()];
+    let mut needed = needed.0;
+    for (i, (current, _)) in holdings
+        .iter()
+        .enumerate()
+        .filter(|(_, (_, target))| *target <= Percent(0.0))
+    {
+        let sell = current.0.min(needed).max(0.0);
+        sells[i] = Dollar(sell);
+        needed -= sell;
+    }
+    if needed <= 0.0 {
+        return sells;
+    }
+    // the holdings that are sold all end up at `level` times their target
+    let mut order: Vec<_> = (0..holdings.len())
+        .filter(|&i| holdings[i].1 > Percent(0.0))
+        .collect();
+    let ratio = |i: usize| holdings[i].0.0 / holdings[i].1.0;
+    order.sort_by(|&a, &b| ratio(b).total_cmp(&ratio(a)));
+    let mut level = 0.0;
+    let (mut value, mut target) = (-needed, 0.0);
+    for (n, &i) in order.iter().enumerate() {
+        value += holdings[i].0.0;
+        target += holdings[i].1.0;
+        level = (value / target).max(0.0);
+        if order.get(n + 1).is_none_or(|&next| level >= ratio(next)) {
+            break;
+        }
+    }
+    for &i in order.iter() {
+        let (current, target) = holdings[i];
+        sells[i] = Dollar((current.0 - level * target.0).max(0.0));
+    }
+    sells
+}
+
+/// Whether the given symbol looks like a US mutual fund ticker (five letters
+/// ending in 'X', e.g. 'FXAIX')
+pub fn is_mutual_fund(symbol: &str) -> bool {
+    symbol.len() == 5 && symbol.chars().all(|c| c.is_ascii_uppercase()) && symbol.ends_with('X')
+}
+
+impl RebalancePlan {
+    /// How far each holding is from its target allocation before the plan is
+    /// carried out, in the same order as [Self::adjustments]. Targets are
+    /// shares of the whole account (including whatever they leave for the cash
+    /// sweep), so the drift of each invested holding is its share of the
+    /// account minus its target. When the cash minimum is more than the cash
+    /// target, the targets of the invested holdings are scaled down to share
+    /// what is left, as they are in the plan. Cash and ignored holdings have no
+    /// drift.
+    pub fn drift(&self) -> Vec<Option<RelativePercent>> {
+        let invested = |adj: &&PositionAdjustment| !adj.ignored && !adj.holding.is_cash;
+        let total: Dollar = self
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.ignored)
+            .map(|adj| adj.holding.current_value)
+            .sum();
+        let invested_target: Percent = self
+            .adjustments
+            .iter()
+            .filter(invested)
+            .map(|adj| &adj.target)
+            .sum();
+        let explanation = &self.explanation;
+        let cash_share = Percent::new(explanation.cash_desired, explanation.distributable);
+        let available = Percent(100.0) - cash_share;
+        let scale = match available < invested_target && invested_target > Percent(0.0) {
+            true => available / invested_target,
+            false => Percent(100.0),
+        };
+        self.adjustments
+            .iter()
+            .map(|adj| {
+                invested(&adj).then(|| {
+                    RelativePercent(
+                        Percent::new(adj.holding.current_value, total) - adj.target * scale,
+                    )
+                })
             })
             .collect()
     }
 
-    fn cash_minimum(&self) -> Dollar {
-        self.cash_sweep
-            .as_ref()
-            .map(|cash| cash.minimum)
-            .unwrap_or_default()
+    /// Whether this plan recommends carrying out any trades. When the account
+    /// has a [RebalancePolicy], its `status` has to warrant a rebalance as well.
+    pub fn recommends_rebalance(&self, status: Option<&RebalanceStatus>) -> bool {
+        status.is_none_or(|status| status.warranted)
+            && self
+                .adjustments
+                .iter()
+                .any(|adj| !matches!(adj.action, Action::DoNothing))
     }
-    /// Compare this configuration with the given `balance` and calculate what adjustments need to be
-    /// made in order to align the balance with the desired target allocations
-    pub fn adjust_allocations(&self, balance: &Balance) -> anyhow::Result<Vec<PositionAdjustment>> {
-        anyhow::ensure!(
-            self.account_id == balance.account_id,
-            "The target configuration doesn't apply to this account"
-        );
-        let cash_fallback = self.cash_sweep.as_ref().map(|sweep| Holding {
-            symbol: sweep.symbol.clone(),
-            current_value: Dollar(0.0),
-            is_cash: true,
-        });
-        let cash_sweep = balance
-            .holdings
+
+    /// How far the invested holdings are from their targets as a whole: the
+    /// share of the account that is allocated differently than the
+    /// targets, or `None` if nothing is invested. Every point that one holding
+    /// is over its target is a point that others are under, so this is half
+    /// of the sum of the absolute drifts.
+    pub fn drift_score(&self) -> Option<Percent> {
+        let drifts: Vec<_> = self.drift().into_iter().flatten().collect();
+        (!drifts.is_empty())
+            .then(|| Percent(drifts.iter().map(|drift| drift.0.0.abs()).sum::<f32>() / 2.0))
+    }
+
+    /// The gains (or losses, if negative) that the sells in this plan would
+    /// realize, estimated from the cost basis of each holding, or `None` if
+    /// the cost basis of none of the sold holdings is known
+    pub fn realized_gains(&self) -> Option<Dollar> {
+        self.adjustments
             .iter()
-            .find(|&pos| Some(&pos.symbol) == self.cash_sweep.as_ref().map(|s| &s.symbol))
-            .or(cash_fallback.as_ref());
-        debug!(?cash_sweep, ?balance);
+            .filter(|adj| !adj.ignored)
+            .filter_map(|adj| adj.realized_gain())
+            .fold(None, |total, gain| Some(total.unwrap_or_default() + gain))
+    }
 
-        // make sure the output contains information about all holdings in the
-        // account balance
-        let mut adjustments: HashMap<String, PositionAdjustment> = HashMap::new();
-        for holding in balance.holdings.iter() {
-            let ignored = self.ignored_holdings.contains(&holding.symbol);
-            adjustments.insert(
-                holding.symbol.clone(),
-                PositionAdjustment {
-                    holding: holding.clone(),
-                    ignored,
-                    ..Default::default()
+    /// The part of [Self::realized_gains] that is short-term, or `None` if no
+    /// sold holding is known to be short-term
+    pub fn short_term_gains(&self) -> Option<Dollar> {
+        self.adjustments
+            .iter()
+            .filter(|adj| !adj.ignored && adj.short_term_until.is_some())
+            .filter_map(|adj| adj.realized_gain())
+            .fold(None, |total, gain| Some(total.unwrap_or_default() + gain))
+    }
+
+    /// The cash that the buys of this plan can use before any sells settle:
+    /// the cash position (if it isn't negative) and any contribution
+    pub fn settled_cash(&self) -> Dollar {
+        let cash = self
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+            .map(|adj| adj.holding.current_value.max(Dollar(0.0)))
+            .unwrap_or_default();
+        cash + self.contribution.unwrap_or_default()
+    }
+
+    /// The steps that carry out this plan: the sells first, largest first,
+    /// then (if the buys need the proceeds of the sells) waiting for the
+    /// sells to settle, and then the buys, largest first
+    pub fn steps(&self) -> Vec<Step> {
+        let trades = |buys: bool| {
+            let mut trades: Vec<_> = self
+                .adjustments
+                .iter()
+                .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+                .filter_map(|adj| match adj.action {
+                    Action::Buy(amount) if buys => Some((adj.holding.symbol.clone(), amount)),
+                    Action::Sell(amount) if !buys => Some((adj.holding.symbol.clone(), amount)),
+                    _ => None,
+                })
+                .collect();
+            trades.sort_by(|a, b| b.1.0.total_cmp(&a.1.0));
+            trades
+        };
+        let (sells, buys) = (trades(false), trades(true));
+        let bought = buys
+            .iter()
+            .fold(Dollar(0.0), |total, (_, amount)| total + *amount);
+        let needed = (bought - self.settled_cash()).round_cents();
+        let mut steps: Vec<_> = sells
+            .into_iter()
+            .map(|(symbol, amount)| Step::Sell { symbol, amount })
+            .collect();
+        if needed > Dollar(0.0) && !steps.is_empty() {
+            steps.push(Step::WaitForSettlement { needed });
+        }
+        steps.extend(
+            buys.into_iter()
+                .map(|(symbol, amount)| Step::Buy { symbol, amount }),
+        );
+        steps
+    }
+
+    /// How much more the buys of this plan cost than the proceeds of its
+    /// sells and the [Self::settled_cash] can pay for, or `None` if they are
+    /// covered
+    pub fn unfunded(&self) -> Option<Dollar> {
+        let (bought, sold) = self
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+            .fold(
+                (Dollar(0.0), Dollar(0.0)),
+                |(bought, sold), adj| match adj.action {
+                    Action::Buy(amount) => (bought + amount, sold),
+                    Action::Sell(amount) => (bought, sold + amount),
+                    Action::DoNothing => (bought, sold),
                 },
             );
-        }
+        let unfunded = (bought - sold - self.settled_cash()).round_cents();
+        (unfunded > Dollar(0.0)).then_some(unfunded)
+    }
 
-        // make sure the output contains information about all targets in the
-        // allocation configuration
-        for (target_symbol, &target_percent) in self.targets.iter() {
-            adjustments
-                .entry(target_symbol.clone())
-                .and_modify(|e| e.target = target_percent)
-                .or_insert(PositionAdjustment {
-                    target: target_percent,
-                    holding: Holding {
-                        symbol: target_symbol.clone(),
-                        ..Default::default()
-                    },
-                    ..Default::default()
-                });
+    /// The total amount that is bought and sold in this plan, not counting
+    /// the cash position
+    pub fn turnover(&self) -> Dollar {
+        self.adjustments
+            .iter()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+            .fold(Dollar(0.0), |total, adj| total + adj.action.change().abs())
+    }
+
+    /// Leave out the trade of the holding at `index`, which is then absorbed
+    /// by the cash position (or added to [Self::residual] if there is none).
+    /// A sell isn't left out if the cash position would end up with less than
+    /// `cash_minimum` without it. Returns whether the trade was left out.
+    pub fn hold_trade(&mut self, index: usize, reason: HoldReason, cash_minimum: Dollar) -> bool {
+        if self.adjustments[index].action.change() == Dollar(0.0)
+            || !self.change_trade(index, Dollar(0.0), cash_minimum)
+        {
+            return false;
         }
+        self.adjustments[index].held = Some(reason);
+        true
+    }
 
-        if let Some(cash_sweep) = cash_sweep {
-            // make sure the output contains a cash sweep if a cash minimum is specified
-            adjustments
-                .entry(cash_sweep.symbol.to_owned())
-                .and_modify(|e| e.holding.current_value = cash_sweep.current_value)
-                .or_insert(PositionAdjustment {
-                    holding: cash_sweep.clone(),
-                    ignored: self.ignored_holdings.contains(&cash_sweep.symbol),
-                    ..Default::default()
-                });
+    /// Change the trade of the holding at `index` to `change`, and move the
+    /// difference into the cash position (or [Self::residual] if there is no
+    /// cash position). The trade isn't changed if that would take the cash
+    /// position below `cash_minimum`. Returns whether the trade was changed.
+    pub fn change_trade(&mut self, index: usize, change: Dollar, cash_minimum: Dollar) -> bool {
+        let difference = self.adjustments[index].action.change() - change;
+        match self
+            .adjustments
+            .iter_mut()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+        {
+            Some(cash) => {
+                let cash_change = cash.action.change() + difference;
+                if difference < Dollar(0.0)
+                    && cash.holding.current_value + cash_change < cash_minimum
+                {
+                    return false;
+                }
+                cash.action = Action::from_change(cash_change);
+            }
+            None => self.residual += difference,
         }
+        self.adjustments[index].action = Action::from_change(change);
+        true
+    }
 
-        for (symbol, adj) in adjustments.iter() {
-            if adj.ignored && adj.target != Percent(0.0) {
-                bail!("Can't ignore symbol '{symbol}': it is specified in the target allocation")
+    /// Reduce the buys in the plan by `amount` in total, in proportion to
+    /// their size, because that much less is sold to pay for them. The cash
+    /// position is then set to pay for whatever is still traded.
+    pub fn reduce_buys(&mut self, amount: Dollar) {
+        self.reduce_buys_except(amount, &[]);
+    }
+
+    /// Like [Self::reduce_buys], but the buys of the holdings at the indexes
+    /// in `kept` aren't reduced. Whatever the other buys can't make up for is
+    /// paid for by the cash position.
+    fn reduce_buys_except(&mut self, amount: Dollar, kept: &[usize]) {
+        let reducible = |(i, adj): &(usize, &mut PositionAdjustment)| {
+            !adj.ignored && !adj.holding.is_cash && !kept.contains(i)
+        };
+        let bought = self
+            .adjustments
+            .iter_mut()
+            .enumerate()
+            .filter(reducible)
+            .filter_map(|(_, adj)| match adj.action {
+                Action::Buy(amount) => Some(amount),
+                _ => None,
+            })
+            .fold(Dollar(0.0), |total, amount| total + amount);
+        if amount > Dollar(0.0) {
+            let scale = match bought > Dollar(0.0) {
+                true => ((bought - amount).0 / bought.0).max(0.0),
+                false => 1.0,
+            };
+            for (_, adj) in self.adjustments.iter_mut().enumerate().filter(reducible) {
+                if let Action::Buy(amount) = adj.action {
+                    adj.action = Action::Buy(amount * scale);
+                }
             }
+            self.buys_reduced = true;
         }
+        self.balance_cash(Dollar(0.0));
+    }
 
-        let total_val = adjustments
+    /// Set the action of the cash position (if there is one) to pay for the
+    /// trades of the other holdings, with `inflow` of new money
+    fn balance_cash(&mut self, inflow: Dollar) {
+        let net = self
+            .adjustments
             .iter()
-            .filter_map(|(_, adj)| {
-                if adj.ignored {
-                    None
-                } else {
-                    Some(adj.holding.current_value)
-                }
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+            .fold(Dollar(0.0), |net, adj| net + adj.action.change());
+        if let Some(cash) = self
+            .adjustments
+            .iter_mut()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+        {
+            cash.action = Action::from_change(inflow - net);
+            cash.desired_value = cash.holding.current_value + inflow - net;
+        }
+    }
+
+    /// Round all non-cash trades to whole cents and adjust the cash position
+    /// so that every dollar is accounted for, and the trades (including the
+    /// change of the cash position) add up to exactly the contribution (see
+    /// [Self::net]). Without a cash position, the cents that rounding leaves
+    /// over are given to the trades that were rounded the most, so that the
+    /// trades still add up to what they did before they were rounded. The
+    /// difference between what the cash position ends up with and what it
+    /// would have had otherwise is added to [Self::residual].
+    pub fn settle_residual(&mut self) {
+        // whole cents are added up as integers, so that they add up exactly
+        let mut trades: Vec<(usize, f64, i64)> = self
+            .adjustments
+            .iter()
+            .enumerate()
+            .filter(|(_, adj)| !adj.ignored && !adj.holding.is_cash)
+            .map(|(i, adj)| {
+                let cents = f64::from(adj.action.change().0) * 100.0;
+                (i, cents, cents.round() as i64)
             })
-            .sum::<Dollar>();
-        let cash_target = adjustments
-            .values()
-            .find(|v| v.holding.is_cash)
-            .map(|adj| adj.target);
-        let cash_desired = self
-            .cash_minimum()
-            .max(cash_target.map(|t| total_val * t).unwrap_or_default());
-        debug!(?cash_target, ?cash_desired);
+            .collect();
+        let exact: f64 = trades.iter().map(|(_, cents, _)| cents).sum();
+        let mut net: i64 = trades.iter().map(|(_, _, rounded)| rounded).sum();
+        let cash = self
+            .adjustments
+            .iter()
+            .position(|adj| !adj.ignored && adj.holding.is_cash);
+        if cash.is_none() {
+            let leftover = exact.round() as i64 - net;
+            // the trades that were rounded down the most get the cents that are
+            // missing, and vice versa
+            trades.sort_by(|a, b| {
+                let (a, b) = ((a.1 - a.2 as f64), (b.1 - b.2 as f64));
+                match leftover > 0 {
+                    true => b.total_cmp(&a),
+                    false => a.total_cmp(&b),
+                }
+            });
+            for trade in trades.iter_mut().take(leftover.unsigned_abs() as usize) {
+                trade.2 += leftover.signum();
+            }
+            net += leftover;
+            self.residual += Dollar(((exact - net as f64) / 100.0) as f32);
+        }
+        for (i, _, rounded) in trades {
+            self.adjustments[i].action = Action::from_change(cents_to_dollar(rounded));
+        }
+        if let Some(cash) = cash {
+            let cash = &mut self.adjustments[cash];
+            let before = cash.action.change();
+            let contribution = dollar_to_cents(self.contribution.unwrap_or_default());
+            cash.action = Action::from_change(cents_to_dollar(contribution - net));
+            self.residual += cash.action.change() - before;
+        }
+        debug!(?self.residual, "settled residual");
+    }
 
-        // make sure there is only a single cash holding in the list
-        anyhow::ensure!(
-            adjustments
-                .iter()
-                .filter(|(_k, v)| v.holding.is_cash)
-                .count()
-                <= 1,
-            "Account should contain at most 1 cash holding"
-        );
+    /// What the trades of this plan (including the change of the cash
+    /// position) add to the account beyond the contribution, added up in
+    /// whole cents. This is exactly zero for every plan that has a cash
+    /// position once it is settled (see [Self::settle_residual]).
+    pub fn net(&self) -> Dollar {
+        let net: i64 = self
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.ignored)
+            .map(|adj| dollar_to_cents(adj.action.change()))
+            .sum();
+        cents_to_dollar(net - dollar_to_cents(self.contribution.unwrap_or_default()))
+    }
 
-        let mut adjustments: Vec<_> = adjustments
-            .into_values()
-            .map(|mut adj| {
-                let action = if adj.ignored {
-                    Action::DoNothing
-                } else {
-                    let mut desired_val = total_val * adj.target;
-                    debug!(?desired_val, ?adj);
-                    if adj.holding.is_cash {
-                        desired_val = cash_desired;
-                        debug!(?desired_val, "Setting cash val");
-                    } else {
-                        // if the minimum cash position was enforced, that
-                        // leaves less to allocate for other holdings, so we
-                        // allocate the rest of the holdings proportionally to
-                        // their targets, even if they can't be acheived
-                        if cash_desired == self.cash_minimum() {
-                            let remainder = total_val - cash_desired;
-                            let noncash_pct = Percent(100.0) - cash_target.unwrap_or_default();
-                            let pct = adj.target / noncash_pct;
-                            desired_val = remainder * pct;
-                            debug!(
-                                ?remainder,
-                                ?noncash_pct,
-                                ?pct,
-                                ?desired_val,
-                                ?adj.target,
-                                "Setting adjusted non-cash val"
-                            );
-                        } else {
-                            desired_val = total_val * adj.target;
-                            debug!(?desired_val, "Setting non-cash val");
-                        }
-                    }
-                    debug!(?desired_val, ?adj.holding.current_value, "setting action");
-                    match desired_val - adj.holding.current_value {
-                        val if val > Dollar(0.0) => Action::Buy(val.abs()),
-                        val if val < Dollar(0.0) => Action::Sell(val.abs()),
-                        _ => Action::DoNothing,
+    /// Pair up the mutual fund sells and buys in this plan into exchange
+    /// orders. The largest sells are matched with the largest buys first, and
+    /// whatever can't be matched must still be traded separately. Cash
+    /// holdings are never part of an exchange.
+    pub fn exchanges(&self) -> Vec<Exchange> {
+        let funds = |want_sell: bool| {
+            let mut funds: Vec<(String, Dollar)> = self
+                .adjustments
+                .iter()
+                .filter(|adj| !adj.holding.is_cash && is_mutual_fund(&adj.holding.symbol))
+                .filter_map(|adj| match (&adj.action, want_sell) {
+                    (Action::Sell(val), true) | (Action::Buy(val), false) => {
+                        Some((adj.holding.symbol.clone(), *val))
                     }
-                };
-                adj.action = action;
-                adj
-            })
-            .collect();
-        // sort core position first, then by current value, then by symbol name
-        adjustments.sort_by(|a, b| match b.holding.is_cash.cmp(&a.holding.is_cash) {
-            std::cmp::Ordering::Equal => match a
-                .holding
-                .current_value
-                .partial_cmp(&b.holding.current_value)
-                .unwrap_or(std::cmp::Ordering::Equal)
-                .reverse()
+                    _ => None,
+                })
+                .collect();
+            funds.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal));
+            funds
+        };
+        let mut sells = funds(true);
+        let mut buys = funds(false);
+        let mut exchanges = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < sells.len() && j < buys.len() {
+            let amount = sells[i].1.min(buys[j].1);
+            exchanges.push(Exchange {
+                from: sells[i].0.clone(),
+                to: buys[j].0.clone(),
+                amount,
+            });
+            sells[i].1 = sells[i].1 - amount;
+            buys[j].1 = buys[j].1 - amount;
+            if sells[i].1 <= Dollar(0.0) {
+                i += 1;
+            }
+            if buys[j].1 <= Dollar(0.0) {
+                j += 1;
+            }
+        }
+        exchanges
+    }
+
+    /// Produce the balance that would result from carrying out every action
+    /// in this plan against the given `balance`. Holdings that are not
+    /// mentioned in the plan are carried over unchanged, and targets that are
+    /// not yet held are added as new holdings if the plan buys them.
+    pub fn apply(&self, balance: &Balance) -> Balance {
+        let mut projected = balance.clone();
+        for holding in projected.holdings.iter_mut() {
+            if let Some(adj) = self
+                .adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == holding.symbol)
             {
-                std::cmp::Ordering::Equal => a.holding.symbol.cmp(&b.holding.symbol),
-                res => res,
+                holding.current_value = holding.current_value + &adj.action;
+            }
+        }
+        // cash that is spent beyond what the cash sweep holds comes out of
+        // the other cash holdings
+        let is_other_cash =
+            |holding: &Holding| self.other_cash.iter().any(|o| o.symbol == holding.symbol);
+        let is_sweep = |holding: &Holding| {
+            !self.other_cash.is_empty()
+                && self
+                    .adjustments
+                    .iter()
+                    .any(|adj| adj.holding.is_cash && adj.holding.symbol == holding.symbol)
+        };
+        let mut shortfall = projected
+            .holdings
+            .iter()
+            .filter(|holding| is_sweep(holding))
+            .fold(Dollar(0.0), |total, holding| {
+                total - holding.current_value.min(Dollar(0.0))
+            });
+        for holding in projected.holdings.iter_mut() {
+            if is_sweep(holding) {
+                holding.current_value = holding.current_value.max(Dollar(0.0));
+            } else if is_other_cash(holding) {
+                let spent = shortfall.min(holding.current_value);
+                holding.current_value = holding.current_value - spent;
+                shortfall = shortfall - spent;
+            }
+        }
+        let other_cash = self
+            .other_cash
+            .iter()
+            .fold(Dollar(0.0), |total, holding| total + holding.current_value);
+        for adj in self.adjustments.iter() {
+            // the other cash holdings keep their own value
+            let result = match adj.holding.is_cash {
+                true => adj.result() - other_cash,
+                false => adj.result(),
+            };
+            if !balance
+                .holdings
+                .iter()
+                .any(|holding| holding.symbol == adj.holding.symbol)
+                && result > Dollar(0.0)
+            {
+                projected.holdings.push(Holding {
+                    current_value: result,
+                    ..adj.holding.clone()
+                });
+            }
+        }
+        projected
+    }
+}
+
+/// The format of a target allocation configuration file
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ConfigFormat {
+    #[default]
+    Yaml,
+    /// A TOML document with an `[[Accounts]]` table for each account
+    Toml,
+}
+
+impl ConfigFormat {
+    /// The format of the file at `path`, which is TOML if it has a `.toml`
+    /// extension and YAML otherwise
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension() {
+            Some(ext) if ext.eq_ignore_ascii_case("toml") => Self::Toml,
+            _ => Self::Yaml,
+        }
+    }
+}
+
+/// A configuration with named models that accounts can refer to. This is the
+/// top level of a TOML configuration, since a TOML document must be a table,
+/// while a YAML configuration may also be just the list of accounts.
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+struct ConfigDocument {
+    /// Target allocations that can be shared by several accounts (see
+    /// [AllocationConfig::model]), by name
+    #[serde(default)]
+    models: HashMap<String, HashMap<String, Percent>>,
+    accounts: Vec<AllocationConfig>,
+}
+
+/// A definition of the desired state of the cash sweep within a given brokerage account
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct CashConfig {
+    /// The fund that represents the cash sweep (perhaps a money market fund)
+    pub symbol: String,
+    /// Minimum amount to retain in the core position. This is expressed in
+    /// dollars unless `currency` is specified.
+    #[serde(default)]
+    pub minimum: Dollar,
+    /// Minimum share of the account's value to retain in the core position.
+    /// If `minimum` is also given, the core position keeps whichever is more.
+    #[serde(
+        default,
+        alias = "ReservePercent",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub minimum_percent: Option<Percent>,
+    /// Other cash-like holdings (e.g. a second money market fund) that are
+    /// counted as part of the cash sweep. They aren't traded, but their value
+    /// counts towards the minimum, and any trades go through `symbol`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub other_symbols: Vec<String>,
+    /// The currency of the cash position (e.g. "EUR"). When specified, only a
+    /// cash holding in this currency is considered to be the cash sweep, and
+    /// `minimum` is expressed in this currency.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub currency: Option<String>,
+}
+
+/// How far a holding may drift from its target before it is traded. A holding
+/// is within the band if its drift (see [RebalancePlan::drift]) is no larger
+/// than every limit that is given, e.g. both 5 percentage points and 25% of
+/// its target for the "5/25 rule".
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct ToleranceBand {
+    /// The largest tolerated drift, in percentage points
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub absolute: Option<Percent>,
+    /// The largest tolerated drift, as a percentage of the target
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub relative: Option<Percent>,
+}
+
+impl ToleranceBand {
+    /// Whether a holding with the given target and drift is within the band
+    pub fn contains(&self, target: Percent, drift: Percent) -> bool {
+        let drift = Percent(drift.0.abs());
+        (self.absolute.is_some() || self.relative.is_some())
+            && self.absolute.is_none_or(|limit| drift <= limit)
+            && self.relative.is_none_or(|limit| drift <= target * limit)
+    }
+}
+
+/// The range that a holding may be anywhere within, as percentages of the
+/// account (see [AllocationConfig::target_ranges])
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct TargetRange {
+    pub min: Percent,
+    pub max: Percent,
+}
+
+/// How often an account is rebalanced on a calendar
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq)]
+pub enum RebalanceFrequency {
+    Monthly,
+    Quarterly,
+    SemiAnnually,
+    Annually,
+}
+
+impl RebalanceFrequency {
+    /// The number of months between rebalances
+    pub fn months(&self) -> u32 {
+        match self {
+            Self::Monthly => 1,
+            Self::Quarterly => 3,
+            Self::SemiAnnually => 6,
+            Self::Annually => 12,
+        }
+    }
+}
+
+/// When an account should be rebalanced. A rebalance is warranted as soon as
+/// either of the triggers that are given is met.
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct RebalancePolicy {
+    /// Rebalance this often, counting from the last rebalance
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub every: Option<RebalanceFrequency>,
+    /// Rebalance once any holding drifts further than this from its target,
+    /// in percentage points (see [RebalancePlan::drift])
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_drift: Option<Percent>,
+}
+
+/// Whether the [RebalancePolicy] of an account currently calls for a rebalance
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RebalanceStatus {
+    pub warranted: bool,
+    /// Why a rebalance is warranted
+    pub reasons: Vec<String>,
+    /// The date on which a rebalance is next due on the calendar, if the
+    /// policy has a calendar trigger
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub next_date: Option<Date>,
+}
+
+/// What to do with activity that hasn't settled yet (see [Holding::pending])
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum PendingActivity {
+    /// Count it as part of the cash sweep, so that it is invested like the
+    /// rest of the cash
+    #[default]
+    Cash,
+    /// Show it as its own line, and leave it out of the value that is
+    /// distributed among the targets until it settles
+    Separate,
+}
+
+/// What to do with holdings that have a negative value (e.g. short positions
+/// or a margin debit) and no target allocation
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum NegativeHoldings {
+    /// Buy them back to zero, paying for it like any other trade. They count
+    /// towards the value of the account, so they reduce what is distributed
+    /// among the targets.
+    #[default]
+    Cover,
+    /// Leave them alone and out of the value of the account, like ignored
+    /// holdings
+    Ignore,
+}
+
+/// What to do with holdings that have no value and no target allocation
+/// (e.g. funds that have been sold but are still listed by the brokerage)
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ZeroValueHoldings {
+    /// Show them like any other holding
+    #[default]
+    Keep,
+    /// Leave them out of the plan entirely
+    Hide,
+    /// Leave them out of the plan, but list their symbols in
+    /// [RebalancePlan::empty_holdings]
+    Collapse,
+}
+
+/// An entry of [AllocationConfig::nested_targets], which is either the target
+/// of a single holding or of a group of targets
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(untagged)]
+pub enum NestedTarget {
+    Holding(Percent),
+    Group(TargetGroup),
+}
+
+/// A group of targets (e.g. "Equity") that together make up a share of the
+/// group that contains it
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct TargetGroup {
+    /// The share of the containing group, or of the account at the top level
+    pub percent: Percent,
+    /// The targets within the group, as shares of the group, which should add
+    /// up to 100%
+    pub targets: HashMap<String, NestedTarget>,
+}
+
+impl NestedTarget {
+    /// The share of the containing group
+    fn percent(&self) -> Percent {
+        match self {
+            NestedTarget::Holding(percent) => *percent,
+            NestedTarget::Group(group) => group.percent,
+        }
+    }
+
+    /// Add the targets of the holdings in `targets` to `flattened`, as shares
+    /// of the account, given the share of the account that `targets` make up
+    fn flatten(
+        targets: &HashMap<String, NestedTarget>,
+        share: f32,
+        flattened: &mut Vec<(String, Percent)>,
+    ) {
+        for (name, target) in targets.iter() {
+            match target {
+                NestedTarget::Holding(percent) => {
+                    flattened.push((name.clone(), Percent(percent.0 * share)))
+                }
+                NestedTarget::Group(group) => {
+                    Self::flatten(&group.targets, share * group.percent.0 / 100.0, flattened)
+                }
+            }
+        }
+    }
+}
+
+/// A point on the glide path of an account (see
+/// [AllocationConfig::glide_path]): the targets that apply on a date, or at
+/// an age of the account owner
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct GlidePathStep {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub date: Option<Date>,
+    /// The age at which these targets apply, counted from
+    /// [AllocationConfig::birth_date]
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub age: Option<u32>,
+    pub targets: HashMap<String, Percent>,
+}
+
+/// Limits on how the planner may trade a single holding
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct HoldingConstraints {
+    /// Never sell the holding (e.g. because selling it would realize large
+    /// capital gains). It can still be bought, and it still counts towards
+    /// the value of the account.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub no_sell: bool,
+    /// The smallest share of the account that the holding may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min: Option<Percent>,
+    /// The largest share of the account that the holding may have
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max: Option<Percent>,
+}
+
+/// How trades of holdings that can only be traded in whole shares are rounded
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Default)]
+pub enum ShareRounding {
+    /// Round to the nearest whole number of shares
+    #[default]
+    Nearest,
+    /// Round towards fewer shares, so that no trade is larger than planned
+    Down,
+}
+
+/// A definition of the desired allocations for a given brokerage account
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Default)]
+#[serde(rename_all = "PascalCase", deny_unknown_fields)]
+pub struct AllocationConfig {
+    /// The account that is being configured
+    pub account_id: String,
+    /// A nickname for the account, which is shown instead of the name that
+    /// the brokerage uses
+    #[serde(alias = "Name", default, skip_serializing_if = "Option::is_none")]
+    pub nickname: Option<String>,
+    /// Short names (e.g. "roth") that select this account with `--account`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub aliases: Vec<String>,
+    /// the desired state of the cash sweep for this account
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cash_sweep: Option<CashConfig>,
+    /// The name of a model in the configuration whose targets this account
+    /// uses. Any `targets` of the account are added to those of the model, and
+    /// take precedence for the same symbol. This is cleared once the model has
+    /// been applied when the configuration is loaded.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// The desired target allocation for specific holdings within this account.
+    /// The percentages for all targets should add up to 100%, or to less if
+    /// the rest is to be kept in the cash sweep
+    #[serde(default)]
+    pub targets: HashMap<String, Percent>,
+    /// Targets that are grouped into a hierarchy (e.g. Equity 70%, split
+    /// into US 60% and International 40%), in addition to `targets`. The
+    /// targets within each group are shares of the group.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub nested_targets: HashMap<String, NestedTarget>,
+    /// Targets that change over time, instead of `targets`. Between two
+    /// steps of the glide path, the targets move gradually from those of the
+    /// earlier step to those of the later one. Before the first step and after
+    /// the last, the targets of that step apply.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub glide_path: Vec<GlidePathStep>,
+    /// The birth date of the account owner, for glide path steps that are
+    /// given by age
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub birth_date: Option<Date>,
+    /// Holdings that should be worth a fixed amount (e.g. $10,000), by
+    /// symbol. These amounts are set aside first, and the percentage targets
+    /// apply to the rest of the account.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub fixed_targets: HashMap<String, Dollar>,
+    /// Scale the targets proportionally so that they add up to 100% when
+    /// they don't, instead of rejecting the configuration
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub normalize_targets: bool,
+    /// How far (in percentage points) the targets may be from adding up to
+    /// 100% before the configuration is rejected, to allow for rounding such
+    /// as 33.3/33.3/33.4. By default this is 0.01 percentage points.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub target_sum_tolerance: Option<Percent>,
+    /// Any symbols listed here will be ignored from all analysis, along with
+    /// any that are given with `--ignore`
+    #[serde(default, alias = "Ignore", skip_serializing_if = "Vec::is_empty")]
+    pub ignored_holdings: Vec<String>,
+    /// How to handle holdings with a value of zero that have no target allocation
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub zero_value_holdings: ZeroValueHoldings,
+    /// What to do with activity that hasn't settled yet
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub pending_activity: PendingActivity,
+    /// What to do with holdings that have a negative value and no target.
+    /// A negative cash position (e.g. a margin debit) is always paid off.
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub negative_holdings: NegativeHoldings,
+    /// When the account should be rebalanced. Plans then say whether a
+    /// rebalance is currently warranted.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rebalance_policy: Option<RebalancePolicy>,
+    /// Maturity dates for fixed-maturity instruments (e.g. CDs) that are held
+    /// in this account. These take precedence over any maturity date found in
+    /// the account balance data.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub maturities: HashMap<String, Date>,
+    /// Whether to combine mutual fund sells and buys into exchange orders
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub exchange_orders: bool,
+    /// Short descriptions of holdings (e.g. "US total market"), by symbol,
+    /// which are shown next to their symbols in plans
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub labels: HashMap<String, String>,
+    /// The asset class (e.g. "Stocks" or "Bonds") of holdings in this account,
+    /// by symbol. If any are given, plans are grouped by asset class.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub asset_classes: HashMap<String, String>,
+    /// Targets for whole asset classes (e.g. "Bonds"), in addition to the
+    /// targets for specific holdings. The target of a class is split among
+    /// the holdings in it, in proportion to their `class_weights`, or to their
+    /// current values if none of them are given a weight.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub class_targets: HashMap<String, Percent>,
+    /// The relative weights of holdings within their asset class, by symbol
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub class_weights: HashMap<String, f32>,
+    /// What funds in this account hold, as the share of each fund in each
+    /// asset class (e.g. a target-date fund that is 54% "US Stocks", 36%
+    /// "International Stocks" and 10% "Bonds"), by symbol. If any are given,
+    /// plans show the exposure of the account to each asset class.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub fund_compositions: HashMap<String, HashMap<String, Percent>>,
+    /// Holdings that are within this band of their targets aren't traded
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tolerance_band: Option<ToleranceBand>,
+    /// Tolerance bands for specific holdings, by symbol, which take precedence
+    /// over `tolerance_band`
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub tolerance_bands: HashMap<String, ToleranceBand>,
+    /// Ranges that holdings may be anywhere within, by symbol. Holdings within
+    /// their range aren't traded, and the others are only traded back to the
+    /// nearest end of their range rather than all the way to their target.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub target_ranges: HashMap<String, TargetRange>,
+    /// Trade as few holdings as possible while still bringing every holding
+    /// within its tolerance band (or target range), rather than trading every
+    /// holding all the way to its target
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub fewest_trades: bool,
+    /// Trades smaller than this are left out of plans, and the cash sweep
+    /// keeps the difference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub minimum_trade: Option<Dollar>,
+    /// Trades are rounded to a multiple of this amount (e.g. $50), and the
+    /// cash sweep keeps the difference
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub trade_increment: Option<Dollar>,
+    /// Limits on how specific holdings may be traded, by symbol
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub constraints: HashMap<String, HoldingConstraints>,
+    /// The dates on which holdings in this account were acquired, by symbol.
+    /// These take precedence over any acquisition date found in the account
+    /// balance data.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub acquisition_dates: HashMap<String, Date>,
+    /// Whether to leave out sells that would realize short-term gains, until
+    /// the holding has been held long enough for them to be long-term
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub defer_short_term_gains: bool,
+    /// Symbols that count towards the target of another symbol, by the symbol
+    /// that has the target (e.g. VTSAX and FSKAX for VTI, since they hold the
+    /// same investments). The target is split among the symbols that are held
+    /// in proportion to their values.
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub symbol_aliases: HashMap<String, Vec<String>>,
+    /// Groups of symbols that are substantially identical (e.g. two funds
+    /// that track the same index), so that buying one soon after selling
+    /// another at a loss is warned about as a possible wash sale
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub equivalent_symbols: Vec<Vec<String>>,
+    /// Sells are reduced so that a plan doesn't realize more than this amount
+    /// of gains, starting with the holdings that have the largest gains
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_realized_gains: Option<Dollar>,
+    /// The most that a plan may buy and sell in total. If the plan would trade
+    /// more, the trades of the holdings that are furthest from their targets
+    /// are reduced the least.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turnover: Option<Dollar>,
+    /// Like `max_turnover`, but as a percentage of the account. If both are
+    /// given, the smaller one applies.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_turnover_percent: Option<Percent>,
+    /// Holdings that can only be bought or sold in whole shares, by symbol.
+    /// Their trades are rounded to whole shares (when the share price is
+    /// known), and the cash sweep makes up the difference.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub whole_shares: Vec<String>,
+    /// How trades of `whole_shares` are rounded
+    #[serde(default, skip_serializing_if = "is_default")]
+    pub share_rounding: ShareRounding,
+}
+
+fn is_default<T: Default + PartialEq>(val: &T) -> bool {
+    *val == T::default()
+}
+
+impl AllocationConfig {
+    /// The asset class of `holding`, if asset classes are configured for this
+    /// account. Cash holdings are in the "Cash" class unless configured
+    /// otherwise, and other holdings without a class are in the "Other" class.
+    pub fn asset_class(&self, holding: &Holding) -> Option<&str> {
+        if self.asset_classes.is_empty() {
+            return None;
+        }
+        Some(match self.asset_classes.get(&holding.symbol) {
+            Some(class) => class,
+            None if holding.is_cash => "Cash",
+            None => "Other",
+        })
+    }
+
+    /// The holdings in the given asset class, by symbol
+    fn class_members(&self, class: &str) -> Vec<&str> {
+        let mut members: Vec<&str> = self
+            .asset_classes
+            .iter()
+            .filter(|(_, c)| *c == class)
+            .map(|(symbol, _)| symbol.as_str())
+            .collect();
+        if let Some(sweep) = &self.cash_sweep
+            && class == "Cash"
+            && !self.asset_classes.contains_key(&sweep.symbol)
+        {
+            members.push(&sweep.symbol);
+        }
+        members.retain(|symbol| !self.ignored_holdings.iter().any(|s| s == symbol));
+        members.sort();
+        members
+    }
+
+    /// The target of each holding, including the targets of the holdings in
+    /// asset classes that have a target. Holdings that can't be sold until
+    /// they mature don't get a share of the target of their class.
+    fn symbol_targets(&self, balance: &Balance, today: Date) -> HashMap<String, Percent> {
+        let mut targets = self.flattened_targets();
+        targets.extend(self.glide_path_targets(today).unwrap_or_default());
+        // the percentages of fixed targets depend on the value of the account
+        for symbol in self.fixed_targets.keys() {
+            targets.insert(symbol.clone(), Percent(0.0));
+        }
+        let value = |symbol: &str| {
+            balance
+                .holdings
+                .iter()
+                .filter(|holding| holding.symbol == symbol)
+                .fold(Dollar(0.0), |total, holding| total + holding.current_value)
+        };
+        for (class, &class_target) in self.class_targets.iter() {
+            let mut members = self.class_members(class);
+            members.retain(|symbol| {
+                let maturity = self.maturities.get(*symbol).copied().or_else(|| {
+                    balance
+                        .holdings
+                        .iter()
+                        .find(|holding| holding.symbol == *symbol)
+                        .and_then(|holding| holding.maturity)
+                });
+                maturity.is_none_or(|maturity| maturity <= today)
+            });
+            let weights: Vec<f32> = match members
+                .iter()
+                .any(|symbol| self.class_weights.contains_key(*symbol))
+            {
+                true => members
+                    .iter()
+                    .map(|symbol| self.class_weights.get(*symbol).copied().unwrap_or_default())
+                    .collect(),
+                false => members
+                    .iter()
+                    .map(|symbol| value(symbol).0.max(0.0))
+                    .collect(),
+            };
+            let total: f32 = weights.iter().sum();
+            for (symbol, weight) in members.iter().zip(weights) {
+                // a class that isn't held yet is split evenly
+                let share = match total > 0.0 {
+                    true => weight / total,
+                    false => 1.0 / members.len() as f32,
+                };
+                if share > 0.0 {
+                    targets.insert(symbol.to_string(), Percent(class_target.0 * share));
+                }
+            }
+        }
+        for (symbol, aliases) in self.symbol_aliases.iter() {
+            let Some(target) = targets.get(symbol).copied() else {
+                continue;
+            };
+            let members: Vec<&str> = std::iter::once(symbol)
+                .chain(aliases.iter())
+                .filter(|symbol| !self.ignored_holdings.contains(symbol))
+                .map(String::as_str)
+                .collect();
+            let values: Vec<f32> = members
+                .iter()
+                .map(|symbol| value(symbol).0.max(0.0))
+                .collect();
+            let total: f32 = values.iter().sum();
+            // if none of them are held, the symbol with the target is bought
+            if total <= 0.0 {
+                continue;
+            }
+            for (member, value) in members.iter().zip(values) {
+                targets.insert(member.to_string(), Percent(target.0 * value / total));
+            }
+        }
+        debug!(?targets, "targets by symbol");
+        targets
+    }
+
+    /// The exposure of the account to each asset class before and after
+    /// `plan`, counting the holdings of funds with a known composition in
+    /// their underlying asset classes, and other holdings in their own asset
+    /// class. Empty unless [Self::fund_compositions] are given.
+    pub fn look_through(&self, plan: &RebalancePlan) -> Vec<Exposure> {
+        if self.fund_compositions.is_empty() {
+            return Vec::new();
+        }
+        let active = || plan.adjustments.iter().filter(|adj| !adj.ignored);
+        let total = active().fold(Dollar(0.0), |total, adj| total + adj.holding.current_value);
+        let result_total = active().fold(Dollar(0.0), |total, adj| total + adj.result());
+        let mut exposures: Vec<Exposure> = Vec::new();
+        for adj in active() {
+            let classes: Vec<(&str, f32)> = match self.fund_compositions.get(&adj.holding.symbol) {
+                Some(composition) => composition
+                    .iter()
+                    .map(|(class, percent)| (class.as_str(), percent.0 / 100.0))
+                    .collect(),
+                None => {
+                    let class = match self.asset_class(&adj.holding) {
+                        Some(class) => class,
+                        None if adj.holding.is_cash => "Cash",
+                        None => "Other",
+                    };
+                    vec![(class, 1.0)]
+                }
+            };
+            for (class, share) in classes {
+                let index = match exposures.iter().position(|e| e.class == class) {
+                    Some(index) => index,
+                    None => {
+                        exposures.push(Exposure {
+                            class: class.to_string(),
+                            current: Percent(0.0),
+                            target: Percent(0.0),
+                            result: Percent(0.0),
+                        });
+                        exposures.len() - 1
+                    }
+                };
+                let exposure = &mut exposures[index];
+                exposure.current =
+                    exposure.current + Percent::new(adj.holding.current_value * share, total);
+                exposure.target =
+                    exposure.target + Percent::new(adj.desired_value * share, result_total);
+                exposure.result =
+                    exposure.result + Percent::new(adj.result() * share, result_total);
+            }
+        }
+        exposures.sort_by(|a, b| a.class.cmp(&b.class));
+        exposures
+    }
+
+    /// How far the targets may be from adding up to 100%
+    fn target_sum_tolerance(&self) -> Percent {
+        self.target_sum_tolerance.unwrap_or(Percent(0.01))
+    }
+
+    /// Ensure that the target allocations are reasonable. Configurations are
+    /// validated when they are loaded, so this is only needed after changing
+    /// one.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        anyhow::ensure!(
+            self.glide_path.is_empty() || self.targets.is_empty(),
+            "Account {} has both targets and a glide path",
+            self.account_id
+        );
+        for step in self.glide_path.iter() {
+            anyhow::ensure!(
+                step.date.is_some() != step.age.is_some(),
+                "Each step of the glide path of account {} needs either a date or an age",
+                self.account_id
+            );
+            anyhow::ensure!(
+                step.age.is_none() || self.birth_date.is_some(),
+                "The glide path of account {} is given by age, but there is no birth date",
+                self.account_id
+            );
+        }
+        let other_percent: Percent = self.class_targets.values().sum::<Percent>()
+            + self
+                .nested_targets
+                .values()
+                .map(NestedTarget::percent)
+                .sum();
+        let totals: Vec<(String, Percent)> = match self.glide_path.is_empty() {
+            true => vec![(
+                String::new(),
+                self.targets.values().sum::<Percent>() + other_percent,
+            )],
+            false => self
+                .glide_path
+                .iter()
+                .map(|step| {
+                    let when = match (step.date, step.age) {
+                        (Some(date), _) => format!(" on {date}"),
+                        (None, age) => format!(" at age {}", age.unwrap_or_default()),
+                    };
+                    (when, step.targets.values().sum::<Percent>() + other_percent)
+                })
+                .collect(),
+        };
+        let tolerance = self.target_sum_tolerance().0;
+        for (when, total_percent) in totals.into_iter().filter(|_| !self.normalize_targets) {
+            let difference = total_percent.0 - 100.0;
+            anyhow::ensure!(
+                difference <= tolerance,
+                "Target allocations for account {}{when} add up to {:.2}%, which is {difference:.2}% more than 100%",
+                self.account_id,
+                total_percent.0
+            );
+            anyhow::ensure!(
+                difference >= -tolerance || self.cash_sweep.is_some(),
+                "Target allocations for account {}{when} add up to {:.2}%, which is {:.2}% less than 100%, but there is no cash sweep to hold the rest",
+                self.account_id,
+                total_percent.0,
+                -difference
+            );
+        }
+        for (symbol, constraints) in self.constraints.iter() {
+            if let (Some(min), Some(max)) = (constraints.min, constraints.max) {
+                anyhow::ensure!(
+                    min <= max,
+                    "The minimum for symbol '{symbol}' in account {} is more than its maximum",
+                    self.account_id
+                );
+            }
+        }
+        self.validate_groups(&self.nested_targets)?;
+        let mut flattened = Vec::new();
+        NestedTarget::flatten(&self.nested_targets, 1.0, &mut flattened);
+        for (i, (symbol, _)) in flattened.iter().enumerate() {
+            anyhow::ensure!(
+                !self.targets.contains_key(symbol)
+                    && !flattened[..i].iter().any(|(s, _)| s == symbol)
+                    && !self
+                        .glide_path
+                        .iter()
+                        .any(|step| step.targets.contains_key(symbol)),
+                "Symbol '{symbol}' in account {} has more than one target",
+                self.account_id
+            );
+        }
+        for class in self.class_targets.keys() {
+            let members = self.class_members(class);
+            anyhow::ensure!(
+                !members.is_empty(),
+                "Asset class '{class}' in account {} has a target, but no holdings are in it",
+                self.account_id
+            );
+            for symbol in members {
+                anyhow::ensure!(
+                    !self.has_target(symbol),
+                    "Symbol '{symbol}' in account {} has a target, but so does its asset class '{class}'",
+                    self.account_id
+                );
+            }
+        }
+        for (symbol, composition) in self.fund_compositions.iter() {
+            let total: Percent = composition.values().sum();
+            anyhow::ensure!(
+                (total.0 - 100.0).abs() <= self.target_sum_tolerance().0,
+                "The composition of fund '{symbol}' in account {} adds up to {:.2}% rather than 100%",
+                self.account_id,
+                total.0
+            );
+        }
+        for (symbol, range) in self.target_ranges.iter() {
+            anyhow::ensure!(
+                range.min <= range.max,
+                "The range of symbol '{symbol}' in account {} has a minimum that is more than its maximum",
+                self.account_id
+            );
+        }
+        for (symbol, amount) in self.fixed_targets.iter() {
+            anyhow::ensure!(
+                !self.has_target(symbol)
+                    && !self
+                        .class_targets
+                        .keys()
+                        .any(|class| self.class_members(class).contains(&symbol.as_str())),
+                "Symbol '{symbol}' in account {} has both a fixed and a percentage target",
+                self.account_id
+            );
+            anyhow::ensure!(
+                self.cash_sweep
+                    .as_ref()
+                    .is_none_or(|sweep| sweep.symbol != *symbol),
+                "The cash sweep of account {} can't have a fixed target (use its minimum instead)",
+                self.account_id
+            );
+            anyhow::ensure!(
+                *amount >= Dollar(0.0),
+                "The fixed target of symbol '{symbol}' in account {} is negative",
+                self.account_id
+            );
+        }
+        for (symbol, aliases) in self.symbol_aliases.iter() {
+            anyhow::ensure!(
+                self.has_target(symbol),
+                "Symbol '{symbol}' in account {} has aliases, but no target",
+                self.account_id
+            );
+            for alias in aliases {
+                anyhow::ensure!(
+                    !self.has_target(alias)
+                        && !self.fixed_targets.contains_key(alias)
+                        && !self
+                            .class_targets
+                            .keys()
+                            .any(|class| self.class_members(class).contains(&alias.as_str())),
+                    "Symbol '{alias}' in account {} is an alias of '{symbol}', so it can't have a target of its own",
+                    self.account_id
+                );
+                anyhow::ensure!(
+                    self.cash_sweep
+                        .as_ref()
+                        .is_none_or(|sweep| sweep.symbol != *alias),
+                    "The cash sweep of account {} can't be an alias of '{symbol}'",
+                    self.account_id
+                );
+                anyhow::ensure!(
+                    self.symbol_aliases
+                        .iter()
+                        .all(|(other, others)| other != alias
+                            && (other == symbol || !others.contains(alias))),
+                    "Symbol '{alias}' in account {} is an alias of more than one symbol",
+                    self.account_id
+                );
+            }
+        }
+        for (symbol, weight) in self.class_weights.iter() {
+            anyhow::ensure!(
+                *weight >= 0.0,
+                "The weight of symbol '{symbol}' in account {} is negative",
+                self.account_id
+            );
+        }
+        let total_min: Percent = self.constraints.values().filter_map(|c| c.min).sum();
+        anyhow::ensure!(
+            total_min <= Percent(100.0),
+            "The minimums for account {} add up to more than 100%",
+            self.account_id
+        );
+        for symbol in self
+            .cash_sweep
+            .iter()
+            .flat_map(|sweep| &sweep.other_symbols)
+        {
+            anyhow::ensure!(
+                !self.has_target(symbol),
+                "Symbol '{symbol}' in account {} is counted as cash, so it can't have a target",
+                self.account_id
+            );
+        }
+        if let Some(policy) = self.rebalance_policy {
+            anyhow::ensure!(
+                policy.every.is_some() || policy.max_drift.is_some(),
+                "The rebalance policy for account {} needs `Every` or `MaxDrift`",
+                self.account_id
+            );
+            anyhow::ensure!(
+                policy.max_drift.is_none_or(|drift| drift > Percent(0.0)),
+                "The `MaxDrift` of the rebalance policy for account {} must be more than 0%",
+                self.account_id
+            );
+        }
+        Ok(())
+    }
+
+    /// Ensure that the targets within each of the nested `groups` add up to
+    /// 100%
+    fn validate_groups(&self, groups: &HashMap<String, NestedTarget>) -> anyhow::Result<()> {
+        for (name, target) in groups.iter() {
+            if let NestedTarget::Group(group) = target {
+                let total = group
+                    .targets
+                    .values()
+                    .map(NestedTarget::percent)
+                    .sum::<Percent>();
+                anyhow::ensure!(
+                    (total.0 - 100.0).abs() <= self.target_sum_tolerance().0,
+                    "The targets in group '{name}' of account {} add up to {:.2}% rather than 100%",
+                    self.account_id,
+                    total.0
+                );
+                self.validate_groups(&group.targets)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// The target of each holding that is given in `targets` or
+    /// `nested_targets`, as a share of the account
+    pub(crate) fn flattened_targets(&self) -> HashMap<String, Percent> {
+        let mut flattened = Vec::new();
+        NestedTarget::flatten(&self.nested_targets, 1.0, &mut flattened);
+        let mut targets = self.targets.clone();
+        targets.extend(flattened);
+        targets
+    }
+
+    /// Whether `symbol` has a target of its own (rather than as part of an
+    /// asset class), at any point on the glide path
+    pub(crate) fn has_target(&self, symbol: &str) -> bool {
+        self.flattened_targets().contains_key(symbol)
+            || self
+                .glide_path
+                .iter()
+                .any(|step| step.targets.contains_key(symbol))
+    }
+
+    /// The date on which a step of the glide path applies
+    fn glide_path_date(&self, step: &GlidePathStep) -> Option<Date> {
+        match (step.date, step.age, self.birth_date) {
+            (Some(date), _, _) => Some(date),
+            (None, Some(age), Some(birth_date)) => {
+                let year = birth_date.year() + age as i32;
+                // someone born on February 29th reaches an age on March 1st
+                Date::new(year, birth_date.month(), birth_date.day())
+                    .or_else(|| Date::new(year, 3, 1))
+            }
+            _ => None,
+        }
+    }
+
+    /// The targets that the glide path calls for on `today`, or `None` if the
+    /// account doesn't have a glide path
+    pub fn glide_path_targets(&self, today: Date) -> Option<HashMap<String, Percent>> {
+        let mut steps: Vec<(Date, &HashMap<String, Percent>)> = self
+            .glide_path
+            .iter()
+            .filter_map(|step| Some((self.glide_path_date(step)?, &step.targets)))
+            .collect();
+        steps.sort_by_key(|(date, _)| *date);
+        let (from, to) = match steps.iter().position(|(date, _)| *date > today) {
+            Some(0) => (steps.first()?, steps.first()?),
+            Some(next) => (&steps[next - 1], &steps[next]),
+            None => (steps.last()?, steps.last()?),
+        };
+        let progress = match from.0 == to.0 {
+            true => 0.0,
+            false => from.0.days_until(today) as f32 / from.0.days_until(to.0) as f32,
+        };
+        let mut targets = HashMap::new();
+        for symbol in from.1.keys().chain(to.1.keys()) {
+            let start = from.1.get(symbol).copied().unwrap_or_default();
+            let end = to.1.get(symbol).copied().unwrap_or_default();
+            targets.insert(
+                symbol.clone(),
+                Percent(start.0 + (end.0 - start.0) * progress),
+            );
+        }
+        Some(targets)
+    }
+
+    /// Load a series of [Config] objects from the given yaml (or toml) file
+    /// path
+    pub fn load_from_file<P: AsRef<Path>>(path: P) -> anyhow::Result<Vec<Self>> {
+        Self::load_from_file_normalizing(path, false)
+    }
+
+    /// Like [Self::load_from_file], but if `normalize_targets` is true, the
+    /// targets of every account are scaled to add up to 100% as if
+    /// `NormalizeTargets` were set for it
+    pub fn load_from_file_normalizing<P: AsRef<Path>>(
+        path: P,
+        normalize_targets: bool,
+    ) -> anyhow::Result<Vec<Self>> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            return Self::load_from_dir(path, normalize_targets);
+        }
+        let text = match std::fs::read_to_string(path) {
+            Ok(text) => Ok(text),
+            Err(e) if e.kind() == ErrorKind::NotFound => Err(anyhow!(
+                "Please configure target allocations first. See help for more information."
+            )),
+            e => e.with_context(|| format!("Failed to open file {path:?}")),
+        }?;
+        Self::parse(&text, ConfigFormat::from_path(path), normalize_targets)
+    }
+
+    /// Load the configurations from every yaml (or toml) file in the directory
+    /// `dir`, in the order of their names. Models that are defined in one file
+    /// can be used by accounts in the others, but an account or a model can
+    /// only be defined in one of them.
+    fn load_from_dir(dir: &Path, normalize_targets: bool) -> anyhow::Result<Vec<Self>> {
+        let mut paths = Vec::new();
+        for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {dir:?}"))? {
+            let path = entry?.path();
+            let extension = path
+                .extension()
+                .and_then(|e| e.to_str())
+                .unwrap_or_default();
+            if path.is_file() && ["yml", "yaml", "toml"].contains(&extension) {
+                paths.push(path);
+            }
+        }
+        if paths.is_empty() {
+            bail!("There are no target configuration files in {dir:?}");
+        }
+        paths.sort();
+        let mut merged = ConfigDocument {
+            models: HashMap::new(),
+            accounts: Vec::new(),
+        };
+        for path in paths {
+            let text = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to open file {path:?}"))?;
+            let document = Self::parse_document(&text, ConfigFormat::from_path(&path))
+                .with_context(|| format!("Failed to read {path:?}"))?;
+            for (name, model) in document.models {
+                if merged.models.insert(name.clone(), model).is_some() {
+                    bail!("The model '{name}' in {path:?} is also defined in another file");
+                }
+            }
+            for config in document.accounts {
+                if merged
+                    .accounts
+                    .iter()
+                    .any(|other| other.account_id == config.account_id)
+                {
+                    bail!(
+                        "Account {} in {path:?} is also configured in another file",
+                        config.account_id
+                    );
+                }
+                merged.accounts.push(config);
+            }
+        }
+        Self::resolve(merged, normalize_targets)
+    }
+
+    /// Parse and validate a series of [Config] objects in the given format.
+    /// If `normalize_targets` is true, the targets are normalized as in
+    /// [Self::load_from_file_normalizing].
+    pub fn parse(
+        text: &str,
+        format: ConfigFormat,
+        normalize_targets: bool,
+    ) -> anyhow::Result<Vec<Self>> {
+        Self::resolve(Self::parse_document(text, format)?, normalize_targets)
+    }
+
+    fn parse_document(text: &str, format: ConfigFormat) -> anyhow::Result<ConfigDocument> {
+        Ok(match format {
+            ConfigFormat::Yaml => {
+                if serde_yaml::from_str::<serde_yaml::Value>(text)?.is_mapping() {
+                    serde_yaml::from_str(text)?
+                } else {
+                    ConfigDocument {
+                        models: HashMap::new(),
+                        accounts: serde_yaml::from_str(text)?,
+                    }
+                }
+            }
+            ConfigFormat::Toml => {
+                // deserializing from text rather than from the value keeps track
+                // of the path to any field that is wrong, but the location is
+                // within the YAML text rather than the TOML document
+                let yaml = serde_yaml::to_string(&crate::toml::from_str(text)?)?;
+                match serde_yaml::from_str(&yaml) {
+                    Ok(document) => document,
+                    Err(e) => {
+                        let message = e.to_string();
+                        let location = e
+                            .location()
+                            .map(|l| format!(" at line {} column {}", l.line(), l.column()))
+                            .unwrap_or_default();
+                        bail!("{}", message.strip_suffix(&location).unwrap_or(&message))
+                    }
+                }
+            }
+        })
+    }
+
+    /// Apply the models of `document` to its accounts and validate them
+    fn resolve(document: ConfigDocument, normalize_targets: bool) -> anyhow::Result<Vec<Self>> {
+        let mut targets = document.accounts;
+        let mut aliases = HashMap::new();
+        for config in targets.iter() {
+            for alias in config.aliases.iter() {
+                if let Some(other) = aliases.insert(alias.to_lowercase(), &config.account_id) {
+                    bail!(
+                        "The alias '{alias}' is used by both account {other} and account {}",
+                        config.account_id
+                    );
+                }
+            }
+        }
+        for config in targets.iter_mut() {
+            let Some(name) = &config.model else {
+                continue;
+            };
+            let Some(model) = document.models.get(name) else {
+                bail!(
+                    "Account {} uses the model '{name}', which isn't defined in Models",
+                    config.account_id
+                );
+            };
+            // the account's own targets take precedence over the model's
+            for (symbol, percent) in model {
+                config.targets.entry(symbol.clone()).or_insert(*percent);
+            }
+            // the model has been applied, so the configuration can be saved
+            // without it
+            config.model = None;
+        }
+        targets
+            .into_iter()
+            .map(|mut t| {
+                t.normalize_targets |= normalize_targets;
+                t.validate()?;
+                Ok(t)
+            })
+            .collect()
+    }
+
+    /// Whether this configuration applies to the account with the given ID.
+    /// Besides the exact ID, `account_id` may be a pattern in which `*`
+    /// matches any number of characters and `?` matches one (e.g. `*6789` for
+    /// the account whose number ends in 6789).
+    pub fn matches_account(&self, account_id: &str) -> bool {
+        let pattern: Vec<char> = self.account_id.chars().collect();
+        let id: Vec<char> = account_id.chars().collect();
+        self.account_id == account_id || (self.is_pattern() && glob_match(&pattern, &id))
+    }
+
+    /// Whether `account_id` is a pattern rather than a single account's ID
+    fn is_pattern(&self) -> bool {
+        self.account_id.contains(['*', '?'])
+    }
+
+    /// The label of the holding with the given symbol, if it has one
+    pub fn label(&self, symbol: &str) -> Option<&str> {
+        self.labels.get(symbol).map(String::as_str)
+    }
+
+    /// Whether `name` is one of the aliases of this account, ignoring case
+    pub fn has_alias(&self, name: &str) -> bool {
+        self.aliases
+            .iter()
+            .any(|alias| alias.eq_ignore_ascii_case(name))
+    }
+
+    /// Whether the given holding is the cash sweep configured for this account
+    fn is_cash_sweep(&self, holding: &Holding) -> bool {
+        self.cash_sweep.as_ref().is_some_and(|sweep| {
+            sweep.symbol == holding.symbol
+                && (sweep.currency.is_none() || sweep.currency == holding.currency)
+        })
+    }
+
+    /// Whether `holding` is one of the other cash-like holdings that are
+    /// counted as part of the cash sweep
+    fn is_other_cash(&self, holding: &Holding) -> bool {
+        self.cash_sweep.as_ref().is_some_and(|sweep| {
+            sweep.symbol != holding.symbol && sweep.other_symbols.contains(&holding.symbol)
+        })
+    }
+
+    /// The minimum amount to keep in the given cash holding of an account with
+    /// the given `total` value, converted to the account's base currency. If
+    /// the holding has no exchange rate, the minimum is taken to be in the base
+    /// currency already (see [Self::unconverted_cash_minimum]).
+    pub(crate) fn cash_minimum(&self, cash: Option<&Holding>, total: Dollar) -> Dollar {
+        self.cash_sweep
+            .as_ref()
+            .map(|sweep| {
+                let minimum = match (&sweep.currency, cash.and_then(|c| c.exchange_rate)) {
+                    (Some(_), Some(rate)) => Dollar(sweep.minimum.0 * rate),
+                    _ => sweep.minimum,
+                };
+                match sweep.minimum_percent {
+                    Some(percent) => minimum.max(total * percent),
+                    None => minimum,
+                }
+            })
+            .unwrap_or_default()
+    }
+
+    /// The currency of the cash minimum, if it is in a currency but can't be
+    /// converted into the base currency because the cash holding has no
+    /// exchange rate
+    fn unconverted_cash_minimum(&self, cash: Option<&Holding>) -> Option<&str> {
+        let sweep = self.cash_sweep.as_ref()?;
+        let currency = sweep.currency.as_deref()?;
+        (sweep.minimum != Dollar(0.0) && cash.is_none_or(|cash| cash.exchange_rate.is_none()))
+            .then_some(currency)
+    }
+
+    /// Compare this configuration with the given `balance` and calculate what adjustments need to be
+    /// made in order to align the balance with the desired target allocations
+    pub fn adjust_allocations(&self, balance: &Balance) -> anyhow::Result<RebalancePlan> {
+        self.adjust_allocations_as_of(balance, Date::today())
+    }
+
+    /// Like [Self::adjust_allocations], but calculates the adjustments as of
+    /// the given date rather than today
+    pub fn adjust_allocations_as_of(
+        &self,
+        balance: &Balance,
+        today: Date,
+    ) -> anyhow::Result<RebalancePlan> {
+        let mut plan = self.target_plan(balance, today)?;
+        let cash_minimum = plan.explanation.cash_minimum;
+        if self.fewest_trades {
+            self.trade_fewest(&mut plan, cash_minimum);
+        } else {
+            self.hold_within_bands(&mut plan, cash_minimum);
+            self.trade_to_ranges(&mut plan);
+        }
+        self.hold_short_term_gains(&mut plan, cash_minimum);
+        self.hold_small_trades(&mut plan, cash_minimum);
+        self.limit_gains(&mut plan);
+        self.limit_turnover(&mut plan, cash_minimum);
+        self.round_to_increment(&mut plan, cash_minimum);
+        self.round_to_whole_shares(&mut plan, cash_minimum);
+        plan.settle_residual();
+        Ok(plan)
+    }
+
+    /// Calculate how to invest a new `contribution` to the account (along with
+    /// any cash above what the cash sweep should keep) without selling
+    /// anything. The money is used to buy the holdings that are furthest
+    /// below their targets, so that they all end up the same share of the way
+    /// to their targets.
+    pub fn plan_contribution(
+        &self,
+        balance: &Balance,
+        contribution: Dollar,
+    ) -> anyhow::Result<RebalancePlan> {
+        self.plan_contribution_as_of(balance, contribution, Date::today())
+    }
+
+    /// Like [Self::plan_contribution], but calculates the plan as of the given
+    /// date rather than today
+    pub fn plan_contribution_as_of(
+        &self,
+        balance: &Balance,
+        contribution: Dollar,
+        today: Date,
+    ) -> anyhow::Result<RebalancePlan> {
+        let mut plan = self.target_plan(balance, today)?;
+        plan.contribution = Some(contribution);
+        let cash_holding = plan
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+            .map(|adj| adj.holding.clone());
+        let explanation = &mut plan.explanation;
+        explanation.distributable += contribution;
+        explanation.cash_minimum =
+            self.cash_minimum(cash_holding.as_ref(), explanation.distributable);
+        explanation.cash_desired = explanation
+            .cash_minimum
+            .max(explanation.distributable * explanation.cash_target.unwrap_or_default())
+            .min(explanation.distributable);
+        let cash = plan
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+            .map(|adj| adj.holding.current_value);
+        let available = match cash {
+            Some(cash) => contribution + cash - explanation.cash_desired,
+            None => contribution,
+        }
+        .max(Dollar(0.0));
+
+        let buys = fill_underweight(
+            &plan
+                .adjustments
+                .iter()
+                .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+                .map(|adj| (adj.holding.current_value, adj.target))
+                .collect::<Vec<_>>(),
+            available,
+        );
+        let mut buys = buys.into_iter();
+        for adj in plan
+            .adjustments
+            .iter_mut()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+        {
+            let buy = buys.next().unwrap_or_default();
+            adj.desired_value = adj.holding.current_value + buy;
+            adj.action = Action::from_change(buy);
+        }
+        plan.balance_cash(contribution);
+        debug!(?available, ?plan.adjustments, "planned contribution");
+        let cash_minimum = plan.explanation.cash_minimum;
+        self.hold_small_trades(&mut plan, cash_minimum);
+        self.round_to_increment(&mut plan, cash_minimum);
+        self.round_to_whole_shares(&mut plan, cash_minimum);
+        plan.settle_residual();
+        Ok(plan)
+    }
+
+    /// Calculate how to raise `withdrawal` in the cash sweep (on top of what
+    /// it should keep) by only selling. Holdings without a target are sold
+    /// first, then the holdings that are furthest above their targets, so
+    /// that what remains moves toward its targets.
+    pub fn plan_withdrawal(
+        &self,
+        balance: &Balance,
+        withdrawal: Dollar,
+    ) -> anyhow::Result<RebalancePlan> {
+        self.plan_withdrawal_as_of(balance, withdrawal, Date::today())
+    }
+
+    /// Like [Self::plan_withdrawal], but calculates the plan as of the given
+    /// date rather than today
+    pub fn plan_withdrawal_as_of(
+        &self,
+        balance: &Balance,
+        withdrawal: Dollar,
+        today: Date,
+    ) -> anyhow::Result<RebalancePlan> {
+        let mut plan = self.target_plan(balance, today)?;
+        plan.withdrawal = Some(withdrawal);
+        let cash_holding = plan
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+            .map(|adj| adj.holding.clone());
+        let explanation = &mut plan.explanation;
+        explanation.distributable = explanation.distributable - withdrawal;
+        explanation.cash_minimum =
+            self.cash_minimum(cash_holding.as_ref(), explanation.distributable);
+        explanation.cash_desired = explanation
+            .cash_minimum
+            .max(explanation.distributable * explanation.cash_target.unwrap_or_default())
+            .min(explanation.distributable)
+            .max(Dollar(0.0));
+        let cash_floor = explanation.cash_desired + withdrawal;
+        let Some(cash) = plan
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+        else {
+            bail!(
+                "A withdrawal can only be planned for an account with a cash sweep to raise it in"
+            );
+        };
+        let needed = (cash_floor - cash.holding.current_value).max(Dollar(0.0));
+
+        // holdings that can't be sold don't raise anything
+        let holdings: Vec<_> = plan
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+            .map(|adj| match self.constraints.get(&adj.holding.symbol) {
+                Some(constraints) if constraints.no_sell => (Dollar(0.0), Percent(0.0)),
+                _ => (adj.holding.current_value, adj.target),
+            })
+            .collect();
+        let available = holdings
+            .iter()
+            .fold(Dollar(0.0), |total, (current, _)| total + *current);
+        if available < needed {
+            plan.warnings.push(format!(
+                "Selling every holding only raises {available} of the {needed} needed for the withdrawal"
+            ));
+        }
+        let mut sells = drain_overweight(&holdings, needed).into_iter();
+        for adj in plan
+            .adjustments
+            .iter_mut()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+        {
+            let sell = sells.next().unwrap_or_default();
+            adj.desired_value = adj.holding.current_value - sell;
+            adj.action = Action::from_change(Dollar(0.0) - sell);
+        }
+        plan.balance_cash(Dollar(0.0));
+        debug!(?needed, ?plan.adjustments, "planned withdrawal");
+        self.hold_small_trades(&mut plan, cash_floor);
+        self.round_to_increment(&mut plan, cash_floor);
+        self.round_to_whole_shares(&mut plan, cash_floor);
+        plan.settle_residual();
+        Ok(plan)
+    }
+
+    /// Calculate the adjustments that bring every holding to its target,
+    /// before any trades are left out or rounded
+    fn target_plan(&self, balance: &Balance, today: Date) -> anyhow::Result<RebalancePlan> {
+        anyhow::ensure!(
+            self.account_id == balance.account_id,
+            "The target configuration doesn't apply to this account"
+        );
+        let cash_fallback = self.cash_sweep.as_ref().map(|sweep| Holding {
+            symbol: sweep.symbol.clone(),
+            current_value: Dollar(0.0),
+            is_cash: true,
+            currency: sweep.currency.clone(),
+            ..Default::default()
+        });
+        let cash_sweep = balance
+            .holdings
+            .iter()
+            .find(|&pos| self.is_cash_sweep(pos))
+            .or(cash_fallback.as_ref());
+        let sweep_currency = self.cash_sweep.as_ref().and_then(|s| s.currency.as_ref());
+        debug!(?cash_sweep, ?balance);
+        let targets = self.symbol_targets(balance, today);
+
+        // make sure the output contains information about all holdings in the
+        // account balance
+        let mut adjustments: HashMap<String, PositionAdjustment> = HashMap::new();
+        let mut empty_holdings = Vec::new();
+        let mut upcoming_maturities = Vec::new();
+        let mut other_cash = Vec::new();
+        let has_cash = cash_sweep.is_some() || balance.holdings.iter().any(|h| h.is_cash);
+        let mut uncounted_pending = Dollar(0.0);
+        for holding in balance.holdings.iter() {
+            let ignored_negative = self.negative_holdings == NegativeHoldings::Ignore
+                && holding.current_value < Dollar(0.0)
+                && !targets.contains_key(&holding.symbol);
+            if holding.pending
+                && self.pending_activity == PendingActivity::Cash
+                && !ignored_negative
+            {
+                if has_cash {
+                    debug!(
+                        ?holding,
+                        "counting pending activity as part of the cash sweep"
+                    );
+                    other_cash.push(holding.clone());
+                    continue;
+                }
+                uncounted_pending += holding.current_value;
+            }
+            if self.is_other_cash(holding) {
+                debug!(?holding, "counting holding as part of the cash sweep");
+                other_cash.push(holding.clone());
+                continue;
+            }
+            if self.zero_value_holdings != ZeroValueHoldings::Keep
+                && holding.current_value == Dollar(0.0)
+                && !holding.is_cash
+                && !targets.contains_key(&holding.symbol)
+            {
+                debug!(?holding, "skipping zero-value holding");
+                if self.zero_value_holdings == ZeroValueHoldings::Collapse {
+                    empty_holdings.push(holding.symbol.clone());
+                }
+                continue;
+            }
+            let mut ignored = self.ignored_holdings.contains(&holding.symbol) || holding.pending;
+            let mut holding = holding.clone();
+            if let Some(maturity) = self.maturities.get(&holding.symbol) {
+                holding.maturity = Some(*maturity);
+            }
+            if let Some(acquired) = self.acquisition_dates.get(&holding.symbol) {
+                holding.acquired = Some(*acquired);
+            }
+            if let Some(maturity) = holding.maturity.filter(|m| *m > today) {
+                if targets.contains_key(&holding.symbol) {
+                    bail!(
+                        "Can't set a target for symbol '{}': it can't be sold until it matures on {maturity}",
+                        holding.symbol
+                    );
+                }
+                debug!(?holding, "holding has not matured yet");
+                ignored = true;
+                upcoming_maturities.push(holding.clone());
+            }
+            if !holding.is_cash && self.is_cash_sweep(&holding) {
+                // some providers (e.g. workplace retirement plans) don't mark
+                // any holding as cash, so the configured sweep is used instead
+                if let Some(other) = balance.holdings.iter().find(|other| {
+                    other.is_cash && sweep_currency.is_none() && !self.is_other_cash(other)
+                }) {
+                    bail!(
+                        "The cash sweep '{}' (CashSweep.Symbol) isn't cash in the balance data, but '{}' is. Use '{}' as the cash sweep, or list it in CashSweep.OtherSymbols.",
+                        holding.symbol,
+                        other.symbol,
+                        other.symbol
+                    );
+                }
+                debug!(?holding, "treating configured cash sweep as cash");
+                holding.is_cash = true;
+            }
+            if holding.is_cash && sweep_currency.is_some() && !self.is_cash_sweep(&holding) {
+                // in multi-currency accounts, only cash in the configured
+                // currency is treated as the cash sweep
+                debug!(
+                    ?holding,
+                    "treating cash in another currency as a regular holding"
+                );
+                holding.is_cash = false;
+            }
+            if ignored_negative && !holding.is_cash {
+                debug!(?holding, "ignoring negative holding");
+                ignored = true;
+            }
+            adjustments.insert(
+                holding.symbol.clone(),
+                PositionAdjustment {
+                    short_term_until: holding.long_term_date().filter(|date| *date > today),
+                    holding,
+                    ignored,
+                    ..Default::default()
+                },
+            );
+        }
+
+        let total_target: Percent = targets.values().sum();
+        let normalized = self.normalize_targets
+            && total_target > Percent(0.0)
+            && (total_target.0 - 100.0).abs() > self.target_sum_tolerance().0;
+        // make sure the output contains information about all targets in the
+        // allocation configuration
+        for (target_symbol, &target_percent) in targets.iter() {
+            let target_percent = match normalized {
+                true => target_percent / total_target,
+                false => target_percent,
+            };
+            adjustments
+                .entry(target_symbol.clone())
+                .and_modify(|e| e.target = target_percent)
+                .or_insert(PositionAdjustment {
+                    target: target_percent,
+                    holding: Holding {
+                        symbol: target_symbol.clone(),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                });
+        }
+
+        if let Some(cash_sweep) = cash_sweep {
+            // make sure the output contains a cash sweep if a cash minimum is specified
+            adjustments
+                .entry(cash_sweep.symbol.to_owned())
+                .and_modify(|e| e.holding.current_value = cash_sweep.current_value)
+                .or_insert(PositionAdjustment {
+                    holding: cash_sweep.clone(),
+                    ignored: self.ignored_holdings.contains(&cash_sweep.symbol),
+                    ..Default::default()
+                });
+        }
+
+        if let Some(cash) = adjustments.values_mut().find(|adj| adj.holding.is_cash) {
+            cash.holding.current_value += other_cash
+                .iter()
+                .fold(Dollar(0.0), |total, holding| total + holding.current_value);
+            // whatever the targets leave unallocated is kept in cash
+            let unallocated = Percent(100.0) - total_target;
+            if unallocated > self.target_sum_tolerance() && !normalized {
+                cash.target = cash.target + unallocated;
+            }
+        }
+
+        for (symbol, adj) in adjustments.iter() {
+            if adj.ignored && adj.target != Percent(0.0) {
+                bail!("Can't ignore symbol '{symbol}': it is specified in the target allocation")
+            }
+        }
+
+        let mut warnings = Vec::new();
+        if let Some(currency) = self.unconverted_cash_minimum(cash_sweep) {
+            warnings.push(format!(
+                "The cash minimum is in {currency}, but there is no exchange rate for the cash sweep, so it is taken to be in the base currency"
+            ));
+        }
+        if uncounted_pending != Dollar(0.0) {
+            warnings.push(format!(
+                "The account has {uncounted_pending} in pending activity but no cash position, so it isn't counted"
+            ));
+        }
+        if normalized {
+            warnings.push(format!(
+                "Target allocations add up to {total_target}, so they were scaled to add up to 100%"
+            ));
+        }
+        for symbol in self.ignored_holdings.iter() {
+            if !balance.holdings.iter().any(|h| h.symbol == *symbol) {
+                warnings.push(format!(
+                    "Ignored symbol '{symbol}' is not held in this account"
+                ));
+            }
+        }
+        let mut untargeted: Vec<_> = adjustments
+            .values()
+            .filter(|adj| {
+                !adj.ignored
+                    && !adj.holding.is_cash
+                    && adj.target == Percent(0.0)
+                    && !targets.contains_key(&adj.holding.symbol)
+                    && adj.holding.current_value > Dollar(0.0)
+            })
+            .map(|adj| adj.holding.symbol.clone())
+            .collect();
+        untargeted.sort();
+        for symbol in untargeted {
+            warnings.push(format!(
+                "Holding '{symbol}' has no target allocation, so all of it will be sold"
+            ));
+        }
+        let mut negative: Vec<_> = adjustments
+            .values()
+            .filter(|adj| {
+                !adj.ignored
+                    && !adj.holding.is_cash
+                    && adj.target == Percent(0.0)
+                    && adj.holding.current_value < Dollar(0.0)
+            })
+            .map(|adj| (adj.holding.symbol.clone(), adj.holding.current_value))
+            .collect();
+        negative.sort_by(|a, b| a.0.cmp(&b.0));
+        for (symbol, value) in negative {
+            warnings.push(format!(
+                "Holding '{symbol}' has a negative value ({value}) and no target allocation, so it will be bought back"
+            ));
+        }
+
+        let total_val = adjustments
+            .values()
+            .filter_map(|adj| {
+                if adj.ignored {
+                    None
+                } else {
+                    Some(adj.holding.current_value)
+                }
+            })
+            .sum::<Dollar>();
+        if total_val < Dollar(0.0) {
+            bail!(
+                "The holdings of account {} add up to {total_val}, so there is nothing to distribute among the targets",
+                balance.account_id
+            );
+        }
+        // fixed targets are set aside first (as much as the account can
+        // afford), and the percentage targets apply to the rest
+        let fixed_requested = self
+            .fixed_targets
+            .values()
+            .fold(Dollar(0.0), |total, amount| total + *amount);
+        let fixed_scale = match fixed_requested > total_val {
+            true => {
+                warnings.push(format!(
+                    "The fixed targets add up to {fixed_requested}, more than the account is worth"
+                ));
+                total_val.0 / fixed_requested.0
+            }
+            false => 1.0,
+        };
+        let fixed: HashMap<&str, Dollar> = self
+            .fixed_targets
+            .iter()
+            .map(|(symbol, amount)| (symbol.as_str(), *amount * fixed_scale))
+            .collect();
+        let fixed_total = fixed_requested * fixed_scale;
+        let fixed_pct = Percent::new(fixed_total, total_val);
+        if fixed_total > Dollar(0.0) {
+            for adj in adjustments.values_mut() {
+                adj.target = match fixed.get(adj.holding.symbol.as_str()) {
+                    Some(amount) => Percent::new(*amount, total_val),
+                    None => adj.target * (Percent(100.0) - fixed_pct),
+                };
+            }
+        }
+        let cash_target = adjustments
+            .values()
+            .find(|v| v.holding.is_cash)
+            .map(|adj| adj.target);
+        let cash_minimum = self.cash_minimum(cash_sweep, total_val);
+        let cash_minimum_enforced =
+            cash_minimum >= cash_target.map(|t| total_val * t).unwrap_or_default();
+        // the account can't hold more cash than its total value
+        let cash_desired = cash_minimum
+            .max(cash_target.map(|t| total_val * t).unwrap_or_default())
+            .min(total_val);
+        debug!(?cash_target, ?cash_desired, cash_minimum_enforced);
+
+        // make sure there is only a single cash holding in the list
+        anyhow::ensure!(
+            adjustments
+                .iter()
+                .filter(|(_k, v)| v.holding.is_cash)
+                .count()
+                <= 1,
+            "Account should contain at most 1 cash holding"
+        );
+
+        let mut adjustments: Vec<_> = adjustments
+            .into_values()
+            .map(|mut adj| {
+                let action = if adj.ignored {
+                    Action::DoNothing
+                } else {
+                    let mut desired_val = total_val * adj.target;
+                    debug!(?desired_val, ?adj);
+                    if adj.holding.is_cash {
+                        desired_val = cash_desired;
+                        debug!(?desired_val, "Setting cash val");
+                    } else if let Some(amount) = fixed.get(adj.holding.symbol.as_str()) {
+                        desired_val = *amount;
+                        debug!(?desired_val, "Setting fixed val");
+                    } else {
+                        // if the minimum cash position was enforced, that
+                        // leaves less to allocate for other holdings, so we
+                        // allocate the rest of the holdings proportionally to
+                        // their targets, even if they can't be acheived
+                        if cash_minimum_enforced {
+                            let remainder =
+                                (total_val - cash_desired - fixed_total).max(Dollar(0.0));
+                            let noncash_pct =
+                                Percent(100.0) - cash_target.unwrap_or_default() - fixed_pct;
+                            let pct = adj.target / noncash_pct;
+                            desired_val = remainder * pct;
+                            debug!(
+                                ?remainder,
+                                ?noncash_pct,
+                                ?pct,
+                                ?desired_val,
+                                ?adj.target,
+                                "Setting adjusted non-cash val"
+                            );
+                        } else {
+                            desired_val = total_val * adj.target;
+                            debug!(?desired_val, "Setting non-cash val");
+                        }
+                    }
+                    debug!(?desired_val, ?adj.holding.current_value, "setting action");
+                    adj.desired_value = desired_val;
+                    Action::from_change(desired_val - adj.holding.current_value)
+                };
+                adj.action = action;
+                adj
+            })
+            .collect();
+        if !self.constraints.is_empty() {
+            self.apply_constraints(&mut adjustments, total_val, total_val - cash_desired);
+        }
+        // sort core position first, then by current value, then by symbol name
+        adjustments.sort_by(|a, b| match b.holding.is_cash.cmp(&a.holding.is_cash) {
+            std::cmp::Ordering::Equal => match a
+                .holding
+                .current_value
+                .partial_cmp(&b.holding.current_value)
+                .unwrap_or(std::cmp::Ordering::Equal)
+                .reverse()
+            {
+                std::cmp::Ordering::Equal => a.holding.symbol.cmp(&b.holding.symbol),
+                res => res,
+            },
+            res => res,
+        });
+        empty_holdings.sort();
+        upcoming_maturities.sort_by_key(|holding| holding.maturity);
+        Ok(RebalancePlan {
+            account_id: self.account_id.clone(),
+            adjustments,
+            empty_holdings,
+            upcoming_maturities,
+            warnings,
+            other_cash,
+            explanation: PlanExplanation {
+                distributable: total_val,
+                cash_minimum,
+                cash_target,
+                cash_minimum_enforced,
+                cash_desired,
+            },
+            ..Default::default()
+        })
+    }
+
+    /// Leave out the trades that are smaller than the minimum trade
+    fn hold_small_trades(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        let Some(minimum) = self.minimum_trade else {
+            return;
+        };
+        for i in 0..plan.adjustments.len() {
+            let adj = &plan.adjustments[i];
+            let change = adj.action.change();
+            if !adj.ignored && !adj.holding.is_cash && change.abs() < minimum {
+                debug!(?adj, ?minimum, "trade is smaller than the minimum trade");
+                plan.hold_trade(i, HoldReason::BelowMinimumTrade, cash_minimum);
+            }
+        }
+    }
+
+    /// Change the desired values of the non-cash holdings so that they meet
+    /// their constraints, and share the rest of the `invested` amount between
+    /// the other holdings in proportion to their targets. Minimum and maximum
+    /// percentages are of the `total` value of the account. If nothing is left
+    /// for the other holdings to share, the cash position makes up the
+    /// difference.
+    fn apply_constraints(
+        &self,
+        adjustments: &mut [PositionAdjustment],
+        total: Dollar,
+        invested: Dollar,
+    ) {
+        // the lowest and (if any) highest value that each holding may have
+        let bounds = |adj: &PositionAdjustment| {
+            let constraints = self
+                .constraints
+                .get(&adj.holding.symbol)
+                .copied()
+                .unwrap_or_default();
+            let mut lower = constraints.min.map(|min| total * min).unwrap_or_default();
+            let mut upper = constraints.max.map(|max| total * max);
+            if constraints.no_sell {
+                lower = lower.max(adj.holding.current_value);
+                upper = upper.map(|upper| upper.max(adj.holding.current_value));
+            }
+            (lower, upper)
+        };
+        let mut fixed: Vec<_> = adjustments
+            .iter()
+            .map(|adj| adj.ignored || adj.holding.is_cash)
+            .collect();
+        let (mut free_value, mut free_pct);
+        loop {
+            (free_value, free_pct) = (invested, Percent(0.0));
+            for (adj, &fixed) in adjustments.iter().zip(fixed.iter()) {
+                if fixed {
+                    if !adj.ignored && !adj.holding.is_cash {
+                        free_value = free_value - adj.desired_value;
+                    }
+                } else {
+                    free_pct = free_pct + adj.target;
+                }
+            }
+            let share = |adj: &PositionAdjustment| {
+                if free_pct > Percent(0.0) && free_value > Dollar(0.0) {
+                    free_value * (adj.target / free_pct)
+                } else {
+                    Dollar(0.0)
+                }
+            };
+            for (adj, _) in adjustments
+                .iter_mut()
+                .zip(fixed.iter())
+                .filter(|(_, f)| !**f)
+            {
+                adj.desired_value = share(adj);
+            }
+            // holdings over their upper bound are fixed first, since the
+            // excess that is shared with the rest may take them over theirs
+            let over: Vec<_> = (0..adjustments.len())
+                .filter(|&i| !fixed[i])
+                .filter_map(|i| {
+                    let upper = bounds(&adjustments[i]).1?;
+                    (adjustments[i].desired_value > upper).then_some((i, upper))
+                })
+                .collect();
+            let under: Vec<_> = (0..adjustments.len())
+                .filter(|&i| !fixed[i])
+                .filter_map(|i| {
+                    let lower = bounds(&adjustments[i]).0;
+                    (adjustments[i].desired_value < lower).then_some((i, lower))
+                })
+                .collect();
+            let bound = if over.is_empty() { under } else { over };
+            if bound.is_empty() {
+                break;
+            }
+            for (i, value) in bound {
+                debug!(adj = ?adjustments[i], ?value, "holding is constrained");
+                adjustments[i].desired_value = value;
+                fixed[i] = true;
+            }
+        }
+        if (free_pct == Percent(0.0) || free_value < Dollar(0.0))
+            && let Some(cash) = adjustments
+                .iter_mut()
+                .find(|adj| !adj.ignored && adj.holding.is_cash)
+        {
+            cash.desired_value += free_value;
+        }
+        for adj in adjustments.iter_mut().filter(|adj| !adj.ignored) {
+            adj.action = Action::from_change(adj.desired_value - adj.holding.current_value);
+        }
+    }
+
+    /// Reduce the sells in the plan so that they realize no more than the
+    /// maximum realized gains. The sells with the largest gain for each dollar
+    /// sold are reduced first, and the buys are reduced in proportion, since
+    /// there is less to buy with.
+    fn limit_gains(&self, plan: &mut RebalancePlan) {
+        let Some(max) = self.max_realized_gains else {
+            return;
+        };
+        for adj in plan.adjustments.iter().filter(|adj| {
+            !adj.ignored
+                && matches!(adj.action, Action::Sell(_))
+                && adj.realized_gain().is_none()
+                && !adj.holding.is_cash
+        }) {
+            plan.warnings.push(format!(
+                "The cost basis of '{}' isn't known, so the gains from selling it aren't limited",
+                adj.holding.symbol
+            ));
+        }
+        let mut excess = plan.realized_gains().unwrap_or_default() - max;
+        if excess <= Dollar(0.0) {
+            return;
+        }
+        let mut sells: Vec<_> = plan
+            .adjustments
+            .iter()
+            .enumerate()
+            .filter_map(|(i, adj)| {
+                let gain = adj.realized_gain()?;
+                let sold = Dollar(0.0) - adj.action.change();
+                (gain > Dollar(0.0)).then_some((i, gain.0 / sold.0))
+            })
+            .collect();
+        sells.sort_by(|(_, a), (_, b)| b.total_cmp(a));
+        let mut reduced = Dollar(0.0);
+        for (i, gain_ratio) in sells {
+            if excess <= Dollar(0.0) {
+                break;
+            }
+            let adj = &mut plan.adjustments[i];
+            let sold = Dollar(0.0) - adj.action.change();
+            let cut = sold.min(Dollar(excess.0 / gain_ratio));
+            debug!(?adj, ?cut, "reducing sell to limit realized gains");
+            adj.action = Action::from_change(cut - sold);
+            excess = excess - cut * gain_ratio;
+            reduced += cut;
+            plan.gains_limited.push(adj.holding.symbol.clone());
+        }
+
+        plan.reduce_buys(reduced);
+    }
+
+    /// Reduce the trades so that the plan trades no more than the maximum
+    /// turnover. The sells and the buys share the limit in proportion to how
+    /// much of each the plan would make, so that the cash position changes in
+    /// proportion too. Within each, every trade is reduced by the same amount
+    /// (or left out, if it is smaller than that), so that the holdings that
+    /// are furthest from their targets are still traded the most.
+    fn limit_turnover(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        let limit = match (self.max_turnover, self.max_turnover_percent) {
+            (Some(max), Some(percent)) => max.min(plan.explanation.distributable * percent),
+            (Some(max), None) => max,
+            (None, Some(percent)) => plan.explanation.distributable * percent,
+            (None, None) => return,
+        };
+        let turnover = plan.turnover();
+        if turnover <= limit {
+            return;
+        }
+        let traded = |adj: &&mut PositionAdjustment| !adj.ignored && !adj.holding.is_cash;
+        for selling in [true, false] {
+            let mut trades: Vec<&mut PositionAdjustment> = plan
+                .adjustments
+                .iter_mut()
+                .filter(traded)
+                .filter(|adj| (adj.action.change() < Dollar(0.0)) == selling)
+                .collect();
+            let total: f32 = trades.iter().map(|adj| adj.action.change().abs().0).sum();
+            let budget = limit.max(Dollar(0.0)).0 * total / turnover.0;
+            // find how much to take off of every trade to fit within the
+            // budget: the largest trades are reduced until the smaller ones
+            // are left out entirely
+            let mut sizes: Vec<f32> = trades
+                .iter()
+                .map(|adj| adj.action.change().abs().0)
+                .collect();
+            sizes.sort_by(|a, b| b.total_cmp(a));
+            let mut cut = 0.0;
+            let mut larger = 0.0;
+            for (i, size) in sizes.iter().enumerate() {
+                larger += size;
+                let candidate = (larger - budget) / (i + 1) as f32;
+                if candidate >= sizes.get(i + 1).copied().unwrap_or(0.0) {
+                    cut = candidate;
+                    break;
+                }
+            }
+            debug!(?limit, ?turnover, selling, budget, cut, "limiting turnover");
+            for adj in trades.iter_mut() {
+                let change = adj.action.change();
+                let size = Dollar((change.abs().0 - cut).max(0.0));
+                adj.action = Action::from_change(match selling {
+                    true => Dollar(0.0) - size,
+                    false => size,
+                });
+            }
+        }
+        // the sells and the cash above its minimum have to pay for the buys
+        let (mut sold, mut bought) = (Dollar(0.0), Dollar(0.0));
+        for adj in plan
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+        {
+            match adj.action {
+                Action::Sell(amount) => sold += amount,
+                Action::Buy(amount) => bought += amount,
+                Action::DoNothing => {}
+            }
+        }
+        let available = match plan
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+        {
+            Some(cash) => sold + (cash.holding.current_value - cash_minimum).max(Dollar(0.0)),
+            None => sold,
+        };
+        plan.reduce_buys((bought - available).max(Dollar(0.0)));
+        plan.buys_reduced = true;
+        plan.turnover_limit = Some(limit);
+    }
+
+    /// Round the trades of holdings to the nearest multiple of the trade
+    /// increment, except for holdings that are traded in whole shares or sold
+    /// entirely. If rounding a trade would take the cash sweep below
+    /// `cash_minimum`, it is rounded the other way instead.
+    fn round_to_increment(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        let Some(increment) = self.trade_increment.filter(|increment| increment.0 > 0.0) else {
+            return;
+        };
+        for i in 0..plan.adjustments.len() {
+            let adj = &plan.adjustments[i];
+            let change = adj.action.change();
+            if adj.ignored
+                || adj.holding.is_cash
+                || self.whole_shares.contains(&adj.holding.symbol)
+                || change == Dollar(0.0)
+                || adj.result().abs() < Dollar(0.01)
+            {
+                continue;
+            }
+            let steps = change.0 / increment.0;
+            let (nearest, other) = match steps.round() {
+                rounded if rounded == steps.trunc() => (rounded, rounded + steps.signum()),
+                rounded => (rounded, steps.trunc()),
+            };
+            debug!(?adj, ?increment, nearest, "rounding to the trade increment");
+            if !plan.change_trade(i, increment * nearest, cash_minimum) {
+                plan.change_trade(i, increment * other, cash_minimum);
+            }
+        }
+    }
+
+    /// Round the trades of holdings that can only be traded in whole shares
+    /// to a whole number of shares. If rounding a trade would take the cash
+    /// sweep below `cash_minimum`, it is rounded the other way instead.
+    fn round_to_whole_shares(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        for i in 0..plan.adjustments.len() {
+            let adj = &plan.adjustments[i];
+            if adj.ignored
+                || adj.holding.is_cash
+                || !self.whole_shares.contains(&adj.holding.symbol)
+            {
+                continue;
+            }
+            let change = adj.action.change();
+            let Some(price) = adj.holding.share_price().filter(|price| price.0 > 0.0) else {
+                continue;
+            };
+            // selling everything is always possible
+            if change == Dollar(0.0) || (adj.result().0 / price.0).abs() < 0.001 {
+                continue;
+            }
+            let shares = change.0 / price.0;
+            let fewer = shares.trunc();
+            let more = fewer + shares.signum();
+            let (preferred, other) = match self.share_rounding {
+                ShareRounding::Nearest if (shares - fewer).abs() >= 0.5 => (more, fewer),
+                ShareRounding::Nearest | ShareRounding::Down => (fewer, more),
+            };
+            debug!(?adj, shares, preferred, "rounding to whole shares");
+            if !plan.change_trade(i, Dollar(preferred * price.0), cash_minimum) {
+                plan.change_trade(i, Dollar(other * price.0), cash_minimum);
+            }
+        }
+    }
+
+    /// Leave out the sells that would realize short-term gains, if they are to
+    /// be deferred, and buy that much less of the other holdings. A sell isn't
+    /// left out if there isn't enough being bought for that, and the cash
+    /// position would end up with less than `cash_minimum` without it.
+    fn hold_short_term_gains(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        if !self.defer_short_term_gains {
+            return;
+        }
+        let bought = plan
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+            .filter_map(|adj| match adj.action {
+                Action::Buy(amount) => Some(amount),
+                _ => None,
+            })
+            .fold(Dollar(0.0), |total, amount| total + amount);
+        // how much less may be sold than would pay for the buys, which is
+        // unlimited without a cash position
+        let spare_cash = plan
+            .adjustments
+            .iter()
+            .find(|adj| !adj.ignored && adj.holding.is_cash)
+            .map(|cash| (cash.result() - cash_minimum).max(Dollar(0.0)));
+        let mut unsold = Dollar(0.0);
+        for adj in plan.adjustments.iter_mut().filter(|adj| !adj.ignored) {
+            if adj.short_term_until.is_some()
+                && adj.realized_gain().is_some_and(|gain| gain > Dollar(0.0))
+            {
+                let sold = Dollar(0.0) - adj.action.change();
+                if spare_cash.is_some_and(|spare| unsold + sold > bought + spare) {
+                    debug!(
+                        ?adj,
+                        "sell would realize short-term gains, but is needed for cash"
+                    );
+                    continue;
+                }
+                debug!(?adj, "sell would realize short-term gains");
+                unsold += sold;
+                adj.action = Action::DoNothing;
+                adj.held = Some(HoldReason::ShortTermGain);
+            }
+        }
+        // without these sells, there is less to buy with
+        if unsold > Dollar(0.0) {
+            plan.reduce_buys(unsold);
+        }
+    }
+
+    /// Leave out the trades of holdings that are within their tolerance band,
+    /// so that small drifts don't cause small trades. Holdings without a
+    /// target are always sold.
+    fn hold_within_bands(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        for (i, drift) in plan.drift().into_iter().enumerate() {
+            let adj = &plan.adjustments[i];
+            let band = self
+                .tolerance_bands
+                .get(&adj.holding.symbol)
+                .or(self.tolerance_band.as_ref());
+            if let (Some(drift), Some(band)) = (drift, band)
+                && adj.target > Percent(0.0)
+                && band.contains(adj.target, drift.0)
+            {
+                debug!(?adj, ?drift, "holding is within its tolerance band");
+                plan.hold_trade(i, HoldReason::WithinBand, cash_minimum);
+            }
+        }
+    }
+
+    /// Leave out the trades of holdings that are within their target ranges,
+    /// and only trade the others back to the nearest end of their range.
+    /// Whatever is no longer sold is no longer bought either (except for the
+    /// buys back up to the bottom of a range), and whatever is no longer
+    /// bought is kept in cash.
+    fn trade_to_ranges(&self, plan: &mut RebalancePlan) {
+        let total = plan.explanation.distributable;
+        let mut unsold = Dollar(0.0);
+        let mut changed = false;
+        let mut to_minimum = Vec::new();
+        for (i, adj) in plan
+            .adjustments
+            .iter_mut()
+            .enumerate()
+            .filter(|(_, adj)| !adj.ignored && !adj.holding.is_cash && adj.held.is_none())
+        {
+            let Some(range) = self.target_ranges.get(&adj.holding.symbol) else {
+                continue;
+            };
+            let current = adj.holding.current_value;
+            let change = current.max(total * range.min).min(total * range.max) - current;
+            if change > Dollar(0.0) {
+                to_minimum.push(i);
+            }
+            if change == adj.action.change() {
+                continue;
+            }
+            debug!(
+                ?adj,
+                ?range,
+                ?change,
+                "trading holding only as far as its target range"
+            );
+            if change == Dollar(0.0) {
+                adj.held = Some(HoldReason::WithinRange);
+            }
+            unsold += change - adj.action.change();
+            adj.action = Action::from_change(change);
+            changed = true;
+        }
+        match unsold > Dollar(0.0) {
+            true => plan.reduce_buys_except(unsold, &to_minimum),
+            false if changed => plan.balance_cash(Dollar(0.0)),
+            false => {}
+        }
+    }
+
+    /// Whether the [Self::rebalance_policy] calls for `plan` to be carried out
+    /// `today`, given the date on which the account was `last_rebalanced` (if
+    /// it ever was), or `None` if the account has no policy
+    pub fn rebalance_status(
+        &self,
+        plan: &RebalancePlan,
+        last_rebalanced: Option<Date>,
+        today: Date,
+    ) -> Option<RebalanceStatus> {
+        let policy = self.rebalance_policy?;
+        let mut reasons = Vec::new();
+        let next_date = policy.every.map(|every| {
+            let next = last_rebalanced.map(|last| last.add_months(every.months()));
+            match (last_rebalanced, next) {
+                (Some(last), Some(next)) if next <= today => reasons.push(format!(
+                    "a rebalance was due on {next} (last rebalanced on {last})"
+                )),
+                (None, _) => reasons.push("the account hasn't been rebalanced yet".to_string()),
+                _ => {}
+            }
+            next.unwrap_or(today)
+        });
+        if let Some(limit) = policy.max_drift {
+            for (adj, drift) in plan.adjustments.iter().zip(plan.drift()) {
+                if let Some(drift) = drift.filter(|drift| drift.0.0.abs() > limit.0) {
+                    reasons.push(format!(
+                        "'{}' has drifted {drift} from its target, more than {limit}",
+                        adj.holding.symbol
+                    ));
+                }
+            }
+        }
+        Some(RebalanceStatus {
+            warranted: !reasons.is_empty(),
+            reasons,
+            next_date,
+        })
+    }
+
+    /// The range of values that the holding of `adj` may end up with without
+    /// being traded any further: its target range (a share of the `total`
+    /// value of the account), or its tolerance band around its desired value
+    /// (a drift from its share of the account), within its constraints
+    fn tolerated_values(
+        &self,
+        adj: &PositionAdjustment,
+        total: Dollar,
+        cash_minimum: Dollar,
+    ) -> (Dollar, Dollar) {
+        let symbol = &adj.holding.symbol;
+        let desired = adj.desired_value;
+        let (mut low, mut high) = match self.target_ranges.get(symbol) {
+            Some(range) => (total * range.min, total * range.max),
+            None => {
+                let band = self
+                    .tolerance_bands
+                    .get(symbol)
+                    .or(self.tolerance_band.as_ref())
+                    .filter(|_| adj.target > Percent(0.0));
+                // the largest drift that is within every limit of the band
+                let drift = band.map_or(Percent(0.0), |band| {
+                    let relative = band.relative.map(|limit| adj.target * limit);
+                    match (band.absolute, relative) {
+                        (Some(absolute), Some(relative)) => Percent(absolute.0.min(relative.0)),
+                        (absolute, relative) => absolute.or(relative).unwrap_or_default(),
+                    }
+                });
+                (desired - total * drift, desired + total * drift)
+            }
+        };
+        let constraints = self.constraints.get(symbol).copied().unwrap_or_default();
+        if let Some(min) = constraints.min {
+            low = low.max(total * min);
+        }
+        if let Some(max) = constraints.max {
+            high = high.min(total * max);
+        }
+        if constraints.no_sell {
+            low = low.max(adj.holding.current_value);
+        }
+        if adj.holding.is_cash {
+            low = low.max(cash_minimum);
+        }
+        // the desired value is always tolerated, even if it is outside of the
+        // range (e.g. because a constraint moved it)
+        (low.max(Dollar(0.0)).min(desired), high.max(desired))
+    }
+
+    /// Trade as few holdings as possible, while still bringing every holding
+    /// within its tolerance band (or target range). Holdings that are outside
+    /// of their bands have to be traded, and if that doesn't balance, the
+    /// holdings that can absorb the most are traded as well. The money is
+    /// then divided between the traded holdings in proportion to how far each
+    /// of them may go from its desired value.
+    fn trade_fewest(&self, plan: &mut RebalancePlan, cash_minimum: Dollar) {
+        if self.tolerance_band.is_none()
+            && self.tolerance_bands.is_empty()
+            && self.target_ranges.is_empty()
+        {
+            plan.warnings.push(
+                "Trading as few holdings as possible has no effect without tolerance bands or target ranges"
+                    .to_string(),
+            );
+        }
+        let total = plan.explanation.distributable;
+        struct Candidate {
+            index: usize,
+            current: Dollar,
+            desired: Dollar,
+            low: Dollar,
+            high: Dollar,
+        }
+        let candidates: Vec<_> = plan
+            .adjustments
+            .iter()
+            .enumerate()
+            .filter(|(_, adj)| !adj.ignored)
+            .map(|(index, adj)| {
+                let (low, high) = self.tolerated_values(adj, total, cash_minimum);
+                Candidate {
+                    index,
+                    current: adj.holding.current_value,
+                    desired: adj.desired_value,
+                    low,
+                    high,
+                }
+            })
+            .collect();
+        // the cash position isn't an order, so it is always free to change
+        let (mut traded, mut untraded): (Vec<_>, Vec<_>) = candidates.into_iter().partition(|c| {
+            plan.adjustments[c.index].holding.is_cash || c.current < c.low || c.current > c.high
+        });
+        let room = |traded: &[Candidate]| {
+            traded
+                .iter()
+                .fold((Dollar(0.0), Dollar(0.0)), |(down, up), c| {
+                    (down + (c.current - c.low), up + (c.high - c.current))
+                })
+        };
+        // the traded holdings have to be able to sell as much as they buy, so
+        // whichever side falls short takes the holdings that add the most to
+        // it, which are all within their bands and only add to either side
+        loop {
+            let (down, up) = room(&traded);
+            let short: fn(&Candidate) -> Dollar = match (down < Dollar(0.0), up < Dollar(0.0)) {
+                (true, _) => |c| c.current - c.low,
+                (_, true) => |c| c.high - c.current,
+                _ => break,
+            };
+            let Some(best) = (0..untraded.len()).max_by(|&a, &b| {
+                short(&untraded[a])
+                    .partial_cmp(&short(&untraded[b]))
+                    .unwrap_or(std::cmp::Ordering::Equal)
+            }) else {
+                break;
+            };
+            traded.push(untraded.swap_remove(best));
+        }
+
+        // trading the traded holdings to their desired values buys (or sells)
+        // what the untraded holdings would have sold (or bought), so take the
+        // excess from the traded holdings as evenly as their ranges allow
+        let excess = traded
+            .iter()
+            .fold(Dollar(0.0), |total, c| total + (c.desired - c.current));
+        let (below, above) = traded
+            .iter()
+            .fold((Dollar(0.0), Dollar(0.0)), |(below, above), c| {
+                (below + (c.desired - c.low), above + (c.high - c.desired))
+            });
+        for c in traded.iter() {
+            let value = match excess > Dollar(0.0) {
+                true if below > Dollar(0.0) => {
+                    c.desired - (c.desired - c.low) * (excess.0 / below.0).min(1.0)
+                }
+                false if above > Dollar(0.0) => {
+                    c.desired + (c.high - c.desired) * (-excess.0 / above.0).min(1.0)
+                }
+                _ => c.desired,
+            };
+            let adj = &mut plan.adjustments[c.index];
+            if !adj.holding.is_cash {
+                adj.action = Action::from_change(value - c.current);
+            }
+        }
+        for c in untraded.iter() {
+            let adj = &mut plan.adjustments[c.index];
+            if adj.action.change() != Dollar(0.0) {
+                debug!(?adj, "holding doesn't need to be traded");
+                adj.held = Some(match self.target_ranges.contains_key(&adj.holding.symbol) {
+                    true => HoldReason::WithinRange,
+                    false => HoldReason::WithinBand,
+                });
+            }
+            adj.action = Action::DoNothing;
+        }
+        plan.balance_cash(Dollar(0.0));
+    }
+
+    /// Write `configs` in the given format, so that they can be loaded again
+    /// with [Self::parse]
+    pub fn to_text(configs: &[Self], format: ConfigFormat) -> anyhow::Result<String> {
+        let yaml = serde_yaml::to_string(configs)?;
+        match format {
+            ConfigFormat::Yaml => Ok(yaml),
+            ConfigFormat::Toml => {
+                // going through the YAML text keeps numbers such as 33.3 from
+                // being widened into 33.29999923706055
+                let accounts: serde_yaml::Value = serde_yaml::from_str(&yaml)?;
+                let mut document = serde_yaml::Mapping::new();
+                document.insert("Accounts".into(), accounts);
+                crate::toml::to_string(&serde_yaml::Value::Mapping(document))
+            }
+        }
+    }
+
+    /// Save `configs` to the file at `path`, in the format given by its
+    /// extension (see [ConfigFormat::from_path])
+    pub fn save_to_file<P: AsRef<Path>>(configs: &[Self], path: P) -> anyhow::Result<()> {
+        let path = path.as_ref();
+        let text = Self::to_text(configs, ConfigFormat::from_path(path))?;
+        std::fs::write(path, text).with_context(|| format!("Failed to write file {path:?}"))
+    }
+
+    /// A starting configuration for `balance`, whose targets are the current
+    /// shares of its holdings, rounded to whole percentages that add up to
+    /// 100%. The cash holding becomes the cash sweep (without a minimum), and
+    /// keeps the rest of the account. Pending activity and holdings with a
+    /// negative value are left out.
+    pub fn from_balance(balance: &Balance) -> Self {
+        let holdings: Vec<&Holding> = balance
+            .holdings
+            .iter()
+            .filter(|holding| !holding.pending && holding.current_value >= Dollar(0.0))
+            .collect();
+        let total = holdings
+            .iter()
+            .fold(Dollar(0.0), |total, holding| total + holding.current_value);
+        let shares: Vec<f32> = holdings
+            .iter()
+            .map(|holding| Percent::new(holding.current_value, total).0)
+            .collect();
+        // round by largest remainder so that the percentages add up to 100
+        let mut rounded: Vec<f32> = shares.iter().map(|share| share.floor()).collect();
+        let missing = match total > Dollar(0.0) {
+            true => (100.0 - rounded.iter().sum::<f32>()).round() as usize,
+            false => 0,
+        };
+        let mut order: Vec<usize> = (0..shares.len()).collect();
+        order.sort_by(|a, b| (shares[*b] - rounded[*b]).total_cmp(&(shares[*a] - rounded[*a])));
+        for i in order.into_iter().take(missing) {
+            rounded[i] += 1.0;
+        }
+
+        let mut config = Self {
+            account_id: balance.account_id.clone(),
+            ..Default::default()
+        };
+        for (holding, percent) in holdings.into_iter().zip(rounded) {
+            match (holding.is_cash, config.cash_sweep.as_mut()) {
+                (true, None) => {
+                    config.cash_sweep = Some(CashConfig {
+                        symbol: holding.symbol.clone(),
+                        currency: holding.currency.clone(),
+                        ..Default::default()
+                    })
+                }
+                (true, Some(sweep)) => sweep.other_symbols.push(holding.symbol.clone()),
+                (false, _) => {
+                    config
+                        .targets
+                        .insert(holding.symbol.clone(), Percent(percent));
+                }
+            }
+        }
+        config
+    }
+
+    pub fn example_config(format: ConfigFormat) -> anyhow::Result<String> {
+        let mut targets = HashMap::new();
+        targets.insert("SYMBOL1".to_string(), Percent(75.0));
+        targets.insert("SYMBOL2".to_string(), Percent(25.0));
+        let ignored_holdings = vec!["SYMBOL3".to_string()];
+        let config = Self {
+            account_id: "<ACCOUNT_ID>".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CASH_SYMBOL".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets,
+            ignored_holdings,
+            nickname: None,
+            ..Default::default()
+        };
+        let s = Self::to_text(&[config], format)?;
+        let comment = r#"# This is an example configuration.
+# Modify the following lines to suit your needs
+"#
+        .to_string();
+        Ok(comment + &s)
+    }
+}
+
+fn glob_match(pattern: &[char], text: &[char]) -> bool {
+    match pattern.split_first() {
+        None => text.is_empty(),
+        Some(('*', rest)) => (0..=text.len()).any(|skip| glob_match(rest, &text[skip..])),
+        Some((c, rest)) => text
+            .split_first()
+            .is_some_and(|(t, text)| (c == t || *c == '?') && glob_match(rest, text)),
+    }
+}
+
+/// Pair each of the `balances` with the configuration that applies to it, if
+/// any. A configuration with the exact ID of an account takes precedence over
+/// patterns (see [AllocationConfig::matches_account]). The configurations that
+/// are returned have the ID of the account they are paired with. It is an
+/// error for an account to match more than one pattern, or for a pattern to
+/// match more than one account.
+pub fn pair_with_configs(
+    balances: Vec<Balance>,
+    configs: &[&AllocationConfig],
+) -> anyhow::Result<Vec<(Balance, AllocationConfig)>> {
+    let mut paired = Vec::new();
+    let mut matched: HashMap<&str, String> = HashMap::new();
+    for balance in balances {
+        let config = match configs.iter().find(|c| c.account_id == balance.account_id) {
+            Some(config) => config,
+            None => {
+                let mut patterns = configs
+                    .iter()
+                    .filter(|c| c.is_pattern() && c.matches_account(&balance.account_id));
+                let Some(config) = patterns.next() else {
+                    continue;
+                };
+                if let Some(other) = patterns.next() {
+                    bail!(
+                        "Account {} matches both '{}' and '{}' in the target configuration",
+                        balance.account_id,
+                        config.account_id,
+                        other.account_id
+                    );
+                }
+                if let Some(other) = matched.insert(&config.account_id, balance.account_id.clone())
+                {
+                    bail!(
+                        "'{}' in the target configuration matches both account {other} and account {}",
+                        config.account_id,
+                        balance.account_id
+                    );
+                }
+                config
+            }
+        };
+        let mut config = (*config).clone();
+        config.account_id = balance.account_id.clone();
+        paired.push((balance, config));
+    }
+    Ok(paired)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::Action;
+
+    /// A holding with just a symbol and a value
+    pub(crate) fn holding(symbol: &str, value: f32, is_cash: bool) -> Holding {
+        Holding {
+            symbol: symbol.to_string(),
+            current_value: Dollar(value),
+            is_cash,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_config_validate() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(50.0));
+        targets.insert("B".to_string(), Percent(50.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(100.0),
+                ..Default::default()
+            }),
+            targets,
+            ignored_holdings: vec![],
+            nickname: None,
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(50.0));
+        targets.insert("B".to_string(), Percent(40.0));
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(100.0),
+                ..Default::default()
+            }),
+            targets,
+            ignored_holdings: vec![],
+            nickname: None,
+            ..Default::default()
+        };
+        // the rest is kept in the cash sweep
+        assert!(config.validate().is_ok());
+        config.targets.insert("B".to_string(), Percent(60.0));
+        assert!(config.validate().is_err());
+        config.targets.insert("B".to_string(), Percent(40.0));
+        config.cash_sweep = None;
+        assert!(config.validate().is_err());
+
+        config.targets = HashMap::from([
+            ("A".to_string(), Percent(33.3)),
+            ("B".to_string(), Percent(33.3)),
+            ("C".to_string(), Percent(33.4)),
+        ]);
+        assert!(config.validate().is_ok());
+        config.targets.insert("C".to_string(), Percent(33.3));
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("add up to 99.90%, which is 0.10% less than 100%"));
+        config.target_sum_tolerance = Some(Percent(0.2));
+        assert!(config.validate().is_ok());
+        config.targets.insert("C".to_string(), Percent(33.7));
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("add up to 100.30%, which is 0.30% more than 100%"));
+    }
+
+    #[test]
+    fn test_adjust_allocations_basic() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(50.0));
+        targets.insert("B".to_string(), Percent(50.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets,
+            ignored_holdings: vec![],
+            nickname: None,
+            ..Default::default()
+        };
+
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "CORE".to_string(),
+                    current_value: Dollar(5000.0),
+                    is_cash: true,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "B".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let adjustments = config.adjust_allocations(&balance).unwrap().adjustments;
+        assert_eq!(adjustments.len(), 3);
+
+        let core_adj = adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "CORE")
+            .unwrap();
+        if let Action::Sell(amount) = core_adj.action {
+            assert_eq!(amount, Dollar(4000.0));
+        } else {
+            panic!(
+                "CORE action should be Sell(4000.0), but was {:?}",
+                core_adj.action
+            );
+        }
+
+        let a_adj = adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "A")
+            .unwrap();
+        if let Action::Buy(amount) = a_adj.action {
+            assert_eq!(amount, Dollar(2000.0));
+        } else {
+            panic!("A action should be Buy(2000.0), but was {:?}", a_adj.action);
+        }
+
+        let b_adj = adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "B")
+            .unwrap();
+        if let Action::Buy(amount) = b_adj.action {
+            assert_eq!(amount, Dollar(2000.0));
+        } else {
+            panic!("B action should be Buy(2000.0), but was {:?}", b_adj.action);
+        }
+    }
+
+    #[test]
+    fn test_adjust_allocations_with_ignored() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(100.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets,
+            ignored_holdings: vec!["IGNORED".to_string()],
+            nickname: None,
+            ..Default::default()
+        };
+
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "CORE".to_string(),
+                    current_value: Dollar(5000.0),
+                    is_cash: true,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "IGNORED".to_string(),
+                    current_value: Dollar(2000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let adjustments = config.adjust_allocations(&balance).unwrap().adjustments;
+
+        assert_eq!(adjustments.len(), 3);
+
+        let ignored_adj = adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "IGNORED")
+            .unwrap();
+        assert!(ignored_adj.ignored);
+
+        // the total value to consider for distribution is 6000 (5000 core +
+        // 1000 A), since IGNORED is ignored
+        let a_adj = adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "A")
+            .unwrap();
+        if let Action::Buy(amount) = a_adj.action {
+            assert_eq!(amount, Dollar(4000.0));
+        } else {
+            panic!("A action should be Buy(4000.0), but was {:?}", a_adj.action);
+        }
+        let core_adj = adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "CORE")
+            .unwrap();
+        if let Action::Sell(amount) = core_adj.action {
+            assert_eq!(amount, Dollar(4000.0));
+        } else {
+            panic!(
+                "CORE action should be Sell(4000.0), but was {:?}",
+                core_adj.action
+            );
+        }
+    }
+
+    #[test]
+    fn test_plan_apply() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(50.0));
+        targets.insert("B".to_string(), Percent(50.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets,
+            ignored_holdings: vec![],
+            nickname: None,
+            ..Default::default()
+        };
+
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "CORE".to_string(),
+                    current_value: Dollar(5000.0),
+                    is_cash: true,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let projected = plan.apply(&balance);
+        assert_eq!(projected.total_value(), balance.total_value());
+        assert_eq!(projected.holdings.len(), 3);
+
+        let value_of = |symbol: &str| {
+            projected
+                .holdings
+                .iter()
+                .find(|h| h.symbol == symbol)
+                .map(|h| h.current_value)
+                .unwrap()
+        };
+        assert_eq!(value_of("CORE"), Dollar(1000.0));
+        assert_eq!(value_of("A"), Dollar(2500.0));
+        assert_eq!(value_of("B"), Dollar(2500.0));
+    }
+
+    #[test]
+    fn test_pending_activity() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "Pending activity".to_string(),
+                    current_value: Dollar(1000.0),
+                    pending: true,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "CORE".to_string(),
+                    current_value: Dollar(0.0),
+                    is_cash: true,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(9000.0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets: HashMap::from([("A".to_string(), Percent(100.0))]),
+            ..Default::default()
+        };
+        // by default, pending activity is part of the cash that is invested
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.explanation.distributable, Dollar(10000.0));
+        assert_eq!(plan.other_cash.len(), 1);
+        assert!(plan.adjustments.iter().all(|adj| !adj.holding.pending));
+        let a = plan
+            .adjustments
+            .iter()
+            .find(|adj| adj.holding.symbol == "A");
+        assert!(matches!(a.unwrap().action, Action::Buy(amount) if amount == Dollar(1000.0)));
+
+        // or it is shown on its own and not counted
+        config.pending_activity = PendingActivity::Separate;
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.explanation.distributable, Dollar(9000.0));
+        let pending = plan
+            .adjustments
+            .iter()
+            .find(|adj| adj.holding.pending)
+            .unwrap();
+        assert!(pending.ignored && matches!(pending.action, Action::DoNothing));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // without a cash position, it can't be counted as cash
+        config.pending_activity = PendingActivity::Cash;
+        let mut balance = balance;
+        balance.holdings.retain(|holding| !holding.is_cash);
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.explanation.distributable, Dollar(9000.0));
+        assert_eq!(plan.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_negative_holdings() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                // a margin debit
+                holding("CORE", -500.0, true),
+                holding("A", 10000.0, false),
+                // a short position
+                holding("SHORT", -1000.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets: HashMap::from([("A".to_string(), Percent(100.0))]),
+            ..Default::default()
+        };
+        let find = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .map(|adj| (adj.result().round_cents(), adj.ignored))
+                .unwrap()
+        };
+        // the short position is bought back and the debit is paid off
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.explanation.distributable, Dollar(8500.0));
+        assert_eq!(find(&plan, "SHORT"), (Dollar(0.0), false));
+        assert_eq!(find(&plan, "CORE"), (Dollar(0.0), false));
+        assert_eq!(find(&plan, "A"), (Dollar(8500.0), false));
+        assert_eq!(plan.warnings.len(), 1);
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        config.negative_holdings = NegativeHoldings::Ignore;
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.explanation.distributable, Dollar(9500.0));
+        assert_eq!(find(&plan, "SHORT"), (Dollar(-1000.0), true));
+        assert_eq!(find(&plan, "CORE"), (Dollar(0.0), false));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // an account that is worth less than nothing can't be distributed
+        config.negative_holdings = NegativeHoldings::Cover;
+        let mut balance = balance;
+        balance.holdings.push(holding("SHORT2", -20000.0, false));
+        assert!(config.adjust_allocations(&balance).is_err());
+    }
+
+    #[test]
+    fn test_net_is_zero() {
+        let mut balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 1.0, false),
+                holding("B", 0.0, false),
+                holding("C", 0.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(33.3)),
+                ("B".to_string(), Percent(33.3)),
+                ("C".to_string(), Percent(33.4)),
+            ]),
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.net(), Dollar(0.0));
+
+        // without a cash position, the cent that rounding leaves over goes to
+        // the trade that was rounded down the most
+        balance.holdings.retain(|holding| !holding.is_cash);
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.net(), Dollar(0.0));
+        let result = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .map(|adj| adj.result().round_cents())
+                .unwrap()
+        };
+        assert_eq!(result("A"), Dollar(0.33));
+        assert_eq!(result("B"), Dollar(0.33));
+        assert_eq!(result("C"), Dollar(0.34));
+    }
+
+    #[test]
+    fn test_apply_price_change() {
+        let mut balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "VTI".to_string(),
+                    current_value: Dollar(1000.0),
+                    quantity: Some(4.0),
+                    price: Some(Dollar(250.0)),
+                    cost_basis: Some(Dollar(800.0)),
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "BND".to_string(),
+                    current_value: Dollar(500.0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        assert!(balance.apply_price_change("VTI", Percent(-20.0)));
+        assert!(!balance.apply_price_change("VXUS", Percent(10.0)));
+        let vti = &balance.holdings[0];
+        assert_eq!(vti.current_value, Dollar(800.0));
+        assert_eq!(vti.price, Some(Dollar(200.0)));
+        // the shares and what was paid for them don't change
+        assert_eq!(vti.quantity, Some(4.0));
+        assert_eq!(vti.cost_basis, Some(Dollar(800.0)));
+        assert_eq!(balance.total_value(), Dollar(1300.0));
+    }
+
+    #[test]
+    fn test_rebalance_status() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![holding("A", 5300.0, false), holding("B", 4700.0, false)],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(50.0)),
+            ]),
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let today = Date::new(2026, 10, 14).unwrap();
+        assert_eq!(config.rebalance_status(&plan, None, today), None);
+
+        config.rebalance_policy = Some(RebalancePolicy {
+            every: Some(RebalanceFrequency::Quarterly),
+            max_drift: Some(Percent(5.0)),
+        });
+        assert!(config.validate().is_ok());
+        let last = Date::new(2026, 8, 1).unwrap();
+        let status = config.rebalance_status(&plan, Some(last), today).unwrap();
+        assert!(!status.warranted);
+        assert_eq!(status.next_date, Date::new(2026, 11, 1));
+        // the plan has trades, but the policy says that they can wait
+        assert!(!plan.recommends_rebalance(Some(&status)));
+        assert!(plan.recommends_rebalance(None));
+
+        // a quarter has passed
+        let last = Date::new(2026, 7, 14).unwrap();
+        let status = config.rebalance_status(&plan, Some(last), today).unwrap();
+        assert!(status.warranted);
+        assert!(plan.recommends_rebalance(Some(&status)));
+        assert_eq!(
+            status.reasons,
+            vec!["a rebalance was due on 2026-10-14 (last rebalanced on 2026-07-14)"]
+        );
+        assert!(
+            config
+                .rebalance_status(&plan, None, today)
+                .unwrap()
+                .warranted
+        );
+
+        // a holding has drifted too far
+        config.rebalance_policy = Some(RebalancePolicy {
+            every: None,
+            max_drift: Some(Percent(2.0)),
+        });
+        let status = config.rebalance_status(&plan, None, today).unwrap();
+        assert!(status.warranted);
+        assert_eq!(status.reasons.len(), 2);
+        assert_eq!(status.next_date, None);
+
+        config.rebalance_policy = Some(RebalancePolicy::default());
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let yaml = r#"
+Models:
+  balanced:
+    VTI: 33.3
+    VXUS: 33.3
+Accounts:
+  - AccountId: "1"
+    Name: Roth IRA
+    Model: balanced
+    CashSweep:
+      Symbol: CORE
+      Minimum: 1000
+      MinimumPercent: 2.5
+    Targets:
+      BND: 33.4
+    ToleranceBand:
+      Absolute: 5
+    Constraints:
+      "BRK.B": {NoSell: true}
+    EquivalentSymbols: [[VTI, ITOT]]
+    RebalancePolicy:
+      Every: Quarterly
+    Labels:
+      VTI: "US \"total\" market"
+  - AccountId: "2"
+    GlidePath:
+      - Age: 60
+        Targets: {VTI: 60, BND: 40}
+      - Age: 70
+        Targets: {VTI: 40, BND: 60}
+    BirthDate: 1970-01-01
+    NestedTargets:
+      Equity:
+        Percent: 0
+        Targets:
+          VXUS: 100
+"#;
+        let configs = AllocationConfig::parse(yaml, ConfigFormat::Yaml, false).unwrap();
+        assert_eq!(configs[0].model, None);
+        for format in [ConfigFormat::Yaml, ConfigFormat::Toml] {
+            let text = AllocationConfig::to_text(&configs, format).unwrap();
+            assert_eq!(
+                AllocationConfig::parse(&text, format, false).unwrap(),
+                configs,
+                "{text}"
+            );
+        }
+
+        let path = std::env::temp_dir().join(format!("driftfix-{}.toml", std::process::id()));
+        AllocationConfig::save_to_file(&configs, &path).unwrap();
+        assert_eq!(AllocationConfig::load_from_file(&path).unwrap(), configs);
+        std::fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn test_from_balance() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 500.0, true),
+                holding("A", 3333.0, false),
+                holding("B", 3333.0, false),
+                holding("C", 2834.0, false),
+                holding("SHORT", -100.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig::from_balance(&balance);
+        assert_eq!(config.account_id, "123");
+        assert_eq!(config.cash_sweep.as_ref().unwrap().symbol, "CORE");
+        assert_eq!(config.cash_sweep.as_ref().unwrap().minimum, Dollar(0.0));
+        assert_eq!(
+            config.targets,
+            HashMap::from([
+                ("A".to_string(), Percent(33.0)),
+                ("B".to_string(), Percent(33.0)),
+                ("C".to_string(), Percent(29.0)),
+            ])
+        );
+        // the rest of the account is the target of the cash sweep
+        config.validate().unwrap();
+
+        let config = AllocationConfig::from_balance(&Balance {
+            holdings: vec![holding("A", 1.0, false), holding("B", 2.0, false)],
+            ..balance
+        });
+        assert!(config.cash_sweep.is_none());
+        assert_eq!(
+            config.targets.values().copied().sum::<Percent>(),
+            Percent(100.0)
+        );
+        config.validate().unwrap();
+    }
+
+    #[test]
+    fn test_from_balance_plan() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 500.0, true),
+                holding("A", 6000.0, false),
+                holding("B", 3500.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig::from_balance(&balance);
+        let text = AllocationConfig::to_text(&[config], ConfigFormat::Yaml).unwrap();
+        assert!(!text.contains("IgnoredHoldings"), "{text}");
+        let config = AllocationConfig::parse(&text, ConfigFormat::Yaml, false)
+            .unwrap()
+            .remove(0);
+
+        // a configuration of the current holdings is already on target
+        let plan = config.adjust_allocations(&balance).unwrap();
+        for (adj, drift) in plan.adjustments.iter().zip(plan.drift()) {
+            assert!(matches!(adj.action, Action::DoNothing), "{adj:?}");
+            assert!(drift.is_none_or(|drift| drift.0.0.abs() < 0.01), "{adj:?}");
+        }
+        assert!(plan.drift_score().unwrap().0 < 0.01);
+    }
+
+    #[test]
+    fn test_toml_config() {
+        let yaml = r#"
+- AccountId: "123"
+  CashSweep:
+    Symbol: CORE
+    Minimum: 1000
+  Targets:
+    VTI: 60
+    BND: 40
+  IgnoredHoldings: [IBM]
+"#;
+        let toml = r#"
+[[Accounts]]
+AccountId = "123"
+CashSweep = { Symbol = "CORE", Minimum = 1000 }
+IgnoredHoldings = ["IBM"]
+
+[Accounts.Targets]
+VTI = 60
+BND = 40
+"#;
+        let yaml = AllocationConfig::parse(yaml, ConfigFormat::Yaml, false).unwrap();
+        let toml = AllocationConfig::parse(toml, ConfigFormat::Toml, false).unwrap();
+        assert_eq!(yaml, toml);
+        assert!(AllocationConfig::parse("[[Account]]\n", ConfigFormat::Toml, false).is_err());
+        let error = AllocationConfig::parse(
+            "[[Accounts]]\nAccountId = \"1\"\nTargets.VTI = \"abc\"\n",
+            ConfigFormat::Toml,
+            false,
+        )
+        .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "Accounts[0].Targets.VTI: invalid type: string \"abc\", expected f32"
+        );
+
+        let example = AllocationConfig::example_config(ConfigFormat::Toml).unwrap();
+        assert_eq!(
+            AllocationConfig::parse(&example, ConfigFormat::Toml, false).unwrap(),
+            AllocationConfig::parse(
+                &AllocationConfig::example_config(ConfigFormat::Yaml).unwrap(),
+                ConfigFormat::Yaml,
+                false
+            )
+            .unwrap()
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("target.TOML")),
+            ConfigFormat::Toml
+        );
+        assert_eq!(
+            ConfigFormat::from_path(Path::new("target.yml")),
+            ConfigFormat::Yaml
+        );
+    }
+
+    #[test]
+    fn test_pair_with_configs() {
+        let config = |id: &str| AllocationConfig {
+            account_id: id.to_string(),
+            ..Default::default()
+        };
+        let balance = |id: &str| Balance {
+            account_id: id.to_string(),
+            ..Default::default()
+        };
+        assert!(config("*6789").matches_account("X12346789"));
+        assert!(config("X1234?789").matches_account("X12346789"));
+        assert!(!config("*6789").matches_account("X12346780"));
+        assert!(!config("6789").matches_account("X12346789"));
+
+        let exact = config("X12346789");
+        let suffix = config("*6789");
+        let other = config("Z*");
+        let paired = pair_with_configs(
+            vec![balance("X12346789"), balance("Y00006789"), balance("W1")],
+            &[&suffix, &exact, &other],
+        )
+        .unwrap();
+        // the exact ID takes precedence, and accounts without a configuration
+        // are left out
+        assert_eq!(paired.len(), 2);
+        assert_eq!(paired[0].1, exact);
+        assert_eq!(paired[1].1.account_id, "Y00006789");
+
+        // ambiguous patterns are rejected
+        let error = pair_with_configs(vec![balance("Z6789")], &[&suffix, &other]).unwrap_err();
+        assert!(error.to_string().contains("matches both"));
+        let error =
+            pair_with_configs(vec![balance("A6789"), balance("B6789")], &[&suffix]).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("account A6789 and account B6789")
+        );
+    }
+
+    #[test]
+    fn test_config_dir() {
+        let dir = std::env::temp_dir().join(format!("driftfix-targets-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let write = |name: &str, text: &str| std::fs::write(dir.join(name), text).unwrap();
+        write(
+            "models.yml",
+            "Models:\n  all-vti:\n    VTI: 100\nAccounts: []\n",
+        );
+        write("roth.yml", "- AccountId: \"1\"\n  Model: all-vti\n");
+        write(
+            "taxable.toml",
+            "[[Accounts]]\nAccountId = \"2\"\nModel = \"all-vti\"\n",
+        );
+        write("notes.txt", "not a configuration");
+        let configs = AllocationConfig::load_from_file(&dir).unwrap();
+        let ids: Vec<&str> = configs.iter().map(|c| c.account_id.as_str()).collect();
+        assert_eq!(ids, vec!["1", "2"]);
+        assert_eq!(configs[1].targets.get("VTI"), Some(&Percent(100.0)));
+
+        write("copy.yml", "- AccountId: \"2\"\n  Targets:\n    VTI: 100\n");
+        let error = AllocationConfig::load_from_file(&dir).unwrap_err();
+        assert!(
+            error
+                .to_string()
+                .contains("also configured in another file")
+        );
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_account_aliases() {
+        let yaml = r#"
+- AccountId: "1234"
+  Name: Roth IRA
+  Aliases: [roth]
+  Targets:
+    VTI: 100
+- AccountId: "5678"
+  Aliases: [taxable, brokerage]
+  Targets:
+    VTI: 100
+"#;
+        let configs = AllocationConfig::parse(yaml, ConfigFormat::Yaml, false).unwrap();
+        assert_eq!(configs[0].nickname.as_deref(), Some("Roth IRA"));
+        assert!(configs[0].has_alias("Roth"));
+        assert!(!configs[0].has_alias("taxable"));
+        assert!(configs[1].has_alias("brokerage"));
+
+        let error = AllocationConfig::parse(
+            &yaml.replace("[taxable, brokerage]", "[ROTH]"),
+            ConfigFormat::Yaml,
+            false,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("'ROTH'"));
+    }
+
+    #[test]
+    fn test_ignore_alias() {
+        let configs = AllocationConfig::parse(
+            "- AccountId: \"1\"\n  Targets:\n    VTI: 100\n  Ignore: [IBM]\n",
+            ConfigFormat::Yaml,
+            false,
+        )
+        .unwrap();
+        assert_eq!(configs[0].ignored_holdings, vec!["IBM".to_string()]);
+    }
+
+    #[test]
+    fn test_models() {
+        let yaml = r#"
+Models:
+  three-fund-80/20:
+    VTI: 50
+    VXUS: 30
+    BND: 20
+Accounts:
+  - AccountId: "1"
+    Model: three-fund-80/20
+  - AccountId: "2"
+    Model: three-fund-80/20
+    Targets:
+      BND: 10
+      BNDX: 10
+"#;
+        let configs = AllocationConfig::parse(yaml, ConfigFormat::Yaml, false).unwrap();
+        assert_eq!(configs[0].targets.len(), 3);
+        assert_eq!(configs[0].targets.get("VTI"), Some(&Percent(50.0)));
+        assert_eq!(configs[1].targets.len(), 4);
+        assert_eq!(configs[1].targets.get("BND"), Some(&Percent(10.0)));
+        assert_eq!(configs[1].targets.get("BNDX"), Some(&Percent(10.0)));
+
+        let toml = r#"
+[Models."three-fund-80/20"]
+VTI = 50
+VXUS = 30
+BND = 20
+
+[[Accounts]]
+AccountId = "1"
+Model = "three-fund-80/20"
+"#;
+        let toml = AllocationConfig::parse(toml, ConfigFormat::Toml, false).unwrap();
+        assert_eq!(toml[0], configs[0]);
+
+        let error = AllocationConfig::parse(
+            "Accounts:\n  - AccountId: \"1\"\n    Model: missing\n",
+            ConfigFormat::Yaml,
+            false,
+        )
+        .unwrap_err();
+        assert!(error.to_string().contains("'missing'"));
+    }
+
+    #[test]
+    fn test_steps() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 100.0, true),
+                holding("A", 6000.0, false),
+                holding("B", 3900.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(40.0)),
+                ("B".to_string(), Percent(60.0)),
+            ]),
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        // only $100 of the buy can be paid for before the sell settles
+        assert_eq!(
+            plan.steps(),
+            vec![
+                Step::Sell {
+                    symbol: "A".to_string(),
+                    amount: Dollar(2000.0)
+                },
+                Step::WaitForSettlement {
+                    needed: Dollar(2000.0)
+                },
+                Step::Buy {
+                    symbol: "B".to_string(),
+                    amount: Dollar(2100.0)
+                },
+            ]
+        );
+        assert_eq!(plan.unfunded(), None);
+
+        // without any cash or sells, nothing pays for a buy
+        let plan = RebalancePlan {
+            adjustments: vec![PositionAdjustment {
+                holding: holding("A", 100.0, false),
+                action: Action::Buy(Dollar(50.0)),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(plan.unfunded(), Some(Dollar(50.0)));
+        assert_eq!(plan.steps().len(), 1);
+    }
+
+    #[test]
+    fn test_zero_value_holdings() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(100.0));
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets,
+            ..Default::default()
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "SOLD".to_string(),
+                    current_value: Dollar(0.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.adjustments.len(), 2);
+        assert!(plan.empty_holdings.is_empty());
+
+        config.zero_value_holdings = ZeroValueHoldings::Hide;
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.adjustments.len(), 1);
+        assert!(plan.empty_holdings.is_empty());
+
+        config.zero_value_holdings = ZeroValueHoldings::Collapse;
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.adjustments.len(), 1);
+        assert_eq!(plan.empty_holdings, vec!["SOLD".to_string()]);
+    }
+
+    #[test]
+    fn test_adjust_allocations_multi_currency_cash() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(100.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "EUR".to_string(),
+                minimum: Dollar(1000.0),
+                currency: Some("EUR".to_string()),
+                ..Default::default()
+            }),
+            targets,
+            ..Default::default()
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "USD".to_string(),
+                    current_value: Dollar(500.0),
+                    is_cash: true,
+                    currency: Some("USD".to_string()),
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "EUR".to_string(),
+                    current_value: Dollar(2200.0),
+                    is_cash: true,
+                    currency: Some("EUR".to_string()),
+                    exchange_rate: Some(1.1),
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(800.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let result_of = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|a| a.holding.symbol == symbol)
+                .map(|a| a.result())
+                .unwrap()
+        };
+        // the 1000 EUR minimum is worth 1100 in the base currency
+        assert_eq!(result_of("EUR"), Dollar(1100.0));
+        assert_eq!(result_of("USD"), Dollar(0.0));
+        assert_eq!(result_of("A"), Dollar(2400.0));
+        let currency_warning = |plan: &RebalancePlan| {
+            plan.warnings
+                .iter()
+                .any(|w| w.starts_with("The cash minimum is in EUR"))
+        };
+        assert!(!currency_warning(&plan));
+
+        // without an exchange rate, the minimum can't be converted
+        let mut balance = balance;
+        balance.holdings[1].exchange_rate = None;
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let eur = plan.adjustments.iter().find(|a| a.holding.symbol == "EUR");
+        assert_eq!(eur.unwrap().result(), Dollar(1000.0));
+        assert!(currency_warning(&plan));
+    }
+
+    #[test]
+    fn test_adjust_allocations_unmarked_cash_sweep() {
+        let mut targets = HashMap::new();
+        targets.insert("INDEX FUND".to_string(), Percent(100.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "STABLE VALUE".to_string(),
+                minimum: Dollar(500.0),
+                ..Default::default()
+            }),
+            targets,
+            ..Default::default()
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "401(k)".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "STABLE VALUE".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "INDEX FUND".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let cash = plan
+            .adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "STABLE VALUE")
+            .unwrap();
+        assert!(cash.holding.is_cash);
+        assert_eq!(cash.action.change(), Dollar(-500.0));
+
+        // a different holding that is marked as cash conflicts with the sweep
+        let mut balance = balance;
+        balance.holdings.push(holding("CORE", 100.0, true));
+        let err = config.adjust_allocations(&balance).unwrap_err();
+        assert!(err.to_string().contains("CashSweep.Symbol"), "{err}");
+        assert!(err.to_string().contains("'CORE'"), "{err}");
+    }
+
+    #[test]
+    fn test_plan_warnings() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(100.0));
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets,
+            ignored_holdings: vec!["TYPO".to_string()],
+            ..Default::default()
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "B".to_string(),
+                    current_value: Dollar(500.0),
+                    is_cash: false,
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(
+            plan.warnings,
+            vec![
+                "Ignored symbol 'TYPO' is not held in this account".to_string(),
+                "Holding 'B' has no target allocation, so all of it will be sold".to_string(),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_adjust_allocations_with_maturity() {
+        let mut targets = HashMap::new();
+        targets.insert("A".to_string(), Percent(100.0));
+        let mut maturities = HashMap::new();
+        maturities.insert("CD".to_string(), "2025-06-01".parse().unwrap());
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets,
+            maturities,
+            ..Default::default()
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            account_name: "Test Account".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(1000.0),
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "CD".to_string(),
+                    current_value: Dollar(5000.0),
+                    ..Default::default()
+                },
+            ],
+        };
+
+        let before = Date::new(2025, 1, 1).unwrap();
+        let plan = config.adjust_allocations_as_of(&balance, before).unwrap();
+        let cd = plan
+            .adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "CD")
+            .unwrap();
+        assert!(cd.ignored);
+        assert!(matches!(cd.action, Action::DoNothing));
+        assert_eq!(plan.upcoming_maturities.len(), 1);
+        assert_eq!(plan.upcoming_maturities[0].symbol, "CD");
+
+        let after = Date::new(2025, 7, 1).unwrap();
+        let plan = config.adjust_allocations_as_of(&balance, after).unwrap();
+        let cd = plan
+            .adjustments
+            .iter()
+            .find(|a| a.holding.symbol == "CD")
+            .unwrap();
+        assert!(!cd.ignored);
+        assert!(matches!(cd.action, Action::Sell(Dollar(5000.0))));
+        assert!(plan.upcoming_maturities.is_empty());
+    }
+
+    #[test]
+    fn test_plan_exchanges() {
+        let adjustment = |symbol: &str, action| PositionAdjustment {
+            holding: Holding {
+                symbol: symbol.to_string(),
+                ..Default::default()
+            },
+            action,
+            ..Default::default()
+        };
+        let plan = RebalancePlan {
+            adjustments: vec![
+                adjustment("FSKAX", Action::Sell(Dollar(3000.0))),
+                adjustment("FXNAX", Action::Buy(Dollar(2000.0))),
+                adjustment("FTIHX", Action::Buy(Dollar(1500.0))),
+                adjustment("VTI", Action::Sell(Dollar(500.0))),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            plan.exchanges(),
+            vec![
+                Exchange {
+                    from: "FSKAX".to_string(),
+                    to: "FXNAX".to_string(),
+                    amount: Dollar(2000.0),
+                },
+                Exchange {
+                    from: "FSKAX".to_string(),
+                    to: "FTIHX".to_string(),
+                    amount: Dollar(1000.0),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_settle_residual() {
+        let adjustment = |symbol: &str, is_cash, action| PositionAdjustment {
+            holding: Holding {
+                symbol: symbol.to_string(),
+                current_value: Dollar(100.0),
+                is_cash,
+                ..Default::default()
+            },
+            action,
+            ..Default::default()
+        };
+        let mut plan = RebalancePlan {
+            adjustments: vec![
+                adjustment("CORE", true, Action::Sell(Dollar(20.0))),
+                adjustment("A", false, Action::Buy(Dollar(10.004))),
+                adjustment("B", false, Action::Buy(Dollar(9.996))),
+            ],
+            ..Default::default()
+        };
+        plan.settle_residual();
+        let net: Dollar = plan.adjustments.iter().map(|adj| adj.action.change()).sum();
+        assert_eq!(net.round_cents(), Dollar(0.0));
+        assert!(matches!(
+            plan.adjustments[1].action,
+            Action::Buy(Dollar(10.0))
+        ));
+        assert!(matches!(
+            plan.adjustments[2].action,
+            Action::Buy(Dollar(10.0))
+        ));
+        assert!(matches!(
+            plan.adjustments[0].action,
+            Action::Sell(Dollar(20.0))
+        ));
+    }
+
+    #[test]
+    fn test_drift_with_cash_target() {
+        let close = |a: f32, b: f32| (a - b).abs() < 0.01;
+        // the targets leave 10% for the cash sweep, and the account is exactly
+        // on target
+        let yaml = r#"
+- AccountId: "1"
+  CashSweep:
+    Symbol: CORE
+  Targets:
+    VTI: 60
+    BND: 30
+  RebalancePolicy:
+    MaxDrift: 5
+"#;
+        let config = AllocationConfig::parse(yaml, ConfigFormat::Yaml, false)
+            .unwrap()
+            .remove(0);
+        let balance = Balance {
+            account_id: "1".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("VTI", 6000.0, false),
+                holding("BND", 3000.0, false),
+            ],
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        for (adj, drift) in plan.adjustments.iter().zip(plan.drift()) {
+            assert!(matches!(adj.action, Action::DoNothing), "{adj:?}");
+            assert!(drift.is_none_or(|drift| close(drift.0.0, 0.0)), "{adj:?}");
+        }
+        assert!(close(plan.drift_score().unwrap().0, 0.0));
+        let status = config.rebalance_status(&plan, None, Date::today());
+        assert!(
+            !status
+                .unwrap()
+                .reasons
+                .iter()
+                .any(|r| r.contains("drifted"))
+        );
+
+        // a cash minimum that is more than the cash target leaves less for the
+        // other holdings, which are on target when they share the rest
+        let yaml = r#"
+- AccountId: "1"
+  CashSweep:
+    Symbol: CORE
+    Minimum: 2000
+  Targets:
+    VTI: 60
+    BND: 30
+"#;
+        let config = AllocationConfig::parse(yaml, ConfigFormat::Yaml, false)
+            .unwrap()
+            .remove(0);
+        let balance = Balance {
+            holdings: vec![
+                holding("CORE", 2000.0, true),
+                holding("VTI", 5333.3335, false),
+                holding("BND", 2666.6667, false),
+            ],
+            ..balance
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        for drift in plan.drift().into_iter().flatten() {
+            assert!(close(drift.0.0, 0.0), "{drift:?}");
+        }
+    }
+
+    #[test]
+    fn test_plan_drift() {
+        let adjustment = |symbol: &str, value, target, is_cash, ignored| PositionAdjustment {
+            holding: Holding {
+                symbol: symbol.to_string(),
+                current_value: Dollar(value),
+                is_cash,
+                ..Default::default()
+            },
+            target: Percent(target),
+            ignored,
+            ..Default::default()
+        };
+        let plan = RebalancePlan {
+            adjustments: vec![
+                adjustment("CORE", 500.0, 25.0, true, false),
+                adjustment("A", 1000.0, 37.5, false, false),
+                adjustment("B", 500.0, 37.5, false, false),
+                adjustment("C", 1000.0, 0.0, false, true),
+            ],
+            ..Default::default()
+        };
+        assert_eq!(
+            plan.drift(),
+            vec![
+                None,
+                Some(RelativePercent(Percent(12.5))),
+                Some(RelativePercent(Percent(-12.5))),
+                None
+            ]
+        );
+        assert_eq!(plan.drift_score(), Some(Percent(12.5)));
+        assert_eq!(RebalancePlan::default().drift_score(), None);
+    }
+
+    #[test]
+    fn test_asset_class() {
+        let mut config = AllocationConfig::default();
+        assert_eq!(config.asset_class(&holding("A", 0.0, false)), None);
+        config
+            .asset_classes
+            .insert("A".to_string(), "Stocks".to_string());
+        assert_eq!(
+            config.asset_class(&holding("A", 0.0, false)),
+            Some("Stocks")
+        );
+        assert_eq!(config.asset_class(&holding("B", 0.0, false)), Some("Other"));
+        assert_eq!(
+            config.asset_class(&holding("CORE", 0.0, true)),
+            Some("Cash")
+        );
+    }
+
+    #[test]
+    fn test_symbol_aliases() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("VTI", 3000.0, false),
+                holding("VTSAX", 1000.0, false),
+                holding("BND", 6000.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            targets: HashMap::from([
+                ("VTI".to_string(), Percent(60.0)),
+                ("BND".to_string(), Percent(40.0)),
+            ]),
+            symbol_aliases: HashMap::from([("VTI".to_string(), vec!["VTSAX".to_string()])]),
+            ..Default::default()
+        };
+        config.validate().unwrap();
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let result = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .result()
+        };
+        // VTSAX counts towards the target of VTI rather than being sold
+        assert_eq!(result("VTI"), Dollar(4500.0));
+        assert_eq!(result("VTSAX"), Dollar(1500.0));
+        assert_eq!(result("BND"), Dollar(4000.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        config.targets.insert("VTSAX".to_string(), Percent(0.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_class_targets() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("A", 3000.0, false),
+                holding("B", 1000.0, false),
+                holding("C", 6000.0, false),
+            ],
+            ..Default::default()
+        };
+        let class = |symbol: &str, class: &str| (symbol.to_string(), class.to_string());
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                ..Default::default()
+            }),
+            asset_classes: HashMap::from([
+                class("A", "US"),
+                class("B", "US"),
+                class("C", "Intl"),
+                class("D", "Bonds"),
+            ]),
+            class_targets: HashMap::from([
+                ("US".to_string(), Percent(60.0)),
+                ("Intl".to_string(), Percent(25.0)),
+                ("Bonds".to_string(), Percent(15.0)),
+            ]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        let result = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .result()
+                .round_cents()
+        };
+        // within a class, holdings keep their current weights
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(result(&plan, "A"), Dollar(4500.0));
+        assert_eq!(result(&plan, "B"), Dollar(1500.0));
+        assert_eq!(result(&plan, "C"), Dollar(2500.0));
+        assert_eq!(result(&plan, "D"), Dollar(1500.0));
+        assert_eq!(result(&plan, "CORE"), Dollar(0.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // unless they are given weights
+        config.class_weights = HashMap::from([("A".to_string(), 1.0), ("B".to_string(), 3.0)]);
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(result(&plan, "A"), Dollar(1500.0));
+        assert_eq!(result(&plan, "B"), Dollar(4500.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        config.targets.insert("A".to_string(), Percent(10.0));
+        assert!(config.validate().is_err());
+        config.targets.clear();
+        config
+            .class_targets
+            .insert("Gold".to_string(), Percent(0.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_nested_targets() {
+        let yaml = r#"
+AccountId: "123"
+CashSweep:
+  Symbol: CORE
+NestedTargets:
+  Equity:
+    Percent: 80
+    Targets:
+      US:
+        Percent: 75
+        Targets:
+          VTI: 100
+      VXUS: 25
+  BND: 20
+"#;
+        let mut config: AllocationConfig = serde_yaml::from_str(yaml).unwrap();
+        assert!(config.validate().is_ok());
+        let targets = config.flattened_targets();
+        let target = |symbol: &str| targets.get(symbol).unwrap().0;
+        assert_eq!(targets.len(), 3);
+        assert!((target("VTI") - 60.0).abs() < 0.001);
+        assert!((target("VXUS") - 20.0).abs() < 0.001);
+        assert!((target("BND") - 20.0).abs() < 0.001);
+
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![Holding {
+                symbol: "CORE".to_string(),
+                current_value: Dollar(10000.0),
+                is_cash: true,
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // every level must add up to 100%
+        if let Some(NestedTarget::Group(equity)) = config.nested_targets.get_mut("Equity") {
+            equity
+                .targets
+                .insert("VXUS".to_string(), NestedTarget::Holding(Percent(20.0)));
+        }
+        let err = config.validate().unwrap_err().to_string();
+        assert!(err.contains("group 'Equity'"), "{err}");
+
+        // and a holding can only have one target
+        if let Some(NestedTarget::Group(equity)) = config.nested_targets.get_mut("Equity") {
+            equity
+                .targets
+                .insert("VXUS".to_string(), NestedTarget::Holding(Percent(25.0)));
+        }
+        config.targets.insert("VTI".to_string(), Percent(0.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_look_through() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("TDF", 6000.0, false),
+                holding("VTI", 4000.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("TDF".to_string(), Percent(50.0)),
+                ("VTI".to_string(), Percent(50.0)),
+            ]),
+            asset_classes: HashMap::from([("VTI".to_string(), "US".to_string())]),
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert!(config.look_through(&plan).is_empty());
+
+        config.fund_compositions = HashMap::from([(
+            "TDF".to_string(),
+            HashMap::from([
+                ("US".to_string(), Percent(60.0)),
+                ("Bonds".to_string(), Percent(40.0)),
+            ]),
+        )]);
+        assert!(config.validate().is_ok());
+        let exposures = config.look_through(&plan);
+        let close = |a: Percent, b: f32| (a.0 - b).abs() < 0.001;
+        assert_eq!(
+            exposures
+                .iter()
+                .map(|e| e.class.as_str())
+                .collect::<Vec<_>>(),
+            vec!["Bonds", "Cash", "US"]
+        );
+        assert!(close(exposures[0].current, 24.0));
+        assert!(close(exposures[0].target, 20.0));
+        assert!(close(exposures[0].result, 20.0));
+        assert!(close(exposures[2].current, 76.0));
+        assert!(close(exposures[2].target, 80.0));
+        assert!(close(exposures[2].result, 80.0));
+        assert!(close(exposures[2].drift().0, -4.0));
+
+        config
+            .fund_compositions
+            .get_mut("TDF")
+            .unwrap()
+            .insert("Bonds".to_string(), Percent(30.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_glide_path() {
+        let step = |date, age, stocks, bonds| GlidePathStep {
+            date,
+            age,
+            targets: HashMap::from([
+                ("STOCKS".to_string(), Percent(stocks)),
+                ("BONDS".to_string(), Percent(bonds)),
+            ]),
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            glide_path: vec![
+                step(Date::new(2040, 1, 1), None, 40.0, 60.0),
+                step(Date::new(2030, 1, 1), None, 80.0, 20.0),
+            ],
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        let target = |config: &AllocationConfig, date, symbol: &str| {
+            config
+                .glide_path_targets(date)
+                .unwrap()
+                .get(symbol)
+                .copied()
+                .unwrap()
+        };
+        let close = |a: Percent, b: f32| (a.0 - b).abs() < 0.01;
+        assert!(close(
+            target(&config, Date::new(2020, 6, 1).unwrap(), "STOCKS"),
+            80.0
+        ));
+        assert!(close(
+            target(&config, Date::new(2050, 6, 1).unwrap(), "STOCKS"),
+            40.0
+        ));
+        // halfway between the steps
+        let halfway = Date::new(2030, 1, 1).unwrap().add_days(1826);
+        assert!(close(target(&config, halfway, "STOCKS"), 60.0));
+        assert!(close(target(&config, halfway, "BONDS"), 40.0));
+
+        // the plan uses the targets for its date
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![Holding {
+                symbol: "STOCKS".to_string(),
+                current_value: Dollar(10000.0),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations_as_of(&balance, halfway).unwrap();
+        let bonds = plan
+            .adjustments
+            .iter()
+            .find(|adj| adj.holding.symbol == "BONDS")
+            .unwrap();
+        assert!((bonds.result().0 - 4000.0).abs() < 10.0);
+
+        // steps can be given by age instead
+        config.glide_path = vec![step(None, Some(61), 50.0, 50.0)];
+        assert!(config.validate().is_err());
+        config.birth_date = Date::new(1980, 2, 29);
+        assert!(config.validate().is_ok());
+        assert!(close(
+            target(&config, Date::new(2000, 1, 1).unwrap(), "BONDS"),
+            50.0
+        ));
+        assert_eq!(
+            config.glide_path_date(&config.glide_path[0]),
+            Date::new(2041, 3, 1)
+        );
+
+        config.targets.insert("STOCKS".to_string(), Percent(100.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_fixed_targets() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 500.0, true),
+                holding("IBOND", 5000.0, false),
+                holding("A", 20000.0, false),
+                holding("B", 4500.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(60.0)),
+                ("B".to_string(), Percent(40.0)),
+            ]),
+            fixed_targets: HashMap::from([("IBOND".to_string(), Dollar(10000.0))]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        let result = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .result()
+                .round_cents()
+        };
+        // the percentages apply to what is left after the fixed amount
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(result(&plan, "IBOND"), Dollar(10000.0));
+        assert_eq!(result(&plan, "A"), Dollar(12000.0));
+        assert_eq!(result(&plan, "B"), Dollar(8000.0));
+        assert_eq!(result(&plan, "CORE"), Dollar(0.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // and to what is left after the cash minimum
+        if let Some(sweep) = config.cash_sweep.as_mut() {
+            sweep.minimum = Dollar(1000.0);
+        }
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(result(&plan, "IBOND"), Dollar(10000.0));
+        assert_eq!(result(&plan, "A"), Dollar(11400.0));
+        assert_eq!(result(&plan, "B"), Dollar(7600.0));
+        assert_eq!(result(&plan, "CORE"), Dollar(1000.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        config.targets.insert("IBOND".to_string(), Percent(0.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_ranges() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("A", 3200.0, false),
+                holding("B", 5800.0, false),
+                holding("C", 1000.0, false),
+            ],
+            ..Default::default()
+        };
+        let range = |min, max| TargetRange {
+            min: Percent(min),
+            max: Percent(max),
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(30.0)),
+                ("B".to_string(), Percent(50.0)),
+                ("C".to_string(), Percent(20.0)),
+            ]),
+            target_ranges: HashMap::from([
+                ("A".to_string(), range(25.0, 35.0)),
+                ("B".to_string(), range(40.0, 55.0)),
+            ]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let adj = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+        };
+        // A is within its range, and B is only sold down to the top of its range
+        assert!(matches!(adj("A").action, Action::DoNothing));
+        assert_eq!(adj("A").held, Some(HoldReason::WithinRange));
+        assert_eq!(adj("B").result().round_cents(), Dollar(5500.0));
+        assert_eq!(adj("C").result().round_cents(), Dollar(1300.0));
+        assert_eq!(adj("CORE").result().round_cents(), Dollar(0.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        config
+            .target_ranges
+            .insert("C".to_string(), range(30.0, 20.0));
+        assert!(config.validate().is_err());
+    }
+
+    #[test]
+    fn test_target_ranges_minimum() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("A", 2000.0, false),
+                holding("B", 2500.0, false),
+                holding("C", 5500.0, false),
+            ],
+            ..Default::default()
+        };
+        let range = |min, max| TargetRange {
+            min: Percent(min),
+            max: Percent(max),
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(30.0)),
+                ("B".to_string(), Percent(30.0)),
+                ("C".to_string(), Percent(40.0)),
+            ]),
+            target_ranges: HashMap::from([
+                ("A".to_string(), range(25.0, 35.0)),
+                ("C".to_string(), range(35.0, 50.0)),
+            ]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_ok());
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let change = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+                .round_cents()
+        };
+        // C is only sold down to the top of its range, which leaves too little
+        // to buy with, but A is still bought up to the bottom of its range
+        assert_eq!(change("A"), Dollar(500.0));
+        assert_eq!(change("B"), Dollar(0.0));
+        assert_eq!(change("C"), Dollar(-500.0));
+        assert_eq!(change("CORE"), Dollar(0.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
+
+    #[test]
+    fn test_fewest_trades() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("A", 4600.0, false),
+                holding("B", 2800.0, false),
+                holding("C", 2600.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(40.0)),
+                ("B".to_string(), Percent(30.0)),
+                ("C".to_string(), Percent(30.0)),
+            ]),
+            tolerance_band: Some(ToleranceBand {
+                absolute: Some(Percent(5.0)),
+                relative: None,
+            }),
+            fewest_trades: true,
+            ..Default::default()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let adj = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+        };
+        // only A is outside of its band, and C has the most room to take what
+        // A sells, so B isn't traded
+        assert_eq!(adj("A").result().round_cents(), Dollar(4100.0));
+        assert!(matches!(adj("B").action, Action::DoNothing));
+        assert_eq!(adj("B").held, Some(HoldReason::WithinBand));
+        assert_eq!(adj("C").result().round_cents(), Dollar(3100.0));
+        assert_eq!(adj("CORE").result().round_cents(), Dollar(0.0));
+        assert!(plan.warnings.is_empty());
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // without a band, every holding is traded to its target
+        let config = AllocationConfig {
+            tolerance_band: None,
+            ..config
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.warnings.len(), 1);
+        let traded = plan
+            .adjustments
+            .iter()
+            .filter(|adj| !adj.holding.is_cash && adj.action.change() != Dollar(0.0))
+            .count();
+        assert_eq!(traded, 3);
+    }
+
+    #[test]
+    fn test_max_turnover() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 8000.0, false),
+                holding("B", 1000.0, false),
+                holding("C", 1000.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(40.0)),
+                ("B".to_string(), Percent(30.0)),
+                ("C".to_string(), Percent(30.0)),
+            ]),
+            ..Default::default()
+        };
+        let change = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+                .round_cents()
+        };
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.turnover(), Dollar(8000.0));
+        assert_eq!(plan.turnover_limit, None);
+
+        // half of the limit is sold, and half is bought
+        config.max_turnover = Some(Dollar(4000.0));
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(-2000.0));
+        assert_eq!(change(&plan, "B"), Dollar(1000.0));
+        assert_eq!(change(&plan, "C"), Dollar(1000.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(0.0));
+        assert_eq!(plan.turnover_limit, Some(Dollar(4000.0)));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // the buys with the most to go are made first
+        let balance = Balance {
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 8000.0, false),
+                holding("B", 2000.0, false),
+                holding("C", 0.0, false),
+            ],
+            ..balance
+        };
+        config.max_turnover = None;
+        config.max_turnover_percent = Some(Percent(20.0));
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.turnover().round_cents(), Dollar(2200.0));
+        assert_eq!(change(&plan, "A"), Dollar(-1100.0));
+        assert_eq!(change(&plan, "B"), Dollar(0.0));
+        assert_eq!(change(&plan, "C"), Dollar(1100.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
+
+    #[test]
+    fn test_adjustment_shares() {
+        let adjustment = |quantity, price, action| PositionAdjustment {
+            holding: Holding {
+                symbol: "A".to_string(),
+                current_value: Dollar(1000.0),
+                quantity,
+                price,
+                ..Default::default()
             },
-            res => res,
-        });
-        Ok(adjustments)
+            action,
+            ..Default::default()
+        };
+        let sell = adjustment(Some(10.0), None, Action::Sell(Dollar(250.0)));
+        assert_eq!(sell.holding.share_price(), Some(Dollar(100.0)));
+        assert_eq!(sell.shares(), Some(2.5));
+        let buy = adjustment(None, Some(Dollar(50.0)), Action::Buy(Dollar(100.0)));
+        assert_eq!(buy.shares(), Some(2.0));
+        assert_eq!(
+            adjustment(Some(10.0), None, Action::DoNothing).shares(),
+            None
+        );
+        assert_eq!(
+            adjustment(None, None, Action::Buy(Dollar(100.0))).shares(),
+            None
+        );
+    }
+
+    #[test]
+    fn test_tolerance_band() {
+        let band = ToleranceBand {
+            absolute: Some(Percent(5.0)),
+            relative: Some(Percent(25.0)),
+        };
+        assert!(band.contains(Percent(40.0), Percent(-4.0)));
+        assert!(!band.contains(Percent(40.0), Percent(6.0)));
+        // 25% of a 10% target is 2.5 percentage points
+        assert!(band.contains(Percent(10.0), Percent(2.0)));
+        assert!(!band.contains(Percent(10.0), Percent(-3.0)));
+        assert!(!ToleranceBand::default().contains(Percent(10.0), Percent(0.0)));
+
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                Holding {
+                    symbol: "CORE".to_string(),
+                    current_value: Dollar(1000.0),
+                    is_cash: true,
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "A".to_string(),
+                    current_value: Dollar(5200.0),
+                    ..Default::default()
+                },
+                Holding {
+                    symbol: "B".to_string(),
+                    current_value: Dollar(3800.0),
+                    ..Default::default()
+                },
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(50.0)),
+            ]),
+            tolerance_band: Some(ToleranceBand {
+                absolute: Some(Percent(5.0)),
+                ..Default::default()
+            }),
+            tolerance_bands: HashMap::from([(
+                "B".to_string(),
+                ToleranceBand {
+                    absolute: Some(Percent(1.0)),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        // A is 7.8 points over its target and B is 7.8 points under it, so
+        // both are traded
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert!(plan.adjustments.iter().all(|adj| adj.held.is_none()));
+
+        let balance = Balance {
+            holdings: vec![
+                Holding {
+                    current_value: Dollar(1500.0),
+                    ..balance.holdings[0].clone()
+                },
+                Holding {
+                    current_value: Dollar(4700.0),
+                    ..balance.holdings[1].clone()
+                },
+                Holding {
+                    current_value: Dollar(4300.0),
+                    ..balance.holdings[2].clone()
+                },
+            ],
+            ..balance
+        };
+        // both are 2.2 points from their targets, which is only allowed for A,
+        // so the cash that A would have been bought with is kept
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let find = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+        };
+        assert_eq!(find("A").held, Some(HoldReason::WithinBand));
+        assert!(matches!(find("A").action, Action::DoNothing));
+        assert_eq!(find("B").action.change(), Dollar(450.0));
+        assert_eq!(find("CORE").action.change(), Dollar(-450.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
+
+    #[test]
+    fn test_minimum_trade() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1200.0, true),
+                holding("A", 4496.83, false),
+                holding("B", 4303.17, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(50.0)),
+            ]),
+            minimum_trade: Some(Dollar(10.0)),
+            ..Default::default()
+        };
+        // A would only be bought for $3.17, so the cash sweep keeps that
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let find = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+        };
+        assert_eq!(find("A").held, Some(HoldReason::BelowMinimumTrade));
+        assert!(matches!(find("A").action, Action::DoNothing));
+        assert_eq!(find("B").action.change(), Dollar(196.83));
+        assert_eq!(find("CORE").action.change(), Dollar(-196.83));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
+
+    #[test]
+    fn test_no_sell() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 7000.0, false),
+                holding("B", 500.0, false),
+                holding("C", 1500.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(25.0)),
+                ("C".to_string(), Percent(25.0)),
+            ]),
+            constraints: HashMap::from([(
+                "A".to_string(),
+                HoldingConstraints {
+                    no_sell: true,
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let change = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        // A is far above its target, but it can't be sold, so B and C share
+        // what is left
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(0.0));
+        assert_eq!(change(&plan, "B"), Dollar(500.0));
+        assert_eq!(change(&plan, "C"), Dollar(-500.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(0.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        let plan = config.plan_withdrawal(&balance, Dollar(1000.0)).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(0.0));
+        assert_eq!(change(&plan, "B"), Dollar(0.0));
+        assert_eq!(change(&plan, "C"), Dollar(-1000.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
+
+    #[test]
+    fn test_min_max_constraints() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 3000.0, false),
+                holding("B", 3000.0, false),
+                holding("C", 3000.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(25.0)),
+                ("C".to_string(), Percent(25.0)),
+            ]),
+            constraints: HashMap::from([(
+                "A".to_string(),
+                HoldingConstraints {
+                    max: Some(Percent(40.0)),
+                    ..Default::default()
+                },
+            )]),
+            ..Default::default()
+        };
+        let change = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        // A would get $4,500, but it is capped at 40% of the account, so B and
+        // C share the excess
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(1000.0));
+        assert_eq!(change(&plan, "B"), Dollar(-500.0));
+        assert_eq!(change(&plan, "C"), Dollar(-500.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // then C would get $2,500, which is below its minimum of 30%
+        config.constraints.insert(
+            "C".to_string(),
+            HoldingConstraints {
+                min: Some(Percent(30.0)),
+                ..Default::default()
+            },
+        );
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(1000.0));
+        assert_eq!(change(&plan, "B"), Dollar(-1000.0));
+        assert_eq!(change(&plan, "C"), Dollar(0.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
+
+    #[test]
+    fn test_max_realized_gains() {
+        let holding = |symbol: &str, value, cost_basis: Option<f32>| Holding {
+            cost_basis: cost_basis.map(Dollar),
+            ..holding(symbol, value, symbol == "CORE")
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, None),
+                holding("A", 6000.0, Some(3000.0)),
+                holding("B", 3000.0, Some(3000.0)),
+                holding("C", 0.0, None),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(25.0)),
+                ("C".to_string(), Percent(25.0)),
+            ]),
+            ..Default::default()
+        };
+        let change = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        // half of the value of A is gains, and B has none
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(-1500.0));
+        assert_eq!(change(&plan, "B"), Dollar(-750.0));
+        assert_eq!(plan.realized_gains(), Some(Dollar(750.0)));
+        assert!(plan.gains_limited.is_empty());
+
+        // only $500 of A can be sold, which leaves $1,000 less to buy C with
+        config.max_realized_gains = Some(Dollar(250.0));
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(-500.0));
+        assert_eq!(change(&plan, "B"), Dollar(-750.0));
+        assert_eq!(change(&plan, "C"), Dollar(1250.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(0.0));
+        assert_eq!(plan.realized_gains(), Some(Dollar(250.0)));
+        assert_eq!(plan.gains_limited, vec!["A".to_string()]);
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
     }
 
-    #[doc(hidden)]
-    pub fn example_config() -> anyhow::Result<String> {
-        let mut targets = HashMap::new();
-        targets.insert("SYMBOL1".to_string(), Percent(75.0));
-        targets.insert("SYMBOL2".to_string(), Percent(25.0));
-        let ignored_holdings = vec!["SYMBOL3".to_string()];
-        let config = Self {
-            account_id: "<ACCOUNT_ID>".to_string(),
+    #[test]
+    fn test_defer_short_term_gains() {
+        let holding = |symbol: &str, value, acquired| Holding {
+            cost_basis: Some(Dollar(value / 2.0)),
+            acquired,
+            ..holding(symbol, value, symbol == "CORE")
+        };
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, None),
+                holding("A", 4000.0, Date::new(2026, 3, 1)),
+                holding("B", 4000.0, Date::new(2024, 2, 29)),
+                holding("C", 0.0, None),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
             cash_sweep: Some(CashConfig {
-                symbol: "CASH_SYMBOL".to_string(),
+                symbol: "CORE".to_string(),
                 minimum: Dollar(1000.0),
+                ..Default::default()
             }),
-            targets,
-            ignored_holdings,
-            nickname: None,
+            targets: HashMap::from([
+                ("A".to_string(), Percent(25.0)),
+                ("B".to_string(), Percent(25.0)),
+                ("C".to_string(), Percent(50.0)),
+            ]),
+            ..Default::default()
         };
-        let s = serde_yaml::to_string(&vec![config])?;
-        let comment = r#"# This is an example configuration.
-# Modify the following lines to suit your needs
-"#
-        .to_string();
-        Ok(comment + &s)
-    }
-}
+        let today = Date::new(2026, 10, 14).unwrap();
+        let find = |plan: &RebalancePlan, symbol: &str| {
+            let adj = plan
+                .adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap();
+            (adj.action.change(), adj.held, adj.short_term_until)
+        };
+        let plan = config.adjust_allocations_as_of(&balance, today).unwrap();
+        assert_eq!(
+            find(&plan, "A"),
+            (Dollar(-2000.0), None, Date::new(2027, 3, 1))
+        );
+        assert_eq!(find(&plan, "B"), (Dollar(-2000.0), None, None));
+        assert_eq!(plan.realized_gains(), Some(Dollar(2000.0)));
+        assert_eq!(plan.short_term_gains(), Some(Dollar(1000.0)));
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use crate::Action;
+        // without selling A, there is only half as much to buy C with
+        config.defer_short_term_gains = true;
+        let plan = config.adjust_allocations_as_of(&balance, today).unwrap();
+        assert_eq!(
+            find(&plan, "A"),
+            (
+                Dollar(0.0),
+                Some(HoldReason::ShortTermGain),
+                Date::new(2027, 3, 1)
+            )
+        );
+        assert_eq!(find(&plan, "B").0, Dollar(-2000.0));
+        assert_eq!(find(&plan, "C").0, Dollar(2000.0));
+        assert_eq!(find(&plan, "CORE").0, Dollar(0.0));
+        assert_eq!(plan.short_term_gains(), None);
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // A is still sold when the cash minimum can't be kept without it
+        if let Some(sweep) = config.cash_sweep.as_mut() {
+            sweep.minimum = Dollar(5000.0);
+        }
+        let plan = config.adjust_allocations_as_of(&balance, today).unwrap();
+        assert_eq!(find(&plan, "A").0, Dollar(-3000.0));
+        assert_eq!(find(&plan, "A").1, None);
+        assert_eq!(find(&plan, "B").0, Dollar(-3000.0));
+        assert_eq!(find(&plan, "C").0, Dollar(2000.0));
+        assert_eq!(find(&plan, "CORE").0, Dollar(4000.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
 
     #[test]
-    fn test_config_validate() {
-        let mut targets = HashMap::new();
-        targets.insert("A".to_string(), Percent(50.0));
-        targets.insert("B".to_string(), Percent(50.0));
-        let config = AllocationConfig {
+    fn test_cash_minimum_percent() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![holding("CORE", 0.0, true), holding("A", 20000.0, false)],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
             account_id: "123".to_string(),
             cash_sweep: Some(CashConfig {
                 symbol: "CORE".to_string(),
-                minimum: Dollar(100.0),
+                minimum_percent: Some(Percent(3.0)),
+                ..Default::default()
             }),
-            targets,
-            ignored_holdings: vec![],
-            nickname: None,
+            targets: HashMap::from([("A".to_string(), Percent(100.0))]),
+            ..Default::default()
         };
-        assert!(config.validate().is_ok());
+        let cash = |plan: &RebalancePlan| plan.adjustments[0].result();
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(cash(&plan), Dollar(600.0));
+        assert_eq!(plan.explanation.cash_minimum, Dollar(600.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
 
-        let mut targets = HashMap::new();
-        targets.insert("A".to_string(), Percent(50.0));
-        targets.insert("B".to_string(), Percent(40.0));
+        // the larger of the two minimums is kept
+        if let Some(sweep) = config.cash_sweep.as_mut() {
+            sweep.minimum = Dollar(1000.0);
+        }
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(cash(&plan), Dollar(1000.0));
+        if let Some(sweep) = config.cash_sweep.as_mut() {
+            sweep.minimum_percent = Some(Percent(10.0));
+        }
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(cash(&plan), Dollar(2000.0));
+
+        let sweep: CashConfig =
+            serde_yaml::from_str("Symbol: CORE\nMinimum: 1000\nReservePercent: 10\n").unwrap();
+        assert_eq!(sweep.minimum_percent, Some(Percent(10.0)));
+    }
+
+    #[test]
+    fn test_unallocated_target_kept_in_cash() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("A", 6000.0, false),
+                holding("B", 4000.0, false),
+            ],
+            ..Default::default()
+        };
         let config = AllocationConfig {
             account_id: "123".to_string(),
             cash_sweep: Some(CashConfig {
                 symbol: "CORE".to_string(),
-                minimum: Dollar(100.0),
+                ..Default::default()
             }),
-            targets,
-            ignored_holdings: vec![],
-            nickname: None,
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(45.0)),
+            ]),
+            ..Default::default()
         };
-        assert!(config.validate().is_err());
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let adj = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+        };
+        assert_eq!(adj("CORE").target, Percent(5.0));
+        assert_eq!(adj("CORE").result(), Dollar(500.0));
+        assert_eq!(adj("A").result(), Dollar(5000.0));
+        assert_eq!(adj("B").result(), Dollar(4500.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
     }
 
     #[test]
-    fn test_adjust_allocations_basic() {
-        let mut targets = HashMap::new();
-        targets.insert("A".to_string(), Percent(50.0));
-        targets.insert("B".to_string(), Percent(50.0));
-        let config = AllocationConfig {
+    fn test_normalize_targets() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 0.0, true),
+                holding("A", 5000.0, false),
+                holding("B", 5000.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
             account_id: "123".to_string(),
             cash_sweep: Some(CashConfig {
                 symbol: "CORE".to_string(),
-                minimum: Dollar(1000.0),
+                ..Default::default()
             }),
-            targets,
-            ignored_holdings: vec![],
-            nickname: None,
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(150.0)),
+            ]),
+            ..Default::default()
+        };
+        assert!(config.validate().is_err());
+        config.normalize_targets = true;
+        assert!(config.validate().is_ok());
+
+        let plan = config.adjust_allocations(&balance).unwrap();
+        let adj = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
         };
+        assert_eq!(adj("A").target, Percent(25.0));
+        assert_eq!(adj("B").target, Percent(75.0));
+        assert_eq!(adj("CORE").target, Percent(0.0));
+        assert_eq!(adj("A").result(), Dollar(2500.0));
+        assert_eq!(adj("B").result(), Dollar(7500.0));
+        assert_eq!(
+            plan.warnings,
+            vec!["Target allocations add up to 200.0%, so they were scaled to add up to 100%"]
+        );
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
 
+    #[test]
+    fn test_other_cash() {
         let balance = Balance {
             account_id: "123".to_string(),
-            account_name: "Test Account".to_string(),
             holdings: vec![
-                Holding {
-                    symbol: "CORE".to_string(),
-                    current_value: Dollar(5000.0),
-                    is_cash: true,
-                },
-                Holding {
-                    symbol: "A".to_string(),
-                    current_value: Dollar(1000.0),
-                    is_cash: false,
-                },
-                Holding {
-                    symbol: "B".to_string(),
-                    current_value: Dollar(1000.0),
-                    is_cash: false,
-                },
+                holding("SPAXX", 500.0, true),
+                holding("FDLXX", 2500.0, true),
+                holding("A", 7000.0, false),
             ],
+            ..Default::default()
         };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "SPAXX".to_string(),
+                minimum: Dollar(2000.0),
+                other_symbols: vec!["FDLXX".to_string()],
+                ..Default::default()
+            }),
+            targets: HashMap::from([("A".to_string(), Percent(100.0))]),
+            ..Default::default()
+        };
+        // together they hold $1,000 more than the minimum, which is invested
+        // from the cash sweep
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(plan.adjustments.len(), 2);
+        assert_eq!(plan.adjustments[0].holding.symbol, "SPAXX");
+        assert_eq!(plan.adjustments[0].holding.current_value, Dollar(3000.0));
+        assert_eq!(plan.adjustments[0].action.change(), Dollar(-1000.0));
+        assert_eq!(plan.adjustments[1].action.change(), Dollar(1000.0));
+        assert_eq!(plan.other_cash.len(), 1);
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
 
-        let adjustments = config.adjust_allocations(&balance).unwrap();
-        assert_eq!(adjustments.len(), 3);
-
-        let core_adj = adjustments
-            .iter()
-            .find(|a| a.holding.symbol == "CORE")
-            .unwrap();
-        if let Action::Sell(amount) = core_adj.action {
-            assert_eq!(amount, Dollar(4000.0));
-        } else {
-            panic!(
-                "CORE action should be Sell(4000.0), but was {:?}",
-                core_adj.action
-            );
-        }
+        let projected = plan.apply(&balance);
+        let value = |symbol: &str| {
+            projected
+                .holdings
+                .iter()
+                .find(|h| h.symbol == symbol)
+                .unwrap()
+                .current_value
+        };
+        assert_eq!(value("SPAXX"), Dollar(0.0));
+        assert_eq!(value("FDLXX"), Dollar(2000.0));
+    }
 
-        let a_adj = adjustments
-            .iter()
-            .find(|a| a.holding.symbol == "A")
-            .unwrap();
-        if let Action::Buy(amount) = a_adj.action {
-            assert_eq!(amount, Dollar(2000.0));
-        } else {
-            panic!("A action should be Buy(2000.0), but was {:?}", a_adj.action);
-        }
+    #[test]
+    fn test_trade_increment() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 5220.0, false),
+                holding("B", 2780.0, false),
+            ],
+            ..Default::default()
+        };
+        let mut config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(50.0)),
+            ]),
+            trade_increment: Some(Dollar(50.0)),
+            ..Default::default()
+        };
+        let change = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        // buying $1,220 of B is rounded down to $1,200, but rounding the sale
+        // of A down to $1,200 as well would take the cash sweep below its
+        // minimum while B is still being bought for $1,220, so A is rounded up
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(-1250.0));
+        assert_eq!(change(&plan, "B"), Dollar(1200.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(50.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
 
-        let b_adj = adjustments
-            .iter()
-            .find(|a| a.holding.symbol == "B")
-            .unwrap();
-        if let Action::Buy(amount) = b_adj.action {
-            assert_eq!(amount, Dollar(2000.0));
-        } else {
-            panic!("B action should be Buy(2000.0), but was {:?}", b_adj.action);
-        }
+        config.trade_increment = Some(Dollar(500.0));
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(-1500.0));
+        assert_eq!(change(&plan, "B"), Dollar(1000.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(500.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
     }
 
     #[test]
-    fn test_adjust_allocations_with_ignored() {
-        let mut targets = HashMap::new();
-        targets.insert("A".to_string(), Percent(100.0));
+    fn test_whole_shares() {
+        let holding = |symbol: &str, value, quantity: Option<f32>| Holding {
+            quantity,
+            ..holding(symbol, value, quantity.is_none())
+        };
+        let mut balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, None),
+                holding("A", 5200.0, Some(52.0)),
+                holding("B", 2800.0, Some(10.0)),
+            ],
+            ..Default::default()
+        };
         let config = AllocationConfig {
             account_id: "123".to_string(),
             cash_sweep: Some(CashConfig {
                 symbol: "CORE".to_string(),
                 minimum: Dollar(1000.0),
+                ..Default::default()
             }),
-            targets,
-            ignored_holdings: vec!["IGNORED".to_string()],
-            nickname: None,
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(50.0)),
+            ]),
+            whole_shares: vec!["A".to_string(), "B".to_string()],
+            ..Default::default()
         };
+        let change = |plan: &RebalancePlan, symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        // A would be sold for exactly 12 shares, but the $1,200 of B would be
+        // 4.29 shares, so it's rounded to 4 shares and the cash sweep keeps
+        // the rest
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "A"), Dollar(-1200.0));
+        assert_eq!(change(&plan, "B"), Dollar(1120.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(80.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+
+        // at $250 a share, B would be 4.8 shares, but rounding up to 5 shares
+        // would take the cash sweep below its minimum
+        balance.holdings[2].quantity = Some(11.2);
+        let plan = config.adjust_allocations(&balance).unwrap();
+        assert_eq!(change(&plan, "B"), Dollar(1000.0));
+        assert_eq!(change(&plan, "CORE"), Dollar(200.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
+    }
 
+    #[test]
+    fn test_plan_contribution() {
         let balance = Balance {
             account_id: "123".to_string(),
-            account_name: "Test Account".to_string(),
             holdings: vec![
-                Holding {
-                    symbol: "CORE".to_string(),
-                    current_value: Dollar(5000.0),
-                    is_cash: true,
-                },
-                Holding {
-                    symbol: "A".to_string(),
-                    current_value: Dollar(1000.0),
-                    is_cash: false,
-                },
-                Holding {
-                    symbol: "IGNORED".to_string(),
-                    current_value: Dollar(2000.0),
-                    is_cash: false,
-                },
+                holding("CORE", 1500.0, true),
+                holding("A", 6000.0, false),
+                holding("B", 2000.0, false),
+                holding("C", 2500.0, false),
             ],
+            ..Default::default()
         };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(25.0)),
+                ("C".to_string(), Percent(25.0)),
+            ]),
+            ..Default::default()
+        };
+        // the $1,000 contribution and $500 of excess cash are enough to bring
+        // B up to C, and the rest is split between them, so that both end up
+        // in the same proportion to their targets as A
+        let plan = config.plan_contribution(&balance, Dollar(1000.0)).unwrap();
+        let change = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        assert_eq!(change("A"), Dollar(0.0));
+        assert_eq!(change("B"), Dollar(1000.0));
+        assert_eq!(change("C"), Dollar(500.0));
+        assert_eq!(change("CORE"), Dollar(-500.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
 
-        let adjustments = config.adjust_allocations(&balance).unwrap();
-
-        assert_eq!(adjustments.len(), 3);
+        // a large contribution brings every holding to its target
+        let plan = config.plan_contribution(&balance, Dollar(9000.0)).unwrap();
+        let result = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .result()
+        };
+        assert_eq!(result("A"), Dollar(10000.0));
+        assert_eq!(result("B"), Dollar(5000.0));
+        assert_eq!(result("C"), Dollar(5000.0));
+        assert_eq!(result("CORE"), Dollar(1000.0));
+    }
 
-        let ignored_adj = adjustments
-            .iter()
-            .find(|a| a.holding.symbol == "IGNORED")
-            .unwrap();
-        assert!(ignored_adj.ignored);
+    #[test]
+    fn test_plan_withdrawal() {
+        let balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                holding("CORE", 1000.0, true),
+                holding("A", 6000.0, false),
+                holding("B", 2000.0, false),
+                holding("C", 2500.0, false),
+                holding("D", 500.0, false),
+            ],
+            ..Default::default()
+        };
+        let config = AllocationConfig {
+            account_id: "123".to_string(),
+            cash_sweep: Some(CashConfig {
+                symbol: "CORE".to_string(),
+                minimum: Dollar(1000.0),
+                ..Default::default()
+            }),
+            targets: HashMap::from([
+                ("A".to_string(), Percent(50.0)),
+                ("B".to_string(), Percent(25.0)),
+                ("C".to_string(), Percent(25.0)),
+                ("D".to_string(), Percent(0.0)),
+            ]),
+            ..Default::default()
+        };
+        // D has no target so it is sold first, then A and C are sold until
+        // they are the same share of the way to their targets, while B is
+        // left alone because it is already underweight
+        let plan = config.plan_withdrawal(&balance, Dollar(2250.0)).unwrap();
+        let change = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .action
+                .change()
+        };
+        assert_eq!(change("A"), Dollar(-1500.0));
+        assert_eq!(change("B"), Dollar(0.0));
+        assert_eq!(change("C"), Dollar(-250.0));
+        assert_eq!(change("D"), Dollar(-500.0));
+        assert_eq!(change("CORE"), Dollar(2250.0));
+        assert!(crate::invariants::check_invariants(&config, &balance, &plan).is_ok());
 
-        // the total value to consider for distribution is 6000 (5000 core +
-        // 1000 A), since IGNORED is ignored
-        let a_adj = adjustments
-            .iter()
-            .find(|a| a.holding.symbol == "A")
-            .unwrap();
-        if let Action::Buy(amount) = a_adj.action {
-            assert_eq!(amount, Dollar(4000.0));
-        } else {
-            panic!("A action should be Buy(4000.0), but was {:?}", a_adj.action);
-        }
-        let core_adj = adjustments
-            .iter()
-            .find(|a| a.holding.symbol == "CORE")
-            .unwrap();
-        if let Action::Sell(amount) = core_adj.action {
-            assert_eq!(amount, Dollar(4000.0));
-        } else {
-            panic!(
-                "CORE action should be Sell(4000.0), but was {:?}",
-                core_adj.action
-            );
-        }
+        // a withdrawal that the cash sweep can't cover sells everything
+        let plan = config.plan_withdrawal(&balance, Dollar(20000.0)).unwrap();
+        assert_eq!(plan.warnings.len(), 1);
+        assert_eq!(change("B"), Dollar(0.0));
+        let result = |symbol: &str| {
+            plan.adjustments
+                .iter()
+                .find(|adj| adj.holding.symbol == symbol)
+                .unwrap()
+                .result()
+        };
+        assert_eq!(result("A"), Dollar(0.0));
+        assert_eq!(result("CORE"), Dollar(12000.0));
     }
 }
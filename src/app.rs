@@ -1,31 +1,109 @@
 use std::{
+    collections::HashMap,
     fs::File,
-    io::{ErrorKind, Read, Write, stdout},
+    io::{ErrorKind, IsTerminal, Read, Write, stdout},
     path::{Path, PathBuf},
     process::Stdio,
 };
 
-use anyhow::{anyhow, bail};
+use anyhow::{Context, anyhow, bail};
 use clap::CommandFactory;
 use directories::ProjectDirs;
 use driftfix::{
-    account::{self, Balance},
-    provider::{self, ProviderType},
+    Action, Dollar, RelativePercent,
+    account::{self, Balance, HoldReason},
+    currency::CurrencyFormat,
+    date::Date,
+    fx, invariants,
+    provider::{self, ColumnMapping, CsvFormat, ProviderType},
+    trades,
 };
 use serde::{Deserialize, Serialize};
 use tracing::{debug, trace, warn};
 
 use crate::{
     backup::{self, BackupFile},
-    cli::{self, Cli, DataAddArgs, DataArgs, PlanArgs},
-    output,
+    balance_files, chart,
+    cli::{self, Cli, DataAddArgs, DataArgs, InitArgs, OutputFormat, PlanArgs, SortColumn},
+    clipboard, output,
+    template::Template,
 };
 
 #[derive(Default, Debug, Serialize, Deserialize)]
 struct Config {
     default_provider: Option<ProviderType>,
+    /// A shell command to run before calculating a plan (e.g. to download the
+    /// latest balances)
+    #[serde(default)]
+    pre_hook: Option<String>,
+    /// A shell command to run after a plan has been calculated successfully
+    #[serde(default)]
+    post_hook: Option<String>,
+    /// The columns to read when importing files with the generic provider
+    #[serde(default)]
+    generic_columns: Option<ColumnMapping>,
+    /// How fields and numbers are written in CSV files, for exports that
+    /// don't follow US conventions
+    #[serde(default)]
+    csv_format: Option<CsvFormat>,
+    /// The locale whose format is used to show money (e.g. "en-GB")
+    #[serde(default)]
+    locale: Option<String>,
+    /// How to show money, for locales that don't have a built-in format.
+    /// Takes precedence over `locale`.
+    #[serde(default)]
+    currency_format: Option<CurrencyFormat>,
+    /// Only show the last 4 digits of account numbers, as with `--redact`
+    #[serde(default)]
+    redact_account_numbers: bool,
 }
 
+/// What the program found, which determines its exit status
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum Outcome {
+    Done,
+    /// A plan recommends at least one trade, and the rebalance policy of the
+    /// account (if it has one) warrants a rebalance
+    RebalanceRecommended,
+}
+
+/// Errors that are given their own exit status, so that scripts can tell
+/// them apart. Attached to an error with [anyhow::Context].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, thiserror::Error)]
+pub(crate) enum Failure {
+    #[error("Unable to load the configuration")]
+    Config,
+    #[error("Unable to load the balance data")]
+    Data,
+}
+
+/// The variables that are available to a template for the plan of an account
+#[derive(Serialize)]
+struct TemplateContext<'a> {
+    account: &'a output::PlanReport,
+    positions: &'a [output::PositionReport],
+    /// The positions that have something to buy or sell
+    trades: Vec<&'a output::PositionReport>,
+}
+
+impl<'a> TemplateContext<'a> {
+    fn new(report: &'a output::PlanReport) -> Self {
+        Self {
+            account: report,
+            positions: &report.positions,
+            trades: report
+                .positions
+                .iter()
+                .filter(|position| position.action != "none")
+                .collect(),
+        }
+    }
+}
+
+/// Set in the environment of hook commands so that hooks which invoke this
+/// program don't recursively run hooks themselves
+const HOOK_ENV: &str = "DRIFTFIX_HOOK";
+
 #[derive(Debug)]
 pub struct App {
     dirs: ProjectDirs,
@@ -43,36 +121,155 @@ impl App {
         ensure_dir_exists(dirs.config_dir())?;
         ensure_dir_exists(dirs.data_dir())?;
 
-        let config = Self::load_config(&app_config_dir(&dirs))?;
+        let config = Self::load_config(&app_config_dir(&dirs)).context(Failure::Config)?;
+        let currency_format = match (&args.locale, &config.currency_format, &config.locale) {
+            (Some(locale), _, _) | (None, None, Some(locale)) => {
+                CurrencyFormat::for_locale(locale).ok_or_else(|| {
+                    anyhow!(
+                        "Unknown locale '{locale}'. The supported locales are {}, or the format can be set with 'currency_format' in the application configuration.",
+                        CurrencyFormat::locales().collect::<Vec<_>>().join(", ")
+                    )
+                })?
+            }
+            (None, Some(format), _) => format.clone(),
+            (None, None, None) => CurrencyFormat::default(),
+        };
+        currency_format.set_global();
         Ok(Self {
-            target_config_file: args
-                .target_config
-                .clone()
-                .unwrap_or(dirs.data_dir().join("target.yml")),
+            target_config_file: args.target_config.clone().unwrap_or_else(|| {
+                let yaml = dirs.data_dir().join("target.yml");
+                let toml = dirs.data_dir().join("target.toml");
+                let dir = dirs.data_dir().join("targets.d");
+                if yaml.exists() {
+                    yaml
+                } else if toml.exists() {
+                    toml
+                } else if dir.is_dir() {
+                    dir
+                } else {
+                    yaml
+                }
+            }),
             config,
             dirs,
             args,
         })
     }
 
-    pub fn run(&self) -> anyhow::Result<()> {
+    pub fn run(&self) -> anyhow::Result<Outcome> {
+        self.run_command()
+            .map_err(|err| self.redact_error(err, &[]))
+    }
+
+    fn run_command(&self) -> anyhow::Result<Outcome> {
         match &self.args.command {
-            cli::MainCommands::Configure => self.edit_command(),
-            cli::MainCommands::Plan(plan_args) => self.plan_command(plan_args),
-            cli::MainCommands::Data(data_args) => self.data_command(data_args),
+            cli::MainCommands::Configure => self.edit_command()?,
+            cli::MainCommands::Plan(plan_args) => {
+                let quiet = plan_args.is_document();
+                self.run_hook("pre", self.config.pre_hook.as_deref(), quiet)?;
+                let outcome = self.plan_command(plan_args)?;
+                self.run_hook("post", self.config.post_hook.as_deref(), quiet)?;
+                return Ok(outcome);
+            }
+            cli::MainCommands::Data(data_args) => self.data_command(data_args)?,
+            cli::MainCommands::Init(init_args) => self.init_command(init_args)?,
             cli::MainCommands::Completion { shell } => {
                 let mut cmd = Cli::command();
                 let bin_name = cmd.get_name().to_string();
                 clap_complete::generate(*shell, &mut cmd, bin_name, &mut stdout());
-                Ok(())
             }
         }
+        Ok(Outcome::Done)
+    }
+
+    /// How to show an account number to the user, which may be redacted
+    fn display_account_id(&self, account_id: &str) -> String {
+        match self.redacting() {
+            true => output::redact_account_id(account_id),
+            false => account_id.to_string(),
+        }
+    }
+
+    fn redacting(&self) -> bool {
+        self.args.redact || self.config.redact_account_numbers
+    }
+
+    /// Redact the account numbers in `err` and its context, if account numbers
+    /// are redacted in the rest of the output. These are the accounts of the
+    /// imported balances and of the target configuration, along with `others`
+    /// (e.g. those of balances that are being imported).
+    fn redact_error(&self, err: anyhow::Error, others: &[&str]) -> anyhow::Error {
+        if !self.redacting() {
+            return err;
+        }
+        let balances = self.load_balances().unwrap_or_default();
+        let configs = self.load_account_configs(true).unwrap_or_default();
+        let ids: Vec<String> = balances
+            .iter()
+            .map(|balance| balance.account_id.clone())
+            .chain(configs.iter().map(|config| config.account_id.clone()))
+            .chain(others.iter().map(|id| id.to_string()))
+            .collect();
+        // rebuild the error from its innermost cause, keeping the failures
+        // that determine the exit status
+        let mut redacted: Option<anyhow::Error> = None;
+        for cause in err.chain().rev() {
+            let message = cause.to_string();
+            let failure = [Failure::Config, Failure::Data]
+                .into_iter()
+                .find(|failure| failure.to_string() == message);
+            let message = output::redact_account_ids(&message, &ids);
+            redacted = Some(match (redacted, failure) {
+                (Some(inner), Some(failure)) => inner.context(failure),
+                (Some(inner), None) => inner.context(message),
+                (None, _) => anyhow!(message),
+            });
+        }
+        redacted.unwrap_or(err)
+    }
+
+    /// Run a hook command. If `quiet` is set, the command's output is sent to
+    /// stderr so that it doesn't get mixed into machine-readable output.
+    fn run_hook(&self, name: &str, command: Option<&str>, quiet: bool) -> anyhow::Result<()> {
+        let Some(command) = command else {
+            return Ok(());
+        };
+        if self.args.no_hooks || std::env::var_os(HOOK_ENV).is_some() {
+            debug!(name, command, "Skipping hook");
+            return Ok(());
+        }
+        debug!(name, command, "Running hook");
+        let mut cmd = std::process::Command::new("sh");
+        cmd.arg("-c")
+            .arg(command)
+            .env(HOOK_ENV, name)
+            .env("DRIFTFIX_TARGET_CONFIG", &self.target_config_file)
+            .env("DRIFTFIX_BALANCES", self.cached_balance_file());
+        if quiet {
+            cmd.stdout(std::io::stderr());
+        }
+        let status = cmd
+            .status()
+            .map_err(|e| anyhow!("Failed to run {name}-hook '{command}': {e}"))?;
+        if !status.success() {
+            bail!("The {name}-hook '{command}' failed ({status})");
+        }
+        Ok(())
     }
 
     fn edit_command(&self) -> anyhow::Result<()> {
+        if self.target_config_file.is_dir() {
+            bail!(
+                "The target configuration is the directory {}, so edit its files instead",
+                self.target_config_file.display()
+            );
+        }
+        // the working copy has a different extension, so the format comes from
+        // the configuration file itself
+        let format = account::ConfigFormat::from_path(&self.target_config_file);
         let backup = BackupFile::new(
             self.target_config_file.clone(),
-            Some(account::AllocationConfig::example_config()?),
+            Some(account::AllocationConfig::example_config(format)?),
         )?;
         let editor = std::env::var("VISUAL")
             .or_else(|_| std::env::var("EDITOR"))
@@ -89,7 +286,10 @@ impl App {
                     backup.path().display()
                 );
             } else {
-                match account::AllocationConfig::load_from_file(backup.path()) {
+                let configs = std::fs::read_to_string(backup.path())
+                    .map_err(anyhow::Error::from)
+                    .and_then(|text| account::AllocationConfig::parse(&text, format, false));
+                match configs {
                     Ok(_) => match backup.finish() {
                         Ok(p) => {
                             println!("Updated configuration file '{}'", p.display());
@@ -118,12 +318,12 @@ impl App {
         }
     }
 
-    fn plan_command(&self, args: &PlanArgs) -> anyhow::Result<()> {
-        let mut account_configs = self.load_account_configs()?;
+    fn plan_command(&self, args: &PlanArgs) -> anyhow::Result<Outcome> {
+        let mut account_configs = self.load_account_configs(args.normalize_targets)?;
         let mut filtered_configs = if let Some(arg) = &args.account {
             let mut found = account_configs
                 .iter_mut()
-                .filter(|config| config.account_id == *arg)
+                .filter(|config| config.matches_account(arg) || config.has_alias(arg))
                 .collect::<Vec<_>>();
             if found.is_empty() {
                 found = account_configs
@@ -150,59 +350,275 @@ impl App {
             bail!("No allocation targets are configured. See help for more information.");
         }
         if let Some(keep) = args.cash_minimum {
-            if filtered_configs.len() != 1 {
-                anyhow::bail!(
-                    "--cash-minimum can only be used with a single account. Try specifying --account."
-                );
+            let mut overridden = false;
+            for config in filtered_configs.iter_mut() {
+                if let Some(cash_sweep) = config.cash_sweep.as_mut() {
+                    cash_sweep.minimum = keep;
+                    cash_sweep.minimum_percent = None;
+                    overridden = true;
+                }
             }
-            if let Some(cash_sweep) = filtered_configs[0].cash_sweep.as_mut() {
-                cash_sweep.minimum = keep;
-            } else {
-                anyhow::bail!(
-                    "--cash-minimum can only be used on accounts with a configured cash sweep"
-                )
+            if !overridden {
+                bail!("--cash-minimum can only be used on accounts with a configured cash sweep");
             }
         }
-        let mut accounts = self.load_balances()?;
+        for (symbol, target) in args.target_override.iter() {
+            let mut overridden = false;
+            for config in filtered_configs.iter_mut() {
+                if let Some(current) = config.targets.get_mut(symbol) {
+                    *current = *target;
+                    overridden = true;
+                }
+            }
+            if !overridden {
+                bail!("--target-override: no account has a target for '{symbol}'");
+            }
+        }
+        if !args.target_override.is_empty() {
+            for config in filtered_configs.iter() {
+                config.validate().with_context(|| {
+                    format!(
+                        "The targets of account {} are invalid after --target-override",
+                        config.account_id
+                    )
+                })?;
+            }
+        }
+        if args.contribute.is_some() && filtered_configs.len() != 1 {
+            bail!("--contribute can only be used with a single account. Try specifying --account.");
+        }
+        if args.withdraw.is_some() && filtered_configs.len() != 1 {
+            bail!("--withdraw can only be used with a single account. Try specifying --account.");
+        }
+        let mut accounts = self.load_balances().context(Failure::Data)?;
         if accounts.is_empty() {
             bail!("Please import account balance data first. See help for more information.")
         }
         let naccounts = accounts.len();
-        let mut accounts_with_config = Vec::<(account::Balance, account::AllocationConfig)>::new();
         sort_accounts(&mut accounts);
-        for account in accounts {
-            if let Some(cfg) = filtered_configs
-                .iter()
-                .find(|t| t.account_id == account.account_id)
-            {
-                accounts_with_config.push((account, (*cfg).clone()));
-            }
-        }
+        let configs: Vec<&account::AllocationConfig> =
+            filtered_configs.iter().map(|config| &**config).collect();
+        let mut accounts_with_config = account::pair_with_configs(accounts, &configs)?;
         if accounts_with_config.is_empty() {
             bail!(
                 "Balance data has been imported for {naccounts} accounts, but no target allocation configuration exists for any of these accounts."
             );
         }
-        for (account, mut config) in accounts_with_config {
-            config.ignored_holdings.extend(args.ignore.iter().cloned());
-
-            let adjustments = config.adjust_allocations(&account)?;
-            let table = output::format_adjustments(adjustments);
+        for (symbol, change) in args.shock.iter() {
+            let mut found = false;
+            for (account, _) in accounts_with_config.iter_mut() {
+                found |= account.apply_price_change(symbol, *change);
+            }
+            if !found {
+                bail!("--shock: no account holds '{symbol}'");
+            }
+        }
+        if args.output.is_some() && !args.is_document() {
+            bail!("--output can't be used with --format table");
+        }
+        if (args.chart || args.explain) && args.is_document() {
+            bail!("--chart and --explain can only be used with --format table");
+        }
+        if !args.columns.is_empty() && args.is_document() {
+            bail!("--columns can only be used with --format table");
+        }
+        let template = match &args.template {
+            Some(path) => {
+                let source = std::fs::read_to_string(path)
+                    .with_context(|| format!("Failed to read template {}", path.display()))?;
+                Some(
+                    Template::parse(&source)
+                        .with_context(|| format!("Invalid template {}", path.display()))?,
+                )
+            }
+            None => None,
+        };
+        if let Some(path) = &args.chart_file
+            && !path
+                .extension()
+                .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+        {
+            bail!("Charts can only be saved as SVG images (e.g. 'allocation.svg')");
+        }
+        // see https://no-color.org
+        let plain = args.plain
+            || std::env::var_os("NO_COLOR").is_some_and(|val| !val.is_empty())
+            || !stdout().is_terminal();
+        let recent_trades = match &args.trades {
+            Some(path) => trades::load_trades(
+                &mut File::open(path)
+                    .with_context(|| format!("Failed to open trades file {path:?}"))?,
+            )
+            .with_context(|| format!("Failed to load trades from {path:?}"))?,
+            None => Vec::new(),
+        };
+        let mut rebalances = self
+            .load_rebalances()
+            .context("Failed to load the dates of the last rebalances")?;
+        let mut reports = Vec::new();
+        let mut charts = Vec::new();
+        let mut outcome = Outcome::Done;
+        let mut copied = String::new();
+        for (mut account, mut config) in accounts_with_config {
+            for symbol in &args.ignore {
+                if !config.ignored_holdings.contains(symbol) {
+                    config.ignored_holdings.push(symbol.clone());
+                }
+            }
+            if let Some(minimum) = args.min_trade {
+                config.minimum_trade = Some(minimum);
+            }
+            if let Some(increment) = args.round_to {
+                config.trade_increment = Some(increment);
+            }
+            if let Some(max) = args.max_gains {
+                config.max_realized_gains = Some(max);
+            }
+            if args.defer_short_term {
+                config.defer_short_term_gains = true;
+            }
+            if args.fewest_trades {
+                config.fewest_trades = true;
+            }
+            match args.max_turnover {
+                Some(cli::Turnover::Amount(max)) => {
+                    config.max_turnover = Some(max);
+                    config.max_turnover_percent = None;
+                }
+                Some(cli::Turnover::Percent(max)) => {
+                    config.max_turnover = None;
+                    config.max_turnover_percent = Some(max);
+                }
+                None => {}
+            }
 
-            let name = config.nickname.as_ref().unwrap_or(&account.account_name);
-            if !name.is_empty() {
-                println!("{name}");
+            let mut plan = match (args.contribute, args.withdraw) {
+                (Some(contribution), _) => config.plan_contribution(&account, contribution)?,
+                (_, Some(withdrawal)) => config.plan_withdrawal(&account, withdrawal)?,
+                _ => config.adjust_allocations(&account)?,
+            };
+            let shocked: Vec<_> = args
+                .shock
+                .iter()
+                .filter(|(symbol, _)| account.holdings.iter().any(|h| h.symbol == *symbol))
+                .map(|(symbol, change)| format!("{symbol} {}", RelativePercent(*change)))
+                .collect();
+            if !shocked.is_empty() {
+                plan.warnings.push(format!(
+                    "This is a what-if plan, as if prices had moved: {}",
+                    shocked.join(", ")
+                ));
+            }
+            if let Err(violations) = invariants::check_invariants(&config, &account, &plan) {
+                plan.warnings
+                    .extend(violations.iter().map(|violation| violation.to_string()));
+            }
+            if let Some(unfunded) = plan.unfunded() {
+                plan.warnings.push(format!(
+                    "The buys cost {unfunded} more than the sells and the cash in the account can pay for"
+                ));
+            }
+            plan.warnings.extend(trades::wash_sale_warnings(
+                &plan,
+                &recent_trades,
+                &config.equivalent_symbols,
+                Date::today(),
+            ));
+            let status = config.rebalance_status(
+                &plan,
+                rebalances.get(&account.account_id).copied(),
+                Date::today(),
+            );
+            if plan.recommends_rebalance(status.as_ref()) {
+                outcome = Outcome::RebalanceRecommended;
+            }
+            if args.mark_rebalanced && config.rebalance_policy.is_some() {
+                rebalances.insert(account.account_id.clone(), Date::today());
+            }
+            // the plan is checked against the account, so redact it afterwards
+            account.account_id = self.display_account_id(&account.account_id);
+            if let Some(column) = args.sort {
+                sort_plan(&mut plan, column, args.desc);
+            }
+            if args.chart_file.is_some() {
+                let name = config.nickname.as_ref().unwrap_or(&account.account_name);
+                let name = match name.is_empty() {
+                    true => account.account_id.clone(),
+                    false => name.clone(),
+                };
+                charts.push((name, output::allocation_shares(&plan)));
+            }
+            match args.is_document() {
+                false => {
+                    let status = status.as_ref();
+                    print!(
+                        "{}",
+                        format_plan(&account, &config, &plan, status, args, plain)
+                    );
+                    if args.copy {
+                        // colors would be pasted as escape codes
+                        copied += &format_plan(&account, &config, &plan, status, args, true);
+                    }
+                }
+                true => reports.push(output::PlanReport {
+                    rebalance: status,
+                    ..output::PlanReport::new(&account, &config, &plan)
+                }),
             }
-            println!("Account ID: {}", account.account_id);
-            println!("Total balance: {}", account.total_value());
-            println!("{table}");
-            println!();
         }
-        Ok(())
+        if args.mark_rebalanced {
+            self.save_rebalances(&rebalances)
+                .context("Failed to record the rebalances")?;
+        }
+        if let Some(path) = &args.chart_file {
+            std::fs::write(path, chart::allocation_svg(&charts))
+                .with_context(|| format!("Failed to write chart to {}", path.display()))?;
+        }
+        let text = match (template, args.format) {
+            (Some(template), _) => {
+                let mut text = String::new();
+                for report in reports.iter() {
+                    text += &template.render(&TemplateContext::new(report))?;
+                }
+                text
+            }
+            (None, OutputFormat::Table) => {
+                if args.copy {
+                    clipboard::copy(&copied)?;
+                }
+                return Ok(outcome);
+            }
+            (None, OutputFormat::Json) => {
+                #[derive(Serialize)]
+                struct Document {
+                    accounts: Vec<output::PlanReport>,
+                }
+                output::to_json(&Document { accounts: reports })? + "\n"
+            }
+            (None, OutputFormat::Csv) => output::to_csv(&reports)?,
+            (None, OutputFormat::Markdown) => output::to_markdown(&reports),
+            (None, OutputFormat::Html) => output::to_html(&reports, Date::today()),
+        };
+        if args.copy {
+            clipboard::copy(&text)?;
+        }
+        match &args.output {
+            Some(path) => std::fs::write(path, text)
+                .with_context(|| format!("Failed to write plan to {}", path.display()))?,
+            None => print!("{text}"),
+        }
+        Ok(outcome)
     }
 
-    fn load_account_configs(&self) -> Result<Vec<account::AllocationConfig>, anyhow::Error> {
-        account::AllocationConfig::load_from_file(&self.target_config_file)
+    fn load_account_configs(
+        &self,
+        normalize_targets: bool,
+    ) -> Result<Vec<account::AllocationConfig>, anyhow::Error> {
+        account::AllocationConfig::load_from_file_normalizing(
+            &self.target_config_file,
+            normalize_targets,
+        )
+        .context(Failure::Config)
     }
 
     fn data_command(&self, args: &DataArgs) -> anyhow::Result<()> {
@@ -215,38 +631,172 @@ impl App {
         }
     }
 
-    fn data_add_command(&self, args: &DataAddArgs) -> anyhow::Result<()> {
-        let mut f: Box<dyn Read> = if &args.account_balances == "-" {
-            Box::new(std::io::stdin())
-        } else {
-            Box::new(File::open(&args.account_balances)?)
+    fn init_command(&self, args: &InitArgs) -> anyhow::Result<()> {
+        let dest = &self.target_config_file;
+        if dest.is_dir() {
+            bail!(
+                "The target configuration is the directory {}, so add a file to it instead",
+                dest.display()
+            );
+        }
+        if dest.exists() && !args.force {
+            bail!(
+                "The target configuration {} already exists (use --force to replace it)",
+                dest.display()
+            );
+        }
+        let path = match args.account_balances == Path::new("-") {
+            true => args.account_balances.clone(),
+            false => balance_files::resolve(&args.account_balances)?,
         };
-        let portfolio =
-            provider::load_portfolio(&mut f, args.provider.or(self.config.default_provider))?;
+        let mut warnings = Vec::new();
+        let portfolio = self
+            .load_balances_file(
+                &path,
+                args.provider.or(self.config.default_provider),
+                &self.config.csv_format.clone().unwrap_or_default(),
+                &mut warnings,
+            )
+            .context(Failure::Data)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let portfolio = provider::merge_portfolios(vec![portfolio], &mut warnings);
+        if portfolio.is_empty() {
+            bail!("No accounts were found in {}", path.display());
+        }
+        let configs: Vec<_> = portfolio
+            .iter()
+            .map(account::AllocationConfig::from_balance)
+            .collect();
+        let text = "# The targets are the current shares of the holdings. Adjust them to suit\n\
+                    # your needs, and set a minimum for the cash sweep if you want to keep one.\n"
+            .to_string()
+            + &account::AllocationConfig::to_text(
+                &configs,
+                account::ConfigFormat::from_path(dest),
+            )?;
+        std::fs::write(dest, text)
+            .with_context(|| format!("Failed to write {}", dest.display()))?;
+        println!(
+            "Wrote targets for {} accounts to {}",
+            configs.len(),
+            dest.display()
+        );
+        print_warnings(&warnings);
+        Ok(())
+    }
+
+    fn data_add_command(&self, args: &DataAddArgs) -> anyhow::Result<()> {
+        let files = &args.account_balances;
+        if args.provider.len() > 1 && args.provider.len() != files.len() {
+            bail!(
+                "Got {} providers for {} files. Give a single provider for all files, or one for each file.",
+                args.provider.len(),
+                files.len()
+            );
+        }
+        if files.iter().filter(|f| *f == Path::new("-")).count() > 1 {
+            bail!("Standard input ('-') can only be read once");
+        }
+        let mut csv_format = self.config.csv_format.clone().unwrap_or_default();
+        if let Some(delimiter) = args.delimiter {
+            csv_format.delimiter = delimiter;
+        }
+        if let Some(separator) = args.decimal_separator {
+            csv_format.decimal_separator = separator;
+        }
+        csv_format
+            .currency_symbols
+            .extend(args.currency_symbols.iter().cloned());
+        let mut warnings = Vec::new();
+        let mut portfolios = Vec::new();
+        for (i, path) in files.iter().enumerate() {
+            let ptype = args
+                .provider
+                .get(i)
+                .or(args.provider.first())
+                .copied()
+                .or(self.config.default_provider);
+            let path = match path == Path::new("-") {
+                true => path.clone(),
+                false => balance_files::resolve(path)?,
+            };
+            if path != files[i] {
+                println!("Using {}", path.display());
+            }
+            let mut file_warnings = Vec::new();
+            let portfolio = self
+                .load_balances_file(&path, ptype, &csv_format, &mut file_warnings)
+                .context(Failure::Data)
+                .with_context(|| format!("Failed to import {}", path.display()))?;
+            // say which file each warning came from when there is more than one
+            warnings.extend(file_warnings.into_iter().map(|w| match files.len() {
+                1 => w,
+                _ => format!("{}: {w}", path.display()),
+            }));
+            portfolios.push(portfolio);
+        }
+        let mut portfolio = provider::merge_portfolios(portfolios, &mut warnings);
+        if let Some(path) = &args.exchange_rates {
+            let rates = fx::load_exchange_rates(
+                &mut File::open(path)
+                    .with_context(|| format!("Failed to open exchange rates file {path:?}"))?,
+                &args.base_currency,
+            )
+            .with_context(|| format!("Failed to load exchange rates from {path:?}"))?;
+            for account in portfolio.iter_mut() {
+                rates
+                    .convert(account)
+                    .map_err(|err| self.redact_error(err, &[&account.account_id]))?;
+            }
+        }
         if portfolio.is_empty() {
             println!("No data imported");
         } else {
             self.import_account_balances(&portfolio)?;
             println!("Imported {} accounts:", portfolio.len());
             let max_id = portfolio.iter().fold(0, |max, acct| {
-                if acct.account_id.len() > max {
-                    acct.account_id.len()
-                } else {
-                    max
-                }
+                max.max(self.display_account_id(&acct.account_id).len())
             });
             for account in portfolio {
                 println!(
                     "{:>width$}: {}",
-                    account.account_id,
+                    self.display_account_id(&account.account_id),
                     account.total_value(),
                     width = max_id + 2
                 );
             }
         }
+        print_warnings(&warnings);
         Ok(())
     }
 
+    fn load_balances_file(
+        &self,
+        path: &Path,
+        ptype: Option<ProviderType>,
+        csv_format: &CsvFormat,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let mut f: Box<dyn Read> = if path == Path::new("-") {
+            Box::new(std::io::stdin())
+        } else {
+            Box::new(File::open(path)?)
+        };
+        if *csv_format != CsvFormat::default() {
+            let mut data = Vec::new();
+            f.read_to_end(&mut data)?;
+            f = Box::new(std::io::Cursor::new(csv_format.normalize(&data)?));
+        }
+        match ptype {
+            Some(ProviderType::Generic) => provider::load_generic_portfolio(
+                &mut f,
+                &self.config.generic_columns.clone().unwrap_or_default(),
+                warnings,
+            ),
+            ptype => provider::load_portfolio_with_warnings(&mut f, ptype, warnings),
+        }
+    }
+
     fn import_account_balances(&self, new_balances: &[Balance]) -> anyhow::Result<()> {
         let new_account_ids: Vec<_> = new_balances.iter().map(|b| b.account_id.clone()).collect();
         let mut existing = self.load_balances()?;
@@ -258,8 +808,30 @@ impl App {
         Ok(())
     }
 
+    /// The file with the date on which each account was last rebalanced, by
+    /// account id
+    fn rebalances_file(&self) -> PathBuf {
+        self.dirs.data_dir().join("rebalances.yml")
+    }
+
+    fn load_rebalances(&self) -> anyhow::Result<HashMap<String, Date>> {
+        match File::open(self.rebalances_file()) {
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(HashMap::new()),
+            Err(e) => Err(e.into()),
+            Ok(file) => serde_yaml::from_reader(file).map_err(Into::into),
+        }
+    }
+
+    fn save_rebalances(&self, rebalances: &HashMap<String, Date>) -> anyhow::Result<()> {
+        std::fs::write(self.rebalances_file(), serde_yaml::to_string(rebalances)?)?;
+        Ok(())
+    }
+
     fn cached_balance_file(&self) -> PathBuf {
-        self.dirs.data_dir().join("balances.yml")
+        match &self.args.balances {
+            Some(path) => path.clone(),
+            None => self.dirs.data_dir().join("balances.yml"),
+        }
     }
 
     #[tracing::instrument(ret, level = "trace")]
@@ -297,9 +869,16 @@ impl App {
             Some(pos) => {
                 let balance = balances.remove(pos);
                 self.save_balances(&balances)?;
-                println!("Removed account {account} ({})", balance.total_value());
+                println!(
+                    "Removed account {} ({})",
+                    self.display_account_id(account),
+                    balance.total_value()
+                );
             }
-            None => println!("Account {account} not found. Nothing done."),
+            None => println!(
+                "Account {} not found. Nothing done.",
+                self.display_account_id(account)
+            ),
         }
         Ok(())
     }
@@ -337,15 +916,11 @@ impl App {
 
     fn data_list_command(&self) -> Result<(), anyhow::Error> {
         let mut portfolio = self.load_balances()?;
-        let account_configs = self.load_account_configs()?;
+        let account_configs = self.load_account_configs(false)?;
         sort_accounts(&mut portfolio);
         println!("Data from {} accounts:", portfolio.len());
         let max_id = portfolio.iter().fold(0, |max, acct| {
-            if acct.account_id.len() > max {
-                acct.account_id.len()
-            } else {
-                max
-            }
+            max.max(self.display_account_id(&acct.account_id).len())
         });
         for account in portfolio {
             let has_config = account_configs
@@ -357,7 +932,7 @@ impl App {
                     true => " *",
                     false => "  ",
                 },
-                account.account_id,
+                self.display_account_id(&account.account_id),
                 account.total_value(),
                 width = max_id + 2
             );
@@ -371,18 +946,26 @@ impl App {
         let mut portfolio = self.load_balances()?;
         portfolio.retain(|item| item.account_id == account);
         if portfolio.is_empty() {
-            bail!("No data found for account {account}");
+            bail!(
+                "No data found for account {}",
+                self.display_account_id(account)
+            );
         }
-        let acct = portfolio.pop();
+        let acct = portfolio.pop().map(|mut acct| {
+            acct.account_id = self.display_account_id(&acct.account_id);
+            acct
+        });
         let balance_str = serde_yaml::to_string(&acct)?;
         println!("Current balance:");
         println!("----------------");
         println!("{balance_str}");
-        let account_configs = self.load_account_configs()?;
+        let account_configs = self.load_account_configs(false)?;
         if let Some(cfg) = account_configs
             .iter()
             .find(|item| item.account_id == account)
         {
+            let mut cfg = cfg.clone();
+            cfg.account_id = self.display_account_id(&cfg.account_id);
             let config_str = serde_yaml::to_string(&cfg)?;
             println!("Current allocation targets:");
             println!("---------------------------");
@@ -399,6 +982,285 @@ fn sort_accounts(portfolio: &mut [Balance]) {
     });
 }
 
+/// Order the adjustments in `plan` by `column`. Holdings that are equal in
+/// that column keep their order.
+fn sort_plan(plan: &mut account::RebalancePlan, column: SortColumn, descending: bool) {
+    let drift = plan.drift();
+    let mut adjustments: Vec<_> = std::mem::take(&mut plan.adjustments)
+        .into_iter()
+        .zip(drift)
+        .collect();
+    adjustments.sort_by(|(a, a_drift), (b, b_drift)| {
+        let drift = |drift: &Option<RelativePercent>| drift.map_or(0.0, |d| d.0.0.abs());
+        let ordering = match column {
+            SortColumn::Value => a
+                .holding
+                .current_value
+                .0
+                .total_cmp(&b.holding.current_value.0),
+            SortColumn::Drift => drift(a_drift).total_cmp(&drift(b_drift)),
+            SortColumn::Symbol => a.holding.symbol.cmp(&b.holding.symbol),
+            SortColumn::Target => a.target.0.total_cmp(&b.target.0),
+        };
+        match descending {
+            true => ordering.reverse(),
+            false => ordering,
+        }
+    });
+    plan.adjustments = adjustments.into_iter().map(|(adj, _)| adj).collect();
+}
+
+/// Format the plan for an account as it is shown in the terminal
+fn format_plan(
+    account: &Balance,
+    config: &account::AllocationConfig,
+    plan: &account::RebalancePlan,
+    status: Option<&account::RebalanceStatus>,
+    args: &PlanArgs,
+    plain: bool,
+) -> String {
+    let table = output::format_adjustments(plan, config, plain, &args.columns);
+
+    let mut out = String::new();
+    let name = config.nickname.as_ref().unwrap_or(&account.account_name);
+    if !name.is_empty() {
+        out += &format!("{name}\n");
+    }
+    out += &format!("Account ID: {}\n", account.account_id);
+    out += &format!("Total balance: {}\n", account.total_value());
+    out += &format!("{table}\n");
+    if let Some(score) = plan.drift_score() {
+        out += &format!("Drift score: {score} of the account is off target\n");
+    }
+    match status {
+        Some(status) if status.warranted => {
+            out += &format!("A rebalance is warranted: {}\n", status.reasons.join(", "));
+        }
+        Some(status) => {
+            out += "A rebalance isn't warranted yet";
+            if let Some(next) = status.next_date {
+                out += &format!(" (the next one is due on {next})");
+            }
+            out += "\n";
+        }
+        None => {}
+    }
+    if let Some(targets) = config.glide_path_targets(Date::today()) {
+        let mut targets: Vec<_> = targets.into_iter().collect();
+        targets.sort_by(|a, b| a.0.cmp(&b.0));
+        let targets: Vec<_> = targets
+            .iter()
+            .map(|(symbol, target)| format!("{symbol} {target}"))
+            .collect();
+        out += &format!(
+            "Glide path targets for {}: {}\n",
+            Date::today(),
+            targets.join(", ")
+        );
+    }
+    let exposures = config.look_through(plan);
+    if !exposures.is_empty() {
+        out += "Look-through exposure:\n";
+        for exposure in exposures {
+            out += &format!(
+                "  {}: {} (target {}, drift {}), {} after rebalancing\n",
+                exposure.class,
+                exposure.current,
+                exposure.target,
+                exposure.drift(),
+                exposure.result
+            );
+        }
+    }
+    match (plan.realized_gains(), plan.short_term_gains()) {
+        (Some(gains), Some(short_term)) => {
+            out += &format!(
+                "Estimated realized gains: {} ({} short-term)\n",
+                gains.round_cents(),
+                short_term.round_cents()
+            )
+        }
+        (Some(gains), None) => {
+            out += &format!("Estimated realized gains: {}\n", gains.round_cents())
+        }
+        _ => {}
+    }
+    if !plan.gains_limited.is_empty()
+        && let Some(max) = config.max_realized_gains
+    {
+        out += &format!(
+            "Sells of {} were reduced to keep the realized gains under {max}\n",
+            plan.gains_limited.join(", ")
+        );
+    }
+    if let Some(limit) = plan.turnover_limit {
+        out += &format!(
+            "Trades were reduced to stay within a turnover of {limit}, trading the holdings furthest from their targets the most\n"
+        );
+    }
+    if args.chart {
+        out += &output::format_chart(plan, plain);
+    }
+    if args.explain {
+        out += &output::format_explanation(plan);
+    }
+    if plan.residual.round_cents() != Dollar(0.0) {
+        out += &match plan.adjustments.iter().find(|adj| adj.holding.is_cash) {
+            Some(cash) => format!(
+                "Residual of {} from held and rounded trades is kept in {}\n",
+                plan.residual, cash.holding.symbol
+            ),
+            None => format!(
+                "Residual of {} from held and rounded trades is not allocated to any holding\n",
+                plan.residual
+            ),
+        }
+    }
+    if !plan.other_cash.is_empty()
+        && let Some(cash) = plan.adjustments.iter().find(|adj| adj.holding.is_cash)
+    {
+        let other: Vec<_> = plan
+            .other_cash
+            .iter()
+            .map(|holding| format!("{} ({})", holding.symbol, holding.current_value))
+            .collect();
+        out += &format!(
+            "Cash in {} includes {}\n",
+            cash.holding.symbol,
+            other.join(", ")
+        );
+    }
+    if let Some(withdrawal) = plan.withdrawal
+        && let Some(cash) = plan.adjustments.iter().find(|adj| adj.holding.is_cash)
+    {
+        out += &format!(
+            "Withdrawal: {withdrawal} is raised in {} and can be transferred out\n",
+            cash.holding.symbol
+        );
+    }
+    if !plan.empty_holdings.is_empty() {
+        out += &format!("Empty holdings: {}\n", plan.empty_holdings.join(", "));
+    }
+    let held = |reason| {
+        plan.adjustments
+            .iter()
+            .filter(|adj| adj.held == Some(reason))
+            .map(|adj| adj.holding.symbol.as_str())
+            .collect::<Vec<_>>()
+    };
+    let within_band = held(HoldReason::WithinBand);
+    if !within_band.is_empty() {
+        out += &format!(
+            "Within tolerance band (not traded): {}\n",
+            within_band.join(", ")
+        );
+    }
+    let within_range = held(HoldReason::WithinRange);
+    if !within_range.is_empty() {
+        out += &format!(
+            "Within target range (not traded): {}\n",
+            within_range.join(", ")
+        );
+    }
+    let small_trades = held(HoldReason::BelowMinimumTrade);
+    if let (false, Some(minimum)) = (small_trades.is_empty(), config.minimum_trade) {
+        out += &format!(
+            "Smaller than the minimum trade of {minimum} (not traded): {}\n",
+            small_trades.join(", ")
+        );
+    }
+    let short_term: Vec<_> = plan
+        .adjustments
+        .iter()
+        .filter(|adj| adj.held == Some(HoldReason::ShortTermGain))
+        .filter_map(|adj| {
+            let date = adj.short_term_until?;
+            Some(format!("{} (until {date})", adj.holding.symbol))
+        })
+        .collect();
+    if !short_term.is_empty() {
+        out += &format!(
+            "Would realize short-term gains (not sold): {}\n",
+            short_term.join(", ")
+        );
+    }
+    // brokerages take orders for a number of shares, so give the number of
+    // shares for the holdings whose price is known
+    let orders: Vec<_> = plan
+        .adjustments
+        .iter()
+        .filter_map(|adj| Some((adj, adj.shares()?)))
+        .collect();
+    if !orders.is_empty() {
+        out += "Orders:\n";
+        for (adj, shares) in orders {
+            let verb = match adj.action {
+                Action::Buy(_) => "Buy",
+                _ => "Sell",
+            };
+            let shares = if config.whole_shares.contains(&adj.holding.symbol) {
+                format!("{shares:.0}")
+            } else {
+                format!("{shares:.3}")
+            };
+            out += &format!(
+                "  {verb} {shares} shares of {} {} {}\n",
+                adj.holding.symbol,
+                if plain { "~" } else { "≈" },
+                Dollar(adj.action.change().0.abs())
+            );
+        }
+    }
+    if args.steps {
+        let steps = plan.steps();
+        if !steps.is_empty() {
+            out += "Steps:\n";
+            for (i, step) in steps.iter().enumerate() {
+                out += &format!("  {}. {step}\n", i + 1);
+            }
+        }
+    }
+    if config.exchange_orders {
+        let exchanges = plan.exchanges();
+        if !exchanges.is_empty() {
+            out += "Exchange orders:\n";
+            for exchange in exchanges {
+                out += &format!("  {exchange}\n");
+            }
+        }
+    }
+    if !plan.upcoming_maturities.is_empty() {
+        out += "Upcoming maturities (future investable cash):\n";
+        for holding in plan.upcoming_maturities.iter() {
+            if let Some(maturity) = holding.maturity {
+                out += &format!(
+                    "  {maturity}: {} {}\n",
+                    holding.symbol, holding.current_value
+                );
+            }
+        }
+    }
+    out += &format_warnings(&plan.warnings);
+    out + "\n"
+}
+
+fn format_warnings(warnings: &[String]) -> String {
+    let mut out = String::new();
+    if !warnings.is_empty() {
+        out += "Warnings:\n";
+        for warning in warnings {
+            out += &format!("  {warning}\n");
+        }
+    }
+    out
+}
+
+/// Print the recoverable problems that were encountered after the rest of the
+/// output so that they aren't lost in the logs
+fn print_warnings(warnings: &[String]) {
+    print!("{}", format_warnings(warnings));
+}
+
 fn app_config_dir(dirs: &ProjectDirs) -> PathBuf {
     dirs.config_dir().join("config.yml")
 }
@@ -0,0 +1,113 @@
+use std::{
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use anyhow::{Context, anyhow};
+use tracing::debug;
+
+/// Find the file that the user meant by `path`. A directory or a file name
+/// containing wildcards (`*` or `?`) refers to the most recently modified
+/// file that it contains or matches, so that a new download can be imported
+/// without renaming it. Anything else is returned unchanged.
+pub(crate) fn resolve(path: &Path) -> anyhow::Result<PathBuf> {
+    let path = expand_home(path);
+    let (dir, pattern) = if path.is_dir() {
+        (path.as_path(), "*")
+    } else {
+        match path.file_name().and_then(|name| name.to_str()) {
+            Some(name) if name.contains(['*', '?']) => (
+                path.parent()
+                    .filter(|p| !p.as_os_str().is_empty())
+                    .unwrap_or(Path::new(".")),
+                name,
+            ),
+            _ => return Ok(path),
+        }
+    };
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+    for entry in std::fs::read_dir(dir).with_context(|| format!("Failed to read {dir:?}"))? {
+        let entry = entry?;
+        let name = entry.file_name();
+        let Some(name) = name.to_str() else {
+            continue;
+        };
+        // hidden files are never balance downloads
+        if name.starts_with('.') || !matches(pattern, name) {
+            continue;
+        }
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified()?;
+        debug!(name, ?modified, "found candidate balances file");
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+    newest
+        .map(|(_, path)| path)
+        .ok_or_else(|| anyhow!("No files match {path:?}"))
+}
+
+/// Replace a leading `~` with the user's home directory, since a quoted
+/// pattern isn't expanded by the shell
+fn expand_home(path: &Path) -> PathBuf {
+    match (path.strip_prefix("~"), std::env::var_os("HOME")) {
+        (Ok(rest), Some(home)) => PathBuf::from(home).join(rest),
+        _ => path.to_path_buf(),
+    }
+}
+
+/// Whether `name` matches a shell-style `pattern`, where `*` matches any
+/// number of characters and `?` matches a single character
+fn matches(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    // the positions in the pattern and name to go back to when a `*` needs to
+    // match more characters
+    let mut backtrack: Option<(usize, usize)> = None;
+    let (mut p, mut n) = (0, 0);
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, n));
+                p += 1;
+            }
+            Some(c) if *c == '?' || *c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match backtrack {
+                Some((star, matched)) => {
+                    p = star + 1;
+                    n = matched + 1;
+                    backtrack = Some((star, matched + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|c| *c == '*')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matches() {
+        assert!(matches(
+            "Portfolio_Positions_*.csv",
+            "Portfolio_Positions_Jan-15-2025.csv"
+        ));
+        assert!(matches("*", "anything"));
+        assert!(matches("a?c*", "abc"));
+        assert!(!matches(
+            "Portfolio_Positions_*.csv",
+            "Portfolio_Positions_Jan.xlsx"
+        ));
+        assert!(!matches("a?c", "ac"));
+    }
+}
@@ -0,0 +1,105 @@
+//! Allocation charts as SVG images, which can be included in notes or web
+//! pages without any other files
+
+use std::fmt::Write;
+
+use crate::output::{AllocationShare, escape_html};
+
+/// Colors for the holdings in a chart, which are reused if an account has
+/// more holdings than colors
+const PALETTE: &[&str] = &[
+    "#4e79a7", "#f28e2b", "#59a14f", "#e15759", "#76b7b2", "#edc948", "#b07aa1", "#ff9da7",
+    "#9c755f", "#bab0ac",
+];
+const WIDTH: f32 = 560.0;
+/// The height of the chart for a single account
+const ACCOUNT_HEIGHT: f32 = 300.0;
+const LEGEND_ROW_HEIGHT: f32 = 22.0;
+/// The rings showing the current allocation (outside) and the target
+/// allocation (inside), as a radius and a width
+const CURRENT_RING: (f32, f32) = (100.0, 36.0);
+const TARGET_RING: (f32, f32) = (62.0, 28.0);
+
+/// Draw one ring of a donut chart, with a segment for each percentage. Each
+/// segment is drawn as a dashed stroke of a circle, so that a segment of 100%
+/// doesn't need special handling.
+fn ring(out: &mut String, (cx, cy): (f32, f32), (radius, width): (f32, f32), percents: &[f32]) {
+    let circumference = 2.0 * std::f32::consts::PI * radius;
+    let total: f32 = percents.iter().sum();
+    let mut start = 0.0;
+    for (i, percent) in percents.iter().enumerate() {
+        if total <= 0.0 || *percent <= 0.0 {
+            continue;
+        }
+        let length = percent / total * circumference;
+        // rotate so that the first segment starts at the top
+        let _ = writeln!(
+            out,
+            r#"<circle cx="{cx}" cy="{cy}" r="{radius}" fill="none" stroke="{}" stroke-width="{width}" stroke-dasharray="{length:.2} {circumference:.2}" stroke-dashoffset="{:.2}" transform="rotate(-90 {cx} {cy})"/>"#,
+            PALETTE[i % PALETTE.len()],
+            0.0 - start,
+        );
+        start += length;
+    }
+}
+
+/// Draw a donut chart for each account comparing the current share of each
+/// invested holding (the outer ring) with its target (the inner ring)
+pub(crate) fn allocation_svg(accounts: &[(String, Vec<AllocationShare>)]) -> String {
+    let heights: Vec<f32> = accounts
+        .iter()
+        .map(|(_, shares)| ACCOUNT_HEIGHT.max(60.0 + shares.len() as f32 * LEGEND_ROW_HEIGHT))
+        .collect();
+    let height: f32 = heights.iter().sum();
+    let mut out = String::new();
+    let _ = writeln!(
+        out,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{WIDTH}" height="{height}" viewBox="0 0 {WIDTH} {height}" font-family="sans-serif" font-size="14">"#
+    );
+    let _ = writeln!(out, r#"<rect width="100%" height="100%" fill="white"/>"#);
+    let mut top = 0.0;
+    for ((name, shares), account_height) in accounts.iter().zip(heights) {
+        let _ = writeln!(
+            out,
+            r#"<text x="20" y="{}" font-size="18" font-weight="bold">{}</text>"#,
+            top + 28.0,
+            escape_html(name)
+        );
+        let center = (150.0, top + 30.0 + (ACCOUNT_HEIGHT - 30.0) / 2.0);
+        let current: Vec<f32> = shares.iter().map(|share| share.current.0).collect();
+        let target: Vec<f32> = shares.iter().map(|share| share.target.0).collect();
+        ring(&mut out, center, CURRENT_RING, &current);
+        ring(&mut out, center, TARGET_RING, &target);
+        let _ = writeln!(
+            out,
+            r##"<text x="{}" y="{}" text-anchor="middle" font-size="11" fill="#555">outer: current</text>"##,
+            center.0,
+            center.1 - 4.0
+        );
+        let _ = writeln!(
+            out,
+            r##"<text x="{}" y="{}" text-anchor="middle" font-size="11" fill="#555">inner: target</text>"##,
+            center.0,
+            center.1 + 10.0
+        );
+        for (i, share) in shares.iter().enumerate() {
+            let y = top + 60.0 + i as f32 * LEGEND_ROW_HEIGHT;
+            let _ = writeln!(
+                out,
+                r#"<rect x="300" y="{}" width="14" height="14" fill="{}"/>"#,
+                y - 12.0,
+                PALETTE[i % PALETTE.len()]
+            );
+            let _ = writeln!(
+                out,
+                r#"<text x="322" y="{y}">{} {} (target {})</text>"#,
+                escape_html(&share.symbol),
+                share.current,
+                share.target
+            );
+        }
+        top += account_height;
+    }
+    out.push_str("</svg>\n");
+    out
+}
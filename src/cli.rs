@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
-use clap::{Args, Parser, Subcommand};
-use driftfix::{Dollar, provider::ProviderType};
+use clap::{Args, Parser, Subcommand, ValueEnum, builder::BoolishValueParser};
+use driftfix::{Dollar, Percent, provider::ProviderType};
 
 #[derive(Parser, Debug)]
 pub(crate) struct Cli {
@@ -10,9 +10,41 @@ pub(crate) struct Cli {
         long,
         global = true,
         value_name = "TARGET_CONFIG_FILE",
+        env = "DRIFTFIX_TARGET_CONFIG",
         help = "Override default target allocation configuration file"
     )]
     pub target_config: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        value_name = "FILE",
+        env = "DRIFTFIX_BALANCES",
+        help = "Keep the imported account balances in the given file instead of the data directory (this is where `data add` stores them, not a portfolio file to import)"
+    )]
+    pub balances: Option<PathBuf>,
+    #[arg(
+        long,
+        global = true,
+        env = "DRIFTFIX_NO_HOOKS",
+        value_parser = BoolishValueParser::new(),
+        help = "Don't run the pre- and post-hooks from the application configuration"
+    )]
+    pub no_hooks: bool,
+    #[arg(
+        long,
+        global = true,
+        env = "DRIFTFIX_LOCALE",
+        help = "Show money in the format of the given locale (e.g. 'de-DE'), overriding the application configuration"
+    )]
+    pub locale: Option<String>,
+    #[arg(
+        long,
+        global = true,
+        env = "DRIFTFIX_REDACT",
+        value_parser = BoolishValueParser::new(),
+        help = "Only show the last 4 digits of account numbers, e.g. to share a plan"
+    )]
+    pub redact: bool,
     #[command(subcommand)]
     pub command: MainCommands,
 }
@@ -22,9 +54,11 @@ pub(crate) enum MainCommands {
     #[command(about = "Configure account target allocations", alias = "edit")]
     Configure,
     #[command(about = "Calculate adjustments needed to acheive configured target allocations")]
-    Plan(PlanArgs),
+    Plan(Box<PlanArgs>),
     #[command(about = "Manage account balance data")]
     Data(DataArgs),
+    #[command(about = "Create a target allocation configuration from the current holdings")]
+    Init(InitArgs),
     #[command(about = "Generate shell autocompletion script")]
     Completion { shell: clap_complete::Shell },
 }
@@ -42,15 +76,280 @@ pub(crate) struct PlanArgs {
     #[arg(
         long,
         value_name = "VALUE",
-        help = "Amount to keep in cash sweep (overrides target allocation configuration)"
+        visible_alias = "core-minimum",
+        help = "Amount to keep in the cash sweep of every matching account (overrides target allocation configuration)"
     )]
     pub(crate) cash_minimum: Option<Dollar>,
+    #[arg(
+        long,
+        value_name = "SYMBOL=PCT",
+        value_parser = parse_target_override,
+        help = "Use this target for a holding instead of the configured one, e.g. 'VTI=50' (can be given more than once)"
+    )]
+    pub(crate) target_override: Vec<(String, Percent)>,
+    #[arg(
+        long,
+        value_name = "SYMBOL=±PCT",
+        value_parser = parse_shock,
+        allow_hyphen_values = true,
+        help = "Plan as if the price of a holding had moved by this much, e.g. 'VTI=-20%' to see the plan after a crash (can be given more than once)"
+    )]
+    pub(crate) shock: Vec<(String, Percent)>,
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        help = "Plan how to invest a new contribution of this amount (and any excess cash) without selling anything"
+    )]
+    pub(crate) contribute: Option<Dollar>,
+    #[arg(
+        long,
+        value_name = "AMOUNT",
+        conflicts_with = "contribute",
+        help = "Plan which holdings to sell to raise this amount in cash for a withdrawal, without buying anything"
+    )]
+    pub(crate) withdraw: Option<Dollar>,
+    #[arg(
+        long,
+        help = "Scale the targets of each account proportionally if they don't add up to 100% (overrides target allocation configuration)"
+    )]
+    pub(crate) normalize_targets: bool,
+    #[arg(
+        long,
+        value_name = "VALUE",
+        help = "Leave out trades smaller than this amount (overrides target allocation configuration)"
+    )]
+    pub(crate) min_trade: Option<Dollar>,
+    #[arg(
+        long,
+        value_name = "VALUE",
+        help = "Round trades to a multiple of this amount, e.g. 50 (overrides target allocation configuration)"
+    )]
+    pub(crate) round_to: Option<Dollar>,
+    #[arg(
+        long,
+        value_name = "VALUE",
+        help = "Reduce sells so that the plan realizes at most this amount of gains, based on the cost basis (overrides target allocation configuration)"
+    )]
+    pub(crate) max_gains: Option<Dollar>,
+    #[arg(
+        long,
+        value_name = "VALUE",
+        value_parser = parse_turnover,
+        help = "Limit the total amount bought and sold to this amount, or to a percentage of the account such as '10%', trading the holdings furthest from their targets the most (overrides target allocation configuration)"
+    )]
+    pub(crate) max_turnover: Option<Turnover>,
+    #[arg(
+        long,
+        help = "Trade as few holdings as possible while bringing every holding within its tolerance band (overrides target allocation configuration)"
+    )]
+    pub(crate) fewest_trades: bool,
+    #[arg(
+        long,
+        help = "Don't sell holdings that would realize short-term gains, based on their acquisition dates (overrides target allocation configuration)"
+    )]
+    pub(crate) defer_short_term: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "A CSV file of recent trades (with 'Date', 'Symbol', 'Action' and 'Gain' columns) to warn about buys that may be wash sales"
+    )]
+    pub(crate) trades: Option<PathBuf>,
     #[arg(
         short,
         long,
-        help = "Only show targets for the given account name or id"
+        help = "Only show targets for the given account id, alias or name"
     )]
     pub(crate) account: Option<String>,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        visible_alias = "report",
+        default_value_t = OutputFormat::Table,
+        help = "How to show the plan"
+    )]
+    pub(crate) format: OutputFormat,
+    #[arg(
+        long,
+        value_enum,
+        value_name = "COLUMN",
+        help = "Order the holdings of each account by the given column instead of the order in the balances file"
+    )]
+    pub(crate) sort: Option<SortColumn>,
+    #[arg(long, requires = "sort", help = "Sort in descending order")]
+    pub(crate) desc: bool,
+    #[arg(
+        long,
+        value_enum,
+        value_delimiter = ',',
+        value_name = "COLUMN",
+        help = "Only show the given comma-separated columns of the table (e.g. 'symbol,value,target,change')"
+    )]
+    pub(crate) columns: Vec<TableColumn>,
+    #[arg(
+        long,
+        help = "Show a bar chart of the current and target allocation of each account's investments"
+    )]
+    pub(crate) chart: bool,
+    #[arg(
+        long,
+        help = "Explain how the adjustment for each holding was calculated"
+    )]
+    pub(crate) explain: bool,
+    #[arg(
+        long,
+        help = "Also copy the plan to the clipboard (without colors), e.g. to paste it into notes or a chat"
+    )]
+    pub(crate) copy: bool,
+    #[arg(
+        long,
+        help = "Record that the plans were carried out today, for accounts with a rebalance policy"
+    )]
+    pub(crate) mark_rebalanced: bool,
+    #[arg(
+        long,
+        help = "Show the trades as numbered steps in the order to carry them out: the sells, waiting for them to settle if the buys need their proceeds, and then the buys"
+    )]
+    pub(crate) steps: bool,
+    #[arg(
+        long,
+        visible_alias = "no-color",
+        help = "Draw tables without colors or box-drawing characters, e.g. for dumb terminals (the default if NO_COLOR is set or the output isn't a terminal)"
+    )]
+    pub(crate) plain: bool,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "Save a chart of the current and target allocation of each account's investments as an SVG image"
+    )]
+    pub(crate) chart_file: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with = "format",
+        help = "Show the plan of each account by rendering the given template file (see the README for its syntax)"
+    )]
+    pub(crate) template: Option<PathBuf>,
+    #[arg(
+        short,
+        long,
+        value_name = "FILE",
+        help = "Write the plan to a file instead of standard output (not available for tables, except with --template)"
+    )]
+    pub(crate) output: Option<PathBuf>,
+}
+
+/// A limit on how much a plan may trade
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum Turnover {
+    Amount(Dollar),
+    Percent(Percent),
+}
+
+fn parse_turnover(value: &str) -> Result<Turnover, String> {
+    let invalid = |_| format!("'{value}' is not an amount or a percentage");
+    match value.strip_suffix('%') {
+        Some(percent) => percent
+            .trim()
+            .parse()
+            .map(|percent| Turnover::Percent(Percent(percent)))
+            .map_err(invalid),
+        None => value.parse().map(Turnover::Amount).map_err(invalid),
+    }
+}
+
+fn parse_target_override(value: &str) -> Result<(String, Percent), String> {
+    let invalid = || format!("'{value}' is not of the form SYMBOL=PCT, e.g. 'VTI=50'");
+    let (symbol, percent) = value.split_once('=').ok_or_else(invalid)?;
+    let percent = percent.trim();
+    let percent: f32 = percent
+        .strip_suffix('%')
+        .unwrap_or(percent)
+        .trim()
+        .parse()
+        .map_err(|_| invalid())?;
+    match symbol.trim() {
+        "" => Err(invalid()),
+        symbol => Ok((symbol.to_string(), Percent(percent))),
+    }
+}
+
+fn parse_shock(value: &str) -> Result<(String, Percent), String> {
+    let (symbol, change) = parse_target_override(value)
+        .map_err(|_| format!("'{value}' is not of the form SYMBOL=±PCT, e.g. 'VTI=-20%'"))?;
+    match change > Percent(-100.0) {
+        true => Ok((symbol, change)),
+        false => Err("A price can't fall by 100% or more".to_string()),
+    }
+}
+
+/// The ways that a plan can be shown
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq)]
+pub(crate) enum OutputFormat {
+    /// A table for each account, for reading in a terminal
+    #[default]
+    Table,
+    /// The full plan for every account as a JSON document, for scripts
+    Json,
+    /// The allocation table of every account as CSV, for spreadsheets
+    Csv,
+    /// The plan for every account as GitHub-flavored markdown, for notes
+    Markdown,
+    /// A styled, self-contained HTML report, for archiving or sharing
+    Html,
+}
+
+/// The columns of the table of holdings, in the order that they are shown
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum TableColumn {
+    Symbol,
+    Label,
+    Value,
+    Percent,
+    Target,
+    Drift,
+    Change,
+    Result,
+}
+
+/// The columns that holdings can be sorted by
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SortColumn {
+    Value,
+    /// How far the holding is from its target, in either direction
+    Drift,
+    Symbol,
+    Target,
+}
+
+impl PlanArgs {
+    /// Whether the plan is written as a document to be saved or read by other
+    /// programs, in which case nothing else may be written to stdout
+    pub(crate) fn is_document(&self) -> bool {
+        self.format != OutputFormat::Table || self.template.is_some()
+    }
+}
+
+#[derive(Args, Debug)]
+pub(crate) struct InitArgs {
+    #[arg(
+        value_name = "ACCOUNT_BALANCES",
+        help = "A file containing account balances, or '-' to read from standard input"
+    )]
+    pub(crate) account_balances: PathBuf,
+    #[arg(
+        short,
+        long,
+        value_enum,
+        value_name = "PROVIDER_ID",
+        help = "Investment provider associated with account balances file (detected from the file's contents if not specified)"
+    )]
+    pub(crate) provider: Option<ProviderType>,
+    #[arg(
+        long,
+        help = "Replace the target allocation configuration if it exists"
+    )]
+    pub(crate) force: bool,
 }
 
 #[derive(Args, Debug)]
@@ -83,15 +382,48 @@ pub(crate) enum DataCommands {
 pub(crate) struct DataAddArgs {
     #[arg(
         value_name = "ACCOUNT_BALANCES",
-        help = "A file containing account balances"
+        required = true,
+        help = "Files containing account balances, or '-' to read from standard input. A directory or a wildcard pattern (e.g. 'Downloads/Portfolio_Positions_*.csv') uses the newest matching file"
     )]
-    pub(crate) account_balances: PathBuf,
+    pub(crate) account_balances: Vec<PathBuf>,
     #[arg(
         short,
         long,
         value_enum,
         value_name = "PROVIDER_ID",
-        help = "Investment provider associated with account balances file"
+        env = "DRIFTFIX_PROVIDER",
+        help = "Investment provider associated with account balances file (detected from the file's contents if not specified). Give it once for all files, or once per file in the same order"
     )]
-    pub(crate) provider: Option<ProviderType>,
+    pub(crate) provider: Vec<ProviderType>,
+    #[arg(
+        long,
+        value_name = "CHAR",
+        help = "The character that separates fields in CSV files (e.g. ';')"
+    )]
+    pub(crate) delimiter: Option<char>,
+    #[arg(
+        long,
+        value_name = "CHAR",
+        help = "The character that separates the whole and fractional parts of numbers in CSV files (e.g. ',')"
+    )]
+    pub(crate) decimal_separator: Option<char>,
+    #[arg(
+        long = "currency-symbol",
+        value_name = "SYMBOL",
+        help = "A currency symbol that may appear in values in CSV files (e.g. '€'). May be given more than once"
+    )]
+    pub(crate) currency_symbols: Vec<String>,
+    #[arg(
+        long,
+        value_name = "FILE",
+        help = "A CSV file of exchange rates (with 'Currency' and 'Rate' columns) to convert holdings that are valued in other currencies into the base currency"
+    )]
+    pub(crate) exchange_rates: Option<PathBuf>,
+    #[arg(
+        long,
+        value_name = "CODE",
+        default_value = "USD",
+        help = "The currency that balances are converted into with --exchange-rates"
+    )]
+    pub(crate) base_currency: String,
 }
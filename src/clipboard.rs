@@ -0,0 +1,67 @@
+//! Copying text to the system clipboard, using whichever clipboard command is
+//! available on the platform
+
+use std::{
+    io::{ErrorKind, Write},
+    process::{Command, Stdio},
+};
+
+use anyhow::{Context, bail};
+use tracing::debug;
+
+/// The commands that copy their standard input to the clipboard, with their
+/// arguments, in the order that they are tried
+fn clipboard_commands() -> Vec<(&'static str, &'static [&'static str])> {
+    let mut commands: Vec<(&str, &[&str])> = Vec::new();
+    if cfg!(target_os = "macos") {
+        commands.push(("pbcopy", &[]));
+    }
+    if cfg!(windows) {
+        commands.push(("clip", &[]));
+    }
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        commands.push(("wl-copy", &[]));
+    }
+    commands.push(("xclip", &["-selection", "clipboard"]));
+    commands.push(("xsel", &["--clipboard", "--input"]));
+    // Windows clipboard from WSL
+    commands.push(("clip.exe", &[]));
+    commands
+}
+
+/// Put `text` on the system clipboard
+pub(crate) fn copy(text: &str) -> anyhow::Result<()> {
+    for (program, args) in clipboard_commands() {
+        let mut child = match Command::new(program)
+            .args(args)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        {
+            Ok(child) => child,
+            Err(e) if e.kind() == ErrorKind::NotFound => continue,
+            Err(e) => return Err(e).with_context(|| format!("Failed to run {program}")),
+        };
+        debug!(program, "Copying to the clipboard");
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes())
+            .with_context(|| format!("Failed to copy the plan with {program}"))?;
+        let status = child.wait()?;
+        if !status.success() {
+            bail!("Failed to copy the plan with {program} ({status})");
+        }
+        return Ok(());
+    }
+    bail!(
+        "No clipboard command was found. Install one of {} to use --copy.",
+        clipboard_commands()
+            .iter()
+            .map(|(program, _)| *program)
+            .collect::<Vec<_>>()
+            .join(", ")
+    )
+}
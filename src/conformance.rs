@@ -0,0 +1,168 @@
+//! A test harness for portfolio providers.
+//!
+//! Given the output of a provider for a known fixture file, these functions
+//! verify the contract that every provider is expected to uphold:
+//!
+//! - every account has a unique, non-empty account ID
+//! - each expected account is present and its holdings add up to the declared total
+//! - each account's cash holdings are exactly the expected ones (usually at
+//!   most one, but multi-currency accounts may have one per currency)
+//! - every holding has a symbol, and no symbol appears twice in one account
+//! - the fixture is auto-detected as coming from the provider and parses the
+//!   same way
+//!
+//! Problems are collected and reported together so that a single run shows
+//! everything that is wrong with a provider.
+
+use std::io::{BufRead, Cursor};
+
+use anyhow::{anyhow, bail};
+
+use crate::{
+    Dollar,
+    account::Balance,
+    provider::{self, BalanceProvider, ProviderRegistry, ProviderType},
+};
+
+/// What a provider is expected to produce for a single account in a fixture
+#[derive(Debug, Clone, Default)]
+pub struct ExpectedAccount {
+    pub account_id: String,
+    /// The sum of all holdings in the account
+    pub total: Dollar,
+    /// The symbols of the cash holdings, which is empty if the account
+    /// shouldn't have any
+    pub cash_symbols: Vec<String>,
+}
+
+impl ExpectedAccount {
+    pub fn new(account_id: &str, total: Dollar, cash_symbols: &[&str]) -> Self {
+        Self {
+            account_id: account_id.to_string(),
+            total,
+            cash_symbols: cash_symbols.iter().map(|s| s.to_string()).collect(),
+        }
+    }
+}
+
+/// Check a parsed portfolio against the expected accounts. Accounts that
+/// aren't listed in `expected` are an error.
+pub fn check_portfolio(portfolio: &[Balance], expected: &[ExpectedAccount]) -> anyhow::Result<()> {
+    let mut problems = Vec::new();
+    for (i, account) in portfolio.iter().enumerate() {
+        if account.account_id.trim().is_empty() {
+            problems.push(format!("Account #{i} has an empty account ID"));
+        }
+        if portfolio[..i]
+            .iter()
+            .any(|other| other.account_id == account.account_id)
+        {
+            problems.push(format!(
+                "Account '{}' appears more than once",
+                account.account_id
+            ));
+        }
+        if !expected.iter().any(|e| e.account_id == account.account_id) {
+            problems.push(format!("Unexpected account '{}'", account.account_id));
+        }
+        for (j, holding) in account.holdings.iter().enumerate() {
+            if holding.symbol.trim().is_empty() {
+                problems.push(format!(
+                    "Account '{}' has a holding without a symbol",
+                    account.account_id
+                ));
+            }
+            if account.holdings[..j]
+                .iter()
+                .any(|other| other.symbol == holding.symbol)
+            {
+                problems.push(format!(
+                    "Account '{}' lists symbol '{}' more than once",
+                    account.account_id, holding.symbol
+                ));
+            }
+        }
+    }
+
+    for e in expected {
+        let Some(account) = portfolio.iter().find(|a| a.account_id == e.account_id) else {
+            problems.push(format!("Missing account '{}'", e.account_id));
+            continue;
+        };
+        let total = account.total_value();
+        if (total - e.total).abs() > Dollar(0.01) {
+            problems.push(format!(
+                "Account '{}' has a total of {total}, expected {}",
+                e.account_id, e.total
+            ));
+        }
+        let mut cash: Vec<_> = account
+            .holdings
+            .iter()
+            .filter(|h| h.is_cash)
+            .map(|h| &h.symbol)
+            .collect();
+        cash.sort();
+        let mut expected_cash: Vec<_> = e.cash_symbols.iter().collect();
+        expected_cash.sort();
+        if cash != expected_cash {
+            problems.push(format!(
+                "Account '{}' should have cash holdings {expected_cash:?}, found {cash:?}",
+                e.account_id
+            ));
+        }
+    }
+
+    if problems.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "Provider conformance check failed:\n  {}",
+            problems.join("\n  ")
+        ))
+    }
+}
+
+/// Parse `fixture` with the given built-in provider, both explicitly and
+/// using auto-detection, and check the results against the expected accounts
+pub fn check_provider(
+    ptype: ProviderType,
+    fixture: &[u8],
+    expected: &[ExpectedAccount],
+) -> anyhow::Result<()> {
+    check_balance_provider(
+        provider::provider(ptype).as_ref(),
+        &ProviderRegistry::default(),
+        fixture,
+        expected,
+    )
+}
+
+/// Like [check_provider], but for any provider (e.g. one from another crate).
+/// The fixture has to be detected as being from `provider` by `registry`, and
+/// parse the same way.
+pub fn check_balance_provider(
+    provider: &dyn BalanceProvider,
+    registry: &ProviderRegistry,
+    fixture: &[u8],
+    expected: &[ExpectedAccount],
+) -> anyhow::Result<()> {
+    let mut reader = Cursor::new(fixture);
+    let explicit = provider.parse(&mut *provider::buffer(&mut reader)?, &mut Vec::new())?;
+    check_portfolio(&explicit, expected)?;
+    let mut reader = Cursor::new(fixture);
+    let mut buffered = provider::buffer(&mut reader)?;
+    let detected = registry
+        .detect(buffered.fill_buf()?)
+        .ok_or_else(|| anyhow!("Fixture was not auto-detected"))?;
+    if detected.name() != provider.name() {
+        bail!(
+            "Fixture was detected as '{}' rather than '{}'",
+            detected.name(),
+            provider.name()
+        );
+    }
+    let detected = detected.parse(&mut buffered, &mut Vec::new())?;
+    check_portfolio(&detected, expected)
+        .map_err(|e| anyhow!("Auto-detected provider parsed the fixture differently: {e}"))
+}
@@ -0,0 +1,75 @@
+//! The small CSV files that are read along with the balances (e.g. recent
+//! trades and exchange rates). They have a header row, and their columns are
+//! found by name without regard to case.
+
+use std::io::Read;
+
+use csv::StringRecord;
+
+/// A CSV file with a header row
+pub(crate) struct CsvFile<'a> {
+    reader: csv::Reader<&'a mut dyn Read>,
+    headers: StringRecord,
+}
+
+/// A row of a [CsvFile]
+pub(crate) struct Row {
+    /// The line of the file that the row is on, counting the header as the
+    /// first line
+    pub line: usize,
+    record: StringRecord,
+}
+
+impl<'a> CsvFile<'a> {
+    pub fn new(reader: &'a mut dyn Read) -> anyhow::Result<Self> {
+        let mut reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = reader.headers()?.clone();
+        Ok(Self { reader, headers })
+    }
+
+    /// The index of the column with the given name, if there is one
+    pub fn column(&self, name: &str) -> Option<usize> {
+        self.headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    }
+
+    /// The rows after the header
+    pub fn rows(self) -> impl Iterator<Item = anyhow::Result<Row>> + 'a {
+        self.reader
+            .into_records()
+            .enumerate()
+            .map(|(line, record)| {
+                Ok(Row {
+                    // the header is the first line
+                    line: line + 2,
+                    record: record?,
+                })
+            })
+    }
+}
+
+impl Row {
+    /// The trimmed value in `column`, which is empty if the row is too short
+    pub fn field(&self, column: usize) -> &str {
+        self.record.get(column).map(str::trim).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_file() {
+        let mut text = " Name ,VALUE\n a , 1 \nb\n".as_bytes();
+        let file = CsvFile::new(&mut text).unwrap();
+        assert_eq!(file.column("name"), Some(0));
+        assert_eq!(file.column("Value"), Some(1));
+        assert_eq!(file.column("Other"), None);
+        let rows: Vec<_> = file.rows().map(Result::unwrap).collect();
+        assert_eq!(rows[0].line, 2);
+        assert_eq!((rows[0].field(0), rows[0].field(1)), ("a", "1"));
+        assert_eq!((rows[1].field(0), rows[1].field(1)), ("b", ""));
+    }
+}
@@ -0,0 +1,145 @@
+//! How dollar values are shown to the user, which depends on their locale
+//! (e.g. "$1,234.56" in the US, or "1.234,56 €" in Germany).
+//!
+//! The format is chosen once when the application starts, with
+//! [CurrencyFormat::set_global], and is then used whenever a [crate::Dollar]
+//! is displayed.
+
+use std::sync::OnceLock;
+
+use serde::{Deserialize, Serialize};
+
+static GLOBAL_FORMAT: OnceLock<CurrencyFormat> = OnceLock::new();
+
+/// The locales that have a built-in format. Other locales can be described by
+/// setting each field of [CurrencyFormat] in the application configuration.
+const LOCALES: &[(&str, &str, bool, bool, &str, char)] = &[
+    // (locale, symbol, symbol first, space between symbol and number, grouping, decimal)
+    ("en-US", "$", true, false, ",", '.'),
+    ("en-CA", "$", true, false, ",", '.'),
+    ("en-AU", "$", true, false, ",", '.'),
+    ("en-GB", "£", true, false, ",", '.'),
+    ("en-IE", "€", true, false, ",", '.'),
+    ("de-DE", "€", false, true, ".", ','),
+    ("de-CH", "CHF", true, true, "'", '.'),
+    ("fr-FR", "€", false, true, "\u{202f}", ','),
+    ("fr-CA", "$", false, true, "\u{a0}", ','),
+    ("es-ES", "€", false, true, ".", ','),
+    ("it-IT", "€", false, true, ".", ','),
+    ("nl-NL", "€", true, true, ".", ','),
+    ("pt-BR", "R$", true, true, ".", ','),
+    ("sv-SE", "kr", false, true, "\u{a0}", ','),
+];
+
+/// How to write an amount of money, e.g. "$1,234.56" or "1.234,56 €"
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CurrencyFormat {
+    /// The currency symbol (e.g. "$" or "€")
+    pub symbol: String,
+    /// Whether the symbol comes before the number
+    pub symbol_first: bool,
+    /// Whether the symbol is separated from the number by a space
+    pub space: bool,
+    /// The characters that separate each group of three digits (e.g. ",").
+    /// Digits aren't grouped if this is empty.
+    pub grouping: String,
+    /// The character that separates the dollars from the cents
+    pub decimal_separator: char,
+}
+
+impl Default for CurrencyFormat {
+    fn default() -> Self {
+        Self::for_locale("en-US").expect("en-US is a built-in locale")
+    }
+}
+
+impl CurrencyFormat {
+    /// The format used in the given locale (e.g. "en-US" or "de_DE.UTF-8"),
+    /// if it is one of the built-in locales
+    pub fn for_locale(locale: &str) -> Option<Self> {
+        // accept POSIX locale names like the value of $LANG
+        let locale = locale.split(['.', '@']).next().unwrap_or_default();
+        let locale = locale.replace('_', "-");
+        LOCALES
+            .iter()
+            .find(|(name, ..)| name.eq_ignore_ascii_case(&locale))
+            .map(
+                |(_, symbol, symbol_first, space, grouping, decimal_separator)| Self {
+                    symbol: symbol.to_string(),
+                    symbol_first: *symbol_first,
+                    space: *space,
+                    grouping: grouping.to_string(),
+                    decimal_separator: *decimal_separator,
+                },
+            )
+    }
+
+    /// The names of the built-in locales
+    pub fn locales() -> impl Iterator<Item = &'static str> {
+        LOCALES.iter().map(|(name, ..)| *name)
+    }
+
+    /// Use this format for every dollar value that is displayed from now on.
+    /// The format can only be set once, and later calls are ignored.
+    pub fn set_global(self) {
+        let _ = GLOBAL_FORMAT.set(self);
+    }
+
+    /// The format that dollar values are displayed in
+    pub fn global() -> &'static CurrencyFormat {
+        GLOBAL_FORMAT.get_or_init(Self::default)
+    }
+
+    /// Write `value` rounded to cents, with a leading '-' if it is negative
+    /// (or `sign` if it isn't, e.g. "+" to always show the sign)
+    pub fn format(&self, value: f32, sign: &str) -> String {
+        let cents = (value.abs() * 100.0).round() / 100.0;
+        let sign = match value < 0.0 && cents != 0.0 {
+            true => "-",
+            false => sign,
+        };
+        let digits = format!("{cents:.2}");
+        let (whole, fraction) = digits.split_once('.').unwrap_or((&digits, "00"));
+        let mut number = String::new();
+        for (i, digit) in whole.chars().enumerate() {
+            if i > 0 && (whole.len() - i) % 3 == 0 {
+                number.push_str(&self.grouping);
+            }
+            number.push(digit);
+        }
+        number.push(self.decimal_separator);
+        number.push_str(fraction);
+        let space = if self.space { " " } else { "" };
+        match self.symbol_first {
+            true => format!("{sign}{}{space}{number}", self.symbol),
+            false => format!("{sign}{number}{space}{}", self.symbol),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format() {
+        let us = CurrencyFormat::default();
+        assert_eq!(us.format(12345.678, ""), "$12,345.68");
+        assert_eq!(us.format(-5.0, ""), "-$5.00");
+        assert_eq!(us.format(-0.001, ""), "$0.00");
+        assert_eq!(us.format(89.0, "+"), "+$89.00");
+        assert_eq!(us.format(999.999, ""), "$1,000.00");
+
+        let de = CurrencyFormat::for_locale("de_DE.UTF-8").unwrap();
+        assert_eq!(de.format(1234567.5, ""), "1.234.567,50 €");
+        assert_eq!(de.format(-12.0, "+"), "-12,00 €");
+
+        let custom = CurrencyFormat {
+            grouping: String::new(),
+            ..Default::default()
+        };
+        assert_eq!(custom.format(12345.0, ""), "$12345.00");
+        assert!(CurrencyFormat::for_locale("xx-XX").is_none());
+    }
+}
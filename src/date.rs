@@ -0,0 +1,182 @@
+//! A minimal calendar date type, serialized as `YYYY-MM-DD`
+
+use std::{fmt::Display, str::FromStr, time::SystemTime};
+
+use serde::{Deserialize, Serialize};
+
+/// A date in the proleptic Gregorian calendar
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Date {
+    year: i32,
+    month: u32,
+    day: u32,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("Invalid date '{0}': expected YYYY-MM-DD")]
+pub struct ParseDateError(String);
+
+fn is_leap_year(year: i32) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i32, month: u32) -> u32 {
+    match month {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        _ => 0,
+    }
+}
+
+impl Date {
+    /// Create a new date, returning `None` if the date doesn't exist
+    pub fn new(year: i32, month: u32, day: u32) -> Option<Self> {
+        if (1..=12).contains(&month) && day >= 1 && day <= days_in_month(year, month) {
+            Some(Self { year, month, day })
+        } else {
+            None
+        }
+    }
+
+    /// The current date (in UTC)
+    pub fn today() -> Self {
+        let secs = SystemTime::now()
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or_default();
+        Self::from_days((secs / 86_400) as i64)
+    }
+
+    pub fn year(&self) -> i32 {
+        self.year
+    }
+
+    pub fn month(&self) -> u32 {
+        self.month
+    }
+
+    pub fn day(&self) -> u32 {
+        self.day
+    }
+
+    /// The number of days since 1970-01-01
+    pub fn days(&self) -> i64 {
+        // see http://howardhinnant.github.io/date_algorithms.html#days_from_civil
+        let year = if self.month <= 2 {
+            self.year - 1
+        } else {
+            self.year
+        } as i64;
+        let era = year.div_euclid(400);
+        let yoe = year - era * 400;
+        let month = self.month as i64;
+        let doy =
+            (153 * (if month > 2 { month - 3 } else { month + 9 }) + 2) / 5 + self.day as i64 - 1;
+        let doe = yoe * 365 + yoe / 4 - yoe / 100 + doy;
+        era * 146_097 + doe - 719_468
+    }
+
+    /// The date that is the given number of days since 1970-01-01
+    pub fn from_days(days: i64) -> Self {
+        // see http://howardhinnant.github.io/date_algorithms.html#civil_from_days
+        let z = days + 719_468;
+        let era = z.div_euclid(146_097);
+        let doe = z - era * 146_097;
+        let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+        let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+        let mp = (5 * doy + 2) / 153;
+        let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+        let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+        let year = (yoe + era * 400 + if month <= 2 { 1 } else { 0 }) as i32;
+        Self { year, month, day }
+    }
+
+    /// The number of days from this date until `other` (negative if `other`
+    /// is in the past)
+    pub fn days_until(&self, other: Date) -> i64 {
+        other.days() - self.days()
+    }
+
+    /// The date that is the given number of days after this one
+    pub fn add_days(&self, days: i64) -> Self {
+        Self::from_days(self.days() + days)
+    }
+
+    /// The date that is the given number of months after this one, or the
+    /// last day of that month if it is shorter (e.g. a month after January
+    /// 31st is the end of February)
+    pub fn add_months(&self, months: u32) -> Self {
+        let months = self.month - 1 + months;
+        let year = self.year + (months / 12) as i32;
+        let month = months % 12 + 1;
+        Self {
+            year,
+            month,
+            day: self.day.min(days_in_month(year, month)),
+        }
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:04}-{:02}-{:02}", self.year, self.month, self.day)
+    }
+}
+
+impl FromStr for Date {
+    type Err = ParseDateError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let err = || ParseDateError(s.to_string());
+        let mut parts = s.trim().splitn(3, '-');
+        let mut next = || parts.next().ok_or_else(err);
+        let year = next()?.parse().map_err(|_| err())?;
+        let month = next()?.parse().map_err(|_| err())?;
+        let day = next()?.parse().map_err(|_| err())?;
+        Self::new(year, month, day).ok_or_else(err)
+    }
+}
+
+impl TryFrom<String> for Date {
+    type Error = ParseDateError;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        value.parse()
+    }
+}
+
+impl From<Date> for String {
+    fn from(value: Date) -> Self {
+        value.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_date_round_trip() {
+        let date: Date = "2024-02-29".parse().unwrap();
+        assert_eq!(date, Date::new(2024, 2, 29).unwrap());
+        assert_eq!(date.to_string(), "2024-02-29");
+        assert_eq!(Date::from_days(date.days()), date);
+        assert_eq!(Date::new(1970, 1, 1).unwrap().days(), 0);
+        assert_eq!(date.add_days(1), Date::new(2024, 3, 1).unwrap());
+        assert!("2023-02-29".parse::<Date>().is_err());
+        assert!("yesterday".parse::<Date>().is_err());
+    }
+
+    #[test]
+    fn test_add_months() {
+        let date = Date::new(2024, 1, 31).unwrap();
+        assert_eq!(date.add_months(1), Date::new(2024, 2, 29).unwrap());
+        assert_eq!(date.add_months(3), Date::new(2024, 4, 30).unwrap());
+        assert_eq!(date.add_months(12), Date::new(2025, 1, 31).unwrap());
+        assert_eq!(date.add_months(25), Date::new(2026, 2, 28).unwrap());
+        assert_eq!(date.add_months(0), date);
+    }
+}
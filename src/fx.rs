@@ -0,0 +1,129 @@
+//! Exchange rates, which are used to convert holdings that are valued in
+//! another currency into the base currency of their account before any
+//! targets are calculated.
+//!
+//! Rates are read from a CSV file with a header row and `Currency` and `Rate`
+//! columns, where the rate is the value of one unit of the currency in the
+//! base currency (e.g. `EUR,1.08` when the base currency is US dollars).
+//! Column names are matched without regard to case.
+
+use std::{collections::HashMap, io::Read};
+
+use anyhow::{Context, bail};
+use tracing::debug;
+
+use crate::{account::Balance, csv_file::CsvFile};
+
+/// The value of one unit of each currency in the base currency, by currency
+/// code
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ExchangeRates {
+    pub base: String,
+    pub rates: HashMap<String, f32>,
+}
+
+/// Load the exchange rates into `base` from a CSV file
+pub fn load_exchange_rates(reader: &mut dyn Read, base: &str) -> anyhow::Result<ExchangeRates> {
+    let file = CsvFile::new(reader)?;
+    let (Some(currency_column), Some(rate_column)) = (file.column("Currency"), file.column("Rate"))
+    else {
+        bail!("The exchange rates file must have 'Currency' and 'Rate' columns");
+    };
+
+    let mut rates = HashMap::new();
+    for row in file.rows() {
+        let row = row?;
+        let line = row.line;
+        let rate: f32 = row
+            .field(rate_column)
+            .parse()
+            .ok()
+            .filter(|rate: &f32| *rate > 0.0)
+            .with_context(|| format!("Invalid rate on line {line} of the exchange rates file"))?;
+        let currency = row.field(currency_column).to_ascii_uppercase();
+        debug!(currency, rate, "parsed exchange rate");
+        rates.insert(currency, rate);
+    }
+    Ok(ExchangeRates {
+        base: base.to_ascii_uppercase(),
+        rates,
+    })
+}
+
+impl ExchangeRates {
+    /// Convert the holdings of `balance` that are valued in another currency
+    /// (and haven't been converted by their provider already) into the base
+    /// currency (along with their cost basis, so that gains are in the base
+    /// currency as well), recording the rate that was used. Fails if there is
+    /// no rate for one of the currencies.
+    pub fn convert(&self, balance: &mut Balance) -> anyhow::Result<()> {
+        for holding in balance.holdings.iter_mut() {
+            let Some(currency) = holding.currency.as_ref().map(|c| c.to_ascii_uppercase()) else {
+                continue;
+            };
+            if currency == self.base || holding.exchange_rate.is_some() {
+                continue;
+            }
+            let Some(rate) = self.rates.get(&currency).copied() else {
+                bail!(
+                    "There is no exchange rate for {currency}, which '{}' in account {} is valued in",
+                    holding.symbol,
+                    balance.account_id
+                );
+            };
+            debug!(?holding, rate, "converting holding into the base currency");
+            holding.current_value = holding.current_value * rate;
+            holding.cost_basis = holding.cost_basis.map(|basis| basis * rate);
+            holding.exchange_rate = Some(rate);
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        Dollar,
+        account::{Holding, tests::holding},
+    };
+
+    #[test]
+    fn test_exchange_rates() {
+        let csv = "Currency,Rate\neur,1.1\nGBP,1.25\n";
+        let rates = load_exchange_rates(&mut csv.as_bytes(), "usd").unwrap();
+        assert_eq!(rates.base, "USD");
+        assert_eq!(rates.rates.get("EUR"), Some(&1.1));
+
+        let holding = |symbol: &str, value, currency: Option<&str>| Holding {
+            currency: currency.map(str::to_string),
+            ..holding(symbol, value, false)
+        };
+        let mut balance = Balance {
+            account_id: "123".to_string(),
+            holdings: vec![
+                Holding {
+                    cost_basis: Some(Dollar(800.0)),
+                    ..holding("VWCE", 1000.0, Some("EUR"))
+                },
+                holding("VTI", 1000.0, Some("USD")),
+                holding("BND", 1000.0, None),
+            ],
+            ..Default::default()
+        };
+        rates.convert(&mut balance).unwrap();
+        assert_eq!(balance.holdings[0].current_value, Dollar(1100.0));
+        assert_eq!(balance.holdings[0].exchange_rate, Some(1.1));
+        assert_eq!(balance.holdings[0].cost_basis, Some(Dollar(880.0)));
+        assert_eq!(balance.holdings[1].current_value, Dollar(1000.0));
+        assert_eq!(balance.holdings[2].current_value, Dollar(1000.0));
+        // converting again doesn't change anything
+        rates.convert(&mut balance).unwrap();
+        assert_eq!(balance.total_value(), Dollar(3100.0));
+
+        balance.holdings.push(holding("SONY", 1000.0, Some("JPY")));
+        assert!(rates.convert(&mut balance).is_err());
+        assert!(load_exchange_rates(&mut "Currency,Rate\nEUR,-1\n".as_bytes(), "USD").is_err());
+        assert!(load_exchange_rates(&mut "Code,Value\n".as_bytes(), "USD").is_err());
+    }
+}
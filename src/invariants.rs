@@ -0,0 +1,195 @@
+//! Sanity checks that every [RebalancePlan] is expected to satisfy.
+//!
+//! These are used internally to verify the plans produced by
+//! [AllocationConfig::adjust_allocations], but are also useful for alternative
+//! front-ends that want to make sure a plan is sane before rendering it.
+
+use crate::{
+    Action, Dollar, Percent,
+    account::{AllocationConfig, Balance, HoldingConstraints, PositionAdjustment, RebalancePlan},
+};
+
+/// A single way in which a plan fails to satisfy the expected invariants
+#[derive(Debug, Clone, PartialEq, thiserror::Error)]
+pub enum Violation {
+    #[error("Holding '{symbol}' would have a negative value ({value}) after rebalancing")]
+    NegativeValue { symbol: String, value: Dollar },
+    #[error("Core position would hold {actual}, less than the configured minimum of {minimum}")]
+    CoreMinimum { actual: Dollar, minimum: Dollar },
+    #[error(
+        "Holding '{symbol}' would be worth {actual} after rebalancing, but its target is {expected}"
+    )]
+    TargetMissed {
+        symbol: String,
+        expected: Dollar,
+        actual: Dollar,
+    },
+    #[error("Ignored holding '{symbol}' has a recommended action")]
+    IgnoredAction { symbol: String },
+    #[error("Buys and sells do not balance: the plan is off by {0}")]
+    CashNotConserved(Dollar),
+    #[error("The plan contains a holding '{symbol}' that is not in the account")]
+    UnknownHolding { symbol: String },
+    #[error("Holding '{symbol}' is sold by a plan that only invests a contribution")]
+    SoldInContribution { symbol: String },
+    #[error("Holding '{symbol}' is bought by a plan that only raises cash for a withdrawal")]
+    BoughtInWithdrawal { symbol: String },
+    #[error("Holding '{symbol}' is sold, but it is configured never to be sold")]
+    SoldNoSell { symbol: String },
+}
+
+/// The amount of floating point error that is tolerated when comparing dollar
+/// values for an account with the given total value
+fn tolerance(total: Dollar) -> Dollar {
+    Dollar(0.01).max(total.abs() * Percent(0.001))
+}
+
+/// Verify that `plan` is a valid way to rebalance `balance` according to
+/// `config`. All violations are collected and returned rather than stopping at
+/// the first one.
+pub fn check_invariants(
+    config: &AllocationConfig,
+    balance: &Balance,
+    plan: &RebalancePlan,
+) -> Result<(), Vec<Violation>> {
+    let mut violations = Vec::new();
+    let active = || plan.adjustments.iter().filter(|adj| !adj.ignored);
+    let total: Dollar = active().map(|adj| adj.holding.current_value).sum();
+    let tolerance = tolerance(total);
+
+    for adj in plan.adjustments.iter() {
+        let known = balance
+            .holdings
+            .iter()
+            .any(|h| h.symbol == adj.holding.symbol)
+            || config.has_target(&adj.holding.symbol)
+            || config.asset_classes.contains_key(&adj.holding.symbol)
+            || config
+                .cash_sweep
+                .as_ref()
+                .is_some_and(|sweep| sweep.symbol == adj.holding.symbol);
+        if !known {
+            violations.push(Violation::UnknownHolding {
+                symbol: adj.holding.symbol.clone(),
+            });
+        }
+        if adj.ignored && !matches!(adj.action, Action::DoNothing) {
+            violations.push(Violation::IgnoredAction {
+                symbol: adj.holding.symbol.clone(),
+            });
+        }
+        if plan.contribution.is_some()
+            && matches!(adj.action, Action::Sell(_))
+            && !adj.holding.is_cash
+        {
+            violations.push(Violation::SoldInContribution {
+                symbol: adj.holding.symbol.clone(),
+            });
+        }
+        if plan.withdrawal.is_some() && matches!(adj.action, Action::Buy(_)) && !adj.holding.is_cash
+        {
+            violations.push(Violation::BoughtInWithdrawal {
+                symbol: adj.holding.symbol.clone(),
+            });
+        }
+        if matches!(adj.action, Action::Sell(_))
+            && config
+                .constraints
+                .get(&adj.holding.symbol)
+                .is_some_and(|constraints| constraints.no_sell)
+        {
+            violations.push(Violation::SoldNoSell {
+                symbol: adj.holding.symbol.clone(),
+            });
+        }
+        // ignored holdings may already be negative (e.g. short positions)
+        if !adj.ignored && adj.result() < Dollar(0.0) - tolerance {
+            violations.push(Violation::NegativeValue {
+                symbol: adj.holding.symbol.clone(),
+                value: adj.result(),
+            });
+        }
+    }
+
+    let cash = active().find(|adj| adj.holding.is_cash);
+    // a contribution adds new money to the account
+    let net: Dollar = active()
+        .map(|adj| adj.result() - adj.holding.current_value)
+        .sum::<Dollar>()
+        - plan.contribution.unwrap_or_default();
+    // without a cash position, the trades that were left out of the plan
+    // don't balance, and are accounted for in the residual instead
+    let unaccounted = match cash {
+        Some(_) => net,
+        None => net + plan.residual,
+    };
+    if unaccounted.abs() > tolerance {
+        violations.push(Violation::CashNotConserved(net));
+    }
+
+    let cash_result = cash.map(|adj| adj.result()).unwrap_or_default();
+    let minimum = config.cash_minimum(
+        cash.map(|adj| &adj.holding),
+        total + plan.contribution.unwrap_or_default() - plan.withdrawal.unwrap_or_default(),
+    );
+    if cash.is_some() && cash_result < minimum.min(total) - tolerance {
+        violations.push(Violation::CoreMinimum {
+            actual: cash_result,
+            minimum,
+        });
+    }
+
+    // whatever is not held in cash should be distributed among the other
+    // targets in proportion to their target percentages, except that the cash
+    // position absorbs the trades that were left out of the plan or rounded
+    // to an increment or to whole shares, and that constrained holdings get
+    // what they are constrained to before the rest is distributed
+    let cash_target = cash.map(|adj| adj.target).unwrap_or_default();
+    let adjusted = |adj: &PositionAdjustment| {
+        adj.held.is_some()
+            || config.trade_increment.is_some()
+            || config.fewest_trades
+            || config.whole_shares.contains(&adj.holding.symbol)
+            || config.fixed_targets.contains_key(&adj.holding.symbol)
+            || config.target_ranges.contains_key(&adj.holding.symbol)
+            || config
+                .constraints
+                .get(&adj.holding.symbol)
+                .is_some_and(|constraints| *constraints != HoldingConstraints::default())
+    };
+    let (mut held, mut constrained, mut constrained_pct) = (Dollar(0.0), Dollar(0.0), Percent(0.0));
+    for adj in active().filter(|adj| !adj.holding.is_cash && adjusted(adj)) {
+        held += adj.desired_value - adj.result();
+        constrained += adj.desired_value;
+        constrained_pct = constrained_pct + adj.target;
+    }
+    let noncash_pct = Percent(100.0) - cash_target - constrained_pct;
+    // a contribution, a withdrawal, or trading less to limit realized gains
+    // only goes as far as it can towards the targets
+    if noncash_pct > Percent(0.0)
+        && plan.contribution.is_none()
+        && plan.withdrawal.is_none()
+        && !plan.buys_reduced
+    {
+        let remainder = match cash {
+            Some(_) => total - (cash_result - held),
+            None => total,
+        } - constrained;
+        for adj in active().filter(|adj| !adj.holding.is_cash && !adjusted(adj)) {
+            let expected = remainder * (adj.target / noncash_pct);
+            if (adj.result() - expected).abs() > tolerance {
+                violations.push(Violation::TargetMissed {
+                    symbol: adj.holding.symbol.clone(),
+                    expected,
+                    actual: adj.result(),
+                });
+            }
+        }
+    }
+
+    if violations.is_empty() {
+        Ok(())
+    } else {
+        Err(violations)
+    }
+}
@@ -8,7 +8,15 @@ use std::{
 use serde::{Deserialize, Serialize};
 
 pub mod account;
+pub mod conformance;
+mod csv_file;
+pub mod currency;
+pub mod date;
+pub mod fx;
+pub mod invariants;
 pub mod provider;
+mod toml;
+pub mod trades;
 
 /// A type that represents dollar values
 #[derive(
@@ -31,7 +39,7 @@ pub struct Dollar(pub f32);
 
 impl Display for Dollar {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "${:.2}", self.0)
+        f.write_str(&currency::CurrencyFormat::global().format(self.0, ""))
     }
 }
 
@@ -80,6 +88,32 @@ impl Dollar {
     fn max(&self, other: Self) -> Self {
         Self(self.0.max(other.0))
     }
+
+    fn min(&self, other: Self) -> Self {
+        Self(self.0.min(other.0))
+    }
+
+    /// Round to the nearest cent
+    pub fn round_cents(&self) -> Self {
+        Self((self.0 * 100.0).round() / 100.0)
+    }
+}
+
+/// A signed change in a dollar value, which is always shown with its sign
+/// (e.g. "+$1,234.56" or "-$89.00")
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd)]
+pub struct RelativeDollar(pub Dollar);
+
+impl Display for RelativeDollar {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&currency::CurrencyFormat::global().format(self.0.0, "+"))
+    }
+}
+
+impl From<&Action> for RelativeDollar {
+    fn from(action: &Action) -> Self {
+        Self(action.change())
+    }
 }
 
 /// A type that represents percentage values
@@ -104,7 +138,12 @@ impl Percent {
         N: Into<f32>,
         D: Into<f32>,
     {
-        Self(numerator.into() / denominator.into() * 100.0_f32)
+        let denominator = denominator.into();
+        if denominator == 0.0 {
+            // avoid NaN percentages for empty accounts
+            return Self(0.0);
+        }
+        Self(numerator.into() / denominator * 100.0_f32)
     }
 }
 
@@ -142,6 +181,22 @@ impl Div for Percent {
     }
 }
 
+/// A signed difference between two percentages, in percentage points, which
+/// is always shown with its sign (e.g. "+5.4%" or "-1.6%")
+#[derive(Debug, Default, Clone, Copy, PartialEq, PartialOrd, Serialize)]
+pub struct RelativePercent(pub Percent);
+
+impl Display for RelativePercent {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        // round first so that tiny negative values aren't shown as "-0.0%"
+        let tenths = (self.0.0 * 10.0).round() / 10.0;
+        match tenths {
+            val if val < 0.0 => write!(f, "{val:.1}%"),
+            val => write!(f, "+{:.1}%", val.abs()),
+        }
+    }
+}
+
 /// A description of what must be done to achieve a target allocation for an investment
 #[derive(Debug, Default)]
 pub enum Action {
@@ -150,3 +205,44 @@ pub enum Action {
     Sell(Dollar),
     Buy(Dollar),
 }
+
+impl Action {
+    /// The action needed to change the value of a holding by the given
+    /// (signed) amount
+    pub fn from_change(change: Dollar) -> Self {
+        match change {
+            val if val > Dollar(0.0) => Action::Buy(val.abs()),
+            val if val < Dollar(0.0) => Action::Sell(val.abs()),
+            _ => Action::DoNothing,
+        }
+    }
+
+    /// The signed change in value caused by this action
+    pub fn change(&self) -> Dollar {
+        Dollar(0.0) + self
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_relative_dollar_display() {
+        let display = |val| RelativeDollar(Dollar(val)).to_string();
+        assert_eq!(display(1234.56), "+$1,234.56");
+        assert_eq!(display(-89.0), "-$89.00");
+        assert_eq!(display(-1234567.0), "-$1,234,567.00");
+        assert_eq!(display(999.999), "+$1,000.00");
+        assert_eq!(display(0.0), "+$0.00");
+        assert_eq!(display(-0.001), "+$0.00");
+    }
+
+    #[test]
+    fn test_relative_percent_display() {
+        let display = |val| RelativePercent(Percent(val)).to_string();
+        assert_eq!(display(5.44), "+5.4%");
+        assert_eq!(display(-1.66), "-1.7%");
+        assert_eq!(display(-0.01), "+0.0%");
+    }
+}
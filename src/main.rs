@@ -1,15 +1,40 @@
+use std::process::ExitCode;
+
 use clap::Parser;
 
-use crate::app::App;
+use crate::app::{App, Failure, Outcome};
 
 mod app;
 mod backup;
+mod balance_files;
+mod chart;
 mod cli;
+mod clipboard;
 mod output;
+mod template;
+
+/// The exit status when a plan recommends trades
+const EXIT_REBALANCE: u8 = 3;
+/// The exit status when the configuration or target allocations can't be
+/// loaded
+const EXIT_CONFIG: u8 = 4;
+/// The exit status when balance data can't be read
+const EXIT_DATA: u8 = 5;
 
-fn main() -> anyhow::Result<()> {
+fn main() -> ExitCode {
     tracing_subscriber::fmt::init();
+    // invalid arguments exit with status 2
     let opts = cli::Cli::parse();
-    let app = App::new(opts)?;
-    app.run()
+    match App::new(opts).and_then(|app| app.run()) {
+        Ok(Outcome::Done) => ExitCode::SUCCESS,
+        Ok(Outcome::RebalanceRecommended) => ExitCode::from(EXIT_REBALANCE),
+        Err(err) => {
+            eprintln!("Error: {err:?}");
+            match err.downcast_ref::<Failure>() {
+                Some(Failure::Config) => ExitCode::from(EXIT_CONFIG),
+                Some(Failure::Data) => ExitCode::from(EXIT_DATA),
+                None => ExitCode::FAILURE,
+            }
+        }
+    }
 }
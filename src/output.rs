@@ -1,13 +1,23 @@
 use driftfix::{
-    Action, Dollar, Percent,
-    account::{Holding, PositionAdjustment},
+    Action, Dollar, Percent, RelativeDollar, RelativePercent,
+    account::{
+        AllocationConfig, Balance, Exchange, Exposure, HoldReason, Holding, RebalancePlan,
+        RebalanceStatus, Step,
+    },
+    date::Date,
 };
+
+use crate::cli::TableColumn;
+use serde::Serialize;
 use tabled::{
     Table, Tabled,
     derive::display,
     settings::{
-        Alignment, Color, Style,
-        object::{Columns, Rows},
+        Alignment, Color, Panel, Remove, Style,
+        location::ByColumnName,
+        object::{Cell, Columns, Rows},
+        style::HorizontalLine,
+        themes::Theme,
     },
 };
 
@@ -16,22 +26,78 @@ use tabled::{
 struct AllocationTableRow {
     #[tabled(rename = "Symbol")]
     symbol: String,
+    #[tabled(rename = "Label")]
+    label: String,
     #[tabled(rename = "Value")]
     current_value: Dollar,
     #[tabled(rename = "Percent")]
     current_percentage: Percent,
     #[tabled(rename = "Target")]
     target: Option<Percent>,
-    #[tabled(rename = "Sell")]
-    sell: Option<Dollar>,
-    #[tabled(rename = "Buy")]
-    buy: Option<Dollar>,
+    #[tabled(rename = "Drift")]
+    drift: Option<RelativePercent>,
+    /// The amount to buy (positive) or sell (negative)
+    #[tabled(rename = "Change")]
+    change: Option<RelativeDollar>,
     #[tabled(rename = "Result")]
     result: Option<Dollar>,
     #[tabled(skip)]
     ignore: bool,
 }
 
+/// The index of the drift column in [AllocationTableRow]
+const DRIFT_COLUMN: usize = 5;
+
+/// The columns of [AllocationTableRow], in order
+const TABLE_COLUMNS: [TableColumn; 8] = [
+    TableColumn::Symbol,
+    TableColumn::Label,
+    TableColumn::Value,
+    TableColumn::Percent,
+    TableColumn::Target,
+    TableColumn::Drift,
+    TableColumn::Change,
+    TableColumn::Result,
+];
+
+/// Hide all but the last 4 characters of an account number, e.g. "****6789"
+pub fn redact_account_id(account_id: &str) -> String {
+    let chars: Vec<char> = account_id.chars().collect();
+    match chars.len() {
+        0..=4 => "*".repeat(chars.len()),
+        len => "****".to_string() + &chars[len - 4..].iter().collect::<String>(),
+    }
+}
+
+/// Redact every occurrence of the given account numbers in `text` (see
+/// [redact_account_id]). Only whole words are replaced, so that an account
+/// number such as "123" isn't found within "$1,234".
+pub fn redact_account_ids(text: &str, account_ids: &[String]) -> String {
+    let mut ids: Vec<&String> = account_ids.iter().filter(|id| !id.is_empty()).collect();
+    // longer numbers first, in case one contains another
+    ids.sort_by_key(|id| std::cmp::Reverse(id.len()));
+    let mut out = String::new();
+    let mut rest = text;
+    'outer: while !rest.is_empty() {
+        let at_boundary = !out.chars().next_back().is_some_and(char::is_alphanumeric);
+        if at_boundary {
+            for id in ids.iter() {
+                if let Some(after) = rest.strip_prefix(id.as_str())
+                    && !after.chars().next().is_some_and(char::is_alphanumeric)
+                {
+                    out += &redact_account_id(id);
+                    rest = after;
+                    continue 'outer;
+                }
+            }
+        }
+        let mut chars = rest.chars();
+        out.extend(chars.next());
+        rest = chars.as_str();
+    }
+    out
+}
+
 fn holding_display_name(holding: &Holding) -> String {
     (match holding.is_cash {
         true => String::from("*"),
@@ -39,38 +105,938 @@ fn holding_display_name(holding: &Holding) -> String {
     } + &holding.symbol)
 }
 
-pub fn format_adjustments(adjustments: Vec<PositionAdjustment>) -> Table {
-    let total: Dollar = adjustments
+/// Add up the rows of holdings, e.g. for the subtotal of an asset class
+fn total_row(
+    symbol: String,
+    rows: &[&AllocationTableRow],
+    account_total: Dollar,
+) -> AllocationTableRow {
+    let value: Dollar = rows.iter().map(|row| row.current_value).sum();
+    let drifts: Vec<_> = rows.iter().filter_map(|row| row.drift).collect();
+    let change: Dollar = rows
+        .iter()
+        .filter_map(|row| row.change)
+        .fold(Dollar(0.0), |total, change| total + change.0);
+    AllocationTableRow {
+        symbol,
+        label: String::new(),
+        current_value: value,
+        current_percentage: Percent::new(value, account_total),
+        target: Some(rows.iter().filter_map(|row| row.target).sum()),
+        drift: (!drifts.is_empty())
+            .then(|| RelativePercent(drifts.iter().map(|drift| drift.0).sum())),
+        change: (change.round_cents() != Dollar(0.0)).then_some(RelativeDollar(change)),
+        result: Some(rows.iter().filter_map(|row| row.result).sum()),
+        ignore: false,
+    }
+}
+
+/// Format the plan for an account as a table. A `plain` table has no colors
+/// and only uses ASCII characters, so ignored holdings are marked in the
+/// symbol column instead of being grayed out. Only the given `columns` are
+/// shown, or every column if none are given.
+pub fn format_adjustments(
+    plan: &RebalancePlan,
+    config: &AllocationConfig,
+    plain: bool,
+    columns: &[TableColumn],
+) -> Table {
+    let total: Dollar = plan
+        .adjustments
         .iter()
         .map(|adj| adj.holding.current_value)
         .sum();
-    let rows: Vec<AllocationTableRow> = adjustments
+    let holdings: Vec<(Option<&str>, AllocationTableRow)> = plan
+        .adjustments
         .iter()
-        .map(|adj| AllocationTableRow {
-            symbol: holding_display_name(&adj.holding),
-            current_value: adj.holding.current_value,
-            current_percentage: Percent::new(adj.holding.current_value, total),
-            target: Some(adj.target),
-            buy: match adj.action {
-                Action::Buy(val) => Some(val),
-                _ => None,
-            },
-            sell: match adj.action {
-                Action::Sell(val) => Some(val),
-                _ => None,
-            },
-            result: Some(adj.holding.current_value + &adj.action),
-            ignore: adj.ignored,
+        .zip(plan.drift())
+        .map(|(adj, drift)| {
+            let symbol = holding_display_name(&adj.holding);
+            let row = AllocationTableRow {
+                symbol: match (plain && adj.ignored, adj.holding.pending) {
+                    (true, true) => format!("{symbol} (pending)"),
+                    (true, false) => format!("{symbol} (ignored)"),
+                    (false, _) => symbol,
+                },
+                label: config
+                    .label(&adj.holding.symbol)
+                    .unwrap_or_default()
+                    .to_string(),
+                current_value: adj.holding.current_value,
+                current_percentage: Percent::new(adj.holding.current_value, total),
+                target: Some(adj.target),
+                drift,
+                change: match adj.action {
+                    Action::DoNothing => None,
+                    ref action => Some(action.into()),
+                },
+                result: Some(adj.result()),
+                ignore: adj.ignored,
+            };
+            (config.asset_class(&adj.holding), row)
         })
         .collect();
-    let mut table = Table::new(rows.iter());
-    table.with(Style::rounded());
+    let totals = AllocationTableRow {
+        target: None,
+        drift: None,
+        change: None,
+        ..total_row(
+            String::from("Total"),
+            &holdings.iter().map(|(_, row)| row).collect::<Vec<_>>(),
+            total,
+        )
+    };
+    let removed_columns = TABLE_COLUMNS.map(|column| {
+        let empty = match column {
+            TableColumn::Label => holdings.iter().all(|(_, row)| row.label.is_empty()),
+            TableColumn::Target => holdings.iter().all(|(_, row)| row.target.is_none()),
+            TableColumn::Drift => holdings.iter().all(|(_, row)| row.drift.is_none()),
+            TableColumn::Change => holdings.iter().all(|(_, row)| row.change.is_none()),
+            _ => false,
+        };
+        empty || !(columns.is_empty() || columns.contains(&column))
+    });
+
+    // group the holdings by asset class, in the order that each class first
+    // appears, with a subtotal after each group
+    let mut classes: Vec<(Option<&str>, Vec<AllocationTableRow>)> = Vec::new();
+    for (class, row) in holdings {
+        match classes.iter_mut().find(|(c, _)| *c == class) {
+            Some((_, rows)) => rows.push(row),
+            None => classes.push((class, vec![row])),
+        }
+    }
+    let mut rows = Vec::new();
+    // the rows that are followed by a line, counting the header row
+    let mut lines = vec![1];
+    for (class, group) in classes {
+        let subtotal = class.map(|class| {
+            total_row(
+                format!("{class} total"),
+                &group.iter().collect::<Vec<_>>(),
+                total,
+            )
+        });
+        rows.extend(group);
+        if let Some(subtotal) = subtotal {
+            rows.push(subtotal);
+            lines.push(rows.len() + 1);
+        }
+    }
+    lines.push(rows.len() + 1);
+
+    let trades = || plan.adjustments.iter().filter(|adj| !adj.holding.is_cash);
+    let buys: Dollar = trades()
+        .filter_map(|adj| match adj.action {
+            Action::Buy(val) => Some(val),
+            _ => None,
+        })
+        .fold(Dollar(0.0), |total, val| total + val);
+    let sells: Dollar = trades()
+        .filter_map(|adj| match adj.action {
+            Action::Sell(val) => Some(val),
+            _ => None,
+        })
+        .fold(Dollar(0.0), |total, val| total + val);
+
+    let mut table = Table::new(rows.iter().chain([&totals]));
+    // separate the header, asset classes and totals from each other, and the
+    // summary (which spans every column) from the totals
+    let (mut theme, line, last_line) = match plain {
+        true => (
+            Theme::from_style(Style::ascii()),
+            HorizontalLine::full('-', '+', '+', '+'),
+            HorizontalLine::full('-', '+', '+', '+'),
+        ),
+        false => (
+            Theme::from_style(Style::rounded()),
+            HorizontalLine::full('─', '┼', '├', '┤'),
+            HorizontalLine::full('─', '┴', '├', '┤'),
+        ),
+    };
+    theme.remove_horizontal_lines();
+    for row in lines {
+        theme.insert_horizontal_line(row, line);
+    }
+    theme.insert_horizontal_line(rows.len() + 2, last_line);
+    theme.set_borders_intersection_bottom(if plain { '-' } else { '─' });
+    table.with(theme);
     table.modify(Columns::new(..), Alignment::right());
-    for (i, row) in rows.iter().enumerate() {
+    for (i, row) in rows.iter().enumerate().filter(|_| !plain) {
         if row.ignore {
             // header row is technically the first row
             table.modify(Rows::one(i + 1), Color::rgb_fg(150, 150, 150));
         }
+        // the drift is rounded when displayed, so only color drifts that
+        // aren't shown as zero
+        match row.drift {
+            Some(RelativePercent(drift)) if drift.0 >= 0.05 => {
+                table.modify(Cell::new(i + 1, DRIFT_COLUMN), Color::FG_RED);
+            }
+            Some(RelativePercent(drift)) if drift.0 <= -0.05 => {
+                table.modify(Cell::new(i + 1, DRIFT_COLUMN), Color::FG_GREEN);
+            }
+            _ => {}
+        }
     }
+    let headers = AllocationTableRow::headers();
+    remove_empty_columns(
+        &mut table,
+        headers.iter().map(AsRef::as_ref).zip(removed_columns),
+    );
+    // added last so that it spans the remaining columns
+    table.with(Panel::footer(format!(
+        "Buy {buys}, sell {sells}, net cash {}",
+        RelativeDollar(sells - buys)
+    )));
     table
 }
+
+/// Remove the columns (given as their header and whether the column has no
+/// values) that don't have any values, so that e.g. a plan with no trades
+/// doesn't show an empty column for them
+fn remove_empty_columns<'a>(table: &mut Table, columns: impl IntoIterator<Item = (&'a str, bool)>) {
+    for (header, _) in columns.into_iter().filter(|(_, empty)| *empty) {
+        table.with(Remove::column(ByColumnName::new(header)));
+    }
+}
+
+/// Describe how the adjustment for each holding in `plan` was calculated, so
+/// that the plan can be checked by hand
+pub fn format_explanation(plan: &RebalancePlan) -> String {
+    let explanation = &plan.explanation;
+    let distributable = explanation.distributable;
+    let has_cash = plan
+        .adjustments
+        .iter()
+        .any(|adj| adj.holding.is_cash && !adj.ignored);
+    let cash_target = explanation.cash_target.unwrap_or_default();
+    let remainder = distributable - explanation.cash_desired;
+    let mut out = String::from("How this plan was calculated:\n");
+    out += &format!("  Distributable total (every holding that isn't ignored): {distributable}\n");
+    if has_cash {
+        let cash_target_value = distributable * cash_target;
+        out += &format!(
+            "  Cash sweep: target {cash_target} of {distributable} = {cash_target_value}, minimum {}\n",
+            explanation.cash_minimum
+        );
+        out += &format!(
+            "  Desired cash: {}{}\n",
+            explanation.cash_desired,
+            match explanation.cash_minimum_enforced {
+                true => " (the minimum, which isn't less than the target)",
+                false => " (the target, which is more than the minimum)",
+            }
+        );
+    }
+    if explanation.cash_minimum_enforced {
+        out += &format!(
+            "  Left for investments: {distributable} - {} = {remainder}, shared in proportion to the targets of {} for the other holdings\n",
+            explanation.cash_desired,
+            Percent(100.0) - cash_target
+        );
+    }
+    for adj in plan.adjustments.iter() {
+        let symbol = &adj.holding.symbol;
+        let current = adj.holding.current_value;
+        if adj.ignored && adj.holding.pending {
+            out += &format!("  {symbol}: pending, so {current} isn't counted until it settles\n");
+            continue;
+        }
+        if adj.ignored {
+            out += &format!("  {symbol}: ignored, so {current} is left as it is\n");
+            continue;
+        }
+        let desired = match (adj.holding.is_cash, explanation.cash_minimum_enforced) {
+            (true, _) => format!("desired cash {}", adj.desired_value),
+            (false, true) => format!(
+                "target {} / {} of {remainder} = {}",
+                adj.target,
+                Percent(100.0) - cash_target,
+                adj.desired_value
+            ),
+            (false, false) => format!(
+                "target {} of {distributable} = {}",
+                adj.target, adj.desired_value
+            ),
+        };
+        let action = match (&adj.action, adj.held) {
+            (_, Some(HoldReason::WithinBand)) => {
+                String::from("within its tolerance band, so no change")
+            }
+            (_, Some(HoldReason::BelowMinimumTrade)) => {
+                String::from("smaller than the minimum trade, so no change")
+            }
+            (_, Some(HoldReason::ShortTermGain)) => {
+                String::from("would realize short-term gains, so no change")
+            }
+            (_, Some(HoldReason::WithinRange)) => {
+                String::from("within its target range, so no change")
+            }
+            (Action::Buy(val), _) => format!("buy {val}"),
+            (Action::Sell(val), _) => format!("sell {val}"),
+            (Action::DoNothing, _) => String::from("no change"),
+        };
+        out += &format!("  {symbol}: {desired}; current {current}; {action}\n");
+    }
+    if plan.residual.round_cents() != Dollar(0.0) {
+        out += &format!(
+            "  Held and rounded trades leave a residual of {} that isn't traded\n",
+            plan.residual
+        );
+    }
+    out
+}
+
+/// The width of the longest bar in a chart, in characters
+const CHART_WIDTH: usize = 40;
+
+/// An invested holding's share of the account, compared with its target
+pub struct AllocationShare {
+    pub symbol: String,
+    pub current: Percent,
+    pub target: Percent,
+}
+
+/// The share of each invested holding in `plan`, in the same order as its
+/// adjustments. Cash and ignored holdings aren't included.
+pub fn allocation_shares(plan: &RebalancePlan) -> Vec<AllocationShare> {
+    plan.adjustments
+        .iter()
+        .zip(plan.drift())
+        .filter_map(|(adj, drift)| {
+            Some(AllocationShare {
+                symbol: adj.holding.symbol.clone(),
+                current: adj.target + drift?.0,
+                target: adj.target,
+            })
+        })
+        .collect()
+}
+
+/// Draw a horizontal bar chart comparing each invested holding's share of the
+/// account with its target. The bars are scaled so that the largest
+/// percentage fills the chart. A `plain` chart only uses ASCII characters.
+pub fn format_chart(plan: &RebalancePlan, plain: bool) -> String {
+    let shares = allocation_shares(plan);
+    let largest = shares
+        .iter()
+        .map(|share| share.current.0.max(share.target.0))
+        .fold(0.0, f32::max);
+    let symbol_width = shares.iter().map(|share| share.symbol.len()).max();
+    let (Some(symbol_width), true) = (symbol_width, largest > 0.0) else {
+        return String::new();
+    };
+    let bar = |percent: Percent, c: &str| {
+        c.repeat((percent.0 / largest * CHART_WIDTH as f32).round() as usize)
+    };
+    let (current_bar, target_bar) = match plain {
+        true => ("#", "-"),
+        false => ("█", "░"),
+    };
+    let mut out = String::new();
+    for share in shares {
+        out += &format!(
+            "{:>symbol_width$} current {:<CHART_WIDTH$} {:>6}\n",
+            share.symbol,
+            bar(share.current, current_bar),
+            share.current.to_string()
+        );
+        out += &format!(
+            "{:symbol_width$}  target {:<CHART_WIDTH$} {:>6}\n",
+            "",
+            bar(share.target, target_bar),
+            share.target.to_string()
+        );
+    }
+    out
+}
+
+/// A single holding in a [PlanReport]
+#[derive(Debug, Serialize)]
+pub struct PositionReport {
+    pub symbol: String,
+    /// The label of the holding in the configuration, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label: Option<String>,
+    pub is_cash: bool,
+    pub ignored: bool,
+    /// Only included if asset classes are configured for the account
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asset_class: Option<String>,
+    pub value: Dollar,
+    pub percent: Percent,
+    pub target: Percent,
+    /// How far the holding is from its target, in percentage points. Only
+    /// included for invested holdings.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift: Option<Percent>,
+    /// "buy", "sell", or "none"
+    pub action: &'static str,
+    pub amount: Dollar,
+    /// The number of shares to buy or sell, if the price of a share is known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shares: Option<f32>,
+    /// Why the holding isn't traded even though it is off target, if it isn't
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub held: Option<HoldReason>,
+    /// The date from which gains on the holding are long-term, if they are
+    /// still short-term
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_term_until: Option<Date>,
+    pub result: Dollar,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MaturityReport {
+    pub symbol: String,
+    pub value: Dollar,
+    pub maturity: Date,
+}
+
+/// Everything that is shown about the plan for an account, in a form that can
+/// be written in machine-readable formats
+#[derive(Debug, Serialize)]
+pub struct PlanReport {
+    pub account_id: String,
+    pub name: Option<String>,
+    pub total_value: Dollar,
+    /// How far the invested holdings are from their targets as a whole (see
+    /// [RebalancePlan::drift_score]). Only included if anything is invested.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub drift_score: Option<Percent>,
+    /// The gains that the sells would realize (see
+    /// [RebalancePlan::realized_gains]). Only included if the cost basis of
+    /// any sold holding is known.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub realized_gains: Option<Dollar>,
+    /// The part of the realized gains that is short-term. Only included if
+    /// any sold holding is known to be short-term.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub short_term_gains: Option<Dollar>,
+    /// The exposure of the account to each asset class, looking through funds
+    /// (see [AllocationConfig::look_through]). Only included if fund
+    /// compositions are configured.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub look_through: Vec<Exposure>,
+    pub positions: Vec<PositionReport>,
+    pub total_buy: Dollar,
+    pub total_sell: Dollar,
+    pub residual: Dollar,
+    /// What the trades add up to beyond the contribution (see
+    /// [RebalancePlan::net]), which is zero unless the account has no cash
+    /// position to pay for them
+    pub net: Dollar,
+    pub empty_holdings: Vec<String>,
+    /// The sells and buys that can be combined into exchange orders. Only
+    /// included if exchange orders are enabled for the account and there are
+    /// any.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exchanges: Vec<Exchange>,
+    /// The trades in the order to carry them out (see [RebalancePlan::steps])
+    pub steps: Vec<Step>,
+    pub upcoming_maturities: Vec<MaturityReport>,
+    pub warnings: Vec<String>,
+    /// Whether the rebalance policy of the account calls for a rebalance.
+    /// Only included if the account has a policy.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rebalance: Option<RebalanceStatus>,
+}
+
+fn round_percent(percent: Percent) -> Percent {
+    Percent((percent.0 * 100.0).round() / 100.0)
+}
+
+impl PlanReport {
+    pub fn new(account: &Balance, config: &AllocationConfig, plan: &RebalancePlan) -> Self {
+        let name = config
+            .nickname
+            .clone()
+            .unwrap_or_else(|| account.account_name.clone());
+        let total: Dollar = plan
+            .adjustments
+            .iter()
+            .map(|adj| adj.holding.current_value)
+            .sum();
+        let positions: Vec<PositionReport> = plan
+            .adjustments
+            .iter()
+            .zip(plan.drift())
+            .map(|(adj, drift)| {
+                let (action, amount) = match adj.action {
+                    Action::Buy(val) => ("buy", val),
+                    Action::Sell(val) => ("sell", val),
+                    Action::DoNothing => ("none", Dollar(0.0)),
+                };
+                PositionReport {
+                    symbol: adj.holding.symbol.clone(),
+                    label: config.label(&adj.holding.symbol).map(String::from),
+                    is_cash: adj.holding.is_cash,
+                    ignored: adj.ignored,
+                    asset_class: config.asset_class(&adj.holding).map(String::from),
+                    value: adj.holding.current_value.round_cents(),
+                    percent: round_percent(Percent::new(adj.holding.current_value, total)),
+                    target: round_percent(adj.target),
+                    drift: drift.map(|drift| round_percent(drift.0)),
+                    action,
+                    amount: amount.round_cents(),
+                    shares: adj
+                        .shares()
+                        .map(|shares| (shares * 1000.0).round() / 1000.0),
+                    held: adj.held,
+                    short_term_until: adj.short_term_until,
+                    result: adj.result().round_cents(),
+                }
+            })
+            .collect();
+        let total_of = |action: &str| {
+            positions
+                .iter()
+                .filter(|p| !p.is_cash && p.action == action)
+                // summing nothing gives -0.0, which would be shown as "$-0.00"
+                .fold(Dollar(0.0), |total, p| total + p.amount)
+                .round_cents()
+        };
+        Self {
+            account_id: account.account_id.clone(),
+            name: Some(name).filter(|name| !name.is_empty()),
+            total_value: account.total_value().round_cents(),
+            drift_score: plan.drift_score().map(round_percent),
+            realized_gains: plan.realized_gains().map(|gains| gains.round_cents()),
+            short_term_gains: plan.short_term_gains().map(|gains| gains.round_cents()),
+            look_through: config
+                .look_through(plan)
+                .into_iter()
+                .map(|exposure| Exposure {
+                    current: round_percent(exposure.current),
+                    target: round_percent(exposure.target),
+                    result: round_percent(exposure.result),
+                    ..exposure
+                })
+                .collect(),
+            total_buy: total_of("buy"),
+            total_sell: total_of("sell"),
+            positions,
+            residual: plan.residual.round_cents(),
+            net: plan.net(),
+            empty_holdings: plan.empty_holdings.clone(),
+            exchanges: match config.exchange_orders {
+                true => plan.exchanges(),
+                false => Vec::new(),
+            },
+            upcoming_maturities: plan
+                .upcoming_maturities
+                .iter()
+                .filter_map(|holding| {
+                    Some(MaturityReport {
+                        symbol: holding.symbol.clone(),
+                        value: holding.current_value.round_cents(),
+                        maturity: holding.maturity?,
+                    })
+                })
+                .collect(),
+            warnings: plan.warnings.clone(),
+            steps: plan.steps(),
+            rebalance: None,
+        }
+    }
+}
+
+/// Write the allocation tables of `reports` as a single CSV file, with one row
+/// per holding. Values are written as plain numbers so that spreadsheets
+/// recognize them.
+pub fn to_csv(reports: &[PlanReport]) -> anyhow::Result<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    writer.write_record([
+        "Account", "Symbol", "Value", "Percent", "Target", "Sell", "Buy", "Result", "Label",
+    ])?;
+    for report in reports {
+        for position in report.positions.iter() {
+            let amount_if = |action| match position.action == action {
+                true => position.amount.0.to_string(),
+                false => String::new(),
+            };
+            writer.write_record([
+                report.account_id.clone(),
+                position.symbol.clone(),
+                position.value.0.to_string(),
+                position.percent.0.to_string(),
+                position.target.0.to_string(),
+                amount_if("sell"),
+                amount_if("buy"),
+                position.result.0.to_string(),
+                position.label.clone().unwrap_or_default(),
+            ])?;
+        }
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?)
+}
+
+#[derive(Tabled)]
+#[tabled(display(Option, "display::option", ""))]
+struct MarkdownRow {
+    #[tabled(rename = "Symbol")]
+    symbol: String,
+    #[tabled(rename = "Label")]
+    label: String,
+    #[tabled(rename = "Value")]
+    value: Dollar,
+    #[tabled(rename = "Percent")]
+    percent: Percent,
+    #[tabled(rename = "Target")]
+    target: Percent,
+    #[tabled(rename = "Drift")]
+    drift: Option<RelativePercent>,
+    #[tabled(rename = "Sell")]
+    sell: Option<Dollar>,
+    #[tabled(rename = "Buy")]
+    buy: Option<Dollar>,
+    #[tabled(rename = "Result")]
+    result: Dollar,
+}
+
+/// Write the plans in `reports` as GitHub-flavored markdown, with a section
+/// for each account. Cash is marked with `*` and ignored holdings are shown in
+/// italics, since markdown tables can't be colored.
+pub fn to_markdown(reports: &[PlanReport]) -> String {
+    let mut out = String::new();
+    for report in reports {
+        let heading = report.name.as_deref().unwrap_or(&report.account_id);
+        out += &format!("## {heading}\n\n");
+        out += &format!("- Account ID: {}\n", report.account_id);
+        out += &format!("- Total balance: {}\n", report.total_value);
+        if let Some(score) = report.drift_score {
+            out += &format!("- Drift score: {score}\n");
+        }
+        if let Some(gains) = report.realized_gains {
+            out += &format!("- Estimated realized gains: {gains}\n");
+        }
+        for exposure in report.look_through.iter() {
+            out += &format!(
+                "- {} exposure: {} (target {}), {} after rebalancing\n",
+                exposure.class, exposure.current, exposure.target, exposure.result
+            );
+        }
+        out.push('\n');
+        let rows: Vec<MarkdownRow> = report
+            .positions
+            .iter()
+            .map(|position| {
+                let symbol = match position.is_cash {
+                    true => format!("\\*{}", position.symbol),
+                    false => position.symbol.clone(),
+                };
+                let amount_if =
+                    |action| Some(position.amount).filter(|_| position.action == action);
+                MarkdownRow {
+                    symbol: match position.ignored {
+                        true => format!("_{symbol}_"),
+                        false => symbol,
+                    },
+                    label: position.label.clone().unwrap_or_default(),
+                    value: position.value,
+                    percent: position.percent,
+                    target: position.target,
+                    drift: position.drift.map(RelativePercent),
+                    sell: amount_if("sell"),
+                    buy: amount_if("buy"),
+                    result: position.result,
+                }
+            })
+            .collect();
+        let mut table = Table::new(rows.iter());
+        table.with(Style::markdown());
+        remove_empty_columns(
+            &mut table,
+            [
+                ("Label", rows.iter().all(|row| row.label.is_empty())),
+                ("Drift", rows.iter().all(|row| row.drift.is_none())),
+                ("Sell", rows.iter().all(|row| row.sell.is_none())),
+                ("Buy", rows.iter().all(|row| row.buy.is_none())),
+            ],
+        );
+        out += &format!("{table}\n\n");
+
+        let mut list = |title: &str, items: Vec<String>| {
+            if !items.is_empty() {
+                out += &format!("{title}:\n\n");
+                for item in items {
+                    out += &format!("- {item}\n");
+                }
+                out.push('\n');
+            }
+        };
+        list("Empty holdings", report.empty_holdings.clone());
+        list(
+            "Exchange orders",
+            report.exchanges.iter().map(ToString::to_string).collect(),
+        );
+        list(
+            "Upcoming maturities",
+            report
+                .upcoming_maturities
+                .iter()
+                .map(|m| format!("{}: {} {}", m.maturity, m.symbol, m.value))
+                .collect(),
+        );
+        list("Warnings", report.warnings.clone());
+    }
+    out
+}
+
+const HTML_STYLE: &str = "
+body { font-family: system-ui, sans-serif; margin: 2em auto; max-width: 60em; color: #222; }
+h2 { border-bottom: 1px solid #ccc; padding-bottom: 0.2em; margin-top: 2em; }
+table { border-collapse: collapse; margin: 1em 0; }
+th, td { padding: 0.3em 0.8em; text-align: right; border-bottom: 1px solid #eee; }
+th { background: #f4f4f4; }
+td:first-child, th:first-child { text-align: left; }
+tr.ignored td { color: #999; }
+.cash { font-weight: bold; }
+.over { color: #b03030; }
+.under { color: #208040; }
+.warnings { background: #fff6e0; border-left: 4px solid #e0a000; padding: 0.5em 1em; }
+footer { margin-top: 3em; color: #777; font-size: 0.9em; }
+";
+
+pub fn escape_html(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Write the plans in `reports` as a single HTML page with its styles
+/// included, so that it can be archived or shared as one file
+pub fn to_html(reports: &[PlanReport], date: Date) -> String {
+    let mut out = String::new();
+    out += "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n";
+    out += &format!("<title>Rebalancing plan for {date}</title>\n");
+    out += &format!("<style>{HTML_STYLE}</style>\n</head>\n<body>\n");
+    out += &format!("<h1>Rebalancing plan for {date}</h1>\n");
+    for report in reports {
+        let heading = report.name.as_deref().unwrap_or(&report.account_id);
+        out += &format!("<section>\n<h2>{}</h2>\n", escape_html(heading));
+        out += &format!(
+            "<p>Account ID: {}<br>Total balance: {}</p>\n",
+            escape_html(&report.account_id),
+            report.total_value
+        );
+
+        let drifts: Vec<_> = report
+            .positions
+            .iter()
+            .filter_map(|p| Some((p, p.drift?.0)))
+            .collect();
+        let labels = report.positions.iter().any(|p| p.label.is_some());
+        out += "<table>\n<tr><th>Symbol</th>";
+        if labels {
+            out += "<th>Label</th>";
+        }
+        out += "<th>Value</th><th>Percent</th><th>Target</th>\
+                <th>Drift</th><th>Sell</th><th>Buy</th><th>Result</th></tr>\n";
+        for position in report.positions.iter() {
+            let drift_cell = match position.drift {
+                Some(d) if d.0 >= 0.05 => format!("<td class=\"over\">{}</td>", RelativePercent(d)),
+                Some(d) if d.0 <= -0.05 => {
+                    format!("<td class=\"under\">{}</td>", RelativePercent(d))
+                }
+                Some(d) => format!("<td>{}</td>", RelativePercent(d)),
+                None => "<td></td>".to_string(),
+            };
+            let amount_if = |action| match position.action == action {
+                true => position.amount.to_string(),
+                false => String::new(),
+            };
+            let label_cell = match labels {
+                true => format!(
+                    "<td>{}</td>",
+                    escape_html(position.label.as_deref().unwrap_or_default())
+                ),
+                false => String::new(),
+            };
+            out += &format!(
+                "<tr{}><td{}>{}</td>{label_cell}<td>{}</td><td>{}</td><td>{}</td>{drift_cell}\
+                 <td>{}</td><td>{}</td><td>{}</td></tr>\n",
+                match position.ignored {
+                    true => " class=\"ignored\"",
+                    false => "",
+                },
+                match position.is_cash {
+                    true => " class=\"cash\"",
+                    false => "",
+                },
+                escape_html(&position.symbol),
+                position.value,
+                position.percent,
+                position.target,
+                amount_if("sell"),
+                amount_if("buy"),
+                position.result,
+            );
+        }
+        out += "</table>\n";
+
+        out += "<h3>Drift</h3>\n";
+        match (
+            drifts
+                .iter()
+                .max_by(|(_, a), (_, b)| a.abs().total_cmp(&b.abs())),
+            report.drift_score,
+        ) {
+            (Some((position, drift)), Some(score)) => {
+                out += &format!(
+                    "<p>{score} of the account is allocated differently than the \
+                     targets. The largest drift is {} at {:.1} percentage points {} its \
+                     target.</p>\n",
+                    escape_html(&position.symbol),
+                    drift.abs(),
+                    if *drift > 0.0 { "above" } else { "below" },
+                );
+            }
+            _ => out += "<p>There are no invested holdings.</p>\n",
+        }
+
+        out += "<h3>Recommended trades</h3>\n";
+        let trades: Vec<_> = report
+            .positions
+            .iter()
+            .filter(|p| !p.is_cash && p.action != "none")
+            .collect();
+        if trades.is_empty() {
+            out += "<p>No trades are needed.</p>\n";
+        } else {
+            out += "<ul>\n";
+            for trade in trades {
+                let verb = if trade.action == "buy" { "Buy" } else { "Sell" };
+                out += &format!(
+                    "<li>{verb} {} of {}</li>\n",
+                    trade.amount,
+                    escape_html(&trade.symbol)
+                );
+            }
+            out += "</ul>\n";
+            out += &format!(
+                "<p>Total sold: {}<br>Total bought: {}</p>\n",
+                report.total_sell, report.total_buy
+            );
+        }
+        if !report.exchanges.is_empty() {
+            out += "<p>Exchange orders:</p>\n<ul>\n";
+            for exchange in report.exchanges.iter() {
+                out += &format!("<li>{}</li>\n", escape_html(&exchange.to_string()));
+            }
+            out += "</ul>\n";
+        }
+        if !report.upcoming_maturities.is_empty() {
+            out += "<h3>Upcoming maturities</h3>\n<ul>\n";
+            for maturity in report.upcoming_maturities.iter() {
+                out += &format!(
+                    "<li>{}: {} {}</li>\n",
+                    maturity.maturity,
+                    escape_html(&maturity.symbol),
+                    maturity.value
+                );
+            }
+            out += "</ul>\n";
+        }
+        if !report.warnings.is_empty() {
+            out += "<div class=\"warnings\">\n<p>Warnings:</p>\n<ul>\n";
+            for warning in report.warnings.iter() {
+                out += &format!("<li>{}</li>\n", escape_html(warning));
+            }
+            out += "</ul>\n</div>\n";
+        }
+        out += "</section>\n";
+    }
+    out += &format!("<footer>Generated by driftfix on {date}</footer>\n</body>\n</html>\n");
+    out
+}
+
+/// Serialize `value` as pretty-printed JSON. There is no JSON serializer
+/// available, so the value is serialized as YAML and converted, which also
+/// gives floating-point numbers their shortest representation.
+pub fn to_json(value: &impl Serialize) -> anyhow::Result<String> {
+    let yaml = serde_yaml::to_string(value)?;
+    let value: serde_yaml::Value = serde_yaml::from_str(&yaml)?;
+    let mut out = String::new();
+    write_json(&value, 0, &mut out);
+    Ok(out)
+}
+
+fn write_json_string(s: &str, out: &mut String) {
+    out.push('"');
+    for c in s.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", c as u32)),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+fn write_json(value: &serde_yaml::Value, indent: usize, out: &mut String) {
+    use serde_yaml::Value;
+    let newline = |out: &mut String, indent: usize| {
+        out.push('\n');
+        out.push_str(&"  ".repeat(indent));
+    };
+    match value {
+        Value::Null => out.push_str("null"),
+        Value::Bool(b) => out.push_str(&b.to_string()),
+        Value::Number(n) => match n.as_f64() {
+            Some(f) if !f.is_finite() => out.push_str("null"),
+            _ => out.push_str(&n.to_string()),
+        },
+        Value::String(s) => write_json_string(s, out),
+        Value::Sequence(items) if items.is_empty() => out.push_str("[]"),
+        Value::Sequence(items) => {
+            out.push('[');
+            for (i, item) in items.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, indent + 1);
+                write_json(item, indent + 1, out);
+            }
+            newline(out, indent);
+            out.push(']');
+        }
+        Value::Mapping(map) if map.is_empty() => out.push_str("{}"),
+        Value::Mapping(map) => {
+            out.push('{');
+            for (i, (key, value)) in map.iter().enumerate() {
+                if i > 0 {
+                    out.push(',');
+                }
+                newline(out, indent + 1);
+                let key = match key {
+                    Value::String(s) => s.clone(),
+                    other => serde_yaml::to_string(other)
+                        .unwrap_or_default()
+                        .trim()
+                        .to_string(),
+                };
+                write_json_string(&key, out);
+                out.push_str(": ");
+                write_json(value, indent + 1, out);
+            }
+            newline(out, indent);
+            out.push('}');
+        }
+        Value::Tagged(tagged) => write_json(&tagged.value, indent, out),
+    }
+}
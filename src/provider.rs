@@ -5,52 +5,319 @@ use std::{
 
 use clap::ValueEnum;
 use serde::{Deserialize, Serialize};
+use tracing::{debug, warn};
 
-use crate::account::Balance;
+use crate::{Dollar, account::Balance};
 
+mod betterment;
+mod crypto;
+mod etrade;
 mod fidelity;
+mod generic;
+mod ibkr;
+mod json;
+mod ledger;
+mod locale;
+mod merrill;
+mod netbenefits;
+mod ofx;
+mod portfolio_performance;
+mod qif;
+mod robinhood;
+mod schwab;
 mod vanguard;
+mod wealthfront;
+#[cfg(feature = "xlsx")]
+mod xlsx;
+mod xml;
+#[cfg(feature = "xlsx")]
+mod zip;
+
+pub use generic::ColumnMapping;
+pub use locale::CsvFormat;
 
 /// Brokerage providers supported by this tool
-#[derive(Serialize, Deserialize, Clone, Copy, Debug, ValueEnum)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, ValueEnum)]
 #[serde(rename_all = "lowercase")]
 pub enum ProviderType {
     Fidelity,
+    /// Fidelity NetBenefits workplace retirement plans
+    NetBenefits,
     Vanguard,
+    Schwab,
+    Etrade,
+    MerrillEdge,
+    InteractiveBrokers,
+    Robinhood,
+    Betterment,
+    Wealthfront,
+    /// OFX or QFX investment statements
+    Ofx,
+    /// Quicken QIF investment account transactions
+    Qif,
+    /// The JSON portfolio format described by `schema/portfolio.schema.json`
+    Json,
+    /// beancount or hledger/ledger plain-text accounting journals
+    Ledger,
+    /// Portfolio Performance XML data files
+    PortfolioPerformance,
+    /// Balance exports from Coinbase and similar cryptocurrency exchanges
+    Crypto,
+    /// Any CSV file, parsed according to a [ColumnMapping]
+    Generic,
 }
-const PROVIDERS: &[ProviderType] = &[ProviderType::Fidelity, ProviderType::Vanguard];
+/// The providers that are tried, in order, when auto-detecting the format of a
+/// file. The generic provider is left out because it would match too easily.
+const PROVIDERS: &[ProviderType] = &[
+    ProviderType::Fidelity,
+    ProviderType::NetBenefits,
+    ProviderType::Vanguard,
+    ProviderType::Schwab,
+    ProviderType::Etrade,
+    ProviderType::MerrillEdge,
+    ProviderType::InteractiveBrokers,
+    ProviderType::Robinhood,
+    ProviderType::Betterment,
+    ProviderType::Wealthfront,
+    ProviderType::Ofx,
+    ProviderType::Qif,
+    ProviderType::Json,
+    ProviderType::Ledger,
+    ProviderType::PortfolioPerformance,
+    ProviderType::Crypto,
+];
 
-fn provider(t: ProviderType) -> Box<dyn Provider> {
+pub(crate) fn provider(t: ProviderType) -> Box<dyn BalanceProvider> {
     match t {
         ProviderType::Fidelity => Box::new(fidelity::provider()),
+        ProviderType::NetBenefits => Box::new(netbenefits::provider()),
         ProviderType::Vanguard => Box::new(vanguard::provider()),
+        ProviderType::Schwab => Box::new(schwab::provider()),
+        ProviderType::Etrade => Box::new(etrade::provider()),
+        ProviderType::MerrillEdge => Box::new(merrill::provider()),
+        ProviderType::InteractiveBrokers => Box::new(ibkr::provider()),
+        ProviderType::Robinhood => Box::new(robinhood::provider()),
+        ProviderType::Betterment => Box::new(betterment::provider()),
+        ProviderType::Wealthfront => Box::new(wealthfront::provider()),
+        ProviderType::Ofx => Box::new(ofx::provider()),
+        ProviderType::Qif => Box::new(qif::provider()),
+        ProviderType::Json => Box::new(json::provider()),
+        ProviderType::Ledger => Box::new(ledger::provider()),
+        ProviderType::PortfolioPerformance => Box::new(portfolio_performance::provider()),
+        ProviderType::Crypto => Box::new(crypto::provider()),
+        ProviderType::Generic => Box::new(generic::provider(ColumnMapping::default())),
     }
 }
 
+/// Parse a dollar value as formatted by a brokerage (e.g. "$1,234.56", "-$5.00"
+/// or "($5.00)" for a negative value)
+pub(crate) fn parse_dollar(s: &str) -> Option<Dollar> {
+    let s = s.trim();
+    // accounting notation puts negative values in parentheses
+    let (s, sign) = match s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+        Some(inner) => (inner, -1.0),
+        None => (s, 1.0),
+    };
+    let cleaned: String = s
+        .chars()
+        .filter(|c| !matches!(c, '$' | ',' | '+'))
+        .collect();
+    cleaned.parse::<Dollar>().ok().map(|value| value * sign)
+}
+
 /// Load a portfolio from the given file path that conforms to the expected format for the given `ProviderType`
 pub fn load_portfolio(
     reader: &mut dyn Read,
     ptype: Option<ProviderType>,
 ) -> anyhow::Result<Vec<Balance>> {
-    let mut buffered = BufReader::new(reader);
-    if let Some(ptype) = ptype {
-        provider(ptype).parse_portfolio(&mut buffered)
-    } else {
-        let sample = buffered.fill_buf()?;
-        for p in PROVIDERS {
-            let prov = provider(*p);
-            if prov.detect(sample).unwrap_or(false) {
-                return prov.parse_portfolio(&mut buffered);
+    let mut warnings = Vec::new();
+    let portfolio = load_portfolio_with_warnings(reader, ptype, &mut warnings)?;
+    for warning in warnings {
+        warn!("{warning}");
+    }
+    Ok(portfolio)
+}
+
+/// Like [load_portfolio], but recoverable problems with the file (e.g. rows
+/// that had to be skipped) are added to `warnings` so that they can be
+/// reported to the user
+pub fn load_portfolio_with_warnings(
+    reader: &mut dyn Read,
+    ptype: Option<ProviderType>,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<Vec<Balance>> {
+    let mut buffered = buffer(reader)?;
+    let ptype = match ptype {
+        Some(ptype) => ptype,
+        None => detect_provider(buffered.fill_buf()?).ok_or_else(|| {
+            anyhow::anyhow!(
+                "Couldn't find a provider to parse this portfolio file. Try specifying the provider explicitly."
+            )
+        })?,
+    };
+    provider(ptype).parse(&mut buffered, warnings)
+}
+
+/// Guess the provider of a file from its first few kilobytes (e.g. from the
+/// header row of a CSV file or the root element of an XML file). The first
+/// provider in [PROVIDERS] that recognizes the file wins.
+pub fn detect_provider(sample: &[u8]) -> Option<ProviderType> {
+    let ptype = PROVIDERS
+        .iter()
+        .copied()
+        .find(|p| provider(*p).detect(sample).unwrap_or(false));
+    debug!(?ptype, "detected provider");
+    ptype
+}
+
+/// Combine the accounts loaded from several files into a single portfolio. If
+/// the same account appears in more than one file, the balances from the last
+/// file are used and a warning is added to `warnings`.
+pub fn merge_portfolios(
+    portfolios: impl IntoIterator<Item = Vec<Balance>>,
+    warnings: &mut Vec<String>,
+) -> Vec<Balance> {
+    let mut merged = Vec::<Balance>::new();
+    for account in portfolios.into_iter().flatten() {
+        match merged
+            .iter()
+            .position(|a| a.account_id == account.account_id)
+        {
+            Some(pos) => {
+                warnings.push(format!(
+                    "Account '{}' was found in more than one file, so only the last one was used",
+                    account.account_id
+                ));
+                merged[pos] = account;
             }
+            None => merged.push(account),
         }
-        Err(anyhow::anyhow!(
-            "Couldn't find a provider to parse this portfolio file"
-        ))
     }
+    merged
+}
+
+/// Load a portfolio from a CSV file using the given column mapping. The generic
+/// provider is never chosen by auto-detection, since almost any CSV file could
+/// match it.
+pub fn load_generic_portfolio(
+    reader: &mut dyn Read,
+    mapping: &ColumnMapping,
+    warnings: &mut Vec<String>,
+) -> anyhow::Result<Vec<Balance>> {
+    let mut buffered = buffer(reader)?;
+    generic::provider(mapping.clone()).parse(&mut buffered, warnings)
+}
+
+/// The first bytes of a ZIP archive, which is the container format of XLSX files
+const ZIP_SIGNATURE: &[u8] = b"PK\x03\x04";
+
+/// Wrap `reader` so that providers can peek at the start of the file. XLSX
+/// spreadsheets are converted to CSV first, so that they can be parsed by the
+/// same providers as CSV files.
+pub(crate) fn buffer(reader: &mut dyn Read) -> anyhow::Result<Box<dyn BufRead + '_>> {
+    let mut buffered = BufReader::new(reader);
+    if !buffered.fill_buf()?.starts_with(ZIP_SIGNATURE) {
+        return Ok(Box::new(buffered));
+    }
+    #[cfg(feature = "xlsx")]
+    {
+        let mut data = Vec::new();
+        buffered.read_to_end(&mut data)?;
+        Ok(Box::new(std::io::Cursor::new(xlsx::to_csv(&data)?)))
+    }
+    #[cfg(not(feature = "xlsx"))]
+    anyhow::bail!("This looks like a spreadsheet, but XLSX support was not enabled in this build")
 }
 
-/// a trait that must be implemented by providers in order to be supported by this tool
-trait Provider {
-    fn parse_portfolio(&self, reader: &mut dyn BufRead) -> anyhow::Result<Vec<Balance>>;
+/// A parser for the balance files exported by a particular brokerage (or in a
+/// particular file format). The providers built into this crate implement
+/// this trait, and other crates can implement it to add support for more
+/// brokerages through a [ProviderRegistry].
+pub trait BalanceProvider {
+    /// A short, unique name for the provider (e.g. "fidelity"), which is used
+    /// to choose it explicitly
+    fn name(&self) -> &str;
+    /// Parse the balances of all of the accounts in the file
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>>;
+    /// Whether the file that starts with `sample` appears to be in the format
+    /// that this provider parses
     fn detect(&self, sample: &[u8]) -> anyhow::Result<bool>;
 }
+
+/// A set of providers that balance files can be loaded with. The default
+/// registry contains every provider built into this crate except the generic
+/// CSV provider, and more can be added with [ProviderRegistry::register].
+pub struct ProviderRegistry {
+    providers: Vec<Box<dyn BalanceProvider>>,
+}
+
+impl Default for ProviderRegistry {
+    fn default() -> Self {
+        Self {
+            providers: PROVIDERS.iter().map(|p| provider(*p)).collect(),
+        }
+    }
+}
+
+impl ProviderRegistry {
+    /// Add a provider to the registry. It replaces any provider that has the
+    /// same name, and otherwise is tried after the existing providers when
+    /// detecting the format of a file.
+    pub fn register(&mut self, provider: impl BalanceProvider + 'static) {
+        match self
+            .providers
+            .iter()
+            .position(|p| p.name() == provider.name())
+        {
+            Some(pos) => self.providers[pos] = Box::new(provider),
+            None => self.providers.push(Box::new(provider)),
+        }
+    }
+
+    /// The names of the registered providers, in the order that they are tried
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.providers.iter().map(|p| p.name())
+    }
+
+    /// Find the provider with the given name
+    pub fn get(&self, name: &str) -> Option<&dyn BalanceProvider> {
+        self.providers
+            .iter()
+            .find(|p| p.name() == name)
+            .map(|p| p.as_ref())
+    }
+
+    /// Find the first provider that recognizes the file that starts with
+    /// `sample`
+    pub fn detect(&self, sample: &[u8]) -> Option<&dyn BalanceProvider> {
+        self.providers
+            .iter()
+            .find(|p| p.detect(sample).unwrap_or(false))
+            .map(|p| p.as_ref())
+    }
+
+    /// Load a portfolio using the provider with the given name, or the
+    /// provider that recognizes the file if no name is given
+    pub fn load_portfolio(
+        &self,
+        reader: &mut dyn Read,
+        name: Option<&str>,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let mut buffered = buffer(reader)?;
+        let provider = match name {
+            Some(name) => self
+                .get(name)
+                .ok_or_else(|| anyhow::anyhow!("Unknown provider '{name}'"))?,
+            None => self.detect(buffered.fill_buf()?).ok_or_else(|| {
+                anyhow::anyhow!("Couldn't find a provider to parse this portfolio file")
+            })?,
+        };
+        debug!(provider = provider.name(), "loading portfolio");
+        provider.parse(&mut buffered, warnings)
+    }
+}
@@ -0,0 +1,114 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const GOAL_HEADER: &str = "Goal";
+const ACCOUNT_HEADER: &str = "Account";
+const TICKER_HEADER: &str = "Ticker";
+const NAME_HEADER: &str = "Fund Name";
+const VALUE_HEADER: &str = "Value";
+const REQUIRED_HEADERS: &[&str] = &[GOAL_HEADER, ACCOUNT_HEADER, TICKER_HEADER, VALUE_HEADER];
+const CASH_SYMBOL: &str = "CASH";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "betterment"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Betterment CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let required = |name: &str| column(name).ok_or_else(|| anyhow!("Missing '{name}' column"));
+        let goal_column = required(GOAL_HEADER)?;
+        let account_column = required(ACCOUNT_HEADER)?;
+        let ticker_column = required(TICKER_HEADER)?;
+        let value_column = required(VALUE_HEADER)?;
+        let name_column = column(NAME_HEADER);
+
+        let mut accounts = Vec::<Balance>::new();
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let goal = row.get(goal_column).unwrap_or_default().trim();
+            let account_type = row.get(account_column).unwrap_or_default().trim();
+            if goal.is_empty() || account_type.is_empty() {
+                debug!(?row, "Row doesn't have enough fields to be a position");
+                continue;
+            }
+            // Betterment doesn't show account numbers. Each goal is held in
+            // a separate account, so the goal and account type identify it.
+            let account_id = format!("{goal} - {account_type}");
+            let acct = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.clone(),
+                        account_name: goal.to_string(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            let ticker = row.get(ticker_column).unwrap_or_default().trim();
+            let name = name_column
+                .and_then(|c| row.get(c))
+                .unwrap_or_default()
+                .trim();
+            // uninvested cash is listed without a ticker
+            let is_cash = ticker.is_empty() && name.eq_ignore_ascii_case("cash");
+            if ticker.is_empty() && !is_cash {
+                debug!(?row, "Skipping row without a ticker");
+                warnings.push(format!(
+                    "Skipped '{name}' in {account_id} because it doesn't have a ticker"
+                ));
+                continue;
+            }
+            let current_value = row
+                .get(value_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get value for '{ticker}'"))?;
+            let holding = Holding {
+                symbol: match is_cash {
+                    true => CASH_SYMBOL.to_string(),
+                    false => ticker.to_string(),
+                },
+                current_value,
+                is_cash,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(sample);
+        let headers = csv_reader.headers()?;
+        debug!(?headers);
+        Ok(REQUIRED_HEADERS
+            .iter()
+            .all(|expected| headers.iter().any(|h| h.trim() == *expected)))
+    }
+}
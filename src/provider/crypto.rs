@@ -0,0 +1,167 @@
+//! Balance exports from Coinbase and similar cryptocurrency exchanges.
+//!
+//! Exchanges don't agree on the names of their columns, so each column can be
+//! given any of a few common names. The value of each asset is taken from the
+//! file rather than calculated, so it must include a column with the value in
+//! US dollars.
+
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+/// Exchange exports don't include an account number, so all assets are
+/// imported into a single account with this ID unless the file has a column
+/// naming the portfolio or account
+const DEFAULT_ACCOUNT_ID: &str = "crypto";
+const DEFAULT_ACCOUNT_NAME: &str = "Crypto";
+const CASH_SYMBOL: &str = "CASH";
+/// US dollars held at the exchange are imported as cash
+const CASH_ASSET: &str = "USD";
+const ASSET_HEADERS: &[&str] = &["Asset", "Currency", "Coin"];
+const VALUE_HEADERS: &[&str] = &[
+    "USD Value",
+    "Value (USD)",
+    "Balance (USD)",
+    "USD Balance",
+    "Total (USD)",
+    "Native Balance",
+];
+const QUANTITY_HEADERS: &[&str] = &["Quantity", "Balance", "Amount", "Total"];
+const ACCOUNT_HEADERS: &[&str] = &["Portfolio", "Account"];
+/// Some exports start with a few lines describing the report, so the header
+/// row is looked for within this many lines
+const MAX_PREAMBLE_LINES: usize = 10;
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// Find the first of `names` in `headers`
+fn find_column(headers: &csv::StringRecord, names: &[&str]) -> Option<usize> {
+    names.iter().find_map(|name| {
+        headers
+            .iter()
+            .position(|h| h.trim().eq_ignore_ascii_case(name))
+    })
+}
+
+/// Find the line number of the header row in the start of the file
+fn header_line(text: &str) -> Option<usize> {
+    text.lines().take(MAX_PREAMBLE_LINES).position(|line| {
+        let headers = csv::ReaderBuilder::new()
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(line.as_bytes())
+            .records()
+            .next()
+            .and_then(Result::ok);
+        headers.is_some_and(|headers| {
+            find_column(&headers, ASSET_HEADERS).is_some()
+                && find_column(&headers, VALUE_HEADERS).is_some()
+        })
+    })
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "crypto"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid cryptocurrency exchange CSV file.");
+        }
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let text = text.trim_start_matches('\u{feff}');
+        let start = header_line(text).ok_or_else(|| anyhow!("Failed to find the header row"))?;
+        let body = text.lines().skip(start).collect::<Vec<_>>().join("\n");
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .from_reader(body.as_bytes());
+        let headers = csv_reader.headers()?.clone();
+        let asset_column =
+            find_column(&headers, ASSET_HEADERS).ok_or_else(|| anyhow!("Missing asset column"))?;
+        let value_column = find_column(&headers, VALUE_HEADERS)
+            .ok_or_else(|| anyhow!("Missing USD value column"))?;
+        let quantity_column = find_column(&headers, QUANTITY_HEADERS);
+        let account_column = find_column(&headers, ACCOUNT_HEADERS);
+
+        let mut accounts = Vec::<Balance>::new();
+        for (line, row) in csv_reader.records().enumerate() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let asset = row.get(asset_column).unwrap_or_default().trim();
+            if asset.is_empty() {
+                debug!(?row, "Skipping row without an asset");
+                continue;
+            }
+            let Some(current_value) = row.get(value_column).and_then(parse_dollar) else {
+                warnings.push(format!(
+                    "Skipped {asset} on line {} because it doesn't have a USD value",
+                    start + line + 2
+                ));
+                continue;
+            };
+            // exchanges list every asset that they support, even if the
+            // balance is zero
+            let quantity = quantity_column
+                .and_then(|c| row.get(c))
+                .and_then(parse_dollar);
+            if current_value.0 == 0.0 && quantity.is_none_or(|q| q.0 == 0.0) {
+                debug!(asset, "Skipping empty balance");
+                continue;
+            }
+            let account_id = account_column
+                .and_then(|c| row.get(c))
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .unwrap_or(DEFAULT_ACCOUNT_ID);
+            let acct = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.to_string(),
+                        account_name: match account_column {
+                            Some(_) => account_id.to_string(),
+                            None => DEFAULT_ACCOUNT_NAME.to_string(),
+                        },
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            let is_cash = asset.eq_ignore_ascii_case(CASH_ASSET);
+            let holding = Holding {
+                symbol: match is_cash {
+                    true => CASH_SYMBOL.to_string(),
+                    false => asset.to_uppercase(),
+                },
+                current_value,
+                is_cash,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        Ok(header_line(sample.trim_start_matches('\u{feff}')).is_some())
+    }
+}
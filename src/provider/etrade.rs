@@ -0,0 +1,131 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const TITLE: &str = "Account Summary";
+const ACCOUNT_HEADER: &str = "Account";
+const SYMBOL_HEADER: &str = "Symbol";
+const VALUE_HEADER: &str = "Value $";
+const CASH_ROW: &str = "CASH";
+const TOTAL_ROW: &str = "TOTAL";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// The parts of the file that we are interested in
+enum Section {
+    Preamble,
+    AccountSummary,
+    Positions { value_column: usize },
+}
+
+/// Split an account description like "Individual Brokerage -1234" into an
+/// account name and an account number
+fn parse_account(description: &str) -> (String, String) {
+    match description.trim().rsplit_once(char::is_whitespace) {
+        Some((name, id)) => (
+            name.trim().to_string(),
+            id.trim_start_matches('-').to_string(),
+        ),
+        None => (String::new(), description.trim().to_string()),
+    }
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "etrade"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid E*TRADE CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .has_headers(false)
+            .from_reader(reader);
+        let mut account: Option<Balance> = None;
+        let mut section = Section::Preamble;
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let first = row.get(0).unwrap_or_default().trim();
+            if first == ACCOUNT_HEADER {
+                section = Section::AccountSummary;
+                continue;
+            }
+            if first == SYMBOL_HEADER {
+                let value_column = row
+                    .iter()
+                    .position(|header| header.trim() == VALUE_HEADER)
+                    .ok_or_else(|| anyhow!("Failed to find '{VALUE_HEADER}' column"))?;
+                section = Section::Positions { value_column };
+                continue;
+            }
+            match section {
+                Section::Preamble => continue,
+                Section::AccountSummary => {
+                    if first.is_empty() || account.is_some() {
+                        continue;
+                    }
+                    let (account_name, account_id) = parse_account(first);
+                    debug!(account_name, account_id, "found account");
+                    account = Some(Balance {
+                        account_id,
+                        account_name,
+                        ..Default::default()
+                    });
+                }
+                Section::Positions { value_column } => {
+                    if first == TOTAL_ROW {
+                        break;
+                    }
+                    if row.len() <= value_column {
+                        debug!(?row, "Row doesn't have enough fields to be a position");
+                        continue;
+                    }
+                    let Some(acct) = account.as_mut() else {
+                        bail!("Found a position before the account summary");
+                    };
+                    let current_value = row
+                        .get(value_column)
+                        .and_then(parse_dollar)
+                        .ok_or_else(|| anyhow!("Failed to get value for symbol '{first}'"))?;
+                    let holding = Holding {
+                        symbol: first.to_string(),
+                        current_value,
+                        is_cash: first == CASH_ROW,
+                        ..Default::default()
+                    };
+                    debug!(?acct.account_id, ?holding, "adding position");
+                    acct.holdings.push(holding);
+                }
+            }
+        }
+        Ok(account.into_iter().collect())
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        let first_line = sample
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default();
+        Ok(first_line.trim().trim_matches(',') == TITLE)
+    }
+}
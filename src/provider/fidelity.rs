@@ -1,69 +1,82 @@
 use std::{collections::HashMap, io::BufRead};
 
 use anyhow::{anyhow, bail};
-use tracing::{debug, warn};
+use tracing::debug;
 
 use crate::{
-    Dollar,
     account::{Balance, Holding},
-    provider::Provider,
+    provider::{BalanceProvider, parse_dollar},
 };
 
-pub enum Columns {
-    AccountNumber = 0,
-    AccountName = 1,
-    Symbol = 2,
-    CurrentValue = 7,
-}
-
-const EXPECTED_HEADERS: &[&str] = &[
-    "Account Number",
-    "Account Name",
-    "Symbol",
-    "Description",
-    "Quantity",
-    "Last Price",
-    "Last Price Change",
-    "Current Value",
-    "Today's Gain/Loss Dollar",
-    "Today's Gain/Loss Percent",
-    "Total Gain/Loss Dollar",
-    "Total Gain/Loss Percent",
-    "Percent Of Account",
-    "Cost Basis Total",
-    "Average Cost Basis",
-    "Type",
+const ACCOUNT_NUMBER_HEADER: &str = "Account Number";
+const ACCOUNT_NAME_HEADER: &str = "Account Name";
+const SYMBOL_HEADER: &str = "Symbol";
+const DESCRIPTION_HEADER: &str = "Description";
+const CURRENT_VALUE_HEADER: &str = "Current Value";
+const QUANTITY_HEADER: &str = "Quantity";
+const LAST_PRICE_HEADER: &str = "Last Price";
+const COST_BASIS_HEADER: &str = "Cost Basis Total";
+/// The columns that are needed to import a position. Fidelity occasionally
+/// adds or reorders columns, so columns are found by name.
+const REQUIRED_HEADERS: &[&str] = &[
+    ACCOUNT_NUMBER_HEADER,
+    ACCOUNT_NAME_HEADER,
+    SYMBOL_HEADER,
+    CURRENT_VALUE_HEADER,
 ];
 
-pub fn provider() -> impl Provider {
+pub fn provider() -> impl BalanceProvider {
     ProviderImpl
 }
 
 struct ProviderImpl;
 
-impl Provider for ProviderImpl {
-    fn parse_portfolio(&self, reader: &mut dyn BufRead) -> anyhow::Result<Vec<Balance>> {
-        let sample = reader.fill_buf()?;
-        if !self.detect(sample)? {
-            bail!("Portfolio file does not appear to be a valid Fidelity CSV file.");
-        }
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "fidelity"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
         let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
         let mut accounts = HashMap::<String, Balance>::new();
-        // throw away headers. we already checked them in detect()
-        let headers = csv_reader.headers()?;
+        let headers = csv_reader.headers()?.clone();
         debug!(?headers);
+        let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let missing: Vec<_> = REQUIRED_HEADERS
+            .iter()
+            .filter(|name| column(name).is_none())
+            .map(|name| format!("'{name}'"))
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "Fidelity CSV file is missing the required columns: {}",
+                missing.join(", ")
+            );
+        }
+        let account_number_column = column(ACCOUNT_NUMBER_HEADER).unwrap();
+        let account_name_column = column(ACCOUNT_NAME_HEADER).unwrap();
+        let symbol_column = column(SYMBOL_HEADER).unwrap();
+        let current_value_column = column(CURRENT_VALUE_HEADER).unwrap();
+        let quantity_column = column(QUANTITY_HEADER);
+        let last_price_column = column(LAST_PRICE_HEADER);
+        let cost_basis_column = column(COST_BASIS_HEADER);
         let records = csv_reader.records();
         for row in records {
             let row = row?;
             debug!(?row, "parsed row");
-            if row.len() < Columns::CurrentValue as usize {
+            // the export ends with a few lines of disclaimers
+            if row.len() <= current_value_column {
                 debug!(?row, "Row doesn't have enough fields to be a position");
                 break;
             }
-            let Some(account_id) = row.get(Columns::AccountNumber as usize) else {
+            let Some(account_id) = row.get(account_number_column) else {
                 bail!("failed to get account number for row");
             };
-            let Some(account_name) = row.get(Columns::AccountName as usize) else {
+            let Some(account_name) = row.get(account_name_column) else {
                 bail!("failed to get account name for row");
             };
             let acct = accounts.entry(account_id.to_string()).or_insert(Balance {
@@ -72,27 +85,37 @@ impl Provider for ProviderImpl {
                 ..Default::default()
             });
             let symbol = row
-                .get(Columns::Symbol as usize)
+                .get(symbol_column)
                 .ok_or_else(|| anyhow!("Failed to get symbol"))?;
             let current_value = row
-                .get(Columns::CurrentValue as usize)
-                .and_then(|s| s.replace('$', "").parse::<Dollar>().ok())
-                .ok_or_else(|| anyhow!("Failed to get symbol"))?;
+                .get(current_value_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get current value for '{symbol}'"))?;
             if symbol == "Pending activity" {
-                debug!(?acct, "Adding pending activity to core position");
-                if let Some(core) = acct.holdings.iter_mut().find(|p| p.is_cash) {
-                    core.current_value += current_value;
-                } else {
-                    warn!(
-                        "Account '{}' has ${current_value} in pending activity but cannot find core position.",
-                        acct.account_id
-                    );
-                }
+                debug!(?acct, "adding pending activity");
+                acct.holdings.push(Holding {
+                    symbol: symbol.to_string(),
+                    current_value,
+                    pending: true,
+                    ..Default::default()
+                });
             } else {
+                // these are "--" when they don't apply, e.g. for the core
+                // position in some account types
                 let pos = Holding {
                     symbol: symbol.trim_end_matches("**").to_string(),
                     current_value,
                     is_cash: symbol.ends_with("**"),
+                    quantity: quantity_column
+                        .and_then(|c| row.get(c))
+                        .and_then(|s| s.trim().replace(',', "").parse::<f32>().ok()),
+                    price: last_price_column
+                        .and_then(|c| row.get(c))
+                        .and_then(parse_dollar),
+                    cost_basis: cost_basis_column
+                        .and_then(|c| row.get(c))
+                        .and_then(parse_dollar),
+                    ..Default::default()
                 };
                 debug!(?acct, ?pos, "adding regular position");
                 acct.holdings.push(pos);
@@ -104,16 +127,10 @@ impl Provider for ProviderImpl {
     fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
         let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(sample);
         let headers = csv_reader.headers()?;
-        let iter = headers.iter();
-        let expected_iter = EXPECTED_HEADERS.iter().copied();
         debug!(?headers);
-
-        for (got, expected) in iter.zip(expected_iter) {
-            if got != expected {
-                debug!("Expected {expected}, got {got}");
-                return Ok(false);
-            }
-        }
-        Ok(true)
+        Ok(REQUIRED_HEADERS
+            .iter()
+            .chain([&DESCRIPTION_HEADER])
+            .all(|expected| headers.iter().any(|h| h.trim() == *expected)))
     }
 }
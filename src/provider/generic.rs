@@ -0,0 +1,192 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    date::Date,
+    provider::{BalanceProvider, parse_dollar},
+};
+
+/// The names of the columns to read from a CSV file with the generic provider
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct ColumnMapping {
+    /// The column containing the account number
+    pub account_number: String,
+    /// The column containing a descriptive name for the account, if any
+    pub account_name: Option<String>,
+    /// The column containing the symbol of the holding
+    pub symbol: String,
+    /// The column containing the current value of the holding
+    pub value: String,
+    /// A column that marks the cash holding in each account with a value like
+    /// "yes", "true", or "*"
+    pub cash: Option<String>,
+    /// The column containing the currency that the value is in (e.g. "EUR"),
+    /// if holdings are valued in more than one currency
+    pub currency: Option<String>,
+    /// The column containing the date on which a fixed-maturity instrument
+    /// (e.g. a CD) matures, as YYYY-MM-DD or MM/DD/YYYY
+    pub maturity: Option<String>,
+}
+
+impl Default for ColumnMapping {
+    fn default() -> Self {
+        Self {
+            account_number: "Account Number".to_string(),
+            account_name: None,
+            symbol: "Symbol".to_string(),
+            value: "Value".to_string(),
+            cash: None,
+            currency: None,
+            maturity: None,
+        }
+    }
+}
+
+pub fn provider(mapping: ColumnMapping) -> impl BalanceProvider {
+    ProviderImpl { mapping }
+}
+
+struct ProviderImpl {
+    mapping: ColumnMapping,
+}
+
+/// Parse a date as YYYY-MM-DD, or as MM/DD/YYYY as in US exports
+fn parse_date(s: &str) -> Option<Date> {
+    if let Ok(date) = s.parse() {
+        return Some(date);
+    }
+    let mut parts = s.splitn(3, '/').map(|part| part.trim().parse::<u32>().ok());
+    let (month, day, year) = (parts.next()??, parts.next()??, parts.next()??);
+    Date::new(year.try_into().ok()?, month, day)
+}
+
+fn is_cash_marker(s: &str) -> bool {
+    matches!(
+        s.trim().to_lowercase().as_str(),
+        "true" | "yes" | "y" | "1" | "x" | "*"
+    )
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "generic"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!(
+                "Portfolio file doesn't contain the columns configured for the generic provider ({:?}).",
+                self.mapping
+            );
+        }
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| anyhow!("Missing '{name}' column"))
+        };
+        let account_column = column(&self.mapping.account_number)?;
+        let symbol_column = column(&self.mapping.symbol)?;
+        let value_column = column(&self.mapping.value)?;
+        let name_column = self
+            .mapping
+            .account_name
+            .as_deref()
+            .map(column)
+            .transpose()?;
+        let cash_column = self.mapping.cash.as_deref().map(column).transpose()?;
+        let currency_column = self.mapping.currency.as_deref().map(column).transpose()?;
+        let maturity_column = self.mapping.maturity.as_deref().map(column).transpose()?;
+
+        let mut accounts = Vec::<Balance>::new();
+        for (i, row) in csv_reader.records().enumerate() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let account_id = row.get(account_column).unwrap_or_default().trim();
+            let symbol = row.get(symbol_column).unwrap_or_default().trim();
+            if account_id.is_empty() || symbol.is_empty() {
+                debug!(?row, "Skipping row without an account number or symbol");
+                if row.iter().any(|field| !field.trim().is_empty()) {
+                    // the header is line 1
+                    warnings.push(format!(
+                        "Skipped line {} because it doesn't have an account number or symbol",
+                        i + 2
+                    ));
+                }
+                continue;
+            }
+            let acct = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.to_string(),
+                        account_name: name_column
+                            .and_then(|c| row.get(c))
+                            .unwrap_or_default()
+                            .trim()
+                            .to_string(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            let current_value = row
+                .get(value_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get value for symbol '{symbol}'"))?;
+            let maturity = maturity_column
+                .and_then(|c| row.get(c))
+                .map(str::trim)
+                .filter(|maturity| !maturity.is_empty())
+                .map(|maturity| {
+                    parse_date(maturity).ok_or_else(|| {
+                        anyhow!("Invalid maturity date '{maturity}' for symbol '{symbol}'")
+                    })
+                })
+                .transpose()?;
+            let holding = Holding {
+                symbol: symbol.to_string(),
+                current_value,
+                is_cash: cash_column
+                    .and_then(|c| row.get(c))
+                    .is_some_and(is_cash_marker),
+                currency: currency_column
+                    .and_then(|c| row.get(c))
+                    .map(str::trim)
+                    .filter(|currency| !currency.is_empty())
+                    .map(str::to_string),
+                maturity,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(sample);
+        let headers = csv_reader.headers()?;
+        debug!(?headers);
+        let mapping = &self.mapping;
+        Ok([&mapping.account_number, &mapping.symbol, &mapping.value]
+            .into_iter()
+            .chain(mapping.account_name.as_ref())
+            .chain(mapping.cash.as_ref())
+            .chain(mapping.currency.as_ref())
+            .chain(mapping.maturity.as_ref())
+            .all(|expected| headers.iter().any(|h| h.trim() == expected)))
+    }
+}
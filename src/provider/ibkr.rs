@@ -0,0 +1,185 @@
+use std::{collections::HashMap, io::BufRead};
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    provider::{
+        BalanceProvider,
+        xml::{self, Token},
+    },
+};
+
+const ROOT_ELEMENT: &str = "<FlexQueryResponse";
+/// The cash report row that summarizes all currencies in the base currency
+const BASE_SUMMARY: &str = "BASE_SUMMARY";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+fn parse_number(token: &Token, key: &str) -> anyhow::Result<f32> {
+    token
+        .attribute(key)
+        .ok_or_else(|| anyhow!("Missing '{key}' attribute"))?
+        .parse::<f32>()
+        .map_err(|e| anyhow!("Invalid '{key}' attribute: {e}"))
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "interactive-brokers"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!(
+                "Portfolio file does not appear to be a valid Interactive Brokers Flex Query file."
+            );
+        }
+        let mut doc = String::new();
+        reader.read_to_string(&mut doc)?;
+        let tokens = xml::tokenize(&doc)?;
+
+        let mut accounts = Vec::<Balance>::new();
+        let mut base_currencies = HashMap::<String, String>::new();
+        // the exchange rates seen for each currency in each account
+        let mut rates = HashMap::<(String, String), f32>::new();
+        // cash balances by account: (currency, ending cash, fx rate)
+        let mut cash = HashMap::<String, Vec<(String, f32, Option<f32>)>>::new();
+        for token in tokens.iter() {
+            let Token::Start { name, .. } = token else {
+                continue;
+            };
+            let Some(account_id) = token.attribute("accountId") else {
+                continue;
+            };
+            let account = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.to_string(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            match name.as_str() {
+                "AccountInformation" => {
+                    if let Some(alias) = token
+                        .attribute("acctAlias")
+                        .or(token.attribute("name"))
+                        .filter(|alias| !alias.is_empty())
+                    {
+                        account.account_name = alias.to_string();
+                    }
+                    if let Some(currency) = token.attribute("currency") {
+                        base_currencies.insert(account_id.to_string(), currency.to_string());
+                    }
+                }
+                "OpenPosition" => {
+                    let symbol = token
+                        .attribute("symbol")
+                        .ok_or_else(|| anyhow!("Open position is missing a symbol"))?;
+                    let value = parse_number(token, "positionValue")?;
+                    let rate = token
+                        .attribute("fxRateToBase")
+                        .and_then(|r| r.parse::<f32>().ok());
+                    let currency = token.attribute("currency").map(str::to_string);
+                    if let (Some(currency), Some(rate)) = (&currency, rate) {
+                        rates.insert((account_id.to_string(), currency.clone()), rate);
+                    }
+                    let holding = Holding {
+                        symbol: symbol.to_string(),
+                        current_value: Dollar(value * rate.unwrap_or(1.0)),
+                        is_cash: false,
+                        currency,
+                        exchange_rate: rate,
+                        ..Default::default()
+                    };
+                    debug!(?account.account_id, ?holding, "adding position");
+                    account.holdings.push(holding);
+                }
+                "CashReportCurrency" => {
+                    let currency = token
+                        .attribute("currency")
+                        .ok_or_else(|| anyhow!("Cash report is missing a currency"))?;
+                    let ending = parse_number(token, "endingCash")?;
+                    let rate = token
+                        .attribute("fxRateToBase")
+                        .and_then(|r| r.parse::<f32>().ok());
+                    cash.entry(account_id.to_string()).or_default().push((
+                        currency.to_string(),
+                        ending,
+                        rate,
+                    ));
+                }
+                _ => {}
+            }
+        }
+
+        for account in accounts.iter_mut() {
+            let base = base_currencies
+                .get(&account.account_id)
+                .cloned()
+                .unwrap_or_else(|| "USD".to_string());
+            let entries = cash.remove(&account.account_id).unwrap_or_default();
+            let per_currency: Vec<_> = entries
+                .iter()
+                .filter(|(currency, _, _)| currency != BASE_SUMMARY)
+                .collect();
+            let cash_holdings: Vec<(String, f32, Option<f32>)> = if per_currency.is_empty() {
+                // only the summary is available, so report all cash in the
+                // base currency
+                entries
+                    .iter()
+                    .filter(|(currency, _, _)| currency == BASE_SUMMARY)
+                    .map(|(_, ending, _)| (base.clone(), *ending, Some(1.0)))
+                    .collect()
+            } else {
+                per_currency.into_iter().cloned().collect()
+            };
+            for (currency, ending, rate) in cash_holdings {
+                let rate = rate
+                    .or_else(|| {
+                        rates
+                            .get(&(account.account_id.clone(), currency.clone()))
+                            .copied()
+                    })
+                    .or_else(|| (currency == base).then_some(1.0))
+                    .unwrap_or_else(|| {
+                        warnings.push(format!(
+                            "No exchange rate found for {currency} cash in account {}; assuming 1.0",
+                            account.account_id
+                        ));
+                        1.0
+                    });
+                let holding = Holding {
+                    symbol: currency.clone(),
+                    current_value: Dollar(ending * rate),
+                    is_cash: true,
+                    currency: Some(currency),
+                    exchange_rate: Some(rate),
+                    ..Default::default()
+                };
+                debug!(?account.account_id, ?holding, "adding cash balance");
+                account.holdings.push(holding);
+            }
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        Ok(sample.contains(ROOT_ELEMENT))
+    }
+}
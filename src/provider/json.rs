@@ -0,0 +1,158 @@
+//! A simple JSON format for handing portfolios to this tool from scripts and
+//! other programs. The format is described by `schema/portfolio.schema.json`.
+
+use std::io::BufRead;
+
+use anyhow::{Context, bail};
+use serde::Deserialize;
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    date::Date,
+    provider::BalanceProvider,
+};
+
+/// The newest version of the format that this provider understands
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Document {
+    version: u32,
+    accounts: Vec<Account>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct Account {
+    account_id: String,
+    #[serde(default)]
+    account_name: String,
+    holdings: Vec<JsonHolding>,
+}
+
+#[derive(Deserialize, Debug)]
+#[serde(deny_unknown_fields)]
+struct JsonHolding {
+    symbol: String,
+    value: f32,
+    #[serde(default)]
+    is_cash: bool,
+    #[serde(default)]
+    currency: Option<String>,
+    #[serde(default)]
+    exchange_rate: Option<f32>,
+    #[serde(default)]
+    maturity: Option<Date>,
+    #[serde(default)]
+    quantity: Option<f32>,
+    #[serde(default)]
+    price: Option<f32>,
+    #[serde(default)]
+    cost_basis: Option<f32>,
+    #[serde(default)]
+    acquired: Option<Date>,
+    #[serde(default)]
+    pending: bool,
+}
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "json"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid JSON portfolio file.");
+        }
+        let mut contents = String::new();
+        reader.read_to_string(&mut contents)?;
+        let document: Document =
+            serde_yaml::from_str(&untab(&contents)?).context("Failed to parse JSON portfolio")?;
+        debug!(?document);
+        if document.version > FORMAT_VERSION {
+            bail!(
+                "JSON portfolio format version {} is not supported (expected version {FORMAT_VERSION})",
+                document.version
+            );
+        }
+        Ok(document
+            .accounts
+            .into_iter()
+            .map(|account| Balance {
+                account_id: account.account_id,
+                account_name: account.account_name,
+                holdings: account
+                    .holdings
+                    .into_iter()
+                    .map(|holding| Holding {
+                        symbol: holding.symbol,
+                        current_value: Dollar(holding.value),
+                        is_cash: holding.is_cash,
+                        currency: holding.currency,
+                        exchange_rate: holding.exchange_rate,
+                        maturity: holding.maturity,
+                        quantity: holding.quantity,
+                        price: holding.price.map(Dollar),
+                        cost_basis: holding.cost_basis.map(Dollar),
+                        acquired: holding.acquired,
+                        pending: holding.pending,
+                    })
+                    .collect(),
+            })
+            .collect())
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        Ok(sample
+            .trim_start_matches('\u{feff}')
+            .trim_start()
+            .starts_with('{')
+            && sample.contains("\"accounts\""))
+    }
+}
+
+/// Replace the tabs between the tokens of a JSON document with spaces. JSON is
+/// (almost) a subset of YAML, but the YAML parser doesn't accept tabs as
+/// indentation. Tabs can't appear within JSON strings (they have to be
+/// escaped as `\t`), so a string with a tab in it is an error.
+fn untab(contents: &str) -> anyhow::Result<String> {
+    let (mut in_string, mut escaped) = (false, false);
+    let mut line = 1;
+    let mut untabbed = String::with_capacity(contents.len());
+    for c in contents.chars() {
+        match c {
+            '\t' if in_string => {
+                bail!("Line {line} has a tab within a string, which has to be written as \\t")
+            }
+            '\t' => untabbed.push(' '),
+            _ => {
+                if c == '\n' {
+                    line += 1;
+                }
+                if in_string {
+                    in_string = escaped || c != '"';
+                    escaped = !escaped && c == '\\';
+                } else {
+                    in_string = c == '"';
+                }
+                untabbed.push(c);
+            }
+        }
+    }
+    Ok(untabbed)
+}
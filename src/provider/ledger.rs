@@ -0,0 +1,447 @@
+//! Plain-text accounting journals, in either beancount or hledger/ledger
+//! syntax.
+//!
+//! The postings in the journal are added up to find the quantity of each
+//! commodity held in each asset account, and commodities are valued using the
+//! most recent price for them (from a `price` or `P` directive, or from the
+//! price of the last transaction if there is none). A separate price database
+//! can be included by concatenating it with the journal.
+//!
+//! Only accounts under `Assets` that hold something other than the base
+//! currency are imported. Subaccounts that are named after the commodity that
+//! they hold (e.g. `Assets:Brokerage:VTI`) or that are named `Cash` are
+//! combined into their parent account.
+
+use std::{collections::HashMap, io::BufRead};
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    provider::BalanceProvider,
+};
+
+const ASSETS: &str = "assets";
+const DEFAULT_CURRENCY: &str = "USD";
+const CASH_ACCOUNT: &str = "cash";
+/// Quantities smaller than this are left over from rounding and are not
+/// reported as holdings
+const MIN_QUANTITY: f32 = 0.0001;
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// A quantity of a commodity, e.g. "10 VTI" or "$2,300.00"
+#[derive(Debug, Clone, PartialEq)]
+struct Amount {
+    quantity: f32,
+    commodity: String,
+}
+
+/// Normalize the name of a commodity. The `$` symbol that is commonly used in
+/// ledger files is treated as US dollars.
+fn commodity_name(s: &str) -> String {
+    match s.trim().trim_matches('"') {
+        "$" => DEFAULT_CURRENCY.to_string(),
+        other => other.to_string(),
+    }
+}
+
+fn parse_quantity(s: &str) -> Option<f32> {
+    s.replace(',', "").parse::<f32>().ok()
+}
+
+/// Parse an amount in any of the usual forms: "10 VTI", "VTI 10", "$-5",
+/// or "-$5"
+fn parse_amount(s: &str) -> Option<Amount> {
+    let s = s.trim();
+    let (negative, s) = match s.strip_prefix('-') {
+        Some(rest) => (true, rest.trim_start()),
+        None => (false, s),
+    };
+    let amount = if let Some(rest) = s.strip_prefix('$') {
+        Amount {
+            quantity: parse_quantity(rest.trim())?,
+            commodity: commodity_name("$"),
+        }
+    } else {
+        let mut parts = s.split_whitespace();
+        let (first, second) = (parts.next()?, parts.next()?);
+        if parts.next().is_some() {
+            return None;
+        }
+        match parse_quantity(first) {
+            Some(quantity) => Amount {
+                quantity,
+                commodity: commodity_name(second),
+            },
+            None => Amount {
+                quantity: parse_quantity(second)?,
+                commodity: commodity_name(first),
+            },
+        }
+    };
+    Some(Amount {
+        quantity: if negative {
+            -amount.quantity
+        } else {
+            amount.quantity
+        },
+        ..amount
+    })
+}
+
+/// A single posting to an account within a transaction
+#[derive(Debug)]
+struct Posting {
+    account: String,
+    amount: Option<Amount>,
+    /// The price of one unit of the amount, from its cost or price annotation
+    price: Option<Amount>,
+}
+
+impl Posting {
+    /// The amount that this posting contributes to the balance of the
+    /// transaction
+    fn weight(&self) -> Option<Amount> {
+        let amount = self.amount.as_ref()?;
+        Some(match &self.price {
+            Some(price) => Amount {
+                quantity: amount.quantity * price.quantity,
+                commodity: price.commodity.clone(),
+            },
+            None => amount.clone(),
+        })
+    }
+}
+
+/// Parse a posting line such as `Assets:Brokerage  10 VTI {230.00 USD}` or
+/// `assets:brokerage   10 VTI @ $230`
+fn parse_posting(line: &str) -> anyhow::Result<Posting> {
+    // drop any comment and balance assertion
+    let line = line.split(';').next().unwrap_or_default();
+    let line = line.split('=').next().unwrap_or_default().trim();
+    // beancount postings may be flagged
+    let line = line
+        .strip_prefix("* ")
+        .or_else(|| line.strip_prefix("! "))
+        .unwrap_or(line);
+    // hledger account names may contain single spaces, so the amount is
+    // separated by at least two spaces or a tab
+    let split = line
+        .find("  ")
+        .or_else(|| line.find('\t'))
+        .or_else(|| line.find(char::is_whitespace));
+    let Some(split) = split else {
+        return Ok(Posting {
+            account: line.to_string(),
+            amount: None,
+            price: None,
+        });
+    };
+    let account = line[..split].trim().to_string();
+    let rest = line[split..].trim();
+    let (amount, annotation) = match rest.find(['{', '@']) {
+        Some(pos) => (&rest[..pos], Some(&rest[pos..])),
+        None => (rest, None),
+    };
+    let amount = parse_amount(amount)
+        .ok_or_else(|| anyhow!("Failed to parse amount '{amount}' for account {account}"))?;
+    let price = match annotation {
+        None => None,
+        Some(annotation) => {
+            // e.g. "{230.00 USD}", "{230.00 USD, 2025-01-02}", "@ $230" or "@@ $2300"
+            let total = annotation.starts_with("@@");
+            let annotation = annotation.trim_start_matches(['{', '@']);
+            let annotation = annotation
+                .split(['}', ',', '@'])
+                .next()
+                .unwrap_or_default()
+                .trim();
+            parse_amount(annotation).map(|price| match total {
+                true if amount.quantity != 0.0 => Amount {
+                    quantity: price.quantity / amount.quantity.abs(),
+                    ..price
+                },
+                _ => price,
+            })
+        }
+    };
+    Ok(Posting {
+        account,
+        amount: Some(amount),
+        price,
+    })
+}
+
+/// The most recent price of each commodity, keyed by commodity
+#[derive(Debug, Default)]
+struct Prices {
+    prices: HashMap<String, (String, Amount)>,
+}
+
+impl Prices {
+    fn insert(&mut self, date: &str, commodity: &str, price: Amount) {
+        let date = date.replace('/', "-");
+        match self.prices.get(commodity) {
+            Some((latest, _)) if *latest > date => {}
+            _ => {
+                self.prices.insert(commodity.to_string(), (date, price));
+            }
+        }
+    }
+
+    fn get(&self, commodity: &str) -> Option<&Amount> {
+        self.prices.get(commodity).map(|(_, price)| price)
+    }
+}
+
+/// Whether `line` begins with a date like "2025-01-15" or "2025/01/15"
+fn starts_with_date(line: &str) -> bool {
+    let date: String = line.chars().take(10).collect();
+    date.len() == 10
+        && date.chars().enumerate().all(|(i, c)| match i {
+            4 | 7 => c == '-' || c == '/',
+            _ => c.is_ascii_digit(),
+        })
+}
+
+/// Find the account that the holdings in `account` should be reported in
+fn rollup(account: &str, commodity: &str, base_currency: &str) -> String {
+    match account.rsplit_once(':') {
+        Some((parent, name))
+            if name.eq_ignore_ascii_case(commodity)
+                || (commodity == base_currency && name.eq_ignore_ascii_case(CASH_ACCOUNT)) =>
+        {
+            parent.to_string()
+        }
+        _ => account.to_string(),
+    }
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "ledger"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid beancount or ledger file.");
+        }
+        let mut base_currency = DEFAULT_CURRENCY.to_string();
+        let mut prices = Prices::default();
+        // the quantity of each commodity held in each account
+        let mut quantities = Vec::<(String, String, f32)>::new();
+        let mut transaction: Option<(String, Vec<Posting>)> = None;
+
+        let mut finish_transaction = |transaction: Option<(String, Vec<Posting>)>,
+                                      prices: &mut Prices| {
+            let Some((date, postings)) = transaction else {
+                return Ok::<_, anyhow::Error>(());
+            };
+            // an amount may be left out of one posting, which then
+            // balances the transaction
+            let mut residual = Vec::<Amount>::new();
+            for weight in postings.iter().filter_map(Posting::weight) {
+                match residual
+                    .iter_mut()
+                    .find(|a| a.commodity == weight.commodity)
+                {
+                    Some(a) => a.quantity += weight.quantity,
+                    None => residual.push(weight),
+                }
+            }
+            for posting in postings.iter() {
+                let amounts = match &posting.amount {
+                    Some(amount) => vec![amount.clone()],
+                    None => residual
+                        .iter()
+                        .map(|a| Amount {
+                            quantity: -a.quantity,
+                            commodity: a.commodity.clone(),
+                        })
+                        .collect(),
+                };
+                if let (Some(amount), Some(price)) = (&posting.amount, &posting.price) {
+                    prices.insert(&date, &amount.commodity, price.clone());
+                }
+                for amount in amounts {
+                    match quantities.iter_mut().find(|(account, commodity, _)| {
+                        *account == posting.account && *commodity == amount.commodity
+                    }) {
+                        Some((_, _, quantity)) => *quantity += amount.quantity,
+                        None => quantities.push((
+                            posting.account.clone(),
+                            amount.commodity,
+                            amount.quantity,
+                        )),
+                    }
+                }
+            }
+            Ok(())
+        };
+
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            let trimmed = line.trim_start();
+            if trimmed.is_empty() || trimmed.starts_with([';', '#', '%', '|']) {
+                continue;
+            }
+            if line.starts_with(char::is_whitespace) {
+                if let Some((_, postings)) = transaction.as_mut() {
+                    postings.push(parse_posting(trimmed)?);
+                }
+                continue;
+            }
+            finish_transaction(transaction.take(), &mut prices)?;
+            let words: Vec<&str> = line.split_whitespace().collect();
+            match words.as_slice() {
+                // beancount: option "operating_currency" "USD"
+                ["option", name, value, ..] if name.trim_matches('"') == "operating_currency" => {
+                    base_currency = commodity_name(value);
+                }
+                // ledger: P 2025/01/15 VTI $240, optionally with a time
+                ["P", date, rest @ ..] => {
+                    let rest = match rest.first() {
+                        Some(time) if time.contains(':') => &rest[1..],
+                        _ => rest,
+                    };
+                    if let Some((commodity, price)) = rest.split_first()
+                        && let Some(price) = parse_amount(&price.join(" "))
+                    {
+                        prices.insert(date, &commodity_name(commodity), price);
+                    }
+                }
+                // beancount: 2025-01-15 price VTI 240.00 USD
+                [date, "price", commodity, price @ ..] if starts_with_date(date) => {
+                    if let Some(price) = parse_amount(&price.join(" ")) {
+                        prices.insert(date, &commodity_name(commodity), price);
+                    }
+                }
+                // other dated beancount directives
+                [
+                    date,
+                    "open" | "close" | "balance" | "pad" | "note" | "document" | "event"
+                    | "commodity" | "custom" | "query",
+                    ..,
+                ] if starts_with_date(date) => {}
+                [date, ..] if starts_with_date(date) => {
+                    transaction = Some((date.to_string(), Vec::new()));
+                }
+                _ => debug!(line, "Ignoring line"),
+            }
+        }
+        finish_transaction(transaction.take(), &mut prices)?;
+
+        let mut accounts = Vec::<Balance>::new();
+        for (account, commodity, quantity) in quantities {
+            let is_asset = account
+                .split(':')
+                .next()
+                .is_some_and(|top| top.eq_ignore_ascii_case(ASSETS));
+            if !is_asset || quantity.abs() < MIN_QUANTITY {
+                continue;
+            }
+            let is_cash = commodity == base_currency;
+            let value = if is_cash {
+                quantity
+            } else {
+                match prices.get(&commodity) {
+                    Some(price) if price.commodity == base_currency => quantity * price.quantity,
+                    _ => {
+                        warnings.push(format!(
+                            "No {base_currency} price found for {commodity} in {account}, so it is valued at $0.00"
+                        ));
+                        0.0
+                    }
+                }
+            };
+            let account_id = rollup(&account, &commodity, &base_currency);
+            let balance = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.clone(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            match balance.holdings.iter_mut().find(|h| h.symbol == commodity) {
+                Some(holding) => holding.current_value += Dollar(value),
+                None => balance.holdings.push(Holding {
+                    symbol: commodity,
+                    current_value: Dollar(value),
+                    is_cash,
+                    ..Default::default()
+                }),
+            }
+        }
+        // accounts that only hold cash are bank accounts rather than
+        // investment accounts
+        accounts.retain(|a| a.holdings.iter().any(|h| !h.is_cash));
+        for account in accounts.iter_mut() {
+            for holding in account.holdings.iter_mut().filter(|h| h.is_cash) {
+                holding.current_value = holding.current_value.round_cents();
+            }
+        }
+        debug!(?accounts, "parsed ledger");
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        let mut lines = sample
+            .lines()
+            .map(|line| line.trim_start_matches('\u{feff}'))
+            .filter(|line| !line.trim().is_empty() && !line.trim_start().starts_with([';', '#']));
+        let looks_like_directive = |line: &str| {
+            (starts_with_date(line) && !line.contains(','))
+                || line.starts_with("P ")
+                || line.starts_with("option ")
+                || line.starts_with("include ")
+                || line.starts_with("commodity ")
+        };
+        Ok(lines.next().is_some_and(looks_like_directive))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_posting() {
+        let posting = parse_posting("Assets:Brokerage  10 VTI {230.00 USD, 2025-01-02}").unwrap();
+        assert_eq!(posting.account, "Assets:Brokerage");
+        assert_eq!(posting.weight().unwrap().quantity, 2300.0);
+        assert_eq!(posting.weight().unwrap().commodity, "USD");
+
+        let posting = parse_posting("assets:my brokerage   -$2,300.00 ; comment").unwrap();
+        assert_eq!(posting.account, "assets:my brokerage");
+        assert_eq!(
+            posting.amount,
+            Some(Amount {
+                quantity: -2300.0,
+                commodity: "USD".to_string()
+            })
+        );
+
+        let posting = parse_posting("assets:brokerage  4 VTI @@ $960").unwrap();
+        assert_eq!(posting.price.unwrap().quantity, 240.0);
+
+        let posting = parse_posting("Assets:Brokerage:Cash").unwrap();
+        assert!(posting.amount.is_none());
+    }
+}
@@ -0,0 +1,185 @@
+//! Support for CSV exports that don't follow US conventions, e.g. files from
+//! European brokerages that separate fields with semicolons and write
+//! "1.234,56 €" for 1234.56 euros. Such files are rewritten to use commas
+//! between fields and plain decimal numbers, so that they can be parsed by the
+//! same providers as any other file.
+
+use serde::{Deserialize, Serialize};
+use tracing::debug;
+
+/// Characters that are used to group the digits of large numbers, in
+/// addition to whichever of '.' and ',' isn't the decimal separator
+const GROUPING_CHARACTERS: &[char] = &[' ', '\u{a0}', '\u{202f}', '\''];
+/// Currency symbols that are always removed from numbers
+const DEFAULT_CURRENCY_SYMBOLS: &[&str] = &["$"];
+
+/// How fields and numbers are written in a CSV file
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq)]
+#[serde(default, deny_unknown_fields)]
+pub struct CsvFormat {
+    /// The character that separates fields (e.g. ';')
+    pub delimiter: char,
+    /// The character that separates the whole and fractional parts of a
+    /// number (e.g. ',')
+    pub decimal_separator: char,
+    /// Currency symbols or codes that may appear before or after values,
+    /// besides '$' (e.g. "€" or "EUR")
+    pub currency_symbols: Vec<String>,
+}
+
+impl Default for CsvFormat {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            decimal_separator: '.',
+            currency_symbols: Vec::new(),
+        }
+    }
+}
+
+impl CsvFormat {
+    /// Rewrite a single field as a plain number (e.g. "-1234.56"), if it is a
+    /// number in this format. Other fields are returned unchanged.
+    fn normalize_field<'a>(&self, field: &'a str) -> std::borrow::Cow<'a, str> {
+        self.normalize_number(field)
+            .map(std::borrow::Cow::Owned)
+            .unwrap_or(std::borrow::Cow::Borrowed(field))
+    }
+
+    fn normalize_number(&self, field: &str) -> Option<String> {
+        let mut s = field.trim();
+        let mut negative = false;
+        if let Some(inner) = s.strip_prefix('(').and_then(|s| s.strip_suffix(')')) {
+            negative = true;
+            s = inner.trim();
+        }
+        let symbols = DEFAULT_CURRENCY_SYMBOLS
+            .iter()
+            .copied()
+            .chain(self.currency_symbols.iter().map(String::as_str));
+        // the sign and currency symbol can come in either order, and the
+        // symbol can come at either end
+        for _ in 0..2 {
+            if let Some(rest) = s.strip_prefix('-') {
+                negative = !negative;
+                s = rest.trim_start();
+            } else if let Some(rest) = s.strip_prefix('+') {
+                s = rest.trim_start();
+            }
+            for symbol in symbols.clone() {
+                if let Some(rest) = s.strip_prefix(symbol) {
+                    s = rest.trim_start();
+                }
+                if let Some(rest) = s.strip_suffix(symbol) {
+                    s = rest.trim_end();
+                }
+            }
+        }
+        if let Some(rest) = s.strip_suffix('-') {
+            negative = !negative;
+            s = rest.trim_end();
+        }
+        let (s, percent) = match s.strip_suffix('%') {
+            Some(rest) => (rest.trim_end(), "%"),
+            None => (s, ""),
+        };
+        let grouping = match self.decimal_separator {
+            ',' => '.',
+            _ => ',',
+        };
+        let (whole, fraction) = match s.split_once(self.decimal_separator) {
+            Some((whole, fraction)) => (whole, Some(fraction)),
+            None => (s, None),
+        };
+        let is_digits = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+        // every group of digits after the first must have three digits, so
+        // that things like dates ("15.01.2025") aren't mistaken for numbers
+        let mut groups = whole.split(|c| c == grouping || GROUPING_CHARACTERS.contains(&c));
+        let first = groups.next()?;
+        let mut digits = first.to_string();
+        if !is_digits(first) || first.len() > 3 && whole.len() != first.len() {
+            return None;
+        }
+        for group in groups {
+            if group.len() != 3 || !is_digits(group) {
+                return None;
+            }
+            digits.push_str(group);
+        }
+        if let Some(fraction) = fraction {
+            if !is_digits(fraction) {
+                return None;
+            }
+            digits.push('.');
+            digits.push_str(fraction);
+        }
+        let sign = if negative { "-" } else { "" };
+        Some(format!("{sign}{digits}{percent}"))
+    }
+
+    /// Rewrite a CSV file in this format as a comma-separated file with
+    /// plain decimal numbers. Files whose first line doesn't contain the
+    /// delimiter (e.g. XML or JSON files) and spreadsheets are returned
+    /// unchanged, so a format can be configured once without breaking imports
+    /// of other files.
+    pub fn normalize(&self, data: &[u8]) -> anyhow::Result<Vec<u8>> {
+        let Ok(delimiter) = u8::try_from(self.delimiter) else {
+            anyhow::bail!("The CSV delimiter must be a single-byte character");
+        };
+        let first_line = data.split(|b| *b == b'\n').find(|line| !line.is_empty());
+        if *self == Self::default()
+            || data.starts_with(super::ZIP_SIGNATURE)
+            || !first_line.is_some_and(|line| line.contains(&delimiter))
+        {
+            return Ok(data.to_vec());
+        }
+        let mut reader = csv::ReaderBuilder::new()
+            .delimiter(delimiter)
+            .has_headers(false)
+            .flexible(true)
+            .from_reader(data);
+        let mut writer = csv::WriterBuilder::new()
+            .flexible(true)
+            .from_writer(Vec::new());
+        for record in reader.records() {
+            let record = record?;
+            writer.write_record(
+                record
+                    .iter()
+                    .map(|field| self.normalize_field(field).into_owned()),
+            )?;
+        }
+        let normalized = writer.into_inner()?;
+        debug!(
+            normalized = %String::from_utf8_lossy(&normalized),
+            "normalized CSV file"
+        );
+        Ok(normalized)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_number() {
+        let format = CsvFormat {
+            delimiter: ';',
+            decimal_separator: ',',
+            currency_symbols: vec!["€".to_string(), "EUR".to_string()],
+        };
+        let normalize = |s| format.normalize_number(s);
+        assert_eq!(normalize("1.234,56 €"), Some("1234.56".to_string()));
+        assert_eq!(normalize("-€ 1.234.567,8"), Some("-1234567.8".to_string()));
+        assert_eq!(normalize("EUR 12 345,00"), Some("12345.00".to_string()));
+        assert_eq!(normalize("(5,00)"), Some("-5.00".to_string()));
+        assert_eq!(normalize("4,4 %"), Some("4.4%".to_string()));
+        assert_eq!(normalize("0,05"), Some("0.05".to_string()));
+        assert_eq!(normalize("12345678"), Some("12345678".to_string()));
+        assert_eq!(normalize("15.01.2025"), None);
+        assert_eq!(normalize("1234.567"), None);
+        assert_eq!(normalize("DE12345"), None);
+        assert_eq!(normalize(""), None);
+    }
+}
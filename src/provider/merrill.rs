@@ -0,0 +1,131 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const TITLE_PREFIX: &str = "Exported on:";
+const ACCOUNT_PREFIX: &str = "Account:";
+const SYMBOL_HEADER: &str = "Symbol";
+const VALUE_HEADER: &str = "Value";
+/// Merrill lists its money market sweep using a description rather than a
+/// ticker symbol
+const SWEEP_MARKERS: &[&str] = &["Money Market", "Cash Sweep", "ML Bank Deposit"];
+const SWEEP_SYMBOL: &str = "CASH";
+const TOTAL_PREFIX: &str = "Total";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// Split an account header like "Account: CMA-Edge 12X-34567" into an account
+/// name and an account number
+fn parse_account_header(header: &str) -> (String, String) {
+    let header = header.trim_start_matches(ACCOUNT_PREFIX).trim();
+    match header.rsplit_once(char::is_whitespace) {
+        Some((name, id)) => (name.trim().to_string(), id.to_string()),
+        None => (String::new(), header.to_string()),
+    }
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "merrill-edge"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Merrill Edge CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .has_headers(false)
+            .from_reader(reader);
+        let mut accounts = Vec::<Balance>::new();
+        // (symbol column, description column, value column) for the current account
+        let mut columns: Option<(usize, usize, usize)> = None;
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let first = row.get(0).unwrap_or_default().trim();
+            if first.starts_with(TITLE_PREFIX) || first.is_empty() {
+                continue;
+            }
+            if first.starts_with(ACCOUNT_PREFIX) {
+                let (account_name, account_id) = parse_account_header(first);
+                debug!(account_name, account_id, "found account header");
+                accounts.push(Balance {
+                    account_id,
+                    account_name,
+                    ..Default::default()
+                });
+                columns = None;
+                continue;
+            }
+            if first == SYMBOL_HEADER {
+                let find = |name: &str| {
+                    row.iter()
+                        .position(|header| header.trim() == name)
+                        .ok_or_else(|| anyhow!("Failed to find '{name}' column"))
+                };
+                columns = Some((0, find("Description")?, find(VALUE_HEADER)?));
+                continue;
+            }
+            let Some((symbol_column, description_column, value_column)) = columns else {
+                debug!(?row, "Skipping row outside of an account section");
+                continue;
+            };
+            if first.starts_with(TOTAL_PREFIX) {
+                continue;
+            }
+            let Some(acct) = accounts.last_mut() else {
+                bail!("Found a position before any account header");
+            };
+            let description = row.get(description_column).unwrap_or_default();
+            let is_sweep = SWEEP_MARKERS
+                .iter()
+                .any(|marker| first.contains(marker) || description.contains(marker));
+            let symbol = row
+                .get(symbol_column)
+                .map(str::trim)
+                .ok_or_else(|| anyhow!("Failed to get symbol"))?;
+            let current_value = row
+                .get(value_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get value for symbol '{symbol}'"))?;
+            let holding = Holding {
+                symbol: match is_sweep {
+                    true => SWEEP_SYMBOL.to_string(),
+                    false => symbol.to_string(),
+                },
+                current_value,
+                is_cash: is_sweep,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        let first_line = sample
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .find(|line| !line.trim().is_empty())
+            .unwrap_or_default();
+        Ok(first_line.trim_matches('"').starts_with(TITLE_PREFIX))
+    }
+}
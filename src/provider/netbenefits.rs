@@ -0,0 +1,113 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const PLAN_NUMBER_HEADER: &str = "Plan Number";
+const PLAN_NAME_HEADER: &str = "Plan Name";
+const INVESTMENT_HEADER: &str = "Investment Name";
+const BALANCE_HEADER: &str = "Total Balance";
+const REQUIRED_HEADERS: &[&str] = &[
+    PLAN_NUMBER_HEADER,
+    PLAN_NAME_HEADER,
+    INVESTMENT_HEADER,
+    BALANCE_HEADER,
+];
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "net-benefits"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Fidelity NetBenefits CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| anyhow!("Missing '{name}' column"))
+        };
+        let plan_number_column = column(PLAN_NUMBER_HEADER)?;
+        let plan_name_column = column(PLAN_NAME_HEADER)?;
+        let investment_column = column(INVESTMENT_HEADER)?;
+        let balance_column = column(BALANCE_HEADER)?;
+
+        let mut accounts = Vec::<Balance>::new();
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let plan_number = row.get(plan_number_column).unwrap_or_default().trim();
+            // the export ends with a few lines of disclaimers
+            if plan_number.is_empty() || row.len() <= balance_column {
+                debug!(?row, "Row doesn't have enough fields to be a position");
+                continue;
+            }
+            let acct = match accounts.iter().position(|a| a.account_id == plan_number) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: plan_number.to_string(),
+                        account_name: row.get(plan_name_column).unwrap_or_default().to_string(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            // workplace plans often offer funds without a ticker symbol, so
+            // funds are identified by their name
+            let name = row.get(investment_column).unwrap_or_default().trim();
+            if name.is_empty() {
+                bail!("Failed to get investment name for plan '{plan_number}'");
+            }
+            let current_value = row
+                .get(balance_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get balance for '{name}'"))?;
+            // a fund is listed once for each contribution source (e.g.
+            // employee deferrals and employer match) that holds it
+            if let Some(existing) = acct.holdings.iter_mut().find(|h| h.symbol == name) {
+                debug!(?acct.account_id, name, ?current_value, "adding to existing fund");
+                existing.current_value += current_value;
+                continue;
+            }
+            let holding = Holding {
+                symbol: name.to_string(),
+                current_value,
+                is_cash: false,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(sample);
+        let headers = csv_reader.headers()?;
+        debug!(?headers);
+        Ok(REQUIRED_HEADERS
+            .iter()
+            .all(|expected| headers.iter().any(|h| h.trim() == *expected)))
+    }
+}
@@ -0,0 +1,185 @@
+//! Open Financial Exchange (OFX) investment statements, which are also used by
+//! Quicken's QFX files. Both the SGML-based OFX 1.x format (where elements that
+//! contain a value don't have a closing tag) and the XML-based OFX 2.x format
+//! are supported.
+
+use std::{collections::HashMap, io::BufRead};
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    provider::{
+        BalanceProvider,
+        xml::{self, Token},
+    },
+};
+
+const SGML_HEADER: &str = "OFXHEADER:";
+const ROOT_ELEMENT: &str = "<OFX>";
+const CASH_SYMBOL: &str = "CASH";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// A position from an `INVPOSLIST`, which identifies the security by its
+/// CUSIP (or other unique ID) rather than by its ticker
+#[derive(Debug, Default)]
+struct Position {
+    unique_id: String,
+    value: Option<Dollar>,
+}
+
+#[derive(Debug, Default)]
+struct Statement {
+    account_id: String,
+    positions: Vec<Position>,
+    cash: Option<Dollar>,
+}
+
+fn parse_value(element: &str, value: &str) -> anyhow::Result<Dollar> {
+    value
+        .trim()
+        .parse::<Dollar>()
+        .map_err(|_| anyhow!("Invalid value '{value}' for {element}"))
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "ofx"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid OFX file.");
+        }
+        let mut doc = String::new();
+        reader.read_to_string(&mut doc)?;
+        let tokens = xml::tokenize(&doc)?;
+
+        let mut statements = Vec::<Statement>::new();
+        // maps the unique IDs of securities to their ticker symbols
+        let mut tickers = HashMap::<String, String>::new();
+        let mut statement: Option<Statement> = None;
+        let mut position: Option<Position> = None;
+        let mut security: Option<(Option<String>, Option<String>)> = None;
+        let mut element = "";
+        for token in tokens.iter() {
+            match token {
+                Token::Start { name, .. } => {
+                    element = name.as_str();
+                    match element {
+                        "INVSTMTRS" => statement = Some(Statement::default()),
+                        "INVPOS" => position = Some(Position::default()),
+                        "SECINFO" => security = Some((None, None)),
+                        _ => {}
+                    }
+                }
+                Token::End { name } => {
+                    element = "";
+                    match name.as_str() {
+                        "INVPOS" => {
+                            if let (Some(stmt), Some(pos)) = (statement.as_mut(), position.take()) {
+                                debug!(?pos, "found position");
+                                stmt.positions.push(pos);
+                            }
+                        }
+                        "INVSTMTRS" => statements.extend(statement.take()),
+                        "SECINFO" => {
+                            if let Some((Some(id), Some(ticker))) = security.take() {
+                                tickers.insert(id, ticker);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                Token::Text(value) => {
+                    let value = value.trim();
+                    match (element, statement.as_mut()) {
+                        ("ACCTID", Some(stmt)) if position.is_none() => {
+                            stmt.account_id = value.to_string()
+                        }
+                        ("UNIQUEID", _) if position.is_some() => {
+                            position.as_mut().unwrap().unique_id = value.to_string()
+                        }
+                        ("UNIQUEID", _) if security.is_some() => {
+                            security.as_mut().unwrap().0 = Some(value.to_string())
+                        }
+                        ("TICKER", _) if security.is_some() => {
+                            security.as_mut().unwrap().1 = Some(value.to_string())
+                        }
+                        ("MKTVAL", _) if position.is_some() => {
+                            position.as_mut().unwrap().value = Some(parse_value(element, value)?)
+                        }
+                        ("AVAILCASH", Some(stmt)) => stmt.cash = Some(parse_value(element, value)?),
+                        _ => {}
+                    }
+                    // in SGML files, elements with values aren't closed
+                    element = "";
+                }
+            }
+        }
+
+        let mut accounts = Vec::new();
+        for stmt in statements {
+            if stmt.account_id.is_empty() {
+                bail!("Investment statement is missing an account ID");
+            }
+            let mut account = Balance {
+                account_id: stmt.account_id,
+                ..Default::default()
+            };
+            for pos in stmt.positions {
+                let symbol = match tickers.get(&pos.unique_id) {
+                    Some(ticker) => ticker.clone(),
+                    None => {
+                        warnings.push(format!(
+                            "No ticker found for security '{}' in account {}, so it is identified by its ID",
+                            pos.unique_id, account.account_id
+                        ));
+                        pos.unique_id.clone()
+                    }
+                };
+                let current_value = pos
+                    .value
+                    .ok_or_else(|| anyhow!("Position '{symbol}' is missing its market value"))?;
+                match account.holdings.iter_mut().find(|h| h.symbol == symbol) {
+                    // e.g. the same security held both in cash and on margin
+                    Some(existing) => existing.current_value += current_value,
+                    None => account.holdings.push(Holding {
+                        symbol,
+                        current_value,
+                        is_cash: false,
+                        ..Default::default()
+                    }),
+                }
+            }
+            if let Some(cash) = stmt.cash {
+                account.holdings.push(Holding {
+                    symbol: CASH_SYMBOL.to_string(),
+                    current_value: cash,
+                    is_cash: true,
+                    ..Default::default()
+                });
+            }
+            debug!(?account, "parsed account");
+            accounts.push(account);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        Ok(sample.trim_start().starts_with(SGML_HEADER) || sample.contains(ROOT_ELEMENT))
+    }
+}
@@ -0,0 +1,408 @@
+//! The XML data files saved by Portfolio Performance
+//! (<https://www.portfolio-performance.info>).
+//!
+//! The file contains transactions rather than positions, so the shares of each
+//! security held in a securities account (a "portfolio") are calculated by
+//! adding up its transactions, and they are valued at the latest price stored
+//! in the file. The cash in a portfolio's reference account is imported as a
+//! cash holding. Deposit accounts that aren't the reference account of any
+//! portfolio are left out.
+//!
+//! The file is written by XStream, which writes an object that appears more
+//! than once in full the first time and as a `reference` to that element
+//! afterward. References are either an XPath-like path relative to the
+//! referencing element or, in files saved with IDs, the `id` of the element.
+
+use std::{collections::HashMap, io::BufRead};
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    provider::{
+        BalanceProvider,
+        xml::{self, Token},
+    },
+};
+
+const ROOT_ELEMENT: &str = "<client>";
+/// Share counts are stored as integers, scaled by this factor
+const SHARES_FACTOR: f64 = 100_000_000.0;
+/// Prices are stored as integers, scaled by this factor
+const QUOTE_FACTOR: f64 = 100_000_000.0;
+/// Amounts of money are stored as integers, scaled by this factor
+const AMOUNT_FACTOR: f64 = 100.0;
+/// Portfolio transaction types that add shares to the portfolio
+const INBOUND: &[&str] = &["BUY", "TRANSFER_IN", "DELIVERY_INBOUND"];
+/// Portfolio transaction types that remove shares from the portfolio
+const OUTBOUND: &[&str] = &["SELL", "TRANSFER_OUT", "DELIVERY_OUTBOUND"];
+/// Account transaction types that add cash to the account
+const CREDITS: &[&str] = &[
+    "DEPOSIT",
+    "INTEREST",
+    "DIVIDENDS",
+    "SELL",
+    "TRANSFER_IN",
+    "TAX_REFUND",
+    "FEES_REFUND",
+];
+/// Account transaction types that remove cash from the account
+const DEBITS: &[&str] = &[
+    "REMOVAL",
+    "INTEREST_CHARGE",
+    "BUY",
+    "TRANSFER_OUT",
+    "FEES",
+    "TAXES",
+];
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// An element of the XML document
+#[derive(Debug, Default)]
+struct Element {
+    name: String,
+    attributes: Vec<(String, String)>,
+    text: String,
+    parent: Option<usize>,
+    children: Vec<usize>,
+}
+
+/// The XML document as a tree of elements, stored in a flat list so that
+/// references can move to an element's parent
+#[derive(Debug, Default)]
+struct Document {
+    elements: Vec<Element>,
+    ids: HashMap<String, usize>,
+}
+
+impl Document {
+    fn parse(doc: &str) -> anyhow::Result<Self> {
+        let mut document = Document::default();
+        let mut open = Vec::<usize>::new();
+        for token in xml::tokenize(doc)? {
+            match token {
+                Token::Start { name, attributes } => {
+                    let index = document.elements.len();
+                    if let Some((_, id)) = attributes.iter().find(|(name, _)| name == "id") {
+                        document.ids.insert(id.clone(), index);
+                    }
+                    let parent = open.last().copied();
+                    if let Some(parent) = parent {
+                        document.elements[parent].children.push(index);
+                    }
+                    document.elements.push(Element {
+                        name,
+                        attributes,
+                        parent,
+                        ..Default::default()
+                    });
+                    open.push(index);
+                }
+                Token::End { name } => match open.pop() {
+                    Some(index) if document.elements[index].name == name => {}
+                    _ => bail!("Unexpected closing tag '{name}'"),
+                },
+                Token::Text(text) => {
+                    if let Some(index) = open.last() {
+                        document.elements[*index].text.push_str(&text);
+                    }
+                }
+            }
+        }
+        if document.elements.is_empty() {
+            bail!("The file doesn't contain any elements");
+        }
+        Ok(document)
+    }
+
+    fn attribute(&self, index: usize, key: &str) -> Option<&str> {
+        self.elements[index]
+            .attributes
+            .iter()
+            .find(|(name, _)| name == key)
+            .map(|(_, value)| value.as_str())
+    }
+
+    /// Follow the `reference` attribute of an element, if it has one
+    fn resolve(&self, index: usize) -> anyhow::Result<usize> {
+        let Some(reference) = self.attribute(index, "reference") else {
+            return Ok(index);
+        };
+        if let Some(target) = self.ids.get(reference) {
+            return Ok(*target);
+        }
+        let mut current = index;
+        for step in reference.split('/') {
+            current = match step {
+                "" | "." => Some(current),
+                ".." => self.elements[current].parent,
+                _ => {
+                    // e.g. "security[2]", where the index starts at one
+                    let (name, position) =
+                        match step.strip_suffix(']').and_then(|s| s.split_once('[')) {
+                            Some((name, position)) => (name, position.parse::<usize>()?),
+                            None => (step, 1),
+                        };
+                    self.elements[current]
+                        .children
+                        .iter()
+                        .copied()
+                        .filter(|child| self.elements[*child].name == name)
+                        .nth(position.saturating_sub(1))
+                }
+            }
+            .ok_or_else(|| anyhow!("Failed to resolve reference '{reference}'"))?;
+        }
+        Ok(current)
+    }
+
+    /// The (resolved) children of an element with the given name
+    fn children<'a>(
+        &'a self,
+        index: usize,
+        name: &'a str,
+    ) -> impl Iterator<Item = anyhow::Result<usize>> + 'a {
+        self.elements[index]
+            .children
+            .iter()
+            .filter(move |child| self.elements[**child].name == name)
+            .map(|child| self.resolve(*child))
+    }
+
+    /// The first (resolved) child of an element with the given name
+    fn child(&self, index: usize, name: &str) -> anyhow::Result<Option<usize>> {
+        self.children(index, name).next().transpose()
+    }
+
+    /// The text of the first child of an element with the given name
+    fn child_text(&self, index: usize, name: &str) -> anyhow::Result<Option<&str>> {
+        Ok(self
+            .child(index, name)?
+            .map(|child| self.elements[child].text.trim())
+            .filter(|text| !text.is_empty()))
+    }
+
+    fn is_retired(&self, index: usize) -> anyhow::Result<bool> {
+        Ok(self.child_text(index, "isRetired")? == Some("true"))
+    }
+
+    /// The integer value of a child element, divided by `factor`
+    fn scaled(&self, index: usize, name: &str, factor: f64) -> anyhow::Result<f64> {
+        match self.child_text(index, name)? {
+            Some(text) => Ok(text
+                .parse::<i64>()
+                .map_err(|_| anyhow!("Invalid value '{text}' for {name}"))?
+                as f64
+                / factor),
+            None => Ok(0.0),
+        }
+    }
+}
+
+/// The information about a security that is needed to value a position in it
+#[derive(Debug)]
+struct Security {
+    symbol: String,
+    currency: Option<String>,
+    price: Option<f64>,
+}
+
+fn parse_security(doc: &Document, index: usize) -> anyhow::Result<Security> {
+    let symbol = match doc.child_text(index, "tickerSymbol")? {
+        Some(symbol) => symbol,
+        None => doc
+            .child_text(index, "isin")?
+            .or(doc.child_text(index, "name")?)
+            .ok_or_else(|| anyhow!("Security has no ticker symbol, ISIN, or name"))?,
+    };
+    // the latest quote is stored separately from the price history, but
+    // isn't always present
+    let mut latest: Option<(&str, &str)> = doc
+        .child(index, "latest")?
+        .and_then(|latest| Some((doc.attribute(latest, "t")?, doc.attribute(latest, "v")?)));
+    if let Some(prices) = doc.child(index, "prices")? {
+        for price in doc.children(prices, "price") {
+            let price = price?;
+            if let (Some(date), Some(value)) =
+                (doc.attribute(price, "t"), doc.attribute(price, "v"))
+                && latest.is_none_or(|(latest, _)| latest <= date)
+            {
+                latest = Some((date, value));
+            }
+        }
+    }
+    let price = latest
+        .map(|(_, value)| {
+            value
+                .parse::<i64>()
+                .map(|value| value as f64 / QUOTE_FACTOR)
+                .map_err(|_| anyhow!("Invalid price '{value}' for {symbol}"))
+        })
+        .transpose()?;
+    Ok(Security {
+        symbol: symbol.to_string(),
+        currency: doc.child_text(index, "currencyCode")?.map(str::to_string),
+        price,
+    })
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "portfolio-performance"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Portfolio Performance XML file.");
+        }
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let doc = Document::parse(&text)?;
+        let client = 0;
+        let base_currency = doc.child_text(client, "baseCurrency")?.map(str::to_string);
+
+        let mut accounts = Vec::<Balance>::new();
+        let Some(portfolios) = doc.child(client, "portfolios")? else {
+            return Ok(accounts);
+        };
+        for portfolio in doc.children(portfolios, "portfolio") {
+            let portfolio = portfolio?;
+            if doc.is_retired(portfolio)? {
+                continue;
+            }
+            let name = doc.child_text(portfolio, "name")?.unwrap_or_default();
+            let mut balance = Balance {
+                account_id: name.to_string(),
+                account_name: name.to_string(),
+                ..Default::default()
+            };
+
+            // the number of shares of each security, in the order that they
+            // were first bought
+            let mut shares = Vec::<(usize, f64)>::new();
+            if let Some(transactions) = doc.child(portfolio, "transactions")? {
+                for transaction in doc.children(transactions, "portfolio-transaction") {
+                    let transaction = transaction?;
+                    let kind = doc.child_text(transaction, "type")?.unwrap_or_default();
+                    let sign = if INBOUND.contains(&kind) {
+                        1.0
+                    } else if OUTBOUND.contains(&kind) {
+                        -1.0
+                    } else {
+                        warnings.push(format!(
+                            "Unsupported transaction type '{kind}' in portfolio {name}"
+                        ));
+                        continue;
+                    };
+                    let Some(security) = doc.child(transaction, "security")? else {
+                        bail!("Transaction in portfolio {name} has no security");
+                    };
+                    let quantity = sign * doc.scaled(transaction, "shares", SHARES_FACTOR)?;
+                    match shares.iter_mut().find(|(s, _)| *s == security) {
+                        Some((_, total)) => *total += quantity,
+                        None => shares.push((security, quantity)),
+                    }
+                }
+            }
+            for (index, quantity) in shares {
+                // anything smaller than this is left over from rounding
+                if quantity.abs() < 1e-6 {
+                    continue;
+                }
+                let security = parse_security(&doc, index)?;
+                let value = match security.price {
+                    Some(price) => quantity * price,
+                    None => {
+                        warnings.push(format!(
+                            "No price found for {} in portfolio {name}, so it is valued at $0.00",
+                            security.symbol
+                        ));
+                        0.0
+                    }
+                };
+                if security.currency.is_some() && security.currency != base_currency {
+                    warnings.push(format!(
+                        "{} in portfolio {name} is valued in {}, not converted to {}",
+                        security.symbol,
+                        security.currency.as_deref().unwrap_or_default(),
+                        base_currency.as_deref().unwrap_or_default()
+                    ));
+                }
+                balance.holdings.push(Holding {
+                    symbol: security.symbol.clone(),
+                    current_value: Dollar(value as f32),
+                    is_cash: false,
+                    ..Default::default()
+                });
+            }
+
+            if let Some(account) = doc.child(portfolio, "referenceAccount")? {
+                let mut cash = 0.0;
+                if let Some(transactions) = doc.child(account, "transactions")? {
+                    for transaction in doc.children(transactions, "account-transaction") {
+                        let transaction = transaction?;
+                        let kind = doc.child_text(transaction, "type")?.unwrap_or_default();
+                        let amount = doc.scaled(transaction, "amount", AMOUNT_FACTOR)?;
+                        if CREDITS.contains(&kind) {
+                            cash += amount;
+                        } else if DEBITS.contains(&kind) {
+                            cash -= amount;
+                        } else {
+                            warnings.push(format!(
+                                "Unsupported transaction type '{kind}' in the cash account of {name}"
+                            ));
+                        }
+                    }
+                }
+                let currency = doc
+                    .child_text(account, "currencyCode")?
+                    .or(base_currency.as_deref())
+                    .unwrap_or("CASH");
+                balance.holdings.push(Holding {
+                    symbol: currency.to_string(),
+                    current_value: Dollar(cash as f32).round_cents(),
+                    is_cash: true,
+                    ..Default::default()
+                });
+            }
+            debug!(?balance, "parsed portfolio");
+            accounts.push(balance);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        let sample = sample.trim_start_matches('\u{feff}').trim_start();
+        Ok(sample.starts_with('<') && sample.contains(ROOT_ELEMENT))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resolve() {
+        let doc = Document::parse(
+            r#"<client><a><b>1</b><b>2</b></a><c><d reference="../../a/b[2]"/><e reference="7"/></c><f id="7">3</f></client>"#,
+        )
+        .unwrap();
+        let c = doc.child(0, "c").unwrap().unwrap();
+        let d = doc.child(c, "d").unwrap().unwrap();
+        assert_eq!(doc.elements[d].text, "2");
+        assert_eq!(doc.child_text(c, "e").unwrap(), Some("3"));
+    }
+}
@@ -0,0 +1,302 @@
+//! Quicken Interchange Format (QIF) investment accounts.
+//!
+//! Unlike the other formats, QIF files contain a list of transactions rather
+//! than the current positions. The holdings are reconstructed by replaying
+//! the transactions in each investment account, and each security is valued
+//! using the most recent price found in the file (either from its
+//! transactions or from a `!Type:Prices` section).
+
+use std::{collections::HashMap, io::BufRead};
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const INVESTMENT_TYPE: &str = "!Type:Invst";
+const DEFAULT_ACCOUNT_ID: &str = "qif";
+const CASH_SYMBOL: &str = "CASH";
+/// Shares that are left over from rounding in the transaction history are
+/// not reported as holdings
+const MIN_SHARES: f32 = 0.0001;
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// The kind of records that follow a `!` header line
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Section {
+    Accounts,
+    Investments,
+    Securities,
+    Prices,
+    Other,
+}
+
+/// The state of an investment account after replaying its transactions
+#[derive(Debug, Default)]
+struct AccountState {
+    id: String,
+    /// whether any investment transactions were found for this account, since
+    /// QIF files may also list bank and credit card accounts
+    is_investment: bool,
+    cash: f32,
+    /// the number of shares held of each security, in the order in which
+    /// the securities first appear
+    shares: Vec<(String, f32)>,
+}
+
+impl AccountState {
+    fn shares_mut(&mut self, security: &str) -> &mut f32 {
+        let pos = match self.shares.iter().position(|(s, _)| s == security) {
+            Some(pos) => pos,
+            None => {
+                self.shares.push((security.to_string(), 0.0));
+                self.shares.len() - 1
+            }
+        };
+        &mut self.shares[pos].1
+    }
+}
+
+/// The most recent price of each security, along with the order in which the
+/// prices were found. Prices may be listed by security name or by symbol, so
+/// the order is needed to tell which of the two is more recent.
+#[derive(Debug, Default)]
+struct Prices {
+    prices: HashMap<String, (usize, f32)>,
+    count: usize,
+}
+
+impl Prices {
+    fn insert(&mut self, security: &str, price: f32) {
+        self.count += 1;
+        self.prices
+            .insert(security.to_string(), (self.count, price));
+    }
+
+    fn latest(&self, name: &str, symbol: &str) -> Option<f32> {
+        [self.prices.get(name), self.prices.get(symbol)]
+            .into_iter()
+            .flatten()
+            .max_by_key(|(order, _)| *order)
+            .map(|(_, price)| *price)
+    }
+}
+
+fn parse_number(s: &str) -> Option<f32> {
+    parse_dollar(s).map(|d| d.0)
+}
+
+/// A single record (the lines between two `^` separators), keyed by the
+/// first character of each line
+type Record = Vec<(char, String)>;
+
+fn field(record: &Record, code: char) -> Option<&str> {
+    record
+        .iter()
+        .find(|(c, _)| *c == code)
+        .map(|(_, value)| value.as_str())
+}
+
+/// Update `account` with the effect of a single investment transaction
+fn apply_transaction(
+    account: &mut AccountState,
+    record: &Record,
+    prices: &mut Prices,
+    warnings: &mut Vec<String>,
+) {
+    let action = field(record, 'N').unwrap_or_default();
+    let security = field(record, 'Y').unwrap_or_default();
+    let quantity = field(record, 'Q')
+        .and_then(parse_number)
+        .unwrap_or_default();
+    let amount = field(record, 'T')
+        .or(field(record, 'U'))
+        .and_then(parse_number)
+        .unwrap_or_default();
+    if let Some(price) = field(record, 'I').and_then(parse_number)
+        && !security.is_empty()
+    {
+        prices.insert(security, price);
+    }
+    debug!(action, security, quantity, amount, "applying transaction");
+    match action {
+        "Buy" => {
+            *account.shares_mut(security) += quantity;
+            account.cash -= amount;
+        }
+        "BuyX" | "ShrsIn" | "ReinvDiv" | "ReinvInt" | "ReinvLg" | "ReinvMd" | "ReinvSh" => {
+            *account.shares_mut(security) += quantity
+        }
+        "Sell" => {
+            *account.shares_mut(security) -= quantity;
+            account.cash += amount;
+        }
+        "SellX" | "ShrsOut" => *account.shares_mut(security) -= quantity,
+        "StkSplit" => {
+            // the quantity is the ratio of new shares to old shares times 10
+            *account.shares_mut(security) *= quantity / 10.0
+        }
+        "Div" | "IntInc" | "CGLong" | "CGMid" | "CGShort" | "MiscInc" | "XIn" | "Cash" => {
+            account.cash += amount
+        }
+        "MiscExp" | "XOut" => account.cash -= amount,
+        // transfers that don't affect the cash in this account
+        "DivX" | "IntIncX" | "CGLongX" | "CGMidX" | "CGShortX" | "MiscIncX" | "MiscExpX" => {}
+        _ => warnings.push(format!(
+            "Skipped unsupported '{action}' transaction in account {}",
+            account.id
+        )),
+    }
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "qif"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid QIF investment file.");
+        }
+        let mut accounts = Vec::<AccountState>::new();
+        // maps security names to ticker symbols
+        let mut symbols = HashMap::<String, String>::new();
+        let mut prices = Prices::default();
+        let mut section = Section::Other;
+        let mut record = Record::new();
+        for line in reader.lines() {
+            let line = line?;
+            let line = line.trim_start_matches('\u{feff}').trim_end();
+            if line.is_empty() {
+                continue;
+            }
+            if line.starts_with('!') {
+                section = match line {
+                    "!Account" => Section::Accounts,
+                    INVESTMENT_TYPE => Section::Investments,
+                    "!Type:Security" => Section::Securities,
+                    "!Type:Prices" => Section::Prices,
+                    // e.g. "!Option:AutoSwitch"
+                    _ if line.starts_with("!Option") || line.starts_with("!Clear") => section,
+                    _ => Section::Other,
+                };
+                debug!(line, ?section, "found section header");
+                record.clear();
+                continue;
+            }
+            if section == Section::Prices && line != "^" {
+                // e.g. "VTI",240.00,"1/15'25"
+                let mut parts = line.split(',').map(|p| p.trim().trim_matches('"'));
+                if let (Some(security), Some(price)) =
+                    (parts.next(), parts.next().and_then(parse_number))
+                {
+                    prices.insert(security, price);
+                }
+                continue;
+            }
+            if line != "^" {
+                let mut chars = line.chars();
+                let code = chars.next().unwrap_or_default();
+                record.push((code, chars.as_str().trim().to_string()));
+                continue;
+            }
+            debug!(?section, ?record, "parsed record");
+            match section {
+                Section::Accounts => {
+                    let name = field(&record, 'N')
+                        .ok_or_else(|| anyhow!("Account is missing a name"))?
+                        .to_string();
+                    // make the account current
+                    match accounts.iter().position(|a| a.id == name) {
+                        Some(pos) => {
+                            let account = accounts.remove(pos);
+                            accounts.push(account);
+                        }
+                        None => accounts.push(AccountState {
+                            id: name,
+                            ..Default::default()
+                        }),
+                    }
+                }
+                Section::Investments => {
+                    if accounts.is_empty() {
+                        accounts.push(AccountState {
+                            id: DEFAULT_ACCOUNT_ID.to_string(),
+                            ..Default::default()
+                        });
+                    }
+                    let account = accounts.last_mut().unwrap();
+                    account.is_investment = true;
+                    apply_transaction(account, &record, &mut prices, warnings);
+                }
+                Section::Securities => {
+                    if let (Some(name), Some(symbol)) = (field(&record, 'N'), field(&record, 'S')) {
+                        symbols.insert(name.to_string(), symbol.to_string());
+                    }
+                }
+                Section::Prices | Section::Other => {}
+            }
+            record.clear();
+        }
+
+        let mut balances = Vec::new();
+        for account in accounts.into_iter().filter(|a| a.is_investment) {
+            let mut balance = Balance {
+                account_id: account.id,
+                ..Default::default()
+            };
+            for (security, shares) in account.shares {
+                if shares.abs() < MIN_SHARES {
+                    continue;
+                }
+                let symbol = symbols.get(&security).unwrap_or(&security);
+                let price = prices.latest(&security, symbol);
+                if price.is_none() {
+                    warnings.push(format!(
+                        "No price found for '{security}' in account {}, so it is valued at $0.00",
+                        balance.account_id
+                    ));
+                }
+                balance.holdings.push(Holding {
+                    symbol: symbol.clone(),
+                    current_value: Dollar(shares * price.unwrap_or_default()),
+                    is_cash: false,
+                    quantity: Some(shares),
+                    price: price.map(Dollar),
+                    ..Default::default()
+                });
+            }
+            balance.holdings.push(Holding {
+                symbol: CASH_SYMBOL.to_string(),
+                current_value: Dollar(account.cash).round_cents(),
+                is_cash: true,
+                ..Default::default()
+            });
+            debug!(?balance, "parsed account");
+            balances.push(balance);
+        }
+        Ok(balances)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        Ok(sample.trim_start_matches('\u{feff}').starts_with('!')
+            && sample
+                .lines()
+                .any(|line| line.trim_end() == INVESTMENT_TYPE))
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Dollar,
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+/// Robinhood exports don't include an account number, so all positions are
+/// imported into a single account with this ID unless the file has an
+/// "Account Number" column
+const DEFAULT_ACCOUNT_ID: &str = "robinhood";
+const CASH_SYMBOL: &str = "CASH";
+const REQUIRED_HEADERS: &[&str] = &["Name", "Symbol", "Quantity", "Price", "Equity"];
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "robinhood"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Robinhood CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        let column = |name: &str| headers.iter().position(|h| h.trim() == name);
+        let name_column = column("Name").ok_or_else(|| anyhow!("Missing 'Name' column"))?;
+        let symbol_column = column("Symbol").ok_or_else(|| anyhow!("Missing 'Symbol' column"))?;
+        let quantity_column =
+            column("Quantity").ok_or_else(|| anyhow!("Missing 'Quantity' column"))?;
+        let price_column = column("Price").ok_or_else(|| anyhow!("Missing 'Price' column"))?;
+        let equity_column = column("Equity").ok_or_else(|| anyhow!("Missing 'Equity' column"))?;
+        let account_column = column("Account Number");
+
+        let mut accounts = Vec::<Balance>::new();
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let account_id = account_column
+                .and_then(|c| row.get(c))
+                .map(str::trim)
+                .filter(|id| !id.is_empty())
+                .unwrap_or(DEFAULT_ACCOUNT_ID);
+            let acct = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.to_string(),
+                        account_name: "Robinhood".to_string(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            let name = row.get(name_column).unwrap_or_default().trim();
+            let symbol = row.get(symbol_column).unwrap_or_default().trim();
+            // the cash balance is listed in its own row without a symbol
+            let is_cash = symbol.is_empty() && name.eq_ignore_ascii_case("cash");
+            if symbol.is_empty() && !is_cash {
+                debug!(?row, "Skipping row without a symbol");
+                warnings.push(format!("Skipped '{name}' because it doesn't have a symbol"));
+                continue;
+            }
+            let equity = row.get(equity_column).and_then(parse_dollar);
+            // fall back to calculating the value from the (possibly
+            // fractional) number of shares
+            let computed = || {
+                let quantity = row.get(quantity_column).and_then(parse_dollar)?;
+                let price = row.get(price_column).and_then(parse_dollar)?;
+                Some(Dollar(quantity.0 * price.0))
+            };
+            let current_value = equity
+                .or_else(computed)
+                .ok_or_else(|| anyhow!("Failed to get value for '{name}'"))?;
+            let holding = Holding {
+                symbol: match is_cash {
+                    true => CASH_SYMBOL.to_string(),
+                    false => symbol.to_string(),
+                },
+                current_value,
+                is_cash,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(sample);
+        let headers = csv_reader.headers()?;
+        debug!(?headers);
+        Ok(REQUIRED_HEADERS
+            .iter()
+            .all(|expected| headers.iter().any(|h| h.trim() == *expected)))
+    }
+}
@@ -0,0 +1,129 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const TITLE_PREFIX: &str = "Positions for ";
+const SYMBOL_HEADER: &str = "Symbol";
+const VALUE_HEADER: &str = "Market Value";
+const CASH_ROW: &str = "Cash & Cash Investments";
+const TOTAL_ROW: &str = "Account Total";
+/// The symbol that is used for the "Cash & Cash Investments" row
+const CASH_SYMBOL: &str = "CASH";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+/// Split an account section title like "Individual ...123" into an account
+/// name and an account number
+fn parse_account_title(title: &str) -> (String, String) {
+    match title.trim().rsplit_once(char::is_whitespace) {
+        Some((name, id)) => (name.trim().to_string(), id.to_string()),
+        None => (String::new(), title.trim().to_string()),
+    }
+}
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "schwab"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Schwab CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new()
+            .flexible(true)
+            .has_headers(false)
+            .from_reader(reader);
+        let mut accounts = Vec::<Balance>::new();
+        // column indexes for the current account section
+        let mut columns: Option<(usize, usize)> = None;
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let first = row.get(0).unwrap_or_default().trim();
+            if row.len() == 1 || row.iter().skip(1).all(|field| field.trim().is_empty()) {
+                if first.is_empty() || first.starts_with(TITLE_PREFIX) {
+                    continue;
+                }
+                let (account_name, account_id) = parse_account_title(first);
+                debug!(account_name, account_id, "found account section");
+                accounts.push(Balance {
+                    account_id,
+                    account_name,
+                    ..Default::default()
+                });
+                columns = None;
+                continue;
+            }
+            if first == SYMBOL_HEADER {
+                let value_column = row
+                    .iter()
+                    .position(|header| header.contains(VALUE_HEADER))
+                    .ok_or_else(|| anyhow!("Failed to find '{VALUE_HEADER}' column"))?;
+                columns = Some((0, value_column));
+                continue;
+            }
+            let Some((symbol_column, value_column)) = columns else {
+                debug!(?row, "Skipping row outside of an account section");
+                continue;
+            };
+            let Some(acct) = accounts.last_mut() else {
+                bail!("Found a position before any account section");
+            };
+            let symbol = row
+                .get(symbol_column)
+                .map(str::trim)
+                .ok_or_else(|| anyhow!("Failed to get symbol"))?;
+            if symbol == TOTAL_ROW {
+                continue;
+            }
+            let current_value = row
+                .get(value_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get value for symbol '{symbol}'"))?;
+            let holding = if symbol == CASH_ROW {
+                Holding {
+                    symbol: CASH_SYMBOL.to_string(),
+                    current_value,
+                    is_cash: true,
+                    ..Default::default()
+                }
+            } else {
+                Holding {
+                    symbol: symbol.to_string(),
+                    current_value,
+                    is_cash: false,
+                    ..Default::default()
+                }
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let sample = String::from_utf8_lossy(sample);
+        let first_line = sample
+            .trim_start_matches('\u{feff}')
+            .lines()
+            .next()
+            .unwrap_or_default();
+        Ok(first_line.trim_matches('"').starts_with(TITLE_PREFIX))
+    }
+}
@@ -6,7 +6,7 @@ use tracing::debug;
 use crate::{
     Dollar,
     account::{Balance, Holding},
-    provider::Provider,
+    provider::BalanceProvider,
 };
 
 const EXPECTED_HEADERS: &[&str] = &[
@@ -25,12 +25,20 @@ pub enum Columns {
 }
 
 struct ProviderImpl;
-pub fn provider() -> impl Provider {
+pub fn provider() -> impl BalanceProvider {
     ProviderImpl
 }
 
-impl Provider for ProviderImpl {
-    fn parse_portfolio(&self, reader: &mut dyn BufRead) -> anyhow::Result<Vec<Balance>> {
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "vanguard"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
         let sample = reader.fill_buf()?;
         if !self.detect(sample)? {
             bail!("Portfolio file does not appear to be a valid Vanguard CSV file.");
@@ -68,6 +76,7 @@ impl Provider for ProviderImpl {
                 current_value: total_value,
                 // FIXME: is this reasonable?
                 is_cash: symbol.eq_ignore_ascii_case("VMFXX"),
+                ..Default::default()
             };
             debug!(?acct, ?holding, "adding regular position");
             acct.holdings.push(holding);
@@ -0,0 +1,107 @@
+use std::io::BufRead;
+
+use anyhow::{anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    account::{Balance, Holding},
+    provider::{BalanceProvider, parse_dollar},
+};
+
+const ACCOUNT_NAME_HEADER: &str = "Account Name";
+const ACCOUNT_NUMBER_HEADER: &str = "Account Number";
+const SYMBOL_HEADER: &str = "Symbol";
+const VALUE_HEADER: &str = "Market Value";
+const REQUIRED_HEADERS: &[&str] = &[
+    ACCOUNT_NAME_HEADER,
+    ACCOUNT_NUMBER_HEADER,
+    SYMBOL_HEADER,
+    VALUE_HEADER,
+];
+/// Wealthfront lists the uninvested cash in an account with this symbol
+const CASH_ROW: &str = "Cash";
+const CASH_SYMBOL: &str = "CASH";
+
+pub fn provider() -> impl BalanceProvider {
+    ProviderImpl
+}
+
+struct ProviderImpl;
+
+impl BalanceProvider for ProviderImpl {
+    fn name(&self) -> &str {
+        "wealthfront"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let sample = reader.fill_buf()?;
+        if !self.detect(sample)? {
+            bail!("Portfolio file does not appear to be a valid Wealthfront CSV file.");
+        }
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(reader);
+        let headers = csv_reader.headers()?.clone();
+        let column = |name: &str| {
+            headers
+                .iter()
+                .position(|h| h.trim() == name)
+                .ok_or_else(|| anyhow!("Missing '{name}' column"))
+        };
+        let name_column = column(ACCOUNT_NAME_HEADER)?;
+        let number_column = column(ACCOUNT_NUMBER_HEADER)?;
+        let symbol_column = column(SYMBOL_HEADER)?;
+        let value_column = column(VALUE_HEADER)?;
+
+        let mut accounts = Vec::<Balance>::new();
+        for row in csv_reader.records() {
+            let row = row?;
+            debug!(?row, "parsed row");
+            let account_id = row.get(number_column).unwrap_or_default().trim();
+            let symbol = row.get(symbol_column).unwrap_or_default().trim();
+            if account_id.is_empty() || symbol.is_empty() {
+                debug!(?row, "Row doesn't have enough fields to be a position");
+                continue;
+            }
+            let acct = match accounts.iter().position(|a| a.account_id == account_id) {
+                Some(pos) => &mut accounts[pos],
+                None => {
+                    accounts.push(Balance {
+                        account_id: account_id.to_string(),
+                        account_name: row.get(name_column).unwrap_or_default().to_string(),
+                        ..Default::default()
+                    });
+                    accounts.last_mut().unwrap()
+                }
+            };
+            let current_value = row
+                .get(value_column)
+                .and_then(parse_dollar)
+                .ok_or_else(|| anyhow!("Failed to get value for symbol '{symbol}'"))?;
+            let is_cash = symbol.eq_ignore_ascii_case(CASH_ROW);
+            let holding = Holding {
+                symbol: match is_cash {
+                    true => CASH_SYMBOL.to_string(),
+                    false => symbol.to_string(),
+                },
+                current_value,
+                is_cash,
+                ..Default::default()
+            };
+            debug!(?acct.account_id, ?holding, "adding position");
+            acct.holdings.push(holding);
+        }
+        Ok(accounts)
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        let mut csv_reader = csv::ReaderBuilder::new().flexible(true).from_reader(sample);
+        let headers = csv_reader.headers()?;
+        debug!(?headers);
+        Ok(REQUIRED_HEADERS
+            .iter()
+            .all(|expected| headers.iter().any(|h| h.trim() == *expected)))
+    }
+}
@@ -0,0 +1,186 @@
+//! Converts the first worksheet of an XLSX spreadsheet into CSV, so that
+//! spreadsheet downloads can be parsed by the same providers as CSV files.
+
+use anyhow::anyhow;
+use tracing::debug;
+
+use crate::provider::{
+    xml::{self, Token},
+    zip::Archive,
+};
+
+const WORKBOOK: &str = "xl/workbook.xml";
+const WORKBOOK_RELATIONSHIPS: &str = "xl/_rels/workbook.xml.rels";
+const SHARED_STRINGS: &str = "xl/sharedStrings.xml";
+const DEFAULT_SHEET: &str = "xl/worksheets/sheet1.xml";
+
+/// Strip the namespace prefix (if any) from an element or attribute name
+fn local_name(name: &str) -> &str {
+    name.rsplit(':').next().unwrap_or(name)
+}
+
+fn attribute<'a>(token: &'a Token, key: &str) -> Option<&'a str> {
+    match token {
+        Token::Start { attributes, .. } => attributes
+            .iter()
+            .find(|(name, _)| local_name(name) == key)
+            .map(|(_, value)| value.as_str()),
+        _ => None,
+    }
+}
+
+fn read_xml(archive: &Archive, name: &str) -> anyhow::Result<Option<Vec<Token>>> {
+    archive
+        .read(name)?
+        .map(|data| xml::tokenize(&String::from_utf8_lossy(&data)))
+        .transpose()
+}
+
+/// Find the path of the first worksheet in the workbook
+fn first_sheet(archive: &Archive) -> anyhow::Result<String> {
+    let (Some(workbook), Some(relationships)) = (
+        read_xml(archive, WORKBOOK)?,
+        read_xml(archive, WORKBOOK_RELATIONSHIPS)?,
+    ) else {
+        return Ok(DEFAULT_SHEET.to_string());
+    };
+    let id = workbook
+        .iter()
+        .find(|t| matches!(t, Token::Start { name, .. } if local_name(name) == "sheet"))
+        .and_then(|t| attribute(t, "id"))
+        .ok_or_else(|| anyhow!("Spreadsheet doesn't contain any worksheets"))?;
+    let target = relationships
+        .iter()
+        .find(|t| attribute(t, "Id") == Some(id))
+        .and_then(|t| attribute(t, "Target"))
+        .ok_or_else(|| anyhow!("Failed to find worksheet '{id}' in spreadsheet"))?;
+    Ok(match target.strip_prefix('/') {
+        Some(absolute) => absolute.to_string(),
+        None => format!("xl/{target}"),
+    })
+}
+
+fn shared_strings(archive: &Archive) -> anyhow::Result<Vec<String>> {
+    let mut strings = Vec::new();
+    let Some(tokens) = read_xml(archive, SHARED_STRINGS)? else {
+        return Ok(strings);
+    };
+    let mut current = String::new();
+    let mut in_text = false;
+    for token in tokens {
+        match token {
+            Token::Start { name, .. } if local_name(&name) == "t" => in_text = true,
+            Token::End { name } => match local_name(&name) {
+                "t" => in_text = false,
+                // rich text is split into several runs within each item
+                "si" => strings.push(std::mem::take(&mut current)),
+                _ => {}
+            },
+            Token::Text(text) if in_text => current.push_str(&text),
+            _ => {}
+        }
+    }
+    Ok(strings)
+}
+
+/// Convert a cell reference like "AB12" into a zero-based column index
+fn column_index(reference: &str) -> Option<usize> {
+    let letters: Vec<_> = reference
+        .chars()
+        .take_while(|c| c.is_ascii_alphabetic())
+        .collect();
+    if letters.is_empty() {
+        return None;
+    }
+    let index = letters.iter().fold(0, |acc, c| {
+        acc * 26 + (c.to_ascii_uppercase() as usize - 'A' as usize + 1)
+    });
+    Some(index - 1)
+}
+
+/// Convert the first worksheet of the XLSX spreadsheet in `data` into CSV
+pub(crate) fn to_csv(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let archive = Archive::new(data)?;
+    let sheet = first_sheet(&archive)?;
+    debug!(sheet, "converting worksheet");
+    let strings = shared_strings(&archive)?;
+    let tokens = read_xml(&archive, &sheet)?
+        .ok_or_else(|| anyhow!("Failed to find worksheet '{sheet}' in spreadsheet"))?;
+
+    let mut writer = csv::WriterBuilder::new()
+        .flexible(true)
+        .from_writer(Vec::new());
+    let mut row: Vec<String> = Vec::new();
+    // the type and column of the current cell
+    let mut cell: Option<(String, usize)> = None;
+    let mut value = String::new();
+    let mut in_value = false;
+    for token in tokens.iter() {
+        match token {
+            Token::Start { name, .. } => match local_name(name) {
+                "c" => {
+                    let column = attribute(token, "r")
+                        .and_then(column_index)
+                        .unwrap_or(row.len());
+                    let kind = attribute(token, "t").unwrap_or("n").to_string();
+                    cell = Some((kind, column));
+                    value.clear();
+                }
+                "v" | "t" => in_value = true,
+                _ => {}
+            },
+            Token::Text(text) if in_value => value.push_str(text),
+            Token::End { name } => match local_name(name) {
+                "v" | "t" => in_value = false,
+                "c" => {
+                    let Some((kind, column)) = cell.take() else {
+                        continue;
+                    };
+                    let text = match kind.as_str() {
+                        "s" => value
+                            .trim()
+                            .parse::<usize>()
+                            .ok()
+                            .and_then(|i| strings.get(i))
+                            .cloned()
+                            .ok_or_else(|| anyhow!("Invalid shared string '{value}'"))?,
+                        "b" => match value.trim() {
+                            "1" => "TRUE".to_string(),
+                            _ => "FALSE".to_string(),
+                        },
+                        _ => value.clone(),
+                    };
+                    if row.len() <= column {
+                        row.resize(column + 1, String::new());
+                    }
+                    row[column] = text;
+                }
+                "row" => {
+                    // empty rows are skipped when reading CSV anyway
+                    if !row.is_empty() {
+                        writer.write_record(&row)?;
+                    }
+                    row.clear();
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+    }
+    writer
+        .into_inner()
+        .map_err(|e| anyhow!("Failed to convert spreadsheet: {e}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_column_index() {
+        assert_eq!(column_index("A1"), Some(0));
+        assert_eq!(column_index("Z9"), Some(25));
+        assert_eq!(column_index("AB12"), Some(27));
+        assert_eq!(column_index("12"), None);
+    }
+}
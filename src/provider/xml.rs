@@ -0,0 +1,188 @@
+//! A very small, forgiving XML tokenizer. It is only intended to extract
+//! elements, attributes, and text from the XML exports that some brokerages
+//! provide, so it doesn't validate the document or support DTDs.
+
+use anyhow::bail;
+
+/// A single piece of an XML document
+#[derive(Debug, PartialEq)]
+pub(crate) enum Token {
+    /// A start tag. Self-closing tags are reported as a `Start` token that is
+    /// immediately followed by an `End` token.
+    Start {
+        name: String,
+        attributes: Vec<(String, String)>,
+    },
+    End {
+        name: String,
+    },
+    Text(String),
+}
+
+impl Token {
+    /// Get the value of the attribute with the given name, if this is a start tag
+    pub(crate) fn attribute(&self, key: &str) -> Option<&str> {
+        match self {
+            Token::Start { attributes, .. } => attributes
+                .iter()
+                .find(|(name, _)| name == key)
+                .map(|(_, value)| value.as_str()),
+            _ => None,
+        }
+    }
+}
+
+/// Replace the predefined XML entities and character references in `s`
+pub(crate) fn unescape(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut rest = s;
+    while let Some(start) = rest.find('&') {
+        out.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let Some(end) = rest.find(';') else {
+            break;
+        };
+        let entity = &rest[1..end];
+        let replacement = match entity {
+            "amp" => Some('&'),
+            "lt" => Some('<'),
+            "gt" => Some('>'),
+            "quot" => Some('"'),
+            "apos" => Some('\''),
+            _ => entity
+                .strip_prefix("#x")
+                .and_then(|hex| u32::from_str_radix(hex, 16).ok())
+                .or_else(|| entity.strip_prefix('#').and_then(|dec| dec.parse().ok()))
+                .and_then(char::from_u32),
+        };
+        match replacement {
+            Some(c) => {
+                out.push(c);
+                rest = &rest[end + 1..];
+            }
+            None => {
+                out.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn parse_attributes(mut s: &str) -> anyhow::Result<Vec<(String, String)>> {
+    let mut attributes = Vec::new();
+    loop {
+        s = s.trim_start();
+        if s.is_empty() {
+            return Ok(attributes);
+        }
+        let Some(eq) = s.find('=') else {
+            bail!("Malformed XML attribute near '{s}'");
+        };
+        let name = s[..eq].trim().to_string();
+        s = s[eq + 1..].trim_start();
+        let Some(quote) = s.chars().next().filter(|c| *c == '"' || *c == '\'') else {
+            bail!("Unquoted XML attribute value for '{name}'");
+        };
+        let Some(close) = s[1..].find(quote) else {
+            bail!("Unterminated XML attribute value for '{name}'");
+        };
+        attributes.push((name, unescape(&s[1..close + 1])));
+        s = &s[close + 2..];
+    }
+}
+
+/// Split an XML document into a flat list of tokens. Comments, processing
+/// instructions, and declarations are skipped, and whitespace-only text is
+/// dropped.
+pub(crate) fn tokenize(doc: &str) -> anyhow::Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = doc;
+    while let Some(start) = rest.find('<') {
+        let text = rest[..start].trim();
+        if !text.is_empty() {
+            tokens.push(Token::Text(unescape(text)));
+        }
+        rest = &rest[start..];
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            let Some(end) = comment.find("-->") else {
+                bail!("Unterminated XML comment");
+            };
+            rest = &comment[end + 3..];
+            continue;
+        }
+        if let Some(cdata) = rest.strip_prefix("<![CDATA[") {
+            let Some(end) = cdata.find("]]>") else {
+                bail!("Unterminated CDATA section");
+            };
+            tokens.push(Token::Text(cdata[..end].to_string()));
+            rest = &cdata[end + 3..];
+            continue;
+        }
+        let Some(end) = rest.find('>') else {
+            bail!("Unterminated XML tag");
+        };
+        let tag = &rest[1..end];
+        rest = &rest[end + 1..];
+        if tag.starts_with('?') || tag.starts_with('!') {
+            continue;
+        }
+        if let Some(name) = tag.strip_prefix('/') {
+            tokens.push(Token::End {
+                name: name.trim().to_string(),
+            });
+            continue;
+        }
+        let (tag, self_closing) = match tag.strip_suffix('/') {
+            Some(tag) => (tag, true),
+            None => (tag, false),
+        };
+        let (name, attributes) = match tag.find(char::is_whitespace) {
+            Some(pos) => (&tag[..pos], parse_attributes(&tag[pos..])?),
+            None => (tag, Vec::new()),
+        };
+        tokens.push(Token::Start {
+            name: name.to_string(),
+            attributes,
+        });
+        if self_closing {
+            tokens.push(Token::End {
+                name: name.to_string(),
+            });
+        }
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize() {
+        let tokens =
+            tokenize(r#"<?xml version="1.0"?><!-- comment --><a x="1 &amp; 2"><b y='z'/>text</a>"#)
+                .unwrap();
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Start {
+                    name: "a".to_string(),
+                    attributes: vec![("x".to_string(), "1 & 2".to_string())],
+                },
+                Token::Start {
+                    name: "b".to_string(),
+                    attributes: vec![("y".to_string(), "z".to_string())],
+                },
+                Token::End {
+                    name: "b".to_string()
+                },
+                Token::Text("text".to_string()),
+                Token::End {
+                    name: "a".to_string()
+                },
+            ]
+        );
+    }
+}
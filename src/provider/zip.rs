@@ -0,0 +1,334 @@
+//! Just enough of the ZIP archive format (and the DEFLATE compression that it
+//! uses) to read the contents of XLSX spreadsheets. ZIP64 archives,
+//! encryption, and compression methods other than DEFLATE aren't supported.
+
+use anyhow::{anyhow, bail, ensure};
+
+const LOCAL_HEADER_SIGNATURE: u32 = 0x04034b50;
+const CENTRAL_HEADER_SIGNATURE: u32 = 0x02014b50;
+const END_OF_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+const END_OF_DIRECTORY_SIZE: usize = 22;
+const METHOD_STORED: u16 = 0;
+const METHOD_DEFLATE: u16 = 8;
+
+fn u16_at(data: &[u8], pos: usize) -> anyhow::Result<u16> {
+    data.get(pos..pos + 2)
+        .map(|b| u16::from_le_bytes([b[0], b[1]]))
+        .ok_or_else(|| anyhow!("Unexpected end of ZIP archive"))
+}
+
+fn u32_at(data: &[u8], pos: usize) -> anyhow::Result<u32> {
+    data.get(pos..pos + 4)
+        .map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .ok_or_else(|| anyhow!("Unexpected end of ZIP archive"))
+}
+
+/// A file within a ZIP archive
+#[derive(Debug)]
+struct Entry {
+    name: String,
+    method: u16,
+    compressed_size: usize,
+    local_header: usize,
+}
+
+/// A ZIP archive that has been read into memory
+pub(crate) struct Archive<'a> {
+    data: &'a [u8],
+    entries: Vec<Entry>,
+}
+
+impl<'a> Archive<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> anyhow::Result<Self> {
+        // the end of central directory record is followed by a comment of
+        // up to 64k, so search backwards for its signature
+        let search_start = data
+            .len()
+            .saturating_sub(END_OF_DIRECTORY_SIZE + u16::MAX as usize);
+        let end = (search_start..=data.len().saturating_sub(END_OF_DIRECTORY_SIZE))
+            .rev()
+            .find(|&pos| u32_at(data, pos).ok() == Some(END_OF_DIRECTORY_SIGNATURE))
+            .ok_or_else(|| anyhow!("Not a valid ZIP archive"))?;
+        let count = u16_at(data, end + 10)? as usize;
+        let mut pos = u32_at(data, end + 16)? as usize;
+        let mut entries = Vec::with_capacity(count);
+        for _ in 0..count {
+            ensure!(
+                u32_at(data, pos)? == CENTRAL_HEADER_SIGNATURE,
+                "Corrupt ZIP central directory"
+            );
+            let name_len = u16_at(data, pos + 28)? as usize;
+            let extra_len = u16_at(data, pos + 30)? as usize;
+            let comment_len = u16_at(data, pos + 32)? as usize;
+            let name = data
+                .get(pos + 46..pos + 46 + name_len)
+                .ok_or_else(|| anyhow!("Unexpected end of ZIP archive"))?;
+            entries.push(Entry {
+                name: String::from_utf8_lossy(name).into_owned(),
+                method: u16_at(data, pos + 10)?,
+                compressed_size: u32_at(data, pos + 20)? as usize,
+                local_header: u32_at(data, pos + 42)? as usize,
+            });
+            pos += 46 + name_len + extra_len + comment_len;
+        }
+        Ok(Self { data, entries })
+    }
+
+    /// Get the uncompressed contents of the file with the given name, if it
+    /// exists in the archive
+    pub(crate) fn read(&self, name: &str) -> anyhow::Result<Option<Vec<u8>>> {
+        let Some(entry) = self.entries.iter().find(|e| e.name == name) else {
+            return Ok(None);
+        };
+        let pos = entry.local_header;
+        ensure!(
+            u32_at(self.data, pos)? == LOCAL_HEADER_SIGNATURE,
+            "Corrupt ZIP entry '{name}'"
+        );
+        let start = pos
+            + 30
+            + u16_at(self.data, pos + 26)? as usize
+            + u16_at(self.data, pos + 28)? as usize;
+        let compressed = self
+            .data
+            .get(start..start + entry.compressed_size)
+            .ok_or_else(|| anyhow!("Unexpected end of ZIP archive"))?;
+        match entry.method {
+            METHOD_STORED => Ok(Some(compressed.to_vec())),
+            METHOD_DEFLATE => inflate(compressed).map(Some),
+            method => bail!("Unsupported compression method {method} for ZIP entry '{name}'"),
+        }
+    }
+}
+
+const LENGTH_BASE: [u16; 29] = [
+    3, 4, 5, 6, 7, 8, 9, 10, 11, 13, 15, 17, 19, 23, 27, 31, 35, 43, 51, 59, 67, 83, 99, 115, 131,
+    163, 195, 227, 258,
+];
+const LENGTH_EXTRA: [u8; 29] = [
+    0, 0, 0, 0, 0, 0, 0, 0, 1, 1, 1, 1, 2, 2, 2, 2, 3, 3, 3, 3, 4, 4, 4, 4, 5, 5, 5, 5, 0,
+];
+const DISTANCE_BASE: [u16; 30] = [
+    1, 2, 3, 4, 5, 7, 9, 13, 17, 25, 33, 49, 65, 97, 129, 193, 257, 385, 513, 769, 1025, 1537,
+    2049, 3073, 4097, 6145, 8193, 12289, 16385, 24577,
+];
+const DISTANCE_EXTRA: [u8; 30] = [
+    0, 0, 0, 0, 1, 1, 2, 2, 3, 3, 4, 4, 5, 5, 6, 6, 7, 7, 8, 8, 9, 9, 10, 10, 11, 11, 12, 12, 13,
+    13,
+];
+/// The order in which the code lengths of the code length alphabet are stored
+const CODE_LENGTH_ORDER: [usize; 19] = [
+    16, 17, 18, 0, 8, 7, 9, 6, 10, 5, 11, 4, 12, 3, 13, 2, 14, 1, 15,
+];
+const MAX_BITS: usize = 15;
+
+/// Reads a DEFLATE stream, which packs bits starting with the least
+/// significant bit of each byte
+struct BitReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    buffer: u32,
+    count: u32,
+}
+
+impl BitReader<'_> {
+    fn bits(&mut self, n: u32) -> anyhow::Result<u32> {
+        while self.count < n {
+            let byte = *self
+                .data
+                .get(self.pos)
+                .ok_or_else(|| anyhow!("Unexpected end of compressed data"))?;
+            self.buffer |= (byte as u32) << self.count;
+            self.pos += 1;
+            self.count += 8;
+        }
+        let value = self.buffer & ((1u32 << n) - 1);
+        self.buffer >>= n;
+        self.count -= n;
+        Ok(value)
+    }
+
+    /// Discard the remaining bits in the current byte
+    fn align(&mut self) {
+        self.buffer = 0;
+        self.count = 0;
+    }
+
+    fn decode(&mut self, huffman: &Huffman) -> anyhow::Result<u16> {
+        // canonical Huffman codes of each length are consecutive integers, so
+        // a code can be decoded one bit at a time
+        let mut code = 0i32;
+        let mut first = 0i32;
+        let mut index = 0i32;
+        for len in 1..=MAX_BITS {
+            code |= self.bits(1)? as i32;
+            let count = huffman.counts[len] as i32;
+            if code - count < first {
+                return Ok(huffman.symbols[(index + code - first) as usize]);
+            }
+            index += count;
+            first = (first + count) << 1;
+            code <<= 1;
+        }
+        bail!("Invalid Huffman code in compressed data")
+    }
+}
+
+/// A canonical Huffman code, described by the number of codes of each length
+/// and the symbols ordered by their codes
+struct Huffman {
+    counts: [u16; MAX_BITS + 1],
+    symbols: Vec<u16>,
+}
+
+impl Huffman {
+    fn new(lengths: &[u8]) -> Self {
+        let mut counts = [0u16; MAX_BITS + 1];
+        for &len in lengths {
+            counts[len as usize] += 1;
+        }
+        counts[0] = 0;
+        let mut offsets = [0u16; MAX_BITS + 2];
+        for len in 1..=MAX_BITS {
+            offsets[len + 1] = offsets[len] + counts[len];
+        }
+        let mut symbols = vec![0u16; lengths.len()];
+        for (symbol, &len) in lengths.iter().enumerate() {
+            if len != 0 {
+                symbols[offsets[len as usize] as usize] = symbol as u16;
+                offsets[len as usize] += 1;
+            }
+        }
+        Self { counts, symbols }
+    }
+
+    /// The codes used by blocks with fixed Huffman codes
+    fn fixed() -> (Self, Self) {
+        let mut lengths = [0u8; 288];
+        lengths[..144].fill(8);
+        lengths[144..256].fill(9);
+        lengths[256..280].fill(7);
+        lengths[280..].fill(8);
+        (Self::new(&lengths), Self::new(&[5; 30]))
+    }
+}
+
+/// Read the code lengths of a block with dynamic Huffman codes
+fn dynamic_codes(reader: &mut BitReader) -> anyhow::Result<(Huffman, Huffman)> {
+    let nlengths = reader.bits(5)? as usize + 257;
+    let ndistances = reader.bits(5)? as usize + 1;
+    let ncodes = reader.bits(4)? as usize + 4;
+    let mut code_lengths = [0u8; 19];
+    for &i in CODE_LENGTH_ORDER.iter().take(ncodes) {
+        code_lengths[i] = reader.bits(3)? as u8;
+    }
+    let code_lengths = Huffman::new(&code_lengths);
+
+    let mut lengths = Vec::with_capacity(nlengths + ndistances);
+    while lengths.len() < nlengths + ndistances {
+        let symbol = reader.decode(&code_lengths)?;
+        let (value, repeat) = match symbol {
+            0..=15 => (symbol as u8, 1),
+            16 => {
+                let previous = *lengths
+                    .last()
+                    .ok_or_else(|| anyhow!("Repeated code length without a previous length"))?;
+                (previous, 3 + reader.bits(2)?)
+            }
+            17 => (0, 3 + reader.bits(3)?),
+            _ => (0, 11 + reader.bits(7)?),
+        };
+        lengths.extend(std::iter::repeat_n(value, repeat as usize));
+    }
+    ensure!(
+        lengths.len() == nlengths + ndistances,
+        "Too many code lengths in compressed data"
+    );
+    Ok((
+        Huffman::new(&lengths[..nlengths]),
+        Huffman::new(&lengths[nlengths..]),
+    ))
+}
+
+/// Decompress a raw DEFLATE stream (RFC 1951)
+pub(crate) fn inflate(data: &[u8]) -> anyhow::Result<Vec<u8>> {
+    let mut reader = BitReader {
+        data,
+        pos: 0,
+        buffer: 0,
+        count: 0,
+    };
+    let mut out = Vec::with_capacity(data.len() * 4);
+    loop {
+        let last = reader.bits(1)? == 1;
+        match reader.bits(2)? {
+            0 => {
+                reader.align();
+                let len = u16_at(data, reader.pos)?;
+                let nlen = u16_at(data, reader.pos + 2)?;
+                ensure!(len == !nlen, "Corrupt stored block in compressed data");
+                let start = reader.pos + 4;
+                let block = data
+                    .get(start..start + len as usize)
+                    .ok_or_else(|| anyhow!("Unexpected end of compressed data"))?;
+                out.extend_from_slice(block);
+                reader.pos = start + len as usize;
+            }
+            kind @ (1 | 2) => {
+                let (lengths, distances) = match kind {
+                    1 => Huffman::fixed(),
+                    _ => dynamic_codes(&mut reader)?,
+                };
+                loop {
+                    let symbol = reader.decode(&lengths)? as usize;
+                    match symbol {
+                        0..=255 => out.push(symbol as u8),
+                        256 => break,
+                        _ => {
+                            let i = symbol - 257;
+                            ensure!(i < LENGTH_BASE.len(), "Invalid length in compressed data");
+                            let len = LENGTH_BASE[i] as usize
+                                + reader.bits(LENGTH_EXTRA[i] as u32)? as usize;
+                            let d = reader.decode(&distances)? as usize;
+                            ensure!(
+                                d < DISTANCE_BASE.len(),
+                                "Invalid distance in compressed data"
+                            );
+                            let distance = DISTANCE_BASE[d] as usize
+                                + reader.bits(DISTANCE_EXTRA[d] as u32)? as usize;
+                            ensure!(
+                                distance <= out.len(),
+                                "Distance too far back in compressed data"
+                            );
+                            // the copy may overlap the bytes that it produces
+                            let start = out.len() - distance;
+                            for i in 0..len {
+                                out.push(out[start + i]);
+                            }
+                        }
+                    }
+                }
+            }
+            _ => bail!("Invalid block type in compressed data"),
+        }
+        if last {
+            return Ok(out);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_inflate() {
+        // fixed Huffman codes, with a back-reference that overlaps its output
+        let fixed = [203, 72, 205, 201, 201, 87, 200, 64, 39, 1];
+        assert_eq!(inflate(&fixed).unwrap(), b"hello hello hello hello");
+        // a single stored block
+        let stored = [1, 3, 0, 252, 255, 97, 98, 99];
+        assert_eq!(inflate(&stored).unwrap(), b"abc");
+        assert!(inflate(&fixed[..5]).is_err());
+    }
+}
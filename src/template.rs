@@ -0,0 +1,393 @@
+//! A small template language for showing plans in whatever text format a
+//! workflow needs, loosely based on Jinja:
+//!
+//! - `{{ account.name }}` is replaced by a value, which can be passed through
+//!   filters such as `{{ trade.amount | money }}`
+//! - `{% for trade in trades %}...{% endfor %}` repeats its body for each item
+//!   of a list
+//! - `{% if trade.action == "buy" %}...{% else %}...{% endif %}` includes its
+//!   body only if the condition holds (or if the value is not empty, zero or
+//!   false when there is no comparison, or if it is with `if not`)
+//!
+//! A `{% ... %}` tag that is on a line of its own doesn't leave an empty line
+//! in the output, so that loops and conditions can be indented like code.
+
+use anyhow::{anyhow, bail};
+use driftfix::Dollar;
+use serde::Serialize;
+use serde_yaml::Value;
+
+/// The filters that can be applied to a value
+const FILTERS: &[&str] = &["money", "percent", "abs", "upper", "lower"];
+
+/// A value to be looked up, e.g. `trade.symbol | lower`
+#[derive(Debug, PartialEq)]
+struct Expr {
+    path: Vec<String>,
+    filters: Vec<String>,
+}
+
+#[derive(Debug, PartialEq)]
+enum Condition {
+    Truthy(Expr),
+    Falsy(Expr),
+    Equals(Expr, String),
+    NotEquals(Expr, String),
+}
+
+#[derive(Debug, PartialEq)]
+enum Node {
+    Text(String),
+    Value(Expr),
+    For {
+        name: String,
+        list: Expr,
+        body: Vec<Node>,
+    },
+    If {
+        condition: Condition,
+        then: Vec<Node>,
+        otherwise: Vec<Node>,
+    },
+}
+
+/// A parsed template, which can be rendered as many times as needed
+#[derive(Debug)]
+pub(crate) struct Template {
+    nodes: Vec<Node>,
+}
+
+enum Token<'a> {
+    Text(&'a str),
+    Value(&'a str),
+    Tag(&'a str),
+}
+
+fn tokenize(source: &str) -> anyhow::Result<Vec<Token<'_>>> {
+    let mut tokens = Vec::new();
+    let mut rest = source;
+    while let Some(start) = rest.find('{') {
+        let (close, is_tag) = match &rest[start..] {
+            s if s.starts_with("{{") => ("}}", false),
+            s if s.starts_with("{%") => ("%}", true),
+            _ => {
+                tokens.push(Token::Text(&rest[..=start]));
+                rest = &rest[start + 1..];
+                continue;
+            }
+        };
+        let offset = source.len() - rest.len() + start;
+        let inner = &rest[start + 2..];
+        let end = inner.find(close).ok_or_else(|| {
+            let line = source[..offset].lines().count().max(1);
+            anyhow!(
+                "Unclosed '{}' on line {line} of the template",
+                &rest[start..start + 2]
+            )
+        })?;
+        let after = &inner[end + 2..];
+        let line_start = source[..offset].rfind('\n').map_or(0, |i| i + 1);
+        let standalone = is_tag
+            && source[line_start..offset].trim().is_empty()
+            && (after.is_empty() || after.starts_with('\n'));
+        match standalone {
+            // leave out the indentation and the end of the line
+            true => {
+                tokens.push(Token::Text(&rest[..start - (offset - line_start)]));
+                rest = after.strip_prefix('\n').unwrap_or(after);
+            }
+            false => {
+                tokens.push(Token::Text(&rest[..start]));
+                rest = after;
+            }
+        }
+        match is_tag {
+            true => tokens.push(Token::Tag(inner[..end].trim())),
+            false => tokens.push(Token::Value(inner[..end].trim())),
+        }
+    }
+    tokens.push(Token::Text(rest));
+    Ok(tokens)
+}
+
+fn parse_expr(s: &str) -> anyhow::Result<Expr> {
+    let mut parts = s.split('|').map(str::trim);
+    let path = parts.next().unwrap_or_default();
+    if path.is_empty() || !path.split('.').all(is_identifier) {
+        bail!("Invalid value '{s}' in template");
+    }
+    let filters = parts
+        .map(|filter| match FILTERS.contains(&filter) {
+            true => Ok(filter.to_string()),
+            false => Err(anyhow!(
+                "Unknown filter '{filter}' in template. The filters are {}.",
+                FILTERS.join(", ")
+            )),
+        })
+        .collect::<anyhow::Result<_>>()?;
+    Ok(Expr {
+        path: path.split('.').map(String::from).collect(),
+        filters,
+    })
+}
+
+fn is_identifier(s: &str) -> bool {
+    !s.is_empty() && s.chars().all(|c| c.is_alphanumeric() || c == '_')
+}
+
+fn parse_condition(s: &str) -> anyhow::Result<Condition> {
+    let literal = |s: &str| {
+        s.trim()
+            .strip_prefix('"')
+            .and_then(|s| s.strip_suffix('"'))
+            .map(String::from)
+            .ok_or_else(|| {
+                anyhow!(
+                    "Expected a quoted string instead of '{}' in template",
+                    s.trim()
+                )
+            })
+    };
+    if let Some((left, right)) = s.split_once("!=") {
+        Ok(Condition::NotEquals(parse_expr(left)?, literal(right)?))
+    } else if let Some((left, right)) = s.split_once("==") {
+        Ok(Condition::Equals(parse_expr(left)?, literal(right)?))
+    } else if let Some(s) = s.trim().strip_prefix("not ") {
+        Ok(Condition::Falsy(parse_expr(s)?))
+    } else {
+        Ok(Condition::Truthy(parse_expr(s)?))
+    }
+}
+
+/// Parse nodes until one of the `until` tags (or the end of the template, if
+/// `until` is empty), returning the nodes and the tag that ended them
+fn parse_nodes<'a>(
+    tokens: &mut impl Iterator<Item = Token<'a>>,
+    until: &[&str],
+) -> anyhow::Result<(Vec<Node>, Option<&'a str>)> {
+    let mut nodes = Vec::new();
+    while let Some(token) = tokens.next() {
+        match token {
+            Token::Text("") => {}
+            Token::Text(text) => nodes.push(Node::Text(text.to_string())),
+            Token::Value(expr) => nodes.push(Node::Value(parse_expr(expr)?)),
+            Token::Tag(tag) if until.contains(&tag) => return Ok((nodes, Some(tag))),
+            Token::Tag(tag) => {
+                let (keyword, rest) = tag.split_once(' ').unwrap_or((tag, ""));
+                match keyword {
+                    "for" => {
+                        let (name, list) = rest.split_once(" in ").ok_or_else(|| {
+                            anyhow!("Expected '{{% for NAME in LIST %}}' instead of '{{% {tag} %}}' in template")
+                        })?;
+                        let name = name.trim();
+                        if !is_identifier(name) {
+                            bail!("Invalid loop variable '{name}' in template");
+                        }
+                        let (body, _) = parse_nodes(tokens, &["endfor"])?;
+                        nodes.push(Node::For {
+                            name: name.to_string(),
+                            list: parse_expr(list)?,
+                            body,
+                        });
+                    }
+                    "if" => {
+                        let condition = parse_condition(rest)?;
+                        let (then, end) = parse_nodes(tokens, &["else", "endif"])?;
+                        let otherwise = match end {
+                            Some("else") => parse_nodes(tokens, &["endif"])?.0,
+                            _ => Vec::new(),
+                        };
+                        nodes.push(Node::If {
+                            condition,
+                            then,
+                            otherwise,
+                        });
+                    }
+                    _ => bail!("Unexpected '{{% {tag} %}}' in template"),
+                }
+            }
+        }
+    }
+    match until.last() {
+        Some(tag) => bail!("Missing '{{% {tag} %}}' in template"),
+        None => Ok((nodes, None)),
+    }
+}
+
+/// Whether a value counts as true in a condition
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64().is_some_and(|n| n != 0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Sequence(items) => !items.is_empty(),
+        Value::Mapping(map) => !map.is_empty(),
+        Value::Tagged(tagged) => is_truthy(&tagged.value),
+    }
+}
+
+fn apply_filter(filter: &str, value: Value) -> anyhow::Result<Value> {
+    let number = |value: &Value| {
+        value
+            .as_f64()
+            .ok_or_else(|| anyhow!("The '{filter}' filter can only be used with numbers"))
+    };
+    Ok(match filter {
+        "money" => Value::String(Dollar(number(&value)? as f32).to_string()),
+        "percent" => Value::String(format!("{:.1}%", number(&value)?)),
+        "abs" => Value::from(number(&value)?.abs()),
+        "upper" => Value::String(to_text(&value)?.to_uppercase()),
+        "lower" => Value::String(to_text(&value)?.to_lowercase()),
+        _ => unreachable!("filters are checked when parsing"),
+    })
+}
+
+fn to_text(value: &Value) -> anyhow::Result<String> {
+    Ok(match value {
+        Value::Null => String::new(),
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => s.clone(),
+        Value::Tagged(tagged) => to_text(&tagged.value)?,
+        Value::Sequence(_) | Value::Mapping(_) => {
+            bail!("Lists and objects can't be shown in a template, only their fields")
+        }
+    })
+}
+
+impl Template {
+    pub(crate) fn parse(source: &str) -> anyhow::Result<Self> {
+        let tokens = tokenize(source)?;
+        let (nodes, _) = parse_nodes(&mut tokens.into_iter(), &[])?;
+        Ok(Self { nodes })
+    }
+
+    /// Render the template with the fields of `context` as its variables
+    pub(crate) fn render(&self, context: &impl Serialize) -> anyhow::Result<String> {
+        // as in [crate::output::to_json], a round trip through YAML gives
+        // floating-point numbers their shortest representation
+        let yaml = serde_yaml::to_string(context)?;
+        let context: Value = serde_yaml::from_str(&yaml)?;
+        let mut scopes = vec![];
+        if let Value::Mapping(map) = context {
+            for (key, value) in map {
+                if let Value::String(key) = key {
+                    scopes.push((key, value));
+                }
+            }
+        }
+        let mut out = String::new();
+        render_nodes(&self.nodes, &mut scopes, &mut out)?;
+        Ok(out)
+    }
+}
+
+fn lookup(expr: &Expr, scopes: &[(String, Value)]) -> anyhow::Result<Value> {
+    let (first, rest) = expr.path.split_first().expect("paths aren't empty");
+    let mut value = scopes
+        .iter()
+        .rev()
+        .find(|(name, _)| name == first)
+        .map(|(_, value)| value)
+        .ok_or_else(|| anyhow!("Unknown variable '{first}' in template"))?;
+    for field in rest {
+        // optional fields are left out when they have no value
+        value = value.get(field.as_str()).unwrap_or(&Value::Null);
+    }
+    expr.filters
+        .iter()
+        .try_fold(value.clone(), |value, filter| apply_filter(filter, value))
+}
+
+fn render_nodes(
+    nodes: &[Node],
+    scopes: &mut Vec<(String, Value)>,
+    out: &mut String,
+) -> anyhow::Result<()> {
+    for node in nodes {
+        match node {
+            Node::Text(text) => out.push_str(text),
+            Node::Value(expr) => out.push_str(&to_text(&lookup(expr, scopes)?)?),
+            Node::For { name, list, body } => {
+                let items = match lookup(list, scopes)? {
+                    Value::Sequence(items) => items,
+                    Value::Null => Vec::new(),
+                    _ => bail!("'{}' is not a list", list.path.join(".")),
+                };
+                for item in items {
+                    scopes.push((name.clone(), item));
+                    let result = render_nodes(body, scopes, out);
+                    scopes.pop();
+                    result?;
+                }
+            }
+            Node::If {
+                condition,
+                then,
+                otherwise,
+            } => {
+                let holds = match condition {
+                    Condition::Truthy(expr) => is_truthy(&lookup(expr, scopes)?),
+                    Condition::Falsy(expr) => !is_truthy(&lookup(expr, scopes)?),
+                    Condition::Equals(expr, s) => to_text(&lookup(expr, scopes)?)? == *s,
+                    Condition::NotEquals(expr, s) => to_text(&lookup(expr, scopes)?)? != *s,
+                };
+                render_nodes(if holds { then } else { otherwise }, scopes, out)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Serialize)]
+    struct Trade {
+        symbol: &'static str,
+        action: &'static str,
+        amount: f32,
+    }
+
+    #[derive(Serialize)]
+    struct Context {
+        name: &'static str,
+        trades: Vec<Trade>,
+    }
+
+    #[test]
+    fn test_render() -> anyhow::Result<()> {
+        let context = Context {
+            name: "IRA",
+            trades: vec![
+                Trade {
+                    symbol: "VTI",
+                    action: "buy",
+                    amount: 1234.5,
+                },
+                Trade {
+                    symbol: "BND",
+                    action: "sell",
+                    amount: 20.0,
+                },
+            ],
+        };
+        let template = Template::parse(
+            "{{ name | lower }}\n  {% for trade in trades %}\n{% if trade.action == \"buy\" %}+{% else %}-{% endif %}{{ trade.symbol }} {{ trade.amount | money }}\n{% endfor %}\n",
+        )?;
+        assert_eq!(
+            template.render(&context)?,
+            "ira\n+VTI $1,234.50\n-BND $20.00\n"
+        );
+        let template = Template::parse("{% if not name %}x{% else %}y{% endif %}")?;
+        assert_eq!(template.render(&context)?, "y");
+        let template = Template::parse("{% if missing %}x{% endif %}")?;
+        assert!(template.render(&context).is_err());
+        assert!(Template::parse("{% for trade in trades %}").is_err());
+        assert!(Template::parse("{{ name | bold }}").is_err());
+        assert!(Template::parse("{{ name ").is_err());
+        Ok(())
+    }
+}
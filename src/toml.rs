@@ -0,0 +1,570 @@
+//! A reader and writer for the subset of TOML that is needed for
+//! configuration files. Documents are converted to and from a
+//! [serde_yaml::Value] so that they can be (de)serialized with the same
+//! structures as a YAML configuration.
+//!
+//! Tables, arrays of tables, dotted and quoted keys, basic and literal strings,
+//! integers, floats, booleans, arrays and inline tables are supported. Dates
+//! are read as strings, and multi-line strings aren't supported.
+
+use anyhow::{Context, bail};
+use serde_yaml::{Mapping, Value};
+
+/// Parse a TOML document into a mapping
+pub fn from_str(text: &str) -> anyhow::Result<Value> {
+    let mut parser = Parser {
+        chars: text.chars().collect(),
+        pos: 0,
+        line: 1,
+    };
+    parser
+        .document()
+        .with_context(|| format!("Failed to parse the TOML document on line {}", parser.line))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+    line: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.pos += 1;
+        if c == '\n' {
+            self.line += 1;
+        }
+        Some(c)
+    }
+
+    fn expect(&mut self, expected: char) -> anyhow::Result<()> {
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            Some(c) => bail!("Expected '{expected}' but found '{c}'"),
+            None => bail!("Expected '{expected}' but the document ended"),
+        }
+    }
+
+    /// Skip spaces and tabs, and a comment that follows them
+    fn skip_space(&mut self) {
+        while matches!(self.peek(), Some(' ' | '\t')) {
+            self.next();
+        }
+        if self.peek() == Some('#') {
+            while !matches!(self.peek(), None | Some('\n')) {
+                self.next();
+            }
+        }
+    }
+
+    /// Skip whitespace, comments and line breaks
+    fn skip_lines(&mut self) {
+        loop {
+            self.skip_space();
+            match self.peek() {
+                Some('\n' | '\r') => {
+                    self.next();
+                }
+                _ => break,
+            }
+        }
+    }
+
+    /// Expect the end of a line, allowing for a trailing comment
+    fn end_of_line(&mut self) -> anyhow::Result<()> {
+        self.skip_space();
+        match self.next() {
+            None | Some('\n') => Ok(()),
+            Some('\r') if self.peek() == Some('\n') => {
+                self.next();
+                Ok(())
+            }
+            Some(c) => bail!("Expected the end of the line but found '{c}'"),
+        }
+    }
+
+    fn document(&mut self) -> anyhow::Result<Value> {
+        let mut root = Mapping::new();
+        let mut table: Vec<String> = Vec::new();
+        loop {
+            self.skip_lines();
+            match self.peek() {
+                None => break,
+                Some('[') => {
+                    self.next();
+                    let array = self.peek() == Some('[');
+                    if array {
+                        self.next();
+                    }
+                    self.skip_space();
+                    table = self.key()?;
+                    self.skip_space();
+                    self.expect(']')?;
+                    if array {
+                        self.expect(']')?;
+                        let (last, parent) = table.split_last().context("Missing table name")?;
+                        let parent = navigate(&mut root, parent)?;
+                        match parent
+                            .entry(Value::String(last.clone()))
+                            .or_insert_with(|| Value::Sequence(Vec::new()))
+                        {
+                            Value::Sequence(tables) => tables.push(Value::Mapping(Mapping::new())),
+                            _ => bail!("'{last}' is not an array of tables"),
+                        }
+                    } else {
+                        navigate(&mut root, &table)?;
+                    }
+                    self.end_of_line()?;
+                }
+                Some(_) => {
+                    let (key, value) = self.key_value()?;
+                    insert(navigate(&mut root, &table)?, &key, value)?;
+                    self.end_of_line()?;
+                }
+            }
+        }
+        Ok(Value::Mapping(root))
+    }
+
+    fn key_value(&mut self) -> anyhow::Result<(Vec<String>, Value)> {
+        let key = self.key()?;
+        self.skip_space();
+        self.expect('=')?;
+        self.skip_space();
+        let value = self.value()?;
+        Ok((key, value))
+    }
+
+    /// A key, which may be dotted (e.g. `CashSweep.Symbol`)
+    fn key(&mut self) -> anyhow::Result<Vec<String>> {
+        let mut parts = Vec::new();
+        loop {
+            let part = match self.peek() {
+                Some('"') => self.basic_string()?,
+                Some('\'') => self.literal_string()?,
+                _ => {
+                    let mut part = String::new();
+                    while let Some(c) = self
+                        .peek()
+                        .filter(|c| c.is_ascii_alphanumeric() || *c == '_' || *c == '-')
+                    {
+                        part.push(c);
+                        self.next();
+                    }
+                    if part.is_empty() {
+                        bail!("Expected a key");
+                    }
+                    part
+                }
+            };
+            parts.push(part);
+            self.skip_space();
+            if self.peek() != Some('.') {
+                return Ok(parts);
+            }
+            self.next();
+            self.skip_space();
+        }
+    }
+
+    fn value(&mut self) -> anyhow::Result<Value> {
+        match self.peek() {
+            Some('"') => Ok(Value::String(self.basic_string()?)),
+            Some('\'') => Ok(Value::String(self.literal_string()?)),
+            Some('[') => {
+                self.next();
+                let mut items = Vec::new();
+                loop {
+                    self.skip_lines();
+                    if self.peek() == Some(']') {
+                        break;
+                    }
+                    items.push(self.value()?);
+                    self.skip_lines();
+                    if self.peek() == Some(',') {
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect(']')?;
+                Ok(Value::Sequence(items))
+            }
+            Some('{') => {
+                self.next();
+                let mut table = Mapping::new();
+                loop {
+                    self.skip_space();
+                    if self.peek() == Some('}') {
+                        break;
+                    }
+                    let (key, value) = self.key_value()?;
+                    insert(&mut table, &key, value)?;
+                    self.skip_space();
+                    if self.peek() == Some(',') {
+                        self.next();
+                    } else {
+                        break;
+                    }
+                }
+                self.expect('}')?;
+                Ok(Value::Mapping(table))
+            }
+            _ => {
+                let mut token = String::new();
+                while let Some(c) = self
+                    .peek()
+                    .filter(|c| c.is_ascii_alphanumeric() || "+-._:".contains(*c))
+                {
+                    token.push(c);
+                    self.next();
+                }
+                scalar(&token)
+            }
+        }
+    }
+
+    fn basic_string(&mut self) -> anyhow::Result<String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            if matches!(self.peek(), None | Some('\n')) {
+                bail!("Unterminated string");
+            }
+            match self.next() {
+                None => unreachable!(),
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('u') => {
+                        let code: String = (0..4).filter_map(|_| self.next()).collect();
+                        let c = u32::from_str_radix(&code, 16)
+                            .ok()
+                            .and_then(char::from_u32)
+                            .with_context(|| format!("Invalid escape '\\u{code}'"))?;
+                        s.push(c);
+                    }
+                    c => bail!("Invalid escape '\\{}'", c.unwrap_or_default()),
+                },
+                Some(c) => s.push(c),
+            }
+        }
+    }
+
+    fn literal_string(&mut self) -> anyhow::Result<String> {
+        self.expect('\'')?;
+        let mut s = String::new();
+        loop {
+            if matches!(self.peek(), None | Some('\n')) {
+                bail!("Unterminated string");
+            }
+            match self.next() {
+                Some('\'') => return Ok(s),
+                Some(c) => s.push(c),
+                None => unreachable!(),
+            }
+        }
+    }
+}
+
+/// A bare value: a boolean, a number or a date
+fn scalar(token: &str) -> anyhow::Result<Value> {
+    match token {
+        "" => bail!("Expected a value"),
+        "true" => return Ok(Value::Bool(true)),
+        "false" => return Ok(Value::Bool(false)),
+        _ => {}
+    }
+    let bytes = token.as_bytes();
+    if bytes.len() >= 10 && bytes[4] == b'-' && bytes[7] == b'-' {
+        return Ok(Value::String(token.to_string()));
+    }
+    let number = token.replace('_', "");
+    if let Ok(n) = number.parse::<i64>() {
+        Ok(Value::Number(n.into()))
+    } else if let Ok(n) = number.parse::<f64>() {
+        Ok(Value::Number(n.into()))
+    } else {
+        bail!("Invalid value '{token}'")
+    }
+}
+
+/// The table at `path` within `root`, which is created if it doesn't exist.
+/// For an array of tables, this is the last table in the array.
+fn navigate<'a>(root: &'a mut Mapping, path: &[String]) -> anyhow::Result<&'a mut Mapping> {
+    let mut table = root;
+    for key in path {
+        let value = table
+            .entry(Value::String(key.clone()))
+            .or_insert_with(|| Value::Mapping(Mapping::new()));
+        let value = match value {
+            Value::Sequence(tables) => tables.last_mut().context("Empty array of tables")?,
+            value => value,
+        };
+        table = match value {
+            Value::Mapping(m) => m,
+            _ => bail!("'{key}' already has a value, so it can't be a table"),
+        };
+    }
+    Ok(table)
+}
+
+fn insert(table: &mut Mapping, key: &[String], value: Value) -> anyhow::Result<()> {
+    let (last, parent) = key.split_last().context("Missing key")?;
+    let parent = navigate(table, parent)?;
+    if parent.contains_key(last.as_str()) {
+        bail!("'{last}' is defined more than once");
+    }
+    parent.insert(Value::String(last.clone()), value);
+    Ok(())
+}
+
+/// Write a mapping as a TOML document. Nulls are left out, since TOML has no
+/// way to write them.
+pub fn to_string(value: &Value) -> anyhow::Result<String> {
+    let Value::Mapping(table) = value else {
+        bail!("Only a table can be written as a TOML document");
+    };
+    let mut out = String::new();
+    write_table(&mut out, &[], table)?;
+    Ok(out)
+}
+
+/// Whether `value` is written as an array of tables rather than inline
+fn is_table_array(value: &Value) -> bool {
+    matches!(value, Value::Sequence(items)
+        if !items.is_empty() && items.iter().all(Value::is_mapping))
+}
+
+fn write_table(out: &mut String, path: &[String], table: &Mapping) -> anyhow::Result<()> {
+    let mut entries = Vec::new();
+    for (key, value) in table.iter().filter(|(_, value)| !value.is_null()) {
+        let key = key.as_str().context("Only string keys can be written")?;
+        entries.push((key, value));
+    }
+    // the keys of a table must come before any tables within it
+    for (key, value) in entries.iter().copied() {
+        if !value.is_mapping() && !is_table_array(value) {
+            *out += &format!("{} = {}\n", format_key(key), format_value(value)?);
+        }
+    }
+    for (key, value) in entries {
+        let path: Vec<String> = path.iter().cloned().chain([key.to_string()]).collect();
+        let header = path
+            .iter()
+            .map(|k| format_key(k))
+            .collect::<Vec<_>>()
+            .join(".");
+        match value {
+            Value::Mapping(table) => {
+                *out += &format!("\n[{header}]\n");
+                write_table(out, &path, table)?;
+            }
+            Value::Sequence(items) if is_table_array(value) => {
+                for item in items {
+                    *out += &format!("\n[[{header}]]\n");
+                    if let Value::Mapping(table) = item {
+                        write_table(out, &path, table)?;
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+    Ok(())
+}
+
+fn format_key(key: &str) -> String {
+    match !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+    {
+        true => key.to_string(),
+        false => format_string(key),
+    }
+}
+
+fn format_string(s: &str) -> String {
+    let mut out = String::from('"');
+    for c in s.chars() {
+        match c {
+            '"' => out += "\\\"",
+            '\\' => out += "\\\\",
+            '\n' => out += "\\n",
+            '\t' => out += "\\t",
+            '\r' => out += "\\r",
+            c if c.is_control() => out += &format!("\\u{:04X}", c as u32),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+    out
+}
+
+fn format_value(value: &Value) -> anyhow::Result<String> {
+    Ok(match value {
+        Value::Bool(b) => b.to_string(),
+        Value::Number(n) if n.is_f64() => {
+            let f = n.as_f64().unwrap_or_default();
+            match f {
+                f if f.is_nan() => "nan".to_string(),
+                f if f.is_infinite() && f > 0.0 => "inf".to_string(),
+                f if f.is_infinite() => "-inf".to_string(),
+                // a float needs a fractional part, or it would be read as an
+                // integer
+                f if f.fract() == 0.0 && f.abs() < 1e15 => format!("{f:.1}"),
+                f => f.to_string(),
+            }
+        }
+        Value::Number(n) => n.to_string(),
+        Value::String(s) => format_string(s),
+        Value::Sequence(items) => {
+            let items: Vec<String> = items.iter().map(format_value).collect::<Result<_, _>>()?;
+            format!("[{}]", items.join(", "))
+        }
+        Value::Mapping(table) => {
+            let mut entries = Vec::new();
+            for (key, value) in table.iter().filter(|(_, value)| !value.is_null()) {
+                let key = key.as_str().context("Only string keys can be written")?;
+                entries.push(format!("{} = {}", format_key(key), format_value(value)?));
+            }
+            match entries.is_empty() {
+                true => "{}".to_string(),
+                false => format!("{{ {} }}", entries.join(", ")),
+            }
+        }
+        Value::Null => bail!("TOML has no way to write a null value"),
+        Value::Tagged(tagged) => format_value(&tagged.value)?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_str() {
+        let text = r#"
+# a comment
+Title = "a \"quoted\" name"
+Path = 'C:\Users'
+
+[[Accounts]]
+AccountId = "123"   # trailing comment
+IgnoredHoldings = [
+    "IBM",
+    'T',
+]
+CashSweep.Symbol = "FZFXX"
+Limits = { Max = 1_000, Min = -2.5 }
+
+[Accounts.Targets]
+"VTI" = 60
+BND = 40.0
+
+[[Accounts]]
+AccountId = "456"
+Enabled = false
+Since = 2024-01-31
+"#;
+        let value = from_str(text).unwrap();
+        let expected: Value = serde_yaml::from_str(
+            r#"
+Title: a "quoted" name
+Path: C:\Users
+Accounts:
+  - AccountId: "123"
+    IgnoredHoldings: [IBM, T]
+    CashSweep:
+      Symbol: FZFXX
+    Limits:
+      Max: 1000
+      Min: -2.5
+    Targets:
+      VTI: 60
+      BND: 40.0
+  - AccountId: "456"
+    Enabled: false
+    Since: "2024-01-31"
+"#,
+        )
+        .unwrap();
+        assert_eq!(value, expected);
+    }
+
+    #[test]
+    fn test_to_string() {
+        let value: Value = serde_yaml::from_str(
+            r#"
+Title: a "quoted" name
+Empty: null
+Accounts:
+  - AccountId: "123"
+    Minimum: 1000.0
+    Count: 3
+    IgnoredHoldings: [IBM, T]
+    Groups: [[A, B], []]
+    Targets:
+      VTI: 60.5
+      "BRK.B": 39.5
+    Steps:
+      - Age: 60
+        Targets: {VTI: 50.0}
+  - AccountId: "456"
+    Enabled: false
+"#,
+        )
+        .unwrap();
+        let text = to_string(&value).unwrap();
+        assert_eq!(
+            text,
+            r#"Title = "a \"quoted\" name"
+
+[[Accounts]]
+AccountId = "123"
+Minimum = 1000.0
+Count = 3
+IgnoredHoldings = ["IBM", "T"]
+Groups = [["A", "B"], []]
+
+[Accounts.Targets]
+VTI = 60.5
+"BRK.B" = 39.5
+
+[[Accounts.Steps]]
+Age = 60
+
+[Accounts.Steps.Targets]
+VTI = 50.0
+
+[[Accounts]]
+AccountId = "456"
+Enabled = false
+"#
+        );
+        let mut expected = value.clone();
+        expected.as_mapping_mut().unwrap().remove("Empty");
+        assert_eq!(from_str(&text).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = |text: &str| format!("{:#}", from_str(text).unwrap_err());
+        assert!(error("A = 1\nB = \n").contains("line 2"));
+        assert!(error("A = 1\nA = 2\n").contains("more than once"));
+        assert!(error("A = \"open\n").contains("Unterminated"));
+        assert!(error("A = 1\n[A]\n").contains("can't be a table"));
+        assert!(error("A = 1 2\n").contains("end of the line"));
+        assert!(error("A = yes\n").contains("Invalid value"));
+    }
+}
@@ -0,0 +1,221 @@
+//! Recent trades, which are used to warn about plans that would cause a
+//! wash sale.
+//!
+//! Trades are read from a CSV file with a header row. The `Date`, `Symbol` and
+//! `Action` (`Buy` or `Sell`) columns are required, and the `Gain` column
+//! (the realized gain or loss of a sell) and `Account` column are optional.
+//! Column names are matched without regard to case.
+
+use std::io::Read;
+
+use anyhow::{Context, anyhow, bail};
+use tracing::debug;
+
+use crate::{
+    Action, Dollar, account::RebalancePlan, csv_file::CsvFile, date::Date, provider::parse_dollar,
+};
+
+/// Buying a security within this many days of selling it (or an equivalent
+/// security) at a loss is a wash sale
+pub const WASH_SALE_DAYS: i64 = 30;
+
+/// Whether a trade bought or sold shares
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum TradeAction {
+    Buy,
+    Sell,
+}
+
+/// A single trade that was made in the past
+#[derive(Debug, Clone, PartialEq)]
+pub struct Trade {
+    pub date: Date,
+    /// The account that the trade was made in, if it is known. Trades without
+    /// an account apply to every account.
+    pub account_id: Option<String>,
+    pub symbol: String,
+    pub action: TradeAction,
+    /// The gain (or loss, if negative) that a sell realized, if it is known
+    pub gain: Option<Dollar>,
+}
+
+/// Load the trades from a CSV file
+pub fn load_trades(reader: &mut dyn Read) -> anyhow::Result<Vec<Trade>> {
+    let file = CsvFile::new(reader)?;
+    let (Some(date_column), Some(symbol_column), Some(action_column)) = (
+        file.column("Date"),
+        file.column("Symbol"),
+        file.column("Action"),
+    ) else {
+        bail!("The trades file must have 'Date', 'Symbol' and 'Action' columns");
+    };
+    let gain_column = file.column("Gain");
+    let account_column = file.column("Account");
+
+    let mut trades = Vec::new();
+    for row in file.rows() {
+        let row = row?;
+        let line = row.line;
+        let field = |c: usize| row.field(c);
+        let date = field(date_column)
+            .parse()
+            .with_context(|| format!("Invalid date on line {line} of the trades file"))?;
+        let action = match field(action_column).to_ascii_lowercase().as_str() {
+            "buy" | "bought" => TradeAction::Buy,
+            "sell" | "sold" => TradeAction::Sell,
+            other => {
+                return Err(anyhow!(
+                    "Unknown action '{other}' on line {line} of the trades file (expected 'Buy' or 'Sell')"
+                ));
+            }
+        };
+        let trade = Trade {
+            date,
+            account_id: account_column
+                .map(field)
+                .filter(|id| !id.is_empty())
+                .map(str::to_string),
+            symbol: field(symbol_column).to_string(),
+            action,
+            gain: gain_column.map(field).and_then(parse_dollar),
+        };
+        debug!(?trade, "parsed trade");
+        trades.push(trade);
+    }
+    Ok(trades)
+}
+
+/// Warn about the buys in `plan` that may be wash sales, because the same
+/// security, or one of the `equivalents` of it, was sold at a loss in the
+/// [WASH_SALE_DAYS] before `today` or is sold at a loss by the plan itself
+pub fn wash_sale_warnings(
+    plan: &RebalancePlan,
+    trades: &[Trade],
+    equivalents: &[Vec<String>],
+    today: Date,
+) -> Vec<String> {
+    let equivalent = |a: &str, b: &str| {
+        a == b
+            || equivalents
+                .iter()
+                .any(|group| group.iter().any(|s| s == a) && group.iter().any(|s| s == b))
+    };
+    let mut warnings = Vec::new();
+    for buy in plan
+        .adjustments
+        .iter()
+        .filter(|adj| !adj.ignored && !adj.holding.is_cash)
+        .filter(|adj| matches!(adj.action, Action::Buy(_)))
+    {
+        let symbol = &buy.holding.symbol;
+        let recent_loss = trades
+            .iter()
+            .filter(|trade| {
+                trade.action == TradeAction::Sell
+                    && trade.gain.is_some_and(|gain| gain < Dollar(0.0))
+                    && trade
+                        .account_id
+                        .as_ref()
+                        .is_none_or(|id| *id == plan.account_id)
+                    && (0..=WASH_SALE_DAYS).contains(&trade.date.days_until(today))
+                    && equivalent(&trade.symbol, symbol)
+            })
+            .max_by_key(|trade| trade.date);
+        if let Some(trade) = recent_loss {
+            warnings.push(format!(
+                "Buying '{symbol}' may be a wash sale: '{}' was sold at a loss on {}",
+                trade.symbol, trade.date
+            ));
+        }
+        for sell in plan.adjustments.iter().filter(|adj| {
+            !adj.ignored
+                && adj.realized_gain().is_some_and(|gain| gain < Dollar(0.0))
+                && equivalent(&adj.holding.symbol, symbol)
+        }) {
+            warnings.push(format!(
+                "Buying '{symbol}' may be a wash sale: '{}' is sold at a loss in the same plan",
+                sell.holding.symbol
+            ));
+        }
+    }
+    warnings
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::account::{Holding, PositionAdjustment};
+
+    #[test]
+    fn test_load_trades() {
+        let csv = "Date,Symbol,Action,Gain\n\
+                   2026-10-01,VTI,Sell,\"-$1,200.00\"\n\
+                   2026-09-01,VXUS,buy,\n";
+        let trades = load_trades(&mut csv.as_bytes()).unwrap();
+        assert_eq!(
+            trades,
+            vec![
+                Trade {
+                    date: Date::new(2026, 10, 1).unwrap(),
+                    account_id: None,
+                    symbol: "VTI".to_string(),
+                    action: TradeAction::Sell,
+                    gain: Some(Dollar(-1200.0)),
+                },
+                Trade {
+                    date: Date::new(2026, 9, 1).unwrap(),
+                    account_id: None,
+                    symbol: "VXUS".to_string(),
+                    action: TradeAction::Buy,
+                    gain: None,
+                },
+            ]
+        );
+
+        let err = load_trades(&mut "Date,Symbol,Action\n2026-10-01,VTI,Hold\n".as_bytes());
+        assert!(err.is_err());
+        assert!(load_trades(&mut "Date,Symbol\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_wash_sale_warnings() {
+        let buy = |symbol: &str| PositionAdjustment {
+            holding: Holding {
+                symbol: symbol.to_string(),
+                ..Default::default()
+            },
+            action: Action::Buy(Dollar(100.0)),
+            ..Default::default()
+        };
+        let plan = RebalancePlan {
+            account_id: "123".to_string(),
+            adjustments: vec![buy("VTI"), buy("ITOT"), buy("BND")],
+            ..Default::default()
+        };
+        let sale = |symbol: &str, date, gain| Trade {
+            date,
+            account_id: None,
+            symbol: symbol.to_string(),
+            action: TradeAction::Sell,
+            gain: Some(Dollar(gain)),
+        };
+        let today = Date::new(2026, 10, 14).unwrap();
+        let trades = vec![
+            sale("VTI", Date::new(2026, 10, 1).unwrap(), -500.0),
+            // too long ago
+            sale("BND", Date::new(2026, 9, 1).unwrap(), -500.0),
+            // not a loss
+            sale("BND", Date::new(2026, 10, 10).unwrap(), 500.0),
+        ];
+        let equivalents = vec![vec!["VTI".to_string(), "ITOT".to_string()]];
+        let warnings = wash_sale_warnings(&plan, &trades, &equivalents, today);
+        assert_eq!(
+            warnings,
+            vec![
+                "Buying 'VTI' may be a wash sale: 'VTI' was sold at a loss on 2026-10-01",
+                "Buying 'ITOT' may be a wash sale: 'VTI' was sold at a loss on 2026-10-01",
+            ]
+        );
+        assert!(wash_sale_warnings(&plan, &trades, &[], today).len() == 1);
+    }
+}
@@ -0,0 +1,120 @@
+use std::collections::HashMap;
+
+use driftfix::{
+    Dollar, Percent,
+    account::{AllocationConfig, Balance, CashConfig, Holding},
+    invariants::check_invariants,
+};
+
+/// A tiny deterministic pseudo-random number generator so that failures are
+/// reproducible without pulling in an extra dependency
+struct Lcg(u64);
+
+impl Lcg {
+    fn next(&mut self) -> u64 {
+        self.0 = self
+            .0
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        self.0 >> 33
+    }
+
+    fn below(&mut self, n: u64) -> u64 {
+        self.next() % n
+    }
+}
+
+fn random_case(rng: &mut Lcg) -> (AllocationConfig, Balance) {
+    let nsymbols = 1 + rng.below(5) as usize;
+    let symbols: Vec<String> = (0..nsymbols).map(|i| format!("SYM{i}")).collect();
+
+    // split 100% into integer pieces so that the targets sum exactly
+    let mut remaining = 100;
+    let mut targets = HashMap::new();
+    for (i, symbol) in symbols.iter().enumerate() {
+        let pct = if i == nsymbols - 1 {
+            remaining
+        } else {
+            rng.below(remaining + 1)
+        };
+        remaining -= pct;
+        targets.insert(symbol.clone(), Percent(pct as f32));
+    }
+
+    let mut holdings = vec![Holding {
+        symbol: "CORE".to_string(),
+        current_value: Dollar(rng.below(20_000) as f32),
+        is_cash: true,
+        ..Default::default()
+    }];
+    for symbol in symbols.iter() {
+        if rng.below(4) != 0 {
+            holdings.push(Holding {
+                symbol: symbol.clone(),
+                current_value: Dollar(rng.below(50_000) as f32),
+                is_cash: false,
+                ..Default::default()
+            });
+        }
+    }
+    let ignored_holdings = if rng.below(3) == 0 {
+        holdings.push(Holding {
+            symbol: "IGNORED".to_string(),
+            current_value: Dollar(rng.below(10_000) as f32),
+            is_cash: false,
+            ..Default::default()
+        });
+        vec!["IGNORED".to_string()]
+    } else {
+        vec![]
+    };
+
+    let config = AllocationConfig {
+        account_id: "123".to_string(),
+        nickname: None,
+        cash_sweep: Some(CashConfig {
+            symbol: "CORE".to_string(),
+            minimum: Dollar(rng.below(5_000) as f32),
+            ..Default::default()
+        }),
+        targets,
+        ignored_holdings,
+        ..Default::default()
+    };
+    let balance = Balance {
+        account_id: "123".to_string(),
+        account_name: "Random".to_string(),
+        holdings,
+    };
+    (config, balance)
+}
+
+#[test]
+fn random_plans_satisfy_invariants() {
+    let mut rng = Lcg(0x5eed);
+    for _ in 0..500 {
+        let (config, balance) = random_case(&mut rng);
+        let plan = config
+            .adjust_allocations(&balance)
+            .expect("Failed to calculate plan");
+        if let Err(violations) = check_invariants(&config, &balance, &plan) {
+            panic!("{violations:?} for {config:?} and {balance:?}");
+        }
+    }
+}
+
+#[test]
+fn tampered_plan_violates_invariants() {
+    let mut rng = Lcg(42);
+    let (config, balance) = random_case(&mut rng);
+    let mut plan = config
+        .adjust_allocations(&balance)
+        .expect("Failed to calculate plan");
+    let core = plan
+        .adjustments
+        .iter_mut()
+        .find(|adj| adj.holding.is_cash)
+        .unwrap();
+    core.action = driftfix::Action::Buy(Dollar(1_000_000.0));
+    assert!(check_invariants(&config, &balance, &plan).is_err());
+}
@@ -1,8 +1,12 @@
-use std::io::Cursor;
+use std::io::{BufRead, Cursor};
 
 use driftfix::{
     Dollar,
-    provider::{self, ProviderType},
+    account::{Balance, Holding},
+    conformance::{ExpectedAccount, check_balance_provider, check_portfolio, check_provider},
+    date::Date,
+    fx,
+    provider::{self, BalanceProvider, ColumnMapping, CsvFormat, ProviderRegistry, ProviderType},
 };
 const FIDELITY_CSV: &str = r#"
 Account Number,Account Name,Symbol,Description,Quantity,Last Price,Last Price Change,Current Value,Today's Gain/Loss Dollar,Today's Gain/Loss Percent,Total Gain/Loss Dollar,Total Gain/Loss Percent,Percent Of Account,Cost Basis Total,Average Cost Basis,Type
@@ -10,12 +14,265 @@ Z12345678,INDIVIDUAL - TOD,AAPL,APPLE INC,10.000,$175.43,+$1.24,$1754.30,+$12.40
 Z12345678,INDIVIDUAL - TOD,SPAXX**,FIDELITY GOVERNMENT MONEY MARKET,500.00,1.00,--,$500.00,--,--,--,--,4.4%,$500.00,$1.00,Cash
 Y98765432,ROTH IRA,FXAIX,FIDELITY 500 INDEX FUND,150.444,$165.12,-$0.45,$24841.31,-$67.70,-0.27%,+$2144.12,+9.44%,80.1%,$22697.19,$150.87,Cash
 Y98765432,ROTH IRA,FTIHX,FIDELITY INTL INDEX FUND,50.000,$42.10,+$0.12,$2105.00,+$6.00,+0.29%,-$100.00,-4.53%,10.0%,$2205.00,$44.10,Cash"#;
+const NETBENEFITS_CSV: &str = r#"Plan Number,Plan Name,Investment Name,Source,Shares/Units,Closing Price,Total Balance
+12345,ACME CORP 401(K) PLAN,FID 500 INDEX,Employee Deferral,50.000,$150.00,"$7,500.00"
+12345,ACME CORP 401(K) PLAN,FID 500 INDEX,Employer Match,20.000,$150.00,"$3,000.00"
+12345,ACME CORP 401(K) PLAN,VANG INST TR 2055,Employee Deferral,100.000,$40.00,"$4,000.00"
+12345,ACME CORP 401(K) PLAN,FIDELITY STABLE VALUE,Employee Deferral,1500.000,$1.00,"$1,500.00"
+
+"Date downloaded 01/15/2025 09:41 AM ET"
+"The data and information in this spreadsheet is provided to you solely for your use and is not for distribution."
+"#;
 const VANGUARD_CSV: &str = r#"Account Number,Investment Name,Symbol,Shares,Share Price,Total Value
 12345678,Vanguard Federal Money Market Fund,VMFXX,1000.00,1.00,1000.00
 12345678,Vanguard Total Stock Market Index Fund Admiral Shares,VTSAX,250.500,118.42,29664.21
 12345678,Vanguard Total Bond Market II Index Fund Investor Shares,VTBIX,100.000,10.25,1025.00
 87654321,Vanguard S&P 500 ETF,VOO,15.250,465.10,7092.78
 87654321,Vanguard Federal Money Market Fund,VMFXX,500.000,1.00,500.00"#;
+const SCHWAB_CSV: &str = r#""Positions for All-Accounts as of 09:41 AM ET, 2025/01/15"
+
+"Individual ...123"
+"Symbol","Description","Quantity","Price","Price Change %","Price Change $","Market Value","Day Change %","Day Change $","Cost Basis","Gain/Loss %","Gain/Loss $","Security Type"
+"VTI","VANGUARD TOTAL STOCK MARKET ETF","10","$240.00","0.5%","$1.20","$2,400.00","0.5%","$12.00","$2,000.00","20%","$400.00","ETFs & Closed End Funds"
+"SCHB","SCHWAB US BROAD MARKET ETF","50","$20.00","0.1%","$0.02","$1,000.00","0.1%","$1.00","$900.00","11.1%","$100.00","ETFs & Closed End Funds"
+"Cash & Cash Investments","--","--","--","--","--","$1,234.56","--","--","--","--","--","Cash and Money Market"
+"Account Total","--","--","--","--","--","$4,634.56","--","--","--","--","--","--"
+
+"Roth IRA ...456"
+"Symbol","Description","Quantity","Price","Price Change %","Price Change $","Market Value","Day Change %","Day Change $","Cost Basis","Gain/Loss %","Gain/Loss $","Security Type"
+"SWTSX","SCHWAB TOTAL STOCK MARKET INDEX","100","$15.00","0.2%","$0.03","$1,500.00","0.2%","$3.00","$1,400.00","7.1%","$100.00","Mutual Funds"
+"Cash & Cash Investments","--","--","--","--","--","$0.00","--","--","--","--","--","Cash and Money Market"
+"Account Total","--","--","--","--","--","$1,500.00","--","--","--","--","--","--"
+"#;
+const ETRADE_CSV: &str = r#"Account Summary
+
+Account,Net Account Value,Total Gain $,Total Gain %,Day's Gain Unrealized $,Day's Gain Unrealized %,Available For Withdrawal,Cash Purchasing Power
+Individual Brokerage -1234,"3,400.00",400.00,13.33,10.00,0.29,"1,000.00","1,000.00"
+
+View Summary - All Positions
+Symbol,Last Price $,Change $,Change %,Quantity,Price Paid $,Day's Gain $,Total Gain $,Total Gain %,Value $
+VTI,240.00,1.00,0.42,10,200.00,10.00,400.00,20.00,"2,400.00"
+CASH,,,,,,,,,"1,000.00"
+TOTAL,,,,,,10.00,400.00,13.33,"3,400.00"
+
+Generated at Jan 15 2025 09:41 AM ET
+"#;
+const MERRILL_CSV: &str = r#""Exported on: 01/15/2025 09:41 AM ET"
+
+"Account: CMA-Edge 12X-34567"
+"Symbol","Description","Quantity","Price","Value","Day's Value Change","Unrealized Gain/Loss"
+"VTI","VANGUARD TOTAL STOCK MARKET ETF","10","$240.00","$2,400.00","$12.00","$400.00"
+"--","ML Bank Deposit Program","","","$1,000.00","",""
+"Total","","","","$3,400.00","",""
+
+"Account: Roth IRA 34X-56789"
+"Symbol","Description","Quantity","Price","Value","Day's Value Change","Unrealized Gain/Loss"
+"VXUS","VANGUARD TOTAL INTL STOCK ETF","20","$60.00","$1,200.00","-$2.00","$50.00"
+"Total","","","","$1,200.00","",""
+"#;
+const IBKR_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<FlexQueryResponse queryName="Positions" type="AF">
+<FlexStatements count="1">
+<FlexStatement accountId="U1234567" fromDate="20250115" toDate="20250115" period="LastBusinessDay">
+<AccountInformation accountId="U1234567" acctAlias="Main &amp; Only" currency="USD" name="Jane Doe" />
+<OpenPositions>
+<OpenPosition accountId="U1234567" currency="USD" fxRateToBase="1" symbol="VTI" position="10" markPrice="240" positionValue="2400" />
+<OpenPosition accountId="U1234567" currency="EUR" fxRateToBase="1.1" symbol="VWCE" position="10" markPrice="100" positionValue="1000" />
+</OpenPositions>
+<CashReport>
+<CashReportCurrency accountId="U1234567" currency="BASE_SUMMARY" endingCash="1600" />
+<CashReportCurrency accountId="U1234567" currency="USD" endingCash="500" />
+<CashReportCurrency accountId="U1234567" currency="EUR" endingCash="1000" />
+</CashReport>
+</FlexStatement>
+</FlexStatements>
+</FlexQueryResponse>
+"#;
+const ROBINHOOD_CSV: &str = r#"Name,Symbol,Quantity,Average Cost,Price,Equity,Total Return
+Vanguard Total Stock Market ETF,VTI,3.254187,$210.00,$240.00,"$781.00",$97.64
+Apple,AAPL,0.5,$150.00,$180.00,,$15.00
+Cash,,,,,"$1,250.50",
+"#;
+const BETTERMENT_CSV: &str = r#"Goal,Account,Ticker,Fund Name,Shares,Price,Value
+Retirement,Roth IRA,VTI,Vanguard Total Stock Market ETF,10.5,$240.00,"$2,520.00"
+Retirement,Roth IRA,VEA,Vanguard FTSE Developed Markets ETF,20,$50.00,"$1,000.00"
+Retirement,Roth IRA,,Cash,,,$12.34
+Safety Net,Taxable,BNDX,Vanguard Total International Bond ETF,30,$49.00,"$1,470.00"
+"#;
+const WEALTHFRONT_CSV: &str = r#"Account Name,Account Number,Symbol,Description,Quantity,Market Value
+Individual Investment Account,8W123456,VTI,Vanguard Total Stock Market ETF,12.25,"$2,940.00"
+Individual Investment Account,8W123456,VIG,Vanguard Dividend Appreciation ETF,5,$900.00
+Individual Investment Account,8W123456,Cash,Uninvested cash,,$45.67
+"#;
+const GENERIC_CSV: &str = r#"Acct,Nickname,Ticker,Balance,Sweep
+A-1,Brokerage,VTI,"$2,400.00",
+A-1,Brokerage,MMF,$100.00,yes
+,,,,
+B-2,IRA,BND,$500.00,
+"#;
+const OFX_SGML: &str = r#"OFXHEADER:100
+DATA:OFXSGML
+VERSION:102
+SECURITY:NONE
+ENCODING:USASCII
+
+<OFX>
+<SIGNONMSGSRSV1><SONRS><STATUS><CODE>0<SEVERITY>INFO</STATUS><DTSERVER>20250115094100</SONRS></SIGNONMSGSRSV1>
+<INVSTMTMSGSRSV1>
+<INVSTMTTRNRS>
+<TRNUID>1
+<INVSTMTRS>
+<DTASOF>20250115
+<CURDEF>USD
+<INVACCTFROM><BROKERID>example.com<ACCTID>555000111</INVACCTFROM>
+<INVPOSLIST>
+<POSMF><INVPOS><SECID><UNIQUEID>922908769<UNIQUEIDTYPE>CUSIP</SECID><HELDINACCT>CASH<POSTYPE>LONG<UNITS>10<UNITPRICE>240.00<MKTVAL>2400.00<DTPRICEASOF>20250115</INVPOS></POSMF>
+<POSSTOCK><INVPOS><SECID><UNIQUEID>037833100<UNIQUEIDTYPE>CUSIP</SECID><HELDINACCT>CASH<POSTYPE>LONG<UNITS>5<UNITPRICE>180.00<MKTVAL>900.00<DTPRICEASOF>20250115</INVPOS></POSSTOCK>
+</INVPOSLIST>
+<INVBAL><AVAILCASH>250.75<MARGINBALANCE>0<SHORTBALANCE>0</INVBAL>
+</INVSTMTRS>
+</INVSTMTTRNRS>
+</INVSTMTMSGSRSV1>
+<SECLISTMSGSRSV1><SECLIST>
+<MFINFO><SECINFO><SECID><UNIQUEID>922908769<UNIQUEIDTYPE>CUSIP</SECID><SECNAME>VANGUARD TOTAL STOCK MARKET ETF<TICKER>VTI</SECINFO></MFINFO>
+<STOCKINFO><SECINFO><SECID><UNIQUEID>037833100<UNIQUEIDTYPE>CUSIP</SECID><SECNAME>APPLE INC<TICKER>AAPL</SECINFO></STOCKINFO>
+</SECLIST></SECLISTMSGSRSV1>
+</OFX>
+"#;
+const OFX_XML: &str = r#"<?xml version="1.0" encoding="UTF-8"?>
+<?OFX OFXHEADER="200" VERSION="220" SECURITY="NONE"?>
+<OFX>
+  <INVSTMTMSGSRSV1><INVSTMTTRNRS><INVSTMTRS>
+    <INVACCTFROM><BROKERID>example.com</BROKERID><ACCTID>777</ACCTID></INVACCTFROM>
+    <INVPOSLIST>
+      <POSSTOCK><INVPOS><SECID><UNIQUEID>921937835</UNIQUEID><UNIQUEIDTYPE>CUSIP</UNIQUEIDTYPE></SECID><MKTVAL>1000.50</MKTVAL></INVPOS></POSSTOCK>
+    </INVPOSLIST>
+  </INVSTMTRS></INVSTMTTRNRS></INVSTMTMSGSRSV1>
+</OFX>
+"#;
+const QIF: &str = r#"!Option:AutoSwitch
+!Account
+NChecking
+TBank
+^
+NBrokerage
+TInvst
+^
+!Clear:AutoSwitch
+!Type:Security
+NVanguard Total Stock Market ETF
+SVTI
+TETF
+^
+!Account
+NBrokerage
+TInvst
+^
+!Type:Invst
+D1/2'25
+NXIn
+T5,000.00
+^
+D1/3'25
+NBuy
+YVanguard Total Stock Market ETF
+I230.00
+Q10
+T2,300.00
+^
+D1/4'25
+NBuy
+YBND
+I72.00
+Q20
+T1,440.00
+^
+D1/10'25
+NSell
+YBND
+I73.00
+Q5
+T365.00
+^
+D1/12'25
+NDiv
+YVanguard Total Stock Market ETF
+T12.50
+^
+D1/13'25
+NReinvDiv
+YBND
+I72.50
+Q1
+T72.50
+^
+!Type:Prices
+"VTI",240.00,"1/15'25"
+^
+"#;
+const BEANCOUNT: &str = r#"option "operating_currency" "USD"
+
+2025-01-01 open Assets:Brokerage:Cash USD
+2025-01-01 open Assets:Brokerage:VTI VTI
+2025-01-01 open Assets:Checking USD
+
+2025-01-02 * "Deposit"
+  Assets:Brokerage:Cash  5000.00 USD
+  Assets:Checking
+
+2025-01-03 * "Buy VTI"
+  Assets:Brokerage:VTI  10 VTI {230.00 USD}
+  Assets:Brokerage:Cash
+
+2025-01-04 * "Buy BND"
+  Assets:Brokerage  15 BND {72.00 USD}
+  Assets:Brokerage:Cash  -1080.00 USD
+
+2025-01-15 price VTI 240.00 USD
+2025-01-10 price VTI 235.00 USD
+"#;
+const HLEDGER: &str = r#"; prices can also come from a separate price db
+P 2025/01/15 VTI $240.00
+
+2025/01/02 Deposit
+    assets:brokerage:cash        $5,000.00
+    assets:checking
+
+2025/01/03 Buy VTI
+    assets:brokerage:vti         10 VTI @ $230.00
+    assets:brokerage:cash
+"#;
+const PORTFOLIO_PERFORMANCE_XML: &str = include_str!("fixtures/portfolio-performance.xml");
+const COINBASE_CSV: &str = "\
+Balances report generated on 2025-01-15
+User,jane@example.com
+Portfolio,Asset,Quantity,USD Value
+Default,BTC,0.05,\"$4,950.00\"
+Default,ETH,1.2,\"$3,960.00\"
+Default,USD,250.00,$250.00
+Default,DOGE,0,$0.00
+Savings,usdc,500,$500.00
+";
+const EXCHANGE_CSV: &str = "\
+Currency,Balance,Value (USD)
+BTC,0.01,990.00
+SOL,3,
+";
+// indented with tabs, and with an escaped character in a string
+const PORTFOLIO_JSON: &str = r#"{
+	"version": 1,
+	"accounts": [
+		{
+			"account_id": "123",
+			"account_name": "Caf\u00e9 fund",
+			"holdings": [
+				{"symbol": "VTI", "value": 1500.25},
+				{"symbol": "SPAXX", "value": 99.75, "is_cash": true}
+			]
+		},
+		{"account_id": "456", "holdings": []}
+	]
+}"#;
 
 #[test]
 fn parse_fidelity() {
@@ -33,11 +290,37 @@ fn parse_fidelity() {
     assert_eq!(individual.holdings[0].symbol, "AAPL");
     assert_eq!(individual.holdings[0].current_value, Dollar(1754.30));
     assert!(!individual.holdings[0].is_cash);
+    assert_eq!(individual.holdings[0].quantity, Some(10.0));
+    assert_eq!(individual.holdings[0].price, Some(Dollar(175.43)));
+    assert_eq!(individual.holdings[0].cost_basis, Some(Dollar(1600.00)));
+    assert_eq!(
+        individual.holdings[0]
+            .unrealized_gain()
+            .map(|g| g.round_cents()),
+        Some(Dollar(154.30))
+    );
     assert_eq!(individual.holdings[1].symbol, "SPAXX");
     assert_eq!(individual.holdings[1].current_value, Dollar(500.00));
     assert!(individual.holdings[1].is_cash);
 }
 
+#[test]
+fn parse_netbenefits() {
+    let mut reader = Cursor::new(NETBENEFITS_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::NetBenefits))
+        .expect("Failed to parse NetBenefits example");
+
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "12345");
+    assert_eq!(account.account_name, "ACME CORP 401(K) PLAN");
+    assert_eq!(account.holdings.len(), 3);
+    // balances from each contribution source are combined
+    assert_eq!(account.holdings[0].symbol, "FID 500 INDEX");
+    assert_eq!(account.holdings[0].current_value, Dollar(10500.00));
+    assert!(account.holdings.iter().all(|h| !h.is_cash));
+}
+
 #[test]
 fn parse_vanguard() {
     let mut reader = Cursor::new(VANGUARD_CSV);
@@ -56,6 +339,658 @@ fn parse_vanguard() {
     assert!(vmfxx.is_cash);
 }
 
+#[test]
+fn parse_schwab() {
+    let mut reader = Cursor::new(SCHWAB_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Schwab))
+        .expect("Failed to parse schwab example");
+
+    assert_eq!(portfolio.len(), 2);
+
+    let individual = portfolio.iter().find(|a| a.account_id == "...123").unwrap();
+    assert_eq!(individual.account_name, "Individual");
+    assert_eq!(individual.holdings.len(), 3);
+    assert_eq!(individual.holdings[0].symbol, "VTI");
+    assert_eq!(individual.holdings[0].current_value, Dollar(2400.00));
+    let cash = individual.holdings.iter().find(|p| p.is_cash).unwrap();
+    assert_eq!(cash.symbol, "CASH");
+    assert_eq!(cash.current_value, Dollar(1234.56));
+    assert_eq!(individual.total_value(), Dollar(4634.56));
+}
+
+#[test]
+fn parse_etrade() {
+    let mut reader = Cursor::new(ETRADE_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Etrade))
+        .expect("Failed to parse E*TRADE example");
+
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "1234");
+    assert_eq!(account.account_name, "Individual Brokerage");
+    assert_eq!(account.holdings.len(), 2);
+    assert_eq!(account.holdings[0].symbol, "VTI");
+    assert_eq!(account.holdings[0].current_value, Dollar(2400.00));
+    assert!(account.holdings[1].is_cash);
+    assert_eq!(account.total_value(), Dollar(3400.00));
+}
+
+#[test]
+fn parse_merrill() {
+    let mut reader = Cursor::new(MERRILL_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::MerrillEdge))
+        .expect("Failed to parse Merrill Edge example");
+
+    assert_eq!(portfolio.len(), 2);
+    let cma = portfolio
+        .iter()
+        .find(|a| a.account_id == "12X-34567")
+        .unwrap();
+    assert_eq!(cma.account_name, "CMA-Edge");
+    assert_eq!(cma.holdings.len(), 2);
+    let sweep = cma.holdings.iter().find(|p| p.is_cash).unwrap();
+    assert_eq!(sweep.symbol, "CASH");
+    assert_eq!(sweep.current_value, Dollar(1000.00));
+    assert_eq!(cma.total_value(), Dollar(3400.00));
+}
+
+#[test]
+fn parse_interactive_brokers() {
+    let mut reader = Cursor::new(IBKR_XML);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::InteractiveBrokers))
+        .expect("Failed to parse Interactive Brokers example");
+
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "U1234567");
+    assert_eq!(account.account_name, "Main & Only");
+    assert_eq!(account.holdings.len(), 4);
+    let vwce = account
+        .holdings
+        .iter()
+        .find(|p| p.symbol == "VWCE")
+        .unwrap();
+    assert_eq!(vwce.current_value, Dollar(1100.0));
+    assert_eq!(vwce.currency.as_deref(), Some("EUR"));
+    let eur = account.holdings.iter().find(|p| p.symbol == "EUR").unwrap();
+    assert!(eur.is_cash);
+    assert_eq!(eur.current_value, Dollar(1100.0));
+    let usd = account.holdings.iter().find(|p| p.symbol == "USD").unwrap();
+    assert!(usd.is_cash);
+    assert_eq!(usd.current_value, Dollar(500.0));
+}
+
+#[test]
+fn parse_robinhood() {
+    let mut reader = Cursor::new(ROBINHOOD_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Robinhood))
+        .expect("Failed to parse Robinhood example");
+
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "robinhood");
+    assert_eq!(account.holdings.len(), 3);
+    assert_eq!(account.holdings[0].current_value, Dollar(781.00));
+    // the value of fractional shares is calculated when equity is missing
+    assert_eq!(account.holdings[1].current_value, Dollar(90.00));
+    let cash = account.holdings.iter().find(|p| p.is_cash).unwrap();
+    assert_eq!(cash.symbol, "CASH");
+    assert_eq!(cash.current_value, Dollar(1250.50));
+}
+
+#[test]
+fn parse_betterment() {
+    let mut reader = Cursor::new(BETTERMENT_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Betterment))
+        .expect("Failed to parse Betterment example");
+
+    assert_eq!(portfolio.len(), 2);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "Retirement - Roth IRA");
+    assert_eq!(account.account_name, "Retirement");
+    assert_eq!(account.holdings.len(), 3);
+    let cash = account.holdings.iter().find(|p| p.is_cash).unwrap();
+    assert_eq!(cash.symbol, "CASH");
+    assert_eq!(cash.current_value, Dollar(12.34));
+}
+
+#[test]
+fn parse_wealthfront() {
+    let mut reader = Cursor::new(WEALTHFRONT_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Wealthfront))
+        .expect("Failed to parse Wealthfront example");
+
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "8W123456");
+    assert_eq!(account.holdings.len(), 3);
+    assert_eq!(account.holdings[0].current_value, Dollar(2940.00));
+    let cash = account.holdings.iter().find(|p| p.is_cash).unwrap();
+    assert_eq!(cash.symbol, "CASH");
+}
+
+#[test]
+fn parse_generic() {
+    let mapping = ColumnMapping {
+        account_number: "Acct".to_string(),
+        account_name: Some("Nickname".to_string()),
+        symbol: "Ticker".to_string(),
+        value: "Balance".to_string(),
+        cash: Some("Sweep".to_string()),
+        currency: None,
+        maturity: None,
+    };
+    let mut warnings = Vec::new();
+    let portfolio =
+        provider::load_generic_portfolio(&mut Cursor::new(GENERIC_CSV), &mapping, &mut warnings)
+            .expect("Failed to parse generic example");
+    assert!(warnings.is_empty());
+    check_portfolio(
+        &portfolio,
+        &[
+            ExpectedAccount::new("A-1", Dollar(2500.00), &["MMF"]),
+            ExpectedAccount::new("B-2", Dollar(500.00), &[]),
+        ],
+    )
+    .unwrap();
+    assert_eq!(portfolio[0].account_name, "Brokerage");
+
+    // the default mapping doesn't match these columns
+    let result =
+        provider::load_portfolio(&mut Cursor::new(GENERIC_CSV), Some(ProviderType::Generic));
+    assert!(result.is_err());
+}
+
+#[test]
+fn parse_fidelity_pending_activity() {
+    // pending activity can come before the core position
+    let csv = "\
+Account Number,Account Name,Symbol,Description,Current Value
+Z12345678,INDIVIDUAL,Pending activity,,$250.00
+Z12345678,INDIVIDUAL,SPAXX**,FIDELITY GOVERNMENT MONEY MARKET,$500.00
+";
+    let portfolio = provider::load_portfolio(&mut Cursor::new(csv), Some(ProviderType::Fidelity))
+        .expect("Failed to parse fidelity example with pending activity");
+    let holdings = &portfolio[0].holdings;
+    assert_eq!(holdings.len(), 2);
+    assert_eq!(holdings[0].symbol, "Pending activity");
+    assert_eq!(holdings[0].current_value, Dollar(250.00));
+    assert!(holdings[0].pending && !holdings[0].is_cash);
+    assert!(holdings[1].is_cash && !holdings[1].pending);
+}
+
+#[test]
+fn parse_fidelity_negative_values() {
+    let csv = "\
+Account Number,Account Name,Symbol,Description,Quantity,Current Value
+Z12345678,INDIVIDUAL,SPAXX**,FIDELITY GOVERNMENT MONEY MARKET,,-$1500.25
+Z12345678,INDIVIDUAL,TSLA,TESLA INC,-10.000,($2500.00)
+Z12345678,INDIVIDUAL,Pending activity,,,\"-$1,000.00\"
+";
+    let portfolio = provider::load_portfolio(&mut Cursor::new(csv), Some(ProviderType::Fidelity))
+        .expect("Failed to parse fidelity example with negative values");
+    let holdings = &portfolio[0].holdings;
+    assert_eq!(holdings[0].current_value, Dollar(-1500.25));
+    assert_eq!(holdings[1].current_value, Dollar(-2500.00));
+    assert_eq!(holdings[1].quantity, Some(-10.0));
+    assert_eq!(holdings[2].current_value, Dollar(-1000.00));
+}
+
+#[test]
+fn parse_generic_currencies() {
+    let csv = "Account Number,Symbol,Value,Currency\nA-1,VWCE,1000.00,EUR\nA-1,VTI,500.00,\n";
+    let mapping = ColumnMapping {
+        currency: Some("Currency".to_string()),
+        ..Default::default()
+    };
+    let mut portfolio =
+        provider::load_generic_portfolio(&mut Cursor::new(csv), &mapping, &mut Vec::new())
+            .expect("Failed to parse generic example with currencies");
+    let holdings = &portfolio[0].holdings;
+    assert_eq!(holdings[0].currency.as_deref(), Some("EUR"));
+    assert_eq!(holdings[1].currency, None);
+
+    let rates = fx::load_exchange_rates(&mut "Currency,Rate\nEUR,1.2\n".as_bytes(), "USD").unwrap();
+    rates.convert(&mut portfolio[0]).unwrap();
+    assert_eq!(portfolio[0].total_value(), Dollar(1700.00));
+}
+
+#[test]
+fn parse_generic_maturities() {
+    let csv = "Account Number,Symbol,Value,Matures\nA-1,CD123,1000.00,03/01/2027\nA-1,TBILL,500.00,2026-12-15\nA-1,VTI,500.00,\n";
+    let mapping = ColumnMapping {
+        maturity: Some("Matures".to_string()),
+        ..Default::default()
+    };
+    let portfolio =
+        provider::load_generic_portfolio(&mut Cursor::new(csv), &mapping, &mut Vec::new())
+            .expect("Failed to parse generic example with maturities");
+    let holdings = &portfolio[0].holdings;
+    assert_eq!(holdings[0].maturity, Date::new(2027, 3, 1));
+    assert_eq!(holdings[1].maturity, Date::new(2026, 12, 15));
+    assert_eq!(holdings[2].maturity, None);
+
+    let invalid = "Account Number,Symbol,Value,Matures\nA-1,CD123,1000.00,soon\n";
+    assert!(
+        provider::load_generic_portfolio(&mut Cursor::new(invalid), &mapping, &mut Vec::new())
+            .is_err()
+    );
+}
+
+#[test]
+fn parse_fidelity_reordered_columns() {
+    // a newer export with an extra column and the columns in a different order
+    let csv = "\
+Account Name,Account Number,Symbol,Description,Current Value,Quantity,Last Price,Asset Class
+INDIVIDUAL,Z12345678,AAPL,APPLE INC,\"$1,754.30\",10.000,$175.43,Equity
+INDIVIDUAL,Z12345678,SPAXX**,FIDELITY GOVERNMENT MONEY MARKET,$500.00,500.00,$1.00,Cash
+";
+    let portfolio = provider::load_portfolio(&mut Cursor::new(csv), None)
+        .expect("Failed to parse reordered fidelity example");
+    assert_eq!(portfolio.len(), 1);
+    assert_eq!(portfolio[0].account_id, "Z12345678");
+    assert_eq!(portfolio[0].account_name, "INDIVIDUAL");
+    assert_eq!(portfolio[0].total_value(), Dollar(2254.30));
+    assert_eq!(portfolio[0].holdings[0].quantity, Some(10.0));
+    assert_eq!(portfolio[0].holdings[0].cost_basis, None);
+
+    let csv = "Account Number,Account Name,Ticker,Description,Value\nZ1,IRA,VTI,VANGUARD,$1.00\n";
+    let err = provider::load_portfolio(&mut Cursor::new(csv), Some(ProviderType::Fidelity))
+        .unwrap_err()
+        .to_string();
+    assert!(err.contains("'Symbol', 'Current Value'"), "{err}");
+}
+
+#[test]
+fn parse_european_csv() {
+    let csv = "\
+Account Number;Account Name;Symbol;Description;Current Value;Quantity
+DE-1;Depot;VWCE;VANGUARD FTSE ALL-WORLD;12.345,67 €;110,5
+DE-1;Depot;CASH**;Cash;-€ 1.000,00;
+";
+    let format = CsvFormat {
+        delimiter: ';',
+        decimal_separator: ',',
+        currency_symbols: vec!["€".to_string()],
+    };
+    let normalized = format.normalize(csv.as_bytes()).unwrap();
+    let portfolio = provider::load_portfolio(&mut Cursor::new(normalized), None)
+        .expect("Failed to parse European example");
+    assert_eq!(portfolio[0].holdings[0].current_value, Dollar(12345.67));
+    assert_eq!(portfolio[0].holdings[0].quantity, Some(110.5));
+    assert_eq!(portfolio[0].holdings[1].current_value, Dollar(-1000.00));
+    // other formats are left alone
+    assert_eq!(
+        format.normalize(FIDELITY_CSV.as_bytes()).unwrap(),
+        FIDELITY_CSV.as_bytes()
+    );
+}
+
+#[test]
+fn parse_ofx() {
+    let mut reader = Cursor::new(OFX_SGML);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Ofx))
+        .expect("Failed to parse OFX example");
+
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "555000111");
+    let symbols: Vec<_> = account.holdings.iter().map(|h| h.symbol.as_str()).collect();
+    assert_eq!(symbols, vec!["VTI", "AAPL", "CASH"]);
+    let cash = account.holdings.iter().find(|p| p.is_cash).unwrap();
+    assert_eq!(cash.current_value, Dollar(250.75));
+
+    // OFX 2 files are XML, and this one has no security list
+    let mut warnings = Vec::new();
+    let portfolio = provider::load_portfolio_with_warnings(
+        &mut Cursor::new(OFX_XML),
+        Some(ProviderType::Ofx),
+        &mut warnings,
+    )
+    .expect("Failed to parse OFX 2 example");
+    assert_eq!(portfolio[0].account_id, "777");
+    assert_eq!(portfolio[0].holdings[0].symbol, "921937835");
+    assert_eq!(portfolio[0].holdings[0].current_value, Dollar(1000.50));
+    assert_eq!(warnings.len(), 1);
+}
+
+#[test]
+fn parse_qif() {
+    let mut warnings = Vec::new();
+    let portfolio = provider::load_portfolio_with_warnings(
+        &mut Cursor::new(QIF),
+        Some(ProviderType::Qif),
+        &mut warnings,
+    )
+    .expect("Failed to parse QIF example");
+    assert!(warnings.is_empty(), "{warnings:?}");
+
+    // the checking account doesn't have any investment transactions
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "Brokerage");
+    let value_of = |symbol: &str| {
+        account
+            .holdings
+            .iter()
+            .find(|h| h.symbol == symbol)
+            .map(|h| h.current_value)
+            .unwrap()
+    };
+    // the latest price comes from the price list
+    assert_eq!(value_of("VTI"), Dollar(2400.00));
+    // the latest price comes from the reinvested dividend
+    assert_eq!(value_of("BND"), Dollar(1160.00));
+    assert_eq!(value_of("CASH"), Dollar(1637.50));
+    let vti = account.holdings.iter().find(|h| h.symbol == "VTI").unwrap();
+    assert_eq!(
+        (vti.quantity, vti.price),
+        (Some(10.0), Some(Dollar(240.00)))
+    );
+    let cash = account.holdings.iter().find(|h| h.is_cash).unwrap();
+    assert_eq!((cash.quantity, cash.price), (None, None));
+}
+
+#[test]
+fn parse_portfolio_performance() {
+    let mut warnings = Vec::new();
+    let portfolio = provider::load_portfolio_with_warnings(
+        &mut Cursor::new(PORTFOLIO_PERFORMANCE_XML),
+        Some(ProviderType::PortfolioPerformance),
+        &mut warnings,
+    )
+    .expect("Failed to parse Portfolio Performance example");
+    assert!(warnings.is_empty(), "{warnings:?}");
+    // the checking account isn't the reference account of a portfolio
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "Brokerage");
+    let values: Vec<_> = account
+        .holdings
+        .iter()
+        .map(|h| (h.symbol.as_str(), h.current_value, h.is_cash))
+        .collect();
+    assert_eq!(
+        values,
+        vec![
+            ("VTI", Dollar(2400.00), false),
+            // identified by ISIN, since it has no ticker
+            ("US0000000001", Dollar(1000.00), false),
+            ("USD", Dollar(2700.00), true),
+        ]
+    );
+}
+
+#[test]
+fn parse_crypto() {
+    let mut reader = Cursor::new(COINBASE_CSV);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Crypto))
+        .expect("Failed to parse Coinbase example");
+    assert_eq!(portfolio.len(), 2);
+    let default = &portfolio[0];
+    assert_eq!(default.account_id, "Default");
+    // empty balances are left out
+    let symbols: Vec<_> = default.holdings.iter().map(|h| h.symbol.as_str()).collect();
+    assert_eq!(symbols, vec!["BTC", "ETH", "CASH"]);
+    assert_eq!(default.holdings[0].current_value, Dollar(4950.00));
+    assert_eq!(portfolio[1].holdings[0].symbol, "USDC");
+    assert!(!portfolio[1].holdings[0].is_cash);
+
+    let mut warnings = Vec::new();
+    let portfolio = provider::load_portfolio_with_warnings(
+        &mut Cursor::new(EXCHANGE_CSV),
+        Some(ProviderType::Crypto),
+        &mut warnings,
+    )
+    .expect("Failed to parse exchange example");
+    assert_eq!(portfolio.len(), 1);
+    assert_eq!(portfolio[0].account_id, "crypto");
+    assert_eq!(portfolio[0].holdings.len(), 1);
+    assert_eq!(warnings.len(), 1);
+}
+
+#[cfg(feature = "xlsx")]
+#[test]
+fn parse_xlsx() {
+    let xlsx: &[u8] = include_bytes!("fixtures/fidelity.xlsx");
+    let portfolio = provider::load_portfolio(&mut Cursor::new(xlsx), None)
+        .expect("Failed to parse XLSX example");
+    check_portfolio(
+        &portfolio,
+        &[
+            ExpectedAccount::new("Z12345678", Dollar(2254.30), &["SPAXX"]),
+            ExpectedAccount::new("Y98765432", Dollar(24841.31), &[]),
+        ],
+    )
+    .unwrap();
+}
+
+#[test]
+fn parse_json() {
+    let mut reader = Cursor::new(PORTFOLIO_JSON);
+    let portfolio = provider::load_portfolio(&mut reader, Some(ProviderType::Json))
+        .expect("Failed to parse JSON example");
+    assert_eq!(portfolio.len(), 2);
+    assert_eq!(portfolio[0].account_name, "Café fund");
+    assert!(portfolio[0].holdings[1].is_cash);
+
+    let unknown_field =
+        r#"{"version": 1, "accounts": [{"account_id": "1", "holdings": [], "typo": 1}]}"#;
+    assert!(
+        provider::load_portfolio(&mut Cursor::new(unknown_field), Some(ProviderType::Json))
+            .is_err()
+    );
+    let cd = r#"{"version": 1, "accounts": [{"account_id": "1", "holdings": [
+        {"symbol": "CD123", "value": 1000, "maturity": "2027-03-01"}]}]}"#;
+    let portfolio = provider::load_portfolio(&mut Cursor::new(cd), Some(ProviderType::Json))
+        .expect("Failed to parse JSON example with a maturity");
+    assert_eq!(portfolio[0].holdings[0].maturity, Date::new(2027, 3, 1));
+    let details = r#"{"version": 1, "accounts": [{"account_id": "1", "holdings": [
+        {"symbol": "VTI", "value": 2500, "quantity": 10, "price": 250,
+         "cost_basis": 2000, "acquired": "2025-06-02"},
+        {"symbol": "DEPOSIT", "value": 100, "pending": true}]}]}"#;
+    let portfolio = provider::load_portfolio(&mut Cursor::new(details), Some(ProviderType::Json))
+        .expect("Failed to parse JSON example with holding details");
+    let vti = &portfolio[0].holdings[0];
+    assert_eq!(vti.quantity, Some(10.0));
+    assert_eq!(vti.price, Some(Dollar(250.0)));
+    assert_eq!(vti.cost_basis, Some(Dollar(2000.0)));
+    assert_eq!(vti.acquired, Date::new(2025, 6, 2));
+    assert!(!vti.pending && portfolio[0].holdings[1].pending);
+
+    // tabs may indent the document, and escaped tabs in strings are kept
+    let tabs = "{\n\t\"version\": 1,\n\t\"accounts\": [{\"account_id\": \"1\",\n\t\t\"account_name\": \"a\\tb \\\"c\\\"\", \"holdings\": []}]\n}";
+    let portfolio = provider::load_portfolio(&mut Cursor::new(tabs), Some(ProviderType::Json))
+        .expect("Failed to parse JSON example indented with tabs");
+    assert_eq!(portfolio[0].account_name, "a\tb \"c\"");
+    let raw_tab = "{\"version\": 1, \"accounts\": [{\"account_id\": \"a\tb\", \"holdings\": []}]}";
+    let err =
+        provider::load_portfolio(&mut Cursor::new(raw_tab), Some(ProviderType::Json)).unwrap_err();
+    assert!(err.to_string().contains("tab within a string"), "{err}");
+    let future = r#"{"version": 2, "accounts": []}"#;
+    assert!(provider::load_portfolio(&mut Cursor::new(future), Some(ProviderType::Json)).is_err());
+}
+
+#[test]
+fn parse_ledger() {
+    let mut warnings = Vec::new();
+    let portfolio = provider::load_portfolio_with_warnings(
+        &mut Cursor::new(BEANCOUNT),
+        Some(ProviderType::Ledger),
+        &mut warnings,
+    )
+    .expect("Failed to parse beancount example");
+    assert!(warnings.is_empty(), "{warnings:?}");
+    // the checking account only holds cash, so it isn't reported
+    assert_eq!(portfolio.len(), 1);
+    let account = &portfolio[0];
+    assert_eq!(account.account_id, "Assets:Brokerage");
+    let value_of = |symbol: &str| {
+        account
+            .holdings
+            .iter()
+            .find(|h| h.symbol == symbol)
+            .map(|h| h.current_value)
+            .unwrap()
+    };
+    // the latest price, even though it isn't the last one in the file
+    assert_eq!(value_of("VTI"), Dollar(2400.00));
+    // valued at cost, since there is no price directive
+    assert_eq!(value_of("BND"), Dollar(1080.00));
+    assert_eq!(value_of("USD"), Dollar(1620.00));
+
+    let portfolio = provider::load_portfolio(&mut Cursor::new(HLEDGER), Some(ProviderType::Ledger))
+        .expect("Failed to parse hledger example");
+    assert_eq!(portfolio.len(), 1);
+    assert_eq!(portfolio[0].account_id, "assets:brokerage");
+    assert_eq!(portfolio[0].holdings.len(), 2);
+}
+
+#[test]
+fn detect_provider() {
+    let cases = [
+        (FIDELITY_CSV, ProviderType::Fidelity),
+        (VANGUARD_CSV, ProviderType::Vanguard),
+        (SCHWAB_CSV, ProviderType::Schwab),
+        (ETRADE_CSV, ProviderType::Etrade),
+        (MERRILL_CSV, ProviderType::MerrillEdge),
+        (IBKR_XML, ProviderType::InteractiveBrokers),
+        (NETBENEFITS_CSV, ProviderType::NetBenefits),
+        (ROBINHOOD_CSV, ProviderType::Robinhood),
+        (BETTERMENT_CSV, ProviderType::Betterment),
+        (WEALTHFRONT_CSV, ProviderType::Wealthfront),
+        (OFX_SGML, ProviderType::Ofx),
+        (OFX_XML, ProviderType::Ofx),
+        (QIF, ProviderType::Qif),
+        (PORTFOLIO_JSON, ProviderType::Json),
+        (BEANCOUNT, ProviderType::Ledger),
+        (HLEDGER, ProviderType::Ledger),
+        (
+            PORTFOLIO_PERFORMANCE_XML,
+            ProviderType::PortfolioPerformance,
+        ),
+        (COINBASE_CSV, ProviderType::Crypto),
+    ];
+    for (fixture, expected) in cases {
+        assert_eq!(
+            provider::detect_provider(fixture.as_bytes()),
+            Some(expected),
+            "{fixture}"
+        );
+    }
+    assert_eq!(provider::detect_provider(GENERIC_CSV.as_bytes()), None);
+    assert!(provider::load_portfolio(&mut Cursor::new(GENERIC_CSV), None).is_err());
+}
+
+/// A provider for a made-up format with one "SYMBOL=VALUE" line per holding,
+/// as a downstream crate might add
+struct KeyValueProvider;
+
+impl BalanceProvider for KeyValueProvider {
+    fn name(&self) -> &str {
+        "key-value"
+    }
+
+    fn parse(
+        &self,
+        reader: &mut dyn BufRead,
+        _warnings: &mut Vec<String>,
+    ) -> anyhow::Result<Vec<Balance>> {
+        let mut balance = Balance {
+            account_id: "kv".to_string(),
+            ..Default::default()
+        };
+        for line in reader.lines().skip(1) {
+            let line = line?;
+            let (symbol, value) = line.split_once('=').unwrap();
+            balance.holdings.push(Holding {
+                symbol: symbol.to_string(),
+                current_value: value.parse()?,
+                ..Default::default()
+            });
+        }
+        Ok(vec![balance])
+    }
+
+    fn detect(&self, sample: &[u8]) -> anyhow::Result<bool> {
+        Ok(sample.starts_with(b"#key-value"))
+    }
+}
+
+#[test]
+fn provider_registry() {
+    let mut registry = ProviderRegistry::default();
+    assert!(registry.names().any(|name| name == "fidelity"));
+    assert!(registry.get("generic").is_none());
+    // built-in providers are still detected
+    let portfolio = registry
+        .load_portfolio(&mut Cursor::new(FIDELITY_CSV), None, &mut Vec::new())
+        .expect("Failed to parse fidelity example");
+    assert_eq!(portfolio.len(), 2);
+
+    let key_value = "#key-value\nVTI=100.50\nBND=50";
+    assert!(
+        registry
+            .load_portfolio(&mut Cursor::new(key_value), None, &mut Vec::new())
+            .is_err()
+    );
+    registry.register(KeyValueProvider);
+    assert_eq!(registry.names().last(), Some("key-value"));
+    let portfolio = registry
+        .load_portfolio(&mut Cursor::new(key_value), None, &mut Vec::new())
+        .expect("Failed to parse key-value example");
+    assert_eq!(portfolio[0].total_value(), Dollar(150.50));
+    let portfolio = registry
+        .load_portfolio(
+            &mut Cursor::new(key_value),
+            Some("key-value"),
+            &mut Vec::new(),
+        )
+        .expect("Failed to parse key-value example");
+    assert_eq!(portfolio[0].holdings.len(), 2);
+    assert!(
+        registry
+            .load_portfolio(&mut Cursor::new(key_value), Some("nope"), &mut Vec::new())
+            .is_err()
+    );
+
+    let expected = [ExpectedAccount::new("kv", Dollar(150.50), &[])];
+    check_balance_provider(
+        &KeyValueProvider,
+        &registry,
+        key_value.as_bytes(),
+        &expected,
+    )
+    .expect("key-value provider should conform");
+    // the fixture has to be detected as being from the provider
+    assert!(
+        check_balance_provider(
+            &KeyValueProvider,
+            &ProviderRegistry::default(),
+            key_value.as_bytes(),
+            &expected
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn merge_portfolios() {
+    let fidelity = provider::load_portfolio(&mut Cursor::new(FIDELITY_CSV), None).unwrap();
+    let coinbase = provider::load_portfolio(&mut Cursor::new(COINBASE_CSV), None).unwrap();
+    let mut newer = coinbase.clone();
+    newer[0].holdings.truncate(1);
+    let mut warnings = Vec::new();
+    let merged = provider::merge_portfolios([fidelity, coinbase, newer], &mut warnings);
+    let ids: Vec<_> = merged.iter().map(|a| a.account_id.as_str()).collect();
+    assert_eq!(ids.len(), 4);
+    assert!(ids.contains(&"Z12345678") && ids.contains(&"Default"));
+    // the last file wins
+    let default = merged.iter().find(|a| a.account_id == "Default").unwrap();
+    assert_eq!(default.holdings.len(), 1);
+    assert_eq!(warnings.len(), 2);
+}
+
 #[test]
 fn parse_auto() {
     let mut reader = Cursor::new(FIDELITY_CSV);
@@ -66,4 +1001,200 @@ fn parse_auto() {
     let portfolio =
         provider::load_portfolio(&mut reader, None).expect("Failed to parse vanguard example");
     assert_eq!(portfolio.len(), 2);
+    let mut reader = Cursor::new(SCHWAB_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse schwab example");
+    assert_eq!(portfolio.len(), 2);
+    let mut reader = Cursor::new(ETRADE_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse E*TRADE example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(MERRILL_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse Merrill Edge example");
+    assert_eq!(portfolio.len(), 2);
+    let mut reader = Cursor::new(IBKR_XML);
+    let portfolio = provider::load_portfolio(&mut reader, None)
+        .expect("Failed to parse Interactive Brokers example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(ROBINHOOD_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse Robinhood example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(NETBENEFITS_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse NetBenefits example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(BETTERMENT_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse Betterment example");
+    assert_eq!(portfolio.len(), 2);
+    let mut reader = Cursor::new(WEALTHFRONT_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse Wealthfront example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(OFX_SGML);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse OFX example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(QIF);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse QIF example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(BEANCOUNT);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse beancount example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(HLEDGER);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse hledger example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(PORTFOLIO_PERFORMANCE_XML);
+    let portfolio = provider::load_portfolio(&mut reader, None)
+        .expect("Failed to parse Portfolio Performance example");
+    assert_eq!(portfolio.len(), 1);
+    let mut reader = Cursor::new(COINBASE_CSV);
+    let portfolio =
+        provider::load_portfolio(&mut reader, None).expect("Failed to parse Coinbase example");
+    assert_eq!(portfolio.len(), 2);
+}
+
+#[test]
+fn provider_conformance() {
+    let cases = [
+        (
+            ProviderType::Fidelity,
+            FIDELITY_CSV,
+            vec![
+                ExpectedAccount::new("Z12345678", Dollar(2254.30), &["SPAXX"]),
+                ExpectedAccount::new("Y98765432", Dollar(26946.31), &[]),
+            ],
+        ),
+        (
+            ProviderType::Vanguard,
+            VANGUARD_CSV,
+            vec![
+                ExpectedAccount::new("12345678", Dollar(31689.21), &["VMFXX"]),
+                ExpectedAccount::new("87654321", Dollar(7592.78), &["VMFXX"]),
+            ],
+        ),
+        (
+            ProviderType::Schwab,
+            SCHWAB_CSV,
+            vec![
+                ExpectedAccount::new("...123", Dollar(4634.56), &["CASH"]),
+                ExpectedAccount::new("...456", Dollar(1500.00), &["CASH"]),
+            ],
+        ),
+        (
+            ProviderType::Etrade,
+            ETRADE_CSV,
+            vec![ExpectedAccount::new("1234", Dollar(3400.00), &["CASH"])],
+        ),
+        (
+            ProviderType::MerrillEdge,
+            MERRILL_CSV,
+            vec![
+                ExpectedAccount::new("12X-34567", Dollar(3400.00), &["CASH"]),
+                ExpectedAccount::new("34X-56789", Dollar(1200.00), &[]),
+            ],
+        ),
+        (
+            ProviderType::InteractiveBrokers,
+            IBKR_XML,
+            vec![ExpectedAccount::new(
+                "U1234567",
+                Dollar(5100.00),
+                &["USD", "EUR"],
+            )],
+        ),
+        (
+            ProviderType::NetBenefits,
+            NETBENEFITS_CSV,
+            vec![ExpectedAccount::new("12345", Dollar(16000.00), &[])],
+        ),
+        (
+            ProviderType::Robinhood,
+            ROBINHOOD_CSV,
+            vec![ExpectedAccount::new(
+                "robinhood",
+                Dollar(2121.50),
+                &["CASH"],
+            )],
+        ),
+        (
+            ProviderType::Betterment,
+            BETTERMENT_CSV,
+            vec![
+                ExpectedAccount::new("Retirement - Roth IRA", Dollar(3532.34), &["CASH"]),
+                ExpectedAccount::new("Safety Net - Taxable", Dollar(1470.00), &[]),
+            ],
+        ),
+        (
+            ProviderType::Wealthfront,
+            WEALTHFRONT_CSV,
+            vec![ExpectedAccount::new("8W123456", Dollar(3885.67), &["CASH"])],
+        ),
+        (
+            ProviderType::Ofx,
+            OFX_SGML,
+            vec![ExpectedAccount::new(
+                "555000111",
+                Dollar(3550.75),
+                &["CASH"],
+            )],
+        ),
+        (
+            ProviderType::Qif,
+            QIF,
+            vec![ExpectedAccount::new(
+                "Brokerage",
+                Dollar(5197.50),
+                &["CASH"],
+            )],
+        ),
+        (
+            ProviderType::Json,
+            PORTFOLIO_JSON,
+            vec![
+                ExpectedAccount::new("123", Dollar(1600.00), &["SPAXX"]),
+                ExpectedAccount::new("456", Dollar(0.0), &[]),
+            ],
+        ),
+        (
+            ProviderType::Ledger,
+            BEANCOUNT,
+            vec![ExpectedAccount::new(
+                "Assets:Brokerage",
+                Dollar(5100.00),
+                &["USD"],
+            )],
+        ),
+        (
+            ProviderType::Ledger,
+            HLEDGER,
+            vec![ExpectedAccount::new(
+                "assets:brokerage",
+                Dollar(5100.00),
+                &["USD"],
+            )],
+        ),
+        (
+            ProviderType::PortfolioPerformance,
+            PORTFOLIO_PERFORMANCE_XML,
+            vec![ExpectedAccount::new("Brokerage", Dollar(6100.00), &["USD"])],
+        ),
+        (
+            ProviderType::Crypto,
+            COINBASE_CSV,
+            vec![
+                ExpectedAccount::new("Default", Dollar(9160.00), &["CASH"]),
+                ExpectedAccount::new("Savings", Dollar(500.00), &[]),
+            ],
+        ),
+    ];
+    for (ptype, fixture, expected) in cases {
+        check_provider(ptype, fixture.as_bytes(), &expected)
+            .unwrap_or_else(|e| panic!("{ptype:?}: {e}"));
+    }
 }
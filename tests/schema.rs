@@ -0,0 +1,60 @@
+use std::collections::BTreeSet;
+
+use driftfix::account::{
+    AllocationConfig, CashConfig, GlidePathStep, HoldingConstraints, RebalancePolicy, TargetGroup,
+    TargetRange, ToleranceBand,
+};
+use serde::de::DeserializeOwned;
+
+/// The fields that `T` accepts, which are listed by the error for an unknown
+/// field
+fn fields<T: DeserializeOwned + std::fmt::Debug>() -> BTreeSet<String> {
+    let error = serde_yaml::from_str::<T>("UnknownField: 1").unwrap_err();
+    let message = error.to_string();
+    let (_, expected) = message
+        .split_once("expected")
+        .unwrap_or_else(|| panic!("unexpected error: {message}"));
+    expected
+        .split('`')
+        .skip(1)
+        .step_by(2)
+        .map(str::to_string)
+        .collect()
+}
+
+#[test]
+fn target_schema_matches_config() {
+    let schema: serde_yaml::Value =
+        serde_yaml::from_str(include_str!("../schema/target.schema.json")).unwrap();
+    let properties = |def: &str| -> BTreeSet<String> {
+        let properties = &schema["$defs"][def]["properties"];
+        properties
+            .as_mapping()
+            .unwrap_or_else(|| panic!("no properties for {def}"))
+            .keys()
+            .map(|k| k.as_str().unwrap().to_string())
+            .collect()
+    };
+    let group = &schema["$defs"]["nestedTarget"]["oneOf"][1]["properties"];
+    let group: BTreeSet<String> = group
+        .as_mapping()
+        .unwrap()
+        .keys()
+        .map(|k| k.as_str().unwrap().to_string())
+        .collect();
+
+    // the schema also lists the aliases of fields
+    let mut account = fields::<AllocationConfig>();
+    account.insert("Ignore".to_string());
+    account.insert("Name".to_string());
+    assert_eq!(properties("account"), account);
+    let mut cash_sweep = fields::<CashConfig>();
+    cash_sweep.insert("ReservePercent".to_string());
+    assert_eq!(properties("cashSweep"), cash_sweep);
+    assert_eq!(properties("glidePathStep"), fields::<GlidePathStep>());
+    assert_eq!(properties("rebalancePolicy"), fields::<RebalancePolicy>());
+    assert_eq!(properties("toleranceBand"), fields::<ToleranceBand>());
+    assert_eq!(properties("targetRange"), fields::<TargetRange>());
+    assert_eq!(properties("constraints"), fields::<HoldingConstraints>());
+    assert_eq!(group, fields::<TargetGroup>());
+}